@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap, net::ToSocketAddrs, num::NonZeroUsize, path::PathBuf, time::Duration,
+    collections::HashMap, fs, net::ToSocketAddrs, num::NonZeroUsize, path::PathBuf, time::Duration,
 };
 
 use anyhow::{bail, Context};
@@ -17,9 +17,13 @@ use hotshot_types::{
 };
 use libp2p::Multiaddr;
 use sequencer::{
-    options::parse_duration, persistence::no_storage::NoStorage, Genesis, L1Params, NetworkParams,
+    keystore::{Keystore, PassphraseOptions},
+    options::parse_duration,
+    persistence::no_storage::NoStorage,
+    Genesis, L1Params, NetworkParams,
 };
 use url::Url;
+use zeroize::Zeroizing;
 
 #[derive(Parser, Clone, Debug)]
 pub struct PermissionedBuilderOptions {
@@ -100,6 +104,10 @@ pub struct PermissionedBuilderOptions {
     /// * ESPRESSO_BUILDER_PRIVATE_STAKING_KEY
     /// * ESPRESSO_BUILDER_PRIVATE_STATE_KEY
     ///
+    /// Alternatively, the file may be a keystore holding both keys, which is unlocked with the
+    /// keystore passphrase. If the keystore also holds the Ethereum key for the builder account, the
+    /// same file can be passed as eth-keystore.
+    ///
     /// Appropriate key files can be generated with the `keygen` utility program.
     #[clap(long, name = "KEY_FILE", env = "ESPRESSO_BUILDER_KEY_FILE")]
     pub key_file: Option<PathBuf>,
@@ -128,8 +136,27 @@ pub struct PermissionedBuilderOptions {
     ///
     /// This is the address fees will be charged to.
     /// It must be funded with ETH in the Espresso fee ledger
-    #[clap(long, env = "ESPRESSO_BUILDER_ETH_MNEMONIC")]
-    pub eth_mnemonic: String,
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_ETH_MNEMONIC",
+        required_unless_present = "eth_keystore"
+    )]
+    pub eth_mnemonic: Option<String>,
+
+    /// Keystore holding the Ethereum key for the builder account.
+    ///
+    /// This can be used as an alternative to eth-mnemonic. The keystore is unlocked with the
+    /// keystore passphrase.
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_ETH_KEYSTORE",
+        conflicts_with = "eth_mnemonic"
+    )]
+    pub eth_keystore: Option<PathBuf>,
+
+    /// Passphrase for keystores.
+    #[clap(flatten)]
+    pub keystore_passphrase: PassphraseOptions,
 
     /// Index of a funded account derived from eth-mnemonic.
     #[clap(long, env = "ESPRESSO_BUILDER_ETH_ACCOUNT_INDEX", default_value = "8")]
@@ -193,7 +220,16 @@ pub struct PermissionedBuilderOptions {
 impl PermissionedBuilderOptions {
     pub fn private_keys(&self) -> anyhow::Result<(BLSPrivKey, StateSignKey)> {
         if let Some(path) = &self.key_file {
-            let vars = dotenvy::from_path_iter(path)?.collect::<Result<HashMap<_, _>, _>>()?;
+            let bytes = Zeroizing::new(fs::read(path)?);
+            if Keystore::is_keystore(&bytes) {
+                let keys = self.keystore_passphrase.unlock(path)?;
+                let staking = keys.staking.context("keystore missing staking key")?;
+                let state = keys.state.context("keystore missing state key")?;
+                return Ok((staking, state));
+            }
+
+            let vars =
+                dotenvy::from_read_iter(bytes.as_slice()).collect::<Result<HashMap<_, _>, _>>()?;
             let staking = vars
                 .get("ESPRESSO_BUILDER_PRIVATE_STAKING_KEY")
                 .context("key file missing ESPRESSO_BUILDER_PRIVATE_STAKING_KEY")?
@@ -227,7 +263,15 @@ async fn main() -> anyhow::Result<()> {
         events_max_block_range: 10000,
     };
 
-    let builder_key_pair = EthKeyPair::from_mnemonic(&opt.eth_mnemonic, opt.eth_account_index)?;
+    let builder_key_pair = match (&opt.eth_mnemonic, &opt.eth_keystore) {
+        (Some(mnemonic), _) => EthKeyPair::from_mnemonic(mnemonic, opt.eth_account_index)?,
+        (None, Some(path)) => opt
+            .keystore_passphrase
+            .unlock(path)?
+            .eth
+            .context("keystore does not contain an Ethereum key")?,
+        (None, None) => bail!("neither mnemonic nor keystore was provided for builder account"),
+    };
 
     // Parse supplied Libp2p addresses to their socket form
     // We expect all nodes to be reachable via IPv4, so we filter out any IPv6 addresses.
@@ -253,7 +297,7 @@ async fn main() -> anyhow::Result<()> {
         orchestrator_url: opt.orchestrator_url,
         state_relay_server_url: opt.state_relay_server_url,
         private_staking_key: private_staking_key.clone(),
-        private_state_key,
+        private_state_key: Some(private_state_key),
         state_peers: opt.state_peers,
         config_peers: None,
         catchup_backoff: Default::default(),
@@ -1,5 +1,6 @@
 use std::{num::NonZeroUsize, path::PathBuf, str::FromStr, time::Duration};
 
+use anyhow::{bail, Context};
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use builder::non_permissioned::{build_instance_state, BuilderConfig};
 use clap::Parser;
@@ -8,7 +9,7 @@ use es_version::SEQUENCER_VERSION;
 use espresso_types::eth_signature_key::EthKeyPair;
 use hotshot::traits::ValidatedState;
 use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
-use sequencer::{Genesis, L1Params};
+use sequencer::{keystore::PassphraseOptions, Genesis, L1Params};
 use snafu::Snafu;
 use url::Url;
 
@@ -27,8 +28,27 @@ struct NonPermissionedBuilderOptions {
     ///
     /// This is the address fees will be charged to.
     /// It must be funded with ETH in the Espresso fee ledger
-    #[clap(long, env = "ESPRESSO_BUILDER_ETH_MNEMONIC")]
-    eth_mnemonic: String,
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_ETH_MNEMONIC",
+        required_unless_present = "eth_keystore"
+    )]
+    eth_mnemonic: Option<String>,
+
+    /// Keystore holding the Ethereum key for the builder account.
+    ///
+    /// This can be used as an alternative to eth-mnemonic. The keystore is unlocked with the
+    /// keystore passphrase.
+    #[clap(
+        long,
+        env = "ESPRESSO_BUILDER_ETH_KEYSTORE",
+        conflicts_with = "eth_mnemonic"
+    )]
+    eth_keystore: Option<PathBuf>,
+
+    /// Passphrase for keystores.
+    #[clap(flatten)]
+    keystore_passphrase: PassphraseOptions,
 
     /// Index of a funded account derived from eth-mnemonic.
     #[clap(long, env = "ESPRESSO_BUILDER_ETH_ACCOUNT_INDEX", default_value = "8")]
@@ -113,7 +133,15 @@ async fn main() -> anyhow::Result<()> {
         events_max_block_range: 10000,
     };
 
-    let builder_key_pair = EthKeyPair::from_mnemonic(&opt.eth_mnemonic, opt.eth_account_index)?;
+    let builder_key_pair = match (&opt.eth_mnemonic, &opt.eth_keystore) {
+        (Some(mnemonic), _) => EthKeyPair::from_mnemonic(mnemonic, opt.eth_account_index)?,
+        (None, Some(path)) => opt
+            .keystore_passphrase
+            .unlock(path)?
+            .eth
+            .context("keystore does not contain an Ethereum key")?,
+        (None, None) => bail!("neither mnemonic nor keystore was provided for builder account"),
+    };
     let bootstrapped_view = ViewNumber::new(opt.view_number);
 
     let builder_server_url: Url = format!("http://0.0.0.0:{}", opt.port).parse().unwrap();
@@ -158,7 +158,11 @@ pub async fn init_node<P: SequencerPersistence, Ver: StaticVersionType + 'static
         network_config_file: None,
     };
     let orchestrator_client = OrchestratorClient::new(validator_args);
-    let state_key_pair = StateKeyPair::from_sign_key(network_params.private_state_key);
+    let state_key_pair = StateKeyPair::from_sign_key(
+        network_params
+            .private_state_key
+            .context("the builder requires a private state key")?,
+    );
     let my_config = ValidatorConfig {
         public_key: BLSPubKey::from_private(&network_params.private_staking_key),
         private_key: network_params.private_staking_key,
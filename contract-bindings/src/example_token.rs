@@ -0,0 +1,37 @@
+pub use example_token::*;
+/// Bindings for `ExampleToken.sol`, generated by ethers-rs Abigen from the contract's ABI.
+/// More information at: <https://github.com/gakonst/ethers-rs>
+///
+/// These bindings do not include the contract bytecode; `just gen-bindings` replaces them with the
+/// full `forge bind` output, which also provides `ExampleToken::deploy`.
+#[allow(
+    clippy::enum_variant_names,
+    clippy::too_many_arguments,
+    clippy::upper_case_acronyms,
+    clippy::type_complexity,
+    dead_code,
+    non_camel_case_types
+)]
+pub mod example_token {
+    ::ethers::contract::abigen!(
+        ExampleToken,
+        r#"[
+            constructor(uint256 initialSupply)
+            function DOMAIN_SEPARATOR() external view returns (bytes32)
+            function allowance(address, address) external view returns (uint256)
+            function approve(address spender, uint256 amount) external returns (bool)
+            function balanceOf(address) external view returns (uint256)
+            function decimals() external view returns (uint8)
+            function name() external view returns (string)
+            function nonces(address) external view returns (uint256)
+            function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external
+            function symbol() external view returns (string)
+            function totalSupply() external view returns (uint256)
+            function transfer(address to, uint256 amount) external returns (bool)
+            function transferFrom(address from, address to, uint256 amount) external returns (bool)
+            event Approval(address indexed owner, address indexed spender, uint256 amount)
+            event Transfer(address indexed from, address indexed to, uint256 amount)
+        ]"#,
+        derives(serde::Deserialize, serde::Serialize)
+    );
+}
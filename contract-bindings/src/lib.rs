@@ -4,6 +4,7 @@
 //! Do not manually edit these files.
 //! These files may be overwritten by the codegen system at any time.
 pub mod erc1967_proxy;
+pub mod example_token;
 pub mod fee_contract;
 pub mod hot_shot;
 pub mod i_plonk_verifier;
@@ -13,3 +14,4 @@ pub mod light_client_state_update_vk;
 pub mod light_client_state_update_vk_mock;
 pub mod plonk_verifier;
 pub mod shared_types;
+pub mod stake_table;
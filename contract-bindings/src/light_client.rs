@@ -168,6 +168,33 @@ pub mod light_client {
                         state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
                     },],
                 ),
+                (
+                    ::std::borrow::ToOwned::to_owned("getFrozenState"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("getFrozenState"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                            ],),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned(
+                                    "struct LightClient.LightClientState",
+                                ),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
                 (
                     ::std::borrow::ToOwned::to_owned("getGenesisState"),
                     ::std::vec![::ethers::core::abi::ethabi::Function {
@@ -286,6 +313,126 @@ pub mod light_client {
                         state_mutability: ::ethers::core::abi::ethabi::StateMutability::Pure,
                     },],
                 ),
+                (
+                    ::std::borrow::ToOwned::to_owned("getVk"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("getVk"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                                ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                    ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ],),
+                            ],),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned(
+                                    "struct IPlonkVerifier.VerifyingKey",
+                                ),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::Pure,
+                    },],
+                ),
+                (
+                    ::std::borrow::ToOwned::to_owned("getVotingState"),
+                    ::std::vec![::ethers::core::abi::ethabi::Function {
+                        name: ::std::borrow::ToOwned::to_owned("getVotingState"),
+                        inputs: ::std::vec![],
+                        outputs: ::std::vec![::ethers::core::abi::ethabi::Param {
+                            name: ::std::string::String::new(),
+                            kind: ::ethers::core::abi::ethabi::ParamType::Tuple(::std::vec![
+                                ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(64usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                                ::ethers::core::abi::ethabi::ParamType::Uint(256usize),
+                            ],),
+                            internal_type: ::core::option::Option::Some(
+                                ::std::borrow::ToOwned::to_owned(
+                                    "struct LightClient.LightClientState",
+                                ),
+                            ),
+                        },],
+                        constant: ::core::option::Option::None,
+                        state_mutability: ::ethers::core::abi::ethabi::StateMutability::View,
+                    },],
+                ),
                 (
                     ::std::borrow::ToOwned::to_owned("hotShotCommitments"),
                     ::std::vec![::ethers::core::abi::ethabi::Function {
@@ -1226,6 +1373,14 @@ pub mod light_client {
                 .method_hash([130, 208, 127, 243], ())
                 .expect("method not found (this should never happen)")
         }
+        ///Calls the contract's `getFrozenState` (0x9a49a438) function
+        pub fn get_frozen_state(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, LightClientState> {
+            self.0
+                .method_hash([154, 73, 164, 56], ())
+                .expect("method not found (this should never happen)")
+        }
         ///Calls the contract's `getGenesisState` (0x4847ae5d) function
         pub fn get_genesis_state(
             &self,
@@ -1265,6 +1420,20 @@ pub mod light_client {
                 .method_hash([13, 142, 110, 44], ())
                 .expect("method not found (this should never happen)")
         }
+        ///Calls the contract's `getVk` (0x824783c8) function
+        pub fn get_vk(&self) -> ::ethers::contract::builders::ContractCall<M, VerifyingKey> {
+            self.0
+                .method_hash([130, 71, 131, 200], ())
+                .expect("method not found (this should never happen)")
+        }
+        ///Calls the contract's `getVotingState` (0xe9c2e17f) function
+        pub fn get_voting_state(
+            &self,
+        ) -> ::ethers::contract::builders::ContractCall<M, LightClientState> {
+            self.0
+                .method_hash([233, 194, 225, 127], ())
+                .expect("method not found (this should never happen)")
+        }
         ///Calls the contract's `hotShotCommitments` (0xdb13b60a) function
         pub fn hot_shot_commitments(
             &self,
@@ -2745,6 +2914,21 @@ pub mod light_client {
     )]
     #[ethcall(name = "getFinalizedState", abi = "getFinalizedState()")]
     pub struct GetFinalizedStateCall;
+    ///Container type for all input parameters for the `getFrozenState` function with signature `getFrozenState()` and selector `0x9a49a438`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        serde::Serialize,
+        serde::Deserialize,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "getFrozenState", abi = "getFrozenState()")]
+    pub struct GetFrozenStateCall;
     ///Container type for all input parameters for the `getGenesisState` function with signature `getGenesisState()` and selector `0x4847ae5d`
     #[derive(
         Clone,
@@ -2828,6 +3012,36 @@ pub mod light_client {
     )]
     #[ethcall(name = "getVersion", abi = "getVersion()")]
     pub struct GetVersionCall;
+    ///Container type for all input parameters for the `getVk` function with signature `getVk()` and selector `0x824783c8`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        serde::Serialize,
+        serde::Deserialize,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "getVk", abi = "getVk()")]
+    pub struct GetVkCall;
+    ///Container type for all input parameters for the `getVotingState` function with signature `getVotingState()` and selector `0xe9c2e17f`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthCall,
+        ::ethers::contract::EthDisplay,
+        serde::Serialize,
+        serde::Deserialize,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    #[ethcall(name = "getVotingState", abi = "getVotingState()")]
+    pub struct GetVotingStateCall;
     ///Container type for all input parameters for the `hotShotCommitments` function with signature `hotShotCommitments(uint256)` and selector `0xdb13b60a`
     #[derive(
         Clone,
@@ -3111,11 +3325,14 @@ pub mod light_client {
         FrozenStakeTableCommitment(FrozenStakeTableCommitmentCall),
         FrozenThreshold(FrozenThresholdCall),
         GetFinalizedState(GetFinalizedStateCall),
+        GetFrozenState(GetFrozenStateCall),
         GetGenesisState(GetGenesisStateCall),
         GetHotShotBlockCommitmentsCount(GetHotShotBlockCommitmentsCountCall),
         GetHotShotCommitment(GetHotShotCommitmentCall),
         GetStateUpdateBlockNumbersCount(GetStateUpdateBlockNumbersCountCall),
         GetVersion(GetVersionCall),
+        GetVk(GetVkCall),
+        GetVotingState(GetVotingStateCall),
         HotShotCommitments(HotShotCommitmentsCall),
         Initialize(InitializeCall),
         LagOverEscapeHatchThreshold(LagOverEscapeHatchThresholdCall),
@@ -3177,6 +3394,11 @@ pub mod light_client {
             {
                 return Ok(Self::GetFinalizedState(decoded));
             }
+            if let Ok(decoded) =
+                <GetFrozenStateCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::GetFrozenState(decoded));
+            }
             if let Ok(decoded) =
                 <GetGenesisStateCall as ::ethers::core::abi::AbiDecode>::decode(data)
             {
@@ -3204,6 +3426,14 @@ pub mod light_client {
             if let Ok(decoded) = <GetVersionCall as ::ethers::core::abi::AbiDecode>::decode(data) {
                 return Ok(Self::GetVersion(decoded));
             }
+            if let Ok(decoded) = <GetVkCall as ::ethers::core::abi::AbiDecode>::decode(data) {
+                return Ok(Self::GetVk(decoded));
+            }
+            if let Ok(decoded) =
+                <GetVotingStateCall as ::ethers::core::abi::AbiDecode>::decode(data)
+            {
+                return Ok(Self::GetVotingState(decoded));
+            }
             if let Ok(decoded) =
                 <HotShotCommitmentsCall as ::ethers::core::abi::AbiDecode>::decode(data)
             {
@@ -3299,6 +3529,7 @@ pub mod light_client {
                 }
                 Self::FrozenThreshold(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::GetFinalizedState(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::GetFrozenState(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::GetGenesisState(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::GetHotShotBlockCommitmentsCount(element) => {
                     ::ethers::core::abi::AbiEncode::encode(element)
@@ -3310,6 +3541,8 @@ pub mod light_client {
                     ::ethers::core::abi::AbiEncode::encode(element)
                 }
                 Self::GetVersion(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::GetVk(element) => ::ethers::core::abi::AbiEncode::encode(element),
+                Self::GetVotingState(element) => ::ethers::core::abi::AbiEncode::encode(element),
                 Self::HotShotCommitments(element) => {
                     ::ethers::core::abi::AbiEncode::encode(element)
                 }
@@ -3356,6 +3589,7 @@ pub mod light_client {
                 Self::FrozenStakeTableCommitment(element) => ::core::fmt::Display::fmt(element, f),
                 Self::FrozenThreshold(element) => ::core::fmt::Display::fmt(element, f),
                 Self::GetFinalizedState(element) => ::core::fmt::Display::fmt(element, f),
+                Self::GetFrozenState(element) => ::core::fmt::Display::fmt(element, f),
                 Self::GetGenesisState(element) => ::core::fmt::Display::fmt(element, f),
                 Self::GetHotShotBlockCommitmentsCount(element) => {
                     ::core::fmt::Display::fmt(element, f)
@@ -3365,6 +3599,8 @@ pub mod light_client {
                     ::core::fmt::Display::fmt(element, f)
                 }
                 Self::GetVersion(element) => ::core::fmt::Display::fmt(element, f),
+                Self::GetVk(element) => ::core::fmt::Display::fmt(element, f),
+                Self::GetVotingState(element) => ::core::fmt::Display::fmt(element, f),
                 Self::HotShotCommitments(element) => ::core::fmt::Display::fmt(element, f),
                 Self::Initialize(element) => ::core::fmt::Display::fmt(element, f),
                 Self::LagOverEscapeHatchThreshold(element) => ::core::fmt::Display::fmt(element, f),
@@ -3424,6 +3660,11 @@ pub mod light_client {
             Self::GetFinalizedState(value)
         }
     }
+    impl ::core::convert::From<GetFrozenStateCall> for LightClientCalls {
+        fn from(value: GetFrozenStateCall) -> Self {
+            Self::GetFrozenState(value)
+        }
+    }
     impl ::core::convert::From<GetGenesisStateCall> for LightClientCalls {
         fn from(value: GetGenesisStateCall) -> Self {
             Self::GetGenesisState(value)
@@ -3449,6 +3690,16 @@ pub mod light_client {
             Self::GetVersion(value)
         }
     }
+    impl ::core::convert::From<GetVkCall> for LightClientCalls {
+        fn from(value: GetVkCall) -> Self {
+            Self::GetVk(value)
+        }
+    }
+    impl ::core::convert::From<GetVotingStateCall> for LightClientCalls {
+        fn from(value: GetVotingStateCall) -> Self {
+            Self::GetVotingState(value)
+        }
+    }
     impl ::core::convert::From<HotShotCommitmentsCall> for LightClientCalls {
         fn from(value: HotShotCommitmentsCall) -> Self {
             Self::HotShotCommitments(value)
@@ -3627,6 +3878,20 @@ pub mod light_client {
         Hash,
     )]
     pub struct GetFinalizedStateReturn(pub LightClientState);
+    ///Container type for all return fields from the `getFrozenState` function with signature `getFrozenState()` and selector `0x9a49a438`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        serde::Serialize,
+        serde::Deserialize,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct GetFrozenStateReturn(pub LightClientState);
     ///Container type for all return fields from the `getGenesisState` function with signature `getGenesisState()` and selector `0x4847ae5d`
     #[derive(
         Clone,
@@ -3701,6 +3966,34 @@ pub mod light_client {
         pub minor_version: u8,
         pub patch_version: u8,
     }
+    ///Container type for all return fields from the `getVk` function with signature `getVk()` and selector `0x824783c8`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        serde::Serialize,
+        serde::Deserialize,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct GetVkReturn(pub VerifyingKey);
+    ///Container type for all return fields from the `getVotingState` function with signature `getVotingState()` and selector `0xe9c2e17f`
+    #[derive(
+        Clone,
+        ::ethers::contract::EthAbiType,
+        ::ethers::contract::EthAbiCodec,
+        serde::Serialize,
+        serde::Deserialize,
+        Default,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+    )]
+    pub struct GetVotingStateReturn(pub LightClientState);
     ///Container type for all return fields from the `hotShotCommitments` function with signature `hotShotCommitments(uint256)` and selector `0xdb13b60a`
     #[derive(
         Clone,
@@ -0,0 +1,63 @@
+pub use stake_table::*;
+/// Bindings for `StakeTable.sol`, generated by ethers-rs Abigen from the contract's ABI.
+/// More information at: <https://github.com/gakonst/ethers-rs>
+///
+/// These bindings do not include the contract bytecode; `just gen-bindings` replaces them with the
+/// full `forge bind` output, which also provides `StakeTable::deploy`.
+#[allow(
+    clippy::enum_variant_names,
+    clippy::too_many_arguments,
+    clippy::upper_case_acronyms,
+    clippy::type_complexity,
+    dead_code,
+    non_camel_case_types
+)]
+pub mod stake_table {
+    ::ethers::contract::abigen!(
+        StakeTable,
+        r#"[
+            struct G1Point { uint256 x; uint256 y; }
+            struct G2Point { uint256 x0; uint256 x1; uint256 y0; uint256 y1; }
+            struct EdOnBN254Point { uint256 x; uint256 y; }
+            struct Node { address account; uint8 stakeType; uint64 balance; uint64 registerEpoch; uint64 exitEpoch; EdOnBN254Point schnorrVK; }
+            constructor(address _tokenAddress, address _lightClientAddress, uint64 churnRate)
+            function _hashBlsKey(G2Point blsVK) external pure returns (bytes32)
+            function currentEpoch() external view returns (uint64)
+            function deposit(G2Point blsVK, uint64 amount) external returns (uint64, uint64)
+            function exitEscrowPeriod(Node node) external pure returns (uint64)
+            function firstAvailableExitEpoch() external view returns (uint64)
+            function firstAvailableRegistrationEpoch() external view returns (uint64)
+            function lightClient() external view returns (address)
+            function lookupNode(G2Point blsVK) external view returns (Node)
+            function lookupStake(G2Point blsVK) external view returns (uint64)
+            function maxChurnRate() external view returns (uint64)
+            function nextExitEpoch() external view returns (uint64, uint64)
+            function nextRegistrationEpoch() external view returns (uint64, uint64)
+            function nodes(bytes32 keyHash) external view returns (address account, uint8 stakeType, uint64 balance, uint64 registerEpoch, uint64 exitEpoch, EdOnBN254Point schnorrVK)
+            function numPendingExits() external view returns (uint64)
+            function numPendingRegistrations() external view returns (uint64)
+            function register(G2Point blsVK, EdOnBN254Point schnorrVK, uint64 amount, uint8 stakeType, G1Point blsSig, uint64 validUntilEpoch) external
+            function requestExit(G2Point blsVK) external
+            function tokenAddress() external view returns (address)
+            function totalKeys() external view returns (uint32)
+            function totalNativeStake() external view returns (uint256)
+            function totalRestakedStake() external view returns (uint256)
+            function totalStake() external view returns (uint256, uint256)
+            function totalVotingStake() external view returns (uint256)
+            function withdrawFunds(G2Point blsVK) external returns (uint64)
+            event Deposit(bytes32 blsVKhash, uint256 amount)
+            event Exit(bytes32 blsVKhash, uint64 exitEpoch)
+            event Registered(bytes32 blsVKhash, uint64 registerEpoch, uint8 stakeType, uint256 amountDeposited, G2Point blsVK, EdOnBN254Point schnorrVK)
+            error BLSSigVerificationFailed()
+            error ExitRequestInProgress()
+            error InvalidNextRegistrationEpoch(uint64, uint64)
+            error NodeAlreadyRegistered()
+            error PrematureDeposit()
+            error PrematureExit()
+            error PrematureWithdrawal()
+            error RestakingNotImplemented()
+            error Unauthenticated()
+        ]"#,
+        derives(serde::Deserialize, serde::Serialize)
+    );
+}
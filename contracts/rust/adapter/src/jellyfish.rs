@@ -357,6 +357,13 @@ impl From<ParsedPlonkProof> for contract_bindings::i_plonk_verifier::PlonkProof
     }
 }
 
+impl From<contract_bindings::i_plonk_verifier::PlonkProof> for ParsedPlonkProof {
+    fn from(p: contract_bindings::i_plonk_verifier::PlonkProof) -> Self {
+        // see the conversion in the other direction above
+        unsafe { std::mem::transmute(p) }
+    }
+}
+
 #[test]
 fn test_unsafe_plonk_proof_conversion() {
     use ethers::abi::AbiEncode;
@@ -366,6 +373,8 @@ fn test_unsafe_plonk_proof_conversion() {
         let proof: contract_bindings::i_plonk_verifier::PlonkProof = parsed_proof.clone().into();
         // this test abi.encode hex string of both struct which includes the types and values
         assert_eq!(parsed_proof.encode_hex(), proof.encode_hex());
+        let parsed_back: ParsedPlonkProof = proof.into();
+        assert_eq!(parsed_proof.encode_hex(), parsed_back.encode_hex());
     }
 }
 
@@ -2,6 +2,7 @@
 
 pub mod jellyfish;
 pub mod light_client;
+pub mod stake_table;
 
 // Archived, legacy helpers and tests, to be removed soon. not included, reference/read only
 // mod archived
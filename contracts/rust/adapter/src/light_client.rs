@@ -1,13 +1,19 @@
 //! Helpers and test mocks for Light Client logic
 
+use anyhow::{ensure, Context};
 use ark_std::str::FromStr;
+use contract_bindings::light_client::LightClient;
 use diff_test_bn254::{field_to_u256, u256_to_field};
+use espresso_light_client::verify_block_merkle_root;
+pub use espresso_light_client::{BlockMerkleCommitment, BlockMerkleProof, BlockMerkleTree};
 use ethers::{
     abi::AbiDecode,
     prelude::{AbiError, EthAbiCodec, EthAbiType},
+    providers::Middleware,
     types::U256,
 };
 use hotshot_types::light_client::{CircuitField, LightClientState, PublicInput};
+use jf_merkle_tree::{MerkleCommitment, MerkleTreeScheme};
 
 /// Intermediate representations for `LightClientState` in Solidity
 #[derive(Clone, Debug, EthAbiType, EthAbiCodec, PartialEq)]
@@ -23,6 +29,42 @@ pub struct ParsedLightClientState {
 }
 
 impl ParsedLightClientState {
+    /// The state to submit to the contract for `state`, which commits to a stake table with quorum
+    /// threshold `threshold`.
+    pub fn new(state: &LightClientState, threshold: U256) -> Self {
+        Self {
+            view_num: state.view_number as u64,
+            block_height: state.block_height as u64,
+            block_comm_root: field_to_u256(state.block_comm_root),
+            fee_ledger_comm: field_to_u256(state.fee_ledger_comm),
+            bls_key_comm: field_to_u256(state.stake_table_comm.0),
+            schnorr_key_comm: field_to_u256(state.stake_table_comm.1),
+            amount_comm: field_to_u256(state.stake_table_comm.2),
+            threshold,
+        }
+    }
+
+    /// The public input the contract verifies a proof of this state against.
+    ///
+    /// `voting` is the contract's voting state, whose stake table signed this one and whose
+    /// threshold the signatures must reach.
+    pub fn public_input(&self, voting: &Self) -> PublicInput {
+        let fields = vec![
+            u256_to_field(voting.threshold),
+            CircuitField::from(self.view_num),
+            CircuitField::from(self.block_height),
+            u256_to_field(self.block_comm_root),
+            u256_to_field(self.fee_ledger_comm),
+            u256_to_field(self.bls_key_comm),
+            u256_to_field(self.schnorr_key_comm),
+            u256_to_field(self.amount_comm),
+            u256_to_field(voting.bls_key_comm),
+            u256_to_field(voting.schnorr_key_comm),
+            u256_to_field(voting.amount_comm),
+        ];
+        fields.into()
+    }
+
     /// Return a dummy new genesis that will pass constructor/initializer sanity checks
     /// in the contract.
     ///
@@ -80,22 +122,6 @@ impl From<contract_bindings::light_client::LightClientState> for ParsedLightClie
     }
 }
 
-impl From<ParsedLightClientState> for PublicInput {
-    fn from(s: ParsedLightClientState) -> Self {
-        let fields = vec![
-            u256_to_field(s.threshold),
-            CircuitField::from(s.view_num),
-            CircuitField::from(s.block_height),
-            u256_to_field(s.block_comm_root),
-            u256_to_field(s.fee_ledger_comm),
-            u256_to_field(s.bls_key_comm),
-            u256_to_field(s.schnorr_key_comm),
-            u256_to_field(s.amount_comm),
-        ];
-        Self::from(fields)
-    }
-}
-
 impl From<(u64, u64, U256, U256, U256, U256, U256, U256)> for ParsedLightClientState {
     fn from(s: (u64, u64, U256, U256, U256, U256, U256, U256)) -> Self {
         Self {
@@ -133,3 +159,186 @@ impl From<ParsedLightClientState> for contract_bindings::light_client::LightClie
         unsafe { std::mem::transmute(s) }
     }
 }
+
+/// Verify a proof of the header commitment of the block at `height`, against a light client state
+/// read from the contract.
+///
+/// The contract only stores block commitments for the heights at which it was updated. Any earlier
+/// block can be proven against the block Merkle tree of a stored state: `root` is the
+/// `block_merkle_tree_root` of the header at the state's height, which contains every block before
+/// it. Returns the commitment of the header at `height`.
+pub fn verify_block_merkle_proof(
+    state: &ParsedLightClientState,
+    root: &BlockMerkleCommitment,
+    height: u64,
+    proof: &BlockMerkleProof,
+) -> anyhow::Result<[u8; 32]> {
+    ensure!(
+        height < state.block_height,
+        "block {height} is not before light client state at height {}",
+        state.block_height
+    );
+    verify_block_merkle_root(state.clone(), root)?;
+    ensure!(
+        BlockMerkleTree::verify(root.digest(), height, proof)?.is_ok(),
+        "invalid proof for block {height}"
+    );
+    proof
+        .elem()
+        .copied()
+        .context("proof is missing header commitment")
+}
+
+/// The L1 block numbers at which the light client was updated, as recorded in the `LightClient`
+/// contract's `stateUpdateBlockNumbers`.
+///
+/// This lets rollups decide whether to enter escape hatch mode with the same logic as the contract's
+/// `lagOverEscapeHatchThreshold`, without calling the contract for every L1 block they process. Only
+/// the most recent updates are kept, as only those are needed for recent L1 blocks.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateUpdateHistory {
+    /// Total number of updates recorded by the contract.
+    count: u64,
+    /// The L1 block numbers of the most recent updates, oldest first.
+    recent: Vec<u64>,
+}
+
+impl StateUpdateHistory {
+    /// The history consisting of all of `block_numbers`.
+    pub fn new(block_numbers: Vec<u64>) -> Self {
+        Self {
+            count: block_numbers.len() as u64,
+            recent: block_numbers,
+        }
+    }
+
+    /// Read the updates from the contract needed to compute the lag at `block_number`.
+    ///
+    /// This uses `getStateUpdateBlockNumbersCount` and `stateUpdateBlockNumbers`, reading updates
+    /// backwards from the latest one until the last update at or before `block_number`.
+    pub async fn fetch<M: Middleware + 'static>(
+        contract: &LightClient<M>,
+        block_number: u64,
+    ) -> anyhow::Result<Self> {
+        let count = contract
+            .get_state_update_block_numbers_count()
+            .call()
+            .await
+            .context("reading number of state updates")?
+            .as_u64();
+        let mut recent = vec![];
+        // The contract never considers the first update.
+        for i in (1..count).rev() {
+            let update = contract
+                .state_update_block_numbers(i.into())
+                .call()
+                .await
+                .context(format!("reading state update {i}"))?
+                .as_u64();
+            recent.push(update);
+            if update <= block_number {
+                break;
+            }
+        }
+        recent.reverse();
+        Ok(Self { count, recent })
+    }
+
+    /// Total number of updates recorded by the contract.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The number of L1 blocks between `block_number` and the last update at or before it.
+    ///
+    /// Returns `None` if there is not enough history to tell, in which case the contract reverts with
+    /// `InsufficientSnapshotHistory`: when fewer than three updates have been recorded, or when no
+    /// update at or before `block_number` is found. Like the contract, this never considers the
+    /// first update (index 0), which happens while the light client is being initialized. The
+    /// contract also reverts if `block_number` is in the future; it is up to the caller not to ask
+    /// about such blocks.
+    pub fn lag(&self, block_number: u64) -> Option<u64> {
+        if self.count < 3 {
+            return None;
+        }
+        let first = self.count - self.recent.len() as u64;
+        self.recent
+            .iter()
+            .enumerate()
+            .rev()
+            .take_while(|(i, _)| first + *i as u64 >= 1)
+            .find(|(_, update)| **update <= block_number)
+            .map(|(_, update)| block_number - update)
+    }
+
+    /// Whether more than `threshold` L1 blocks passed without an update before `block_number`.
+    ///
+    /// This is the result of the contract's `lagOverEscapeHatchThreshold(block_number, threshold)`,
+    /// or `None` if the contract would revert (see [`lag`](Self::lag)). Rollups should enter escape
+    /// hatch mode if this is `true`.
+    pub fn lag_over_escape_hatch_threshold(
+        &self,
+        block_number: u64,
+        threshold: u64,
+    ) -> Option<bool> {
+        self.lag(block_number).map(|lag| lag > threshold)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DELAY_THRESHOLD: u64 = 6;
+
+    #[test]
+    fn test_lag_over_escape_hatch_threshold() {
+        // The same updates as the contract's tests.
+        let history = StateUpdateHistory::new(vec![1, 4, 7, 18, 21]);
+
+        // The first update is never considered, so blocks before the second one can't be checked.
+        assert_eq!(history.lag(3), None);
+        assert_eq!(history.lag(4), Some(0));
+        assert_eq!(
+            history.lag_over_escape_hatch_threshold(7, DELAY_THRESHOLD),
+            Some(false)
+        );
+        // A block which should have been preceded by an update.
+        assert_eq!(history.lag(15), Some(8));
+        assert_eq!(
+            history.lag_over_escape_hatch_threshold(15, DELAY_THRESHOLD),
+            Some(true)
+        );
+        // Blocks after the last update.
+        assert_eq!(
+            history.lag_over_escape_hatch_threshold(24, DELAY_THRESHOLD),
+            Some(false)
+        );
+        assert_eq!(
+            history.lag_over_escape_hatch_threshold(30, DELAY_THRESHOLD),
+            Some(true)
+        );
+
+        // Not enough updates.
+        for updates in [vec![], vec![1], vec![1, 12]] {
+            assert_eq!(StateUpdateHistory::new(updates).lag(20), None);
+        }
+        assert_eq!(StateUpdateHistory::new(vec![1, 4, 7]).lag(3), None);
+        assert_eq!(StateUpdateHistory::new(vec![1, 4, 7]).lag(16), Some(9));
+    }
+
+    #[test]
+    fn test_recent_state_updates() {
+        // Only the most recent updates are needed for recent blocks.
+        let history = StateUpdateHistory {
+            count: 5,
+            recent: vec![18, 21],
+        };
+        assert_eq!(
+            history.lag(30),
+            StateUpdateHistory::new(vec![1, 4, 7, 18, 21]).lag(30)
+        );
+        assert_eq!(history.lag(19), Some(1));
+        assert_eq!(history.lag(17), None);
+    }
+}
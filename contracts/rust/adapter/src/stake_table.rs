@@ -0,0 +1,75 @@
+//! Helpers for converting keys between HotShot and the `StakeTable` contract
+
+use anyhow::{Context, Result};
+use ark_bn254::{Fq2, G2Affine};
+use ark_ec::CurveGroup;
+use ark_ed_on_bn254::EdwardsAffine;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use contract_bindings::stake_table::{EdOnBN254Point, G1Point, G2Point};
+use diff_test_bn254::{field_to_u256, u256_to_field};
+use ethers::{abi::AbiEncode, types::Address};
+use hotshot_types::{
+    light_client::StateVerKey,
+    signature_key::{BLSPrivKey, BLSPubKey},
+    traits::signature_key::SignatureKey,
+};
+
+/// Convert a BLS verification key into the `G2Point` the contract identifies a node by.
+pub fn bls_key_to_g2_point(key: &BLSPubKey) -> G2Point {
+    let point: G2Affine = key.to_affine();
+    G2Point {
+        x_0: field_to_u256(point.x.c0),
+        x_1: field_to_u256(point.x.c1),
+        y_0: field_to_u256(point.y.c0),
+        y_1: field_to_u256(point.y.c1),
+    }
+}
+
+/// Convert a `G2Point` from the contract into a BLS verification key.
+///
+/// Fails if the point is not a valid key.
+pub fn g2_point_to_bls_key(point: &G2Point) -> Result<BLSPubKey> {
+    let point = G2Affine::new_unchecked(
+        Fq2::new(u256_to_field(point.x_0), u256_to_field(point.x_1)),
+        Fq2::new(u256_to_field(point.y_0), u256_to_field(point.y_1)),
+    );
+    // Keys serialize exactly like the points they wrap, so round-tripping through the
+    // serialization both converts the point and checks that it is a valid key.
+    reinterpret(&point).context("invalid BLS key")
+}
+
+/// Convert a Schnorr verification key into the `EdOnBN254Point` stored in the contract.
+pub fn state_key_to_point(key: &StateVerKey) -> EdOnBN254Point {
+    let point: EdwardsAffine = key.to_affine();
+    EdOnBN254Point {
+        x: field_to_u256(point.x),
+        y: field_to_u256(point.y),
+    }
+}
+
+/// Convert an `EdOnBN254Point` from the contract into a Schnorr verification key.
+///
+/// Fails if the point is not a valid key.
+pub fn point_to_state_key(point: &EdOnBN254Point) -> Result<StateVerKey> {
+    let point = EdwardsAffine::new_unchecked(u256_to_field(point.x), u256_to_field(point.y));
+    reinterpret(&point).context("invalid Schnorr key")
+}
+
+/// Sign `account` with a BLS key, proving to the contract that `account` controls the key.
+///
+/// The contract requires this signature when registering a node, to prevent rogue key attacks.
+pub fn sign_account(key: &BLSPrivKey, account: Address) -> Result<G1Point> {
+    // The contract checks a signature on `abi.encode(msg.sender)`.
+    let sig = BLSPubKey::sign(key, &AbiEncode::encode(account))?;
+    let point = sig.sigma.into_affine();
+    Ok(G1Point {
+        x: field_to_u256(point.x),
+        y: field_to_u256(point.y),
+    })
+}
+
+fn reinterpret<T: CanonicalSerialize, U: CanonicalDeserialize>(t: &T) -> Result<U> {
+    let mut bytes = vec![];
+    t.serialize_compressed(&mut bytes)?;
+    Ok(U::deserialize_compressed(&*bytes)?)
+}
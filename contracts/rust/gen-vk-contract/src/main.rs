@@ -4,38 +4,73 @@
 
 use std::{fs::OpenOptions, io::Write, path::PathBuf, process::Command};
 
+use clap::Parser;
 use hotshot_contract_adapter::jellyfish::ParsedVerifyingKey;
 use hotshot_stake_table::config::STAKE_TABLE_CAPACITY;
-use jf_pcs::prelude::UnivariateUniversalParams;
+use hotshot_state_prover::keys::ProverKeys;
+
+/// Generate the Solidity library with the verifying key of the state update circuit.
+///
+/// Each stake table capacity has its own circuit and verifying key. The library for the default
+/// capacity is `LightClientStateUpdateVK`, which the `LightClient` contract imports. The library
+/// for any other capacity is named after it, e.g. `LightClientStateUpdateVK_200`, so that it never
+/// replaces the key linked into the deployed contract by accident. To support a larger stake table,
+/// generate the library for the new capacity and upgrade the `LightClient` contract to an
+/// implementation importing it.
+#[derive(Debug, Parser)]
+struct Options {
+    /// Stake table capacity of the circuit.
+    ///
+    /// Ignored if `--keys` is given.
+    #[clap(
+        short,
+        long,
+        env = "ESPRESSO_SEQUENCER_STAKE_TABLE_CAPACITY",
+        default_value_t = STAKE_TABLE_CAPACITY
+    )]
+    stake_table_capacity: usize,
+
+    /// Take the verifying key from a file written by `prover-key generate`, instead of generating
+    /// it.
+    ///
+    /// This guarantees that the contract verifies proofs made with that key file.
+    #[clap(long)]
+    keys: Option<PathBuf>,
+
+    /// File to write the library to.
+    ///
+    /// Defaults to `contracts/src/libraries/<library name>.sol`.
+    #[clap(short, long)]
+    out: Option<PathBuf>,
+}
 
 fn main() {
-    let srs = {
-        // load SRS from Aztec's ceremony
-        let srs = ark_srs::kzg10::aztec20::setup(2u64.pow(20) as usize + 2)
-            .expect("Aztec SRS fail to load");
-        // convert to Jellyfish type
-        // TODO: (alex) use constructor instead https://github.com/EspressoSystems/jellyfish/issues/440
-        UnivariateUniversalParams {
-            powers_of_g: srs.powers_of_g,
-            h: srs.h,
-            beta_h: srs.beta_h,
-            powers_of_h: vec![srs.h, srs.beta_h],
-        }
+    let opt = Options::parse();
+    let keys = match &opt.keys {
+        Some(path) => ProverKeys::load(path).expect("Fail to load prover keys").0,
+        None => ProverKeys::generate(opt.stake_table_capacity),
     };
-    let (_, vk) = hotshot_state_prover::preprocess(&srs, STAKE_TABLE_CAPACITY)
-        .expect("Circuit preprocess failed");
-    let vk: ParsedVerifyingKey = vk.into();
+    let stake_table_capacity = keys.stake_table_capacity;
+    let vk: ParsedVerifyingKey = keys.verifying_key.into();
 
     // calculate the path to solidity file
-    let contract_name = "LightClientStateUpdateVK";
-    let mut path = PathBuf::new();
-    path.push(env!("CARGO_MANIFEST_DIR"));
-    path.pop();
-    path.pop();
-    path.push("src/libraries");
-    path.push(contract_name);
-    path.set_extension("sol");
+    let contract_name = if stake_table_capacity == STAKE_TABLE_CAPACITY {
+        "LightClientStateUpdateVK".to_string()
+    } else {
+        format!("LightClientStateUpdateVK_{stake_table_capacity}")
+    };
+    let path = opt.out.unwrap_or_else(|| {
+        let mut path = PathBuf::new();
+        path.push(env!("CARGO_MANIFEST_DIR"));
+        path.pop();
+        path.pop();
+        path.push("src/libraries");
+        path.push(&contract_name);
+        path.set_extension("sol");
+        path
+    });
     println!("Path:{:?}", path.to_str());
+    println!("Stake table capacity: {stake_table_capacity}");
 
     // overwrite the file
     let mut file = OpenOptions::new()
@@ -56,6 +91,7 @@ fn main() {
     // You should have received a copy of the GNU General Public License along with this program. If not, see <https://www.gnu.org/licenses/>.
 
     // NOTE: DO NOT MODIFY! GENERATED BY SCRIPT VIA `cargo run --bin gen-vk-contract --release`.
+    // Verifying key for stake table capacity {stake_table_capacity}.
     pragma solidity ^0.8.0;
 
     import {{ IPlonkVerifier }} from \"../interfaces/IPlonkVerifier.sol\";
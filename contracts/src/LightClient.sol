@@ -57,6 +57,14 @@ contract LightClient is Initializable, OwnableUpgradeable, UUPSUpgradeable {
     /// @notice Finalized HotShot's light client state index
     uint32 internal finalizedState;
 
+    /// @notice Index of the state committing to the stake table used in current voting
+    /// @dev These are constants rather than storage variables, so that they do not change the
+    /// storage layout of existing deployments.
+    uint32 internal constant VOTING_STATE = 2;
+
+    /// @notice Index of the state committing to the stake table frozen for change
+    uint32 internal constant FROZEN_STATE = 3;
+
     // === Storage ===
 
     /// @notice current (finalized) epoch number
@@ -170,6 +178,17 @@ contract LightClient is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         _initializeState(genesis, numBlocksPerEpoch);
     }
 
+    /// @notice Seed the voting and frozen states of a proxy initialized by an implementation which
+    /// did not keep them. Call this when upgrading such a proxy, for example through
+    /// `upgradeToAndCall`.
+    /// @dev Earlier implementations only kept the commitments and thresholds of the voting and
+    /// frozen stake tables. Each state is recovered from the genesis or finalized state committing
+    /// to the same stake table, and the call reverts if neither does.
+    function initializeVotingStates() public reinitializer(2) onlyOwner {
+        states[VOTING_STATE] = _stateForStakeTable(votingStakeTableCommitment, votingThreshold);
+        states[FROZEN_STATE] = _stateForStakeTable(frozenStakeTableCommitment, frozenThreshold);
+    }
+
     /// @notice Use this to get the implementation contract version
     function getVersion()
         public
@@ -202,6 +221,8 @@ contract LightClient is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         }
         states[genesisState] = genesis;
         states[finalizedState] = genesis;
+        states[VOTING_STATE] = genesis;
+        states[FROZEN_STATE] = genesis;
 
         currentEpoch = 0;
 
@@ -231,7 +252,7 @@ contract LightClient is Initializable, OwnableUpgradeable, UUPSUpgradeable {
     /// can call this function
     ///
     /// @notice While `newState.stakeTable*` refers to the (possibly) new stake table states,
-    /// the entire `newState` needs to be signed by stakers in `finalizedState`
+    /// the entire `newState` needs to be signed by stakers in the voting stake table
     /// @param newState new light client state
     /// @param proof PlonkProof
     function newFinalizedState(
@@ -296,24 +317,45 @@ contract LightClient is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         return states[finalizedState];
     }
 
-    /// @notice Verify the Plonk proof, marked as `virtual` for easier testing as we can swap VK
-    /// used in inherited contracts.
+    /// @dev Simple getter function for the state committing to the voting stake table, whose
+    /// threshold is `votingThreshold`
+    function getVotingState() public view returns (LightClientState memory) {
+        return states[VOTING_STATE];
+    }
+
+    /// @dev Simple getter function for the state committing to the frozen stake table, which
+    /// starts voting at the next epoch change
+    function getFrozenState() public view returns (LightClientState memory) {
+        return states[FROZEN_STATE];
+    }
+
+    /// @notice The verifying key proofs are checked against, marked as `virtual` for easier
+    /// testing as we can swap VK used in inherited contracts.
+    /// @dev Provers use this to check that their proving key matches the deployed verifier.
+    function getVk() public pure virtual returns (IPlonkVerifier.VerifyingKey memory) {
+        return VkLib.getVk();
+    }
+
+    /// @notice Verify the Plonk proof
     function verifyProof(LightClientState memory state, IPlonkVerifier.PlonkProof memory proof)
         internal
         virtual
     {
-        IPlonkVerifier.VerifyingKey memory vk = VkLib.getVk();
+        IPlonkVerifier.VerifyingKey memory vk = getVk();
 
         // Prepare the public input
-        uint256[] memory publicInput = new uint256[](8);
+        uint256[] memory publicInput = new uint256[](11);
         publicInput[0] = votingThreshold;
         publicInput[1] = uint256(state.viewNum);
         publicInput[2] = uint256(state.blockHeight);
         publicInput[3] = BN254.ScalarField.unwrap(state.blockCommRoot);
         publicInput[4] = BN254.ScalarField.unwrap(state.feeLedgerComm);
-        publicInput[5] = BN254.ScalarField.unwrap(states[finalizedState].stakeTableBlsKeyComm);
-        publicInput[6] = BN254.ScalarField.unwrap(states[finalizedState].stakeTableSchnorrKeyComm);
-        publicInput[7] = BN254.ScalarField.unwrap(states[finalizedState].stakeTableAmountComm);
+        publicInput[5] = BN254.ScalarField.unwrap(state.stakeTableBlsKeyComm);
+        publicInput[6] = BN254.ScalarField.unwrap(state.stakeTableSchnorrKeyComm);
+        publicInput[7] = BN254.ScalarField.unwrap(state.stakeTableAmountComm);
+        publicInput[8] = BN254.ScalarField.unwrap(states[VOTING_STATE].stakeTableBlsKeyComm);
+        publicInput[9] = BN254.ScalarField.unwrap(states[VOTING_STATE].stakeTableSchnorrKeyComm);
+        publicInput[10] = BN254.ScalarField.unwrap(states[VOTING_STATE].stakeTableAmountComm);
 
         if (!PlonkVerifier.verify(vk, publicInput, proof)) {
             revert InvalidProof();
@@ -330,10 +372,32 @@ contract LightClient is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         votingThreshold = frozenThreshold;
         frozenThreshold = states[finalizedState].threshold;
 
+        states[VOTING_STATE] = states[FROZEN_STATE];
+        states[FROZEN_STATE] = states[finalizedState];
+
         currentEpoch += 1;
         emit EpochChanged(currentEpoch);
     }
 
+    /// @notice Find the genesis or finalized state committing to the stake table with commitment
+    /// `stakeTableComm` and quorum threshold `threshold`
+    function _stateForStakeTable(bytes32 stakeTableComm, uint256 threshold)
+        private
+        view
+        returns (LightClientState memory)
+    {
+        LightClientState memory genesis = states[genesisState];
+        if (computeStakeTableComm(genesis) == stakeTableComm && genesis.threshold == threshold) {
+            return genesis;
+        }
+        LightClientState memory finalized = states[finalizedState];
+        if (computeStakeTableComm(finalized) == stakeTableComm && finalized.threshold == threshold)
+        {
+            return finalized;
+        }
+        revert InvalidArgs();
+    }
+
     /// @notice Given the light client state, compute the short commitment of the stake table
     function computeStakeTableComm(LightClientState memory state) public pure returns (bytes32) {
         return keccak256(
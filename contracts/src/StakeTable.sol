@@ -269,7 +269,7 @@ contract StakeTable is AbstractStakeTable {
             SafeTransferLib.safeTransferFrom(ERC20(tokenAddress), msg.sender, address(this), amount);
         } // Other case will be implemented when we support restaking
 
-        emit Registered(key, registerEpoch, stakeType, amount);
+        emit Registered(key, registerEpoch, stakeType, amount, blsVK, schnorrVK);
     }
 
     /// @notice Deposit more stakes to registered keys
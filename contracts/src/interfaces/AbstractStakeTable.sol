@@ -28,8 +28,15 @@ abstract contract AbstractStakeTable {
     /// @param registerEpoch epoch when the registration becomes effective.
     /// @param stakeType native or restake token.
     /// @param amountDeposited amount deposited when registering the new node.
+    /// @param blsVK the registered BLS public key, since the stake table only stores its hash.
+    /// @param schnorrVK the Schnorr public key associated with the node.
     event Registered(
-        bytes32 blsVKhash, uint64 registerEpoch, StakeType stakeType, uint256 amountDeposited
+        bytes32 blsVKhash,
+        uint64 registerEpoch,
+        StakeType stakeType,
+        uint256 amountDeposited,
+        BN254.G2Point blsVK,
+        EdOnBN254.EdOnBN254Point schnorrVK
     );
 
     /// @notice Signals an exit request has been granted.
@@ -95,14 +95,9 @@ library PlonkVerifier {
     ) internal view returns (bool) {
         _validateProof(proof);
 
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[0]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[1]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[2]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[3]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[4]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[5]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[6]));
-        BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[7]));
+        for (uint256 i = 0; i < publicInput.length; i++) {
+            BN254.validateScalarField(BN254.ScalarField.wrap(publicInput[i]));
+        }
 
         PcsInfo memory pcsInfo = _preparePcsInfo(verifyingKey, publicInput, proof);
         return _verifyOpeningProofs(pcsInfo);
@@ -150,17 +150,10 @@ library Transcript {
         );
 
         // public inputs
-        self.transcript = abi.encodePacked(
-            self.transcript,
-            Utils.reverseEndianness(publicInput[0]),
-            Utils.reverseEndianness(publicInput[1]),
-            Utils.reverseEndianness(publicInput[2]),
-            Utils.reverseEndianness(publicInput[3]),
-            Utils.reverseEndianness(publicInput[4]),
-            Utils.reverseEndianness(publicInput[5]),
-            Utils.reverseEndianness(publicInput[6]),
-            Utils.reverseEndianness(publicInput[7])
-        );
+        for (uint256 i = 0; i < publicInput.length; i++) {
+            self.transcript =
+                abi.encodePacked(self.transcript, Utils.reverseEndianness(publicInput[i]));
+        }
     }
 
     /// @dev Append the proof to the transcript. Only used for test purposes.
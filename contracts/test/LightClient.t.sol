@@ -378,6 +378,13 @@ contract LightClient_newFinalizedState_Test is LightClientCommonTest {
                 assertEq(lc.votingThreshold(), genesis.threshold);
                 assertEq(lc.frozenThreshold(), genesis.threshold);
             }
+            // proofs are checked against the full commitment of the voting stake table
+            LC.LightClientState memory votingState = lc.getVotingState();
+            assertEq(lc.computeStakeTableComm(votingState), lc.votingStakeTableCommitment());
+            assertEq(votingState.threshold, lc.votingThreshold());
+            LC.LightClientState memory frozenState = lc.getFrozenState();
+            assertEq(lc.computeStakeTableComm(frozenState), lc.frozenStakeTableCommitment());
+            assertEq(frozenState.threshold, lc.frozenThreshold());
         }
     }
 
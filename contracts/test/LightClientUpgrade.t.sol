@@ -3,6 +3,9 @@ pragma solidity ^0.8.0;
 pragma experimental ABIEncoderV2;
 
 import { Test } /*, console2*/ from "forge-std/Test.sol";
+import { Initializable } from "@openzeppelin/contracts-upgradeable/proxy/utils/Initializable.sol";
+import { OwnableUpgradeable } from
+    "@openzeppelin/contracts-upgradeable/access/OwnableUpgradeable.sol";
 import { LightClient as LCV1 } from "../src/LightClient.sol";
 import { LightClientV2 as LCV2 } from "../test/LightClientV2.sol";
 import { DeployLightClientContractScript } from "../script/LightClient.s.sol";
@@ -83,6 +86,40 @@ contract LightClientUpgradeTest is Test {
         assertEq(patchV2, 0);
     }
 
+    // a proxy initialized before the voting and frozen states were kept can seed them on upgrade
+    function testUpgradeInitializesVotingStates() public {
+        // Clear the voting and frozen states, as an earlier implementation would have left them.
+        // `states` is in storage slot 5, and each state takes up 7 slots.
+        for (uint256 index = 2; index < 4; index++) {
+            uint256 base = uint256(keccak256(abi.encode(index, uint256(5))));
+            for (uint256 i = 0; i < 7; i++) {
+                vm.store(proxy, bytes32(base + i), bytes32(0));
+            }
+        }
+        assertEq(lcV1Proxy.getVotingState().threshold, 0);
+        assertEq(lcV1Proxy.getFrozenState().threshold, 0);
+
+        // Only the owner can seed the states.
+        address attacker = makeAddr("attacker");
+        vm.prank(attacker);
+        vm.expectRevert(
+            abi.encodeWithSelector(OwnableUpgradeable.OwnableUnauthorizedAccount.selector, attacker)
+        );
+        lcV1Proxy.initializeVotingStates();
+
+        vm.prank(admin);
+        lcV1Proxy.upgradeToAndCall(
+            address(new LCV1()), abi.encodeCall(LCV1.initializeVotingStates, ())
+        );
+        assertEq(abi.encode(lcV1Proxy.getVotingState()), abi.encode(stateV1));
+        assertEq(abi.encode(lcV1Proxy.getFrozenState()), abi.encode(stateV1));
+
+        // The states can only be seeded once.
+        vm.prank(admin);
+        vm.expectRevert(Initializable.InvalidInitialization.selector);
+        lcV1Proxy.initializeVotingStates();
+    }
+
     function testMaliciousUpgradeFails() public {
         address attacker = makeAddr("attacker");
 
@@ -38,6 +38,12 @@ contract LightClientV2 is Initializable, OwnableUpgradeable, UUPSUpgradeable {
     /// @notice Finalized HotShot's light client state index
     uint32 internal finalizedState;
 
+    /// @notice Index of the state committing to the stake table used in current voting
+    uint32 internal constant VOTING_STATE = 2;
+
+    /// @notice Index of the state committing to the stake table frozen for change
+    uint32 internal constant FROZEN_STATE = 3;
+
     // === Storage ===
     //
     /// @notice current (finalized) epoch number
@@ -140,6 +146,8 @@ contract LightClientV2 is Initializable, OwnableUpgradeable, UUPSUpgradeable {
 
         states[genesisState] = genesis;
         states[finalizedState] = genesis;
+        states[VOTING_STATE] = genesis;
+        states[FROZEN_STATE] = genesis;
         currentEpoch = 0;
 
         blocksPerEpoch = numBlockPerEpoch;
@@ -215,7 +223,7 @@ contract LightClientV2 is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         view
         returns (uint256[] memory)
     {
-        uint256[] memory publicInput = new uint256[](8);
+        uint256[] memory publicInput = new uint256[](11);
         publicInput[0] = votingThreshold;
         publicInput[1] = uint256(state.viewNum);
         publicInput[2] = uint256(state.blockHeight);
@@ -224,6 +232,9 @@ contract LightClientV2 is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         publicInput[5] = BN254.ScalarField.unwrap(state.stakeTableBlsKeyComm);
         publicInput[6] = BN254.ScalarField.unwrap(state.stakeTableSchnorrKeyComm);
         publicInput[7] = BN254.ScalarField.unwrap(state.stakeTableAmountComm);
+        publicInput[8] = BN254.ScalarField.unwrap(states[VOTING_STATE].stakeTableBlsKeyComm);
+        publicInput[9] = BN254.ScalarField.unwrap(states[VOTING_STATE].stakeTableSchnorrKeyComm);
+        publicInput[10] = BN254.ScalarField.unwrap(states[VOTING_STATE].stakeTableAmountComm);
         return publicInput;
     }
 
@@ -251,6 +262,9 @@ contract LightClientV2 is Initializable, OwnableUpgradeable, UUPSUpgradeable {
         votingThreshold = frozenThreshold;
         frozenThreshold = getFinalizedState().threshold;
 
+        states[VOTING_STATE] = states[FROZEN_STATE];
+        states[FROZEN_STATE] = getFinalizedState();
+
         currentEpoch += 1;
         emit EpochChanged(currentEpoch);
     }
@@ -334,7 +334,12 @@ contract StakeTable_Test is StakeTableCommonTest {
         // Check event is emitted after calling successfully `register`
         vm.expectEmit(true, true, true, true, address(stakeTable));
         emit AbstractStakeTable.Registered(
-            stakeTable._hashBlsKey(blsVK), node.registerEpoch, node.stakeType, node.balance
+            stakeTable._hashBlsKey(blsVK),
+            node.registerEpoch,
+            node.stakeType,
+            node.balance,
+            blsVK,
+            schnorrVK
         );
         vm.prank(exampleTokenCreator);
         stakeTable.register(
@@ -0,0 +1,419 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, ensure, Context};
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use contract_bindings::light_client::{LightClient, NewFinalizedStateCall};
+use es_version::SequencerVersion;
+use ethers::{
+    abi::AbiDecode,
+    contract::LogMeta,
+    providers::{Http, Middleware, Provider},
+    types::{Address, U256},
+    utils::keccak256,
+};
+use hotshot_contract_adapter::{
+    jellyfish::{field_to_u256, ParsedPlonkProof, ParsedVerifyingKey},
+    light_client::{
+        verify_block_merkle_proof, BlockMerkleCommitment, BlockMerkleProof, ParsedLightClientState,
+    },
+};
+use hotshot_stake_table::{
+    config::STAKE_TABLE_CAPACITY,
+    vec_based::{config::FieldType, StakeTable},
+};
+use hotshot_state_prover::{
+    keys::{check_verifying_key, ProverKeyRegistry, ProverKeys},
+    service::fetch_stake_table_from_sequencer,
+    snark::{self, Proof, VerifyingKey},
+};
+use hotshot_types::{
+    light_client::{CircuitField, LightClientState, StateSignaturesBundle, StateVerKey},
+    signature_key::BLSPubKey,
+    traits::stake_table::{SnapshotVersion, StakeTableScheme},
+};
+use jf_signature::constants::CS_ID_SCHNORR;
+use serde::Deserialize;
+use surf_disco::Client;
+use tide_disco::error::ServerError;
+use url::Url;
+
+/// Check the state finalized by the light client contract against the rest of the network.
+///
+/// This fetches the finalized state from L1 and the transaction which submitted it, and checks
+/// that:
+/// * the PLONK proof submitted with the state is valid for the given verifying key
+/// * the sequencer's stake table matches the stake table committed to by the state, and the
+///   contract's voting stake table
+/// * the block Merkle root of the sequencer's header at the same height matches the state
+/// * the state and signatures held by the relay server match the state
+///
+/// Every check is reported, and the command fails if any of them does.
+#[derive(Debug, Parser)]
+struct Options {
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_L1_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    l1_provider: Url,
+
+    /// Address of LightClient contract on layer 1.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_LIGHTCLIENT_ADDRESS")]
+    light_client_address: Address,
+
+    /// URL of a sequencer node providing the HotShot config and the block state API.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_URL",
+        default_value = "http://localhost:24000"
+    )]
+    sequencer_url: Url,
+
+    /// Url of the state relay server
+    #[clap(
+        long,
+        default_value = "http://localhost:8083",
+        env = "ESPRESSO_STATE_RELAY_SERVER_URL"
+    )]
+    relay_server: Url,
+
+    /// ABI-encoded verifying key to check the proof with, as printed by `prover-key inspect`.
+    #[clap(long, conflicts_with = "keys", required_unless_present = "keys")]
+    verifying_key: Option<ParsedVerifyingKey>,
+
+    /// Key file, or directory of key files, to take the verifying key from.
+    ///
+    /// The key for the capacity of the stake table which signed the finalized state is used.
+    #[clap(long)]
+    keys: Option<PathBuf>,
+
+    /// Stake table capacity of the circuit, if the verifying key is given directly.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_STAKE_TABLE_CAPACITY",
+        default_value_t = STAKE_TABLE_CAPACITY
+    )]
+    stake_table_capacity: usize,
+}
+
+/// The outcome of each check.
+#[derive(Debug, Default)]
+struct Report {
+    failures: usize,
+}
+
+impl Report {
+    fn check(&mut self, name: impl Display, res: anyhow::Result<()>) {
+        match res {
+            Ok(()) => println!("[ok] {name}"),
+            Err(err) => {
+                println!("[MISMATCH] {name}: {err:#}");
+                self.failures += 1;
+            }
+        }
+    }
+
+    fn skip(&self, name: impl Display, reason: impl Display) {
+        println!("[skipped] {name}: {reason}");
+    }
+}
+
+/// Response of the sequencer's `block-state/light-client` endpoint.
+#[derive(Debug, Deserialize)]
+struct BlockMerkleProofQueryData {
+    root: BlockMerkleCommitment,
+    proof: BlockMerkleProof,
+}
+
+type L1Provider = Provider<Http>;
+type SequencerStakeTable = StakeTable<BLSPubKey, StateVerKey, CircuitField>;
+type StakeTableComm = (CircuitField, CircuitField, CircuitField);
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    let mut report = Report::default();
+
+    let provider = Arc::new(L1Provider::try_from(opt.l1_provider.to_string())?);
+    let contract = LightClient::new(opt.light_client_address, provider.clone());
+
+    let finalized: ParsedLightClientState = contract
+        .get_finalized_state()
+        .call()
+        .await
+        .context("reading finalized state")?
+        .into();
+    let height = finalized.block_height;
+    println!(
+        "finalized state: view {}, block height {height}",
+        finalized.view_num
+    );
+
+    // Fetch the sequencer's stake table, padded to each capacity we have a key for.
+    let keys = opt.keys.as_deref().map(load_keys).transpose()?;
+    let capacities = match &keys {
+        Some(keys) => keys.capacities().collect(),
+        None => vec![opt.stake_table_capacity],
+    };
+    let mut stake_tables = vec![];
+    for capacity in capacities {
+        let st = fetch_stake_table_from_sequencer(&opt.sequencer_url, capacity).await?;
+        let comm = st.commitment(SnapshotVersion::LastEpochStart)?;
+        stake_tables.push((capacity, st, comm));
+    }
+
+    // The commitments derived from the sequencer's stake table must match the state, and the stake
+    // table the contract verifies the next update against.
+    report.check(
+        "stake table commitment",
+        check_stake_table_comm(
+            LightClientState::from(finalized.clone()).stake_table_comm,
+            &stake_tables,
+        ),
+    );
+    let voting = contract.voting_stake_table_commitment().call().await?;
+    report.check(
+        "voting stake table commitment",
+        check_voting_stake_table_comm(voting, &stake_tables),
+    );
+
+    // Find the update which finalized the state.
+    let updates = contract
+        .new_state_filter()
+        .from_block(0u64)
+        .query_with_meta()
+        .await
+        .context("fetching light client updates")?;
+    let Some(index) = updates
+        .iter()
+        .position(|(event, _)| event.block_height == height)
+    else {
+        ensure!(
+            ParsedLightClientState::from(contract.get_genesis_state().call().await?) == finalized,
+            "no update found for finalized state at height {height}"
+        );
+        println!("light client has not been updated since genesis");
+        return Ok(());
+    };
+    let update = submitted_update(&provider, &updates[index].1).await?;
+    report.check(
+        "submitted state",
+        if ParsedLightClientState::from(update.new_state.clone()) == finalized {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "transaction {:#x} submitted a different state",
+                updates[index].1.transaction_hash
+            ))
+        },
+    );
+    // The update was checked against the voting stake table, which has not changed since, as the
+    // contract only rotates it when a new state is finalized.
+    let voting_state: ParsedLightClientState = contract.get_voting_state().call().await?.into();
+    let signers_stake_table_comm = LightClientState::from(voting_state.clone()).stake_table_comm;
+
+    // Find the capacity of the stake table which signed the update, and with it the verifying key.
+    let signers = stake_tables
+        .iter()
+        .find(|(_, _, comm)| *comm == signers_stake_table_comm)
+        .map(|(capacity, st, _)| (*capacity, st));
+    match &signers {
+        Some((capacity, _)) => println!("signed by stake table with capacity {capacity}"),
+        None => println!("signing stake table is not the sequencer's current stake table"),
+    }
+    let vk = match (&opt.verifying_key, &keys, &signers) {
+        (Some(vk), _, _) => Some(VerifyingKey::from(vk.clone())),
+        (None, Some(keys), Some((capacity, _))) => {
+            let vk = keys
+                .get(*capacity)
+                .context("no key for signing stake table")?
+                .verifying_key
+                .clone();
+            report.check(
+                format!("verifying key for capacity {capacity}"),
+                check_verifying_key(&vk, &opt.l1_provider, opt.light_client_address).await,
+            );
+            Some(vk)
+        }
+        _ => None,
+    };
+
+    // Check the proof against the public input the contract builds.
+    match vk {
+        Some(vk) => {
+            let public_input = finalized.public_input(&voting_state);
+            let proof: Proof = ParsedPlonkProof::from(update.proof).into();
+            report.check(
+                "proof",
+                snark::verify(&vk, &public_input, &proof).context("invalid proof"),
+            );
+        }
+        None => report.skip("proof", "unknown stake table capacity"),
+    }
+
+    // Check the state against the sequencer's header at the same height.
+    if height == 0 {
+        report.skip("block Merkle root", "no blocks before height 0");
+    } else {
+        report.check(
+            "block Merkle root",
+            check_block_merkle_root(&opt.sequencer_url, &finalized).await,
+        );
+    }
+
+    // Check the state and signatures collected by the relay server.
+    let relay = Client::<ServerError, SequencerVersion>::new(opt.relay_server.clone());
+    match relay
+        .get::<StateSignaturesBundle>(&format!("/api/state/{height}"))
+        .send()
+        .await
+    {
+        Ok(bundle) => {
+            report.check(
+                "relay server state",
+                if bundle.state == LightClientState::from(finalized.clone()) {
+                    Ok(())
+                } else {
+                    Err(anyhow!("relay server has state {:?}", bundle.state))
+                },
+            );
+            match &signers {
+                Some((_, st)) => {
+                    let state_msg: [FieldType; 7] = (&bundle.state).into();
+                    let weight = st
+                        .try_iter(SnapshotVersion::LastEpochStart)?
+                        .filter(|(_, _, key)| {
+                            bundle.signatures.get(key).is_some_and(|sig| {
+                                key.verify(&state_msg, sig, CS_ID_SCHNORR).is_ok()
+                            })
+                        })
+                        .fold(U256::zero(), |weight, (_, stake, _)| weight + stake);
+                    let threshold = voting_state.threshold;
+                    println!("relay server signatures: weight {weight}, threshold {threshold}");
+                    report.check(
+                        "relay server signatures",
+                        if weight >= threshold {
+                            Ok(())
+                        } else {
+                            Err(anyhow!("signed weight {weight} below threshold"))
+                        },
+                    );
+                }
+                None => report.skip("relay server signatures", "unknown signing stake table"),
+            }
+        }
+        Err(err) => report.skip("relay server state", err),
+    }
+
+    if report.failures > 0 {
+        bail!("{} checks failed", report.failures);
+    }
+    Ok(())
+}
+
+/// Check the stake table commitment of the state against the sequencer's stake table at any of
+/// the fetched capacities.
+fn check_stake_table_comm(
+    comm: StakeTableComm,
+    stake_tables: &[(usize, SequencerStakeTable, StakeTableComm)],
+) -> anyhow::Result<()> {
+    match stake_tables.iter().find(|(_, _, st_comm)| *st_comm == comm) {
+        Some((capacity, _, _)) => {
+            println!("state commits to the sequencer's stake table with capacity {capacity}");
+            Ok(())
+        }
+        None => bail!(
+            "state commits to {comm:?}, the sequencer's stake table commits to {:?}",
+            stake_tables
+                .iter()
+                .map(|(capacity, _, st_comm)| (capacity, st_comm))
+                .collect::<Vec<_>>()
+        ),
+    }
+}
+
+/// Check the contract's voting stake table commitment against the sequencer's stake table at any
+/// of the fetched capacities.
+fn check_voting_stake_table_comm(
+    voting: [u8; 32],
+    stake_tables: &[(usize, SequencerStakeTable, StakeTableComm)],
+) -> anyhow::Result<()> {
+    ensure!(
+        stake_tables
+            .iter()
+            .any(|(_, _, comm)| contract_stake_table_comm(comm) == voting),
+        "contract is voting with {}, which is not the sequencer's stake table",
+        U256::from_big_endian(&voting)
+    );
+    Ok(())
+}
+
+/// The commitment the contract stores for a stake table, as in `computeStakeTableComm`.
+fn contract_stake_table_comm(comm: &StakeTableComm) -> [u8; 32] {
+    let mut packed = [0u8; 96];
+    field_to_u256(comm.0).to_big_endian(&mut packed[..32]);
+    field_to_u256(comm.1).to_big_endian(&mut packed[32..64]);
+    field_to_u256(comm.2).to_big_endian(&mut packed[64..]);
+    keccak256(packed)
+}
+
+/// Check the block Merkle tree root in the sequencer's header at the state's height against the
+/// state.
+async fn check_block_merkle_root(
+    sequencer_url: &Url,
+    state: &ParsedLightClientState,
+) -> anyhow::Result<()> {
+    let height = state.block_height;
+    // The proof of the previous block comes with the root of the header at `height`, which
+    // contains it.
+    let url = sequencer_url
+        .join(&format!(
+            "/v0/block-state/light-client/{height}/{}",
+            height - 1
+        ))
+        .context("Invalid URL")?;
+    let res: BlockMerkleProofQueryData = reqwest::get(url)
+        .await
+        .context("fetching block Merkle proof")?
+        .json()
+        .await
+        .context("parsing block Merkle proof")?;
+    verify_block_merkle_proof(state, &res.root, height - 1, &res.proof)?;
+    Ok(())
+}
+
+/// Decode the state and proof submitted by the light client update which emitted `meta`.
+async fn submitted_update(
+    provider: &L1Provider,
+    meta: &LogMeta,
+) -> anyhow::Result<NewFinalizedStateCall> {
+    let tx = provider
+        .get_transaction(meta.transaction_hash)
+        .await?
+        .context(format!(
+            "transaction {:#x} not found",
+            meta.transaction_hash
+        ))?;
+    NewFinalizedStateCall::decode(&tx.input).context(format!(
+        "transaction {:#x} is not a direct call to newFinalizedState",
+        meta.transaction_hash
+    ))
+}
+
+/// Load a key file, or a directory of key files.
+fn load_keys(path: &Path) -> anyhow::Result<ProverKeyRegistry> {
+    if path.is_dir() {
+        Ok(ProverKeyRegistry::load_dir(path)?.0)
+    } else {
+        Ok(ProverKeys::load(path)?.0.into())
+    }
+}
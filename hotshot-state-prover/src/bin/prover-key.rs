@@ -0,0 +1,103 @@
+use std::path::PathBuf;
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use ethers::{abi::AbiEncode, types::Address};
+use hotshot_contract_adapter::jellyfish::ParsedVerifyingKey;
+use hotshot_stake_table::config::STAKE_TABLE_CAPACITY;
+use hotshot_state_prover::keys::{check_verifying_key, ProverKeyRegistry, ProverKeys};
+use url::Url;
+
+/// Command-line utility for managing the state prover's proving key.
+#[derive(Debug, Parser)]
+enum Command {
+    Generate(Generate),
+    Inspect(Inspect),
+}
+
+/// Generate the proving and verifying keys and write them to a file.
+///
+/// This downloads the SRS from Aztec's ceremony, so it requires network access.
+#[derive(Debug, Parser)]
+struct Generate {
+    /// Stake table capacity for the prover circuit.
+    #[clap(
+        short,
+        long,
+        env = "ESPRESSO_SEQUENCER_STAKE_TABLE_CAPACITY",
+        default_value_t = STAKE_TABLE_CAPACITY
+    )]
+    stake_table_capacity: usize,
+
+    /// File to write the keys to.
+    ///
+    /// To support several stake table capacities, write the keys for each capacity to a file in the
+    /// same directory, and give the prover the directory.
+    #[clap(short, long, env = "ESPRESSO_STATE_PROVER_PROVING_KEY_PATH")]
+    out: PathBuf,
+}
+
+/// Print information about a key file, or a directory of key files.
+///
+/// If a light client contract is given, also check which keys match the contract's verifying key.
+#[derive(Debug, Parser)]
+struct Inspect {
+    /// The key file or directory.
+    #[clap(env = "ESPRESSO_STATE_PROVER_PROVING_KEY_PATH")]
+    path: PathBuf,
+
+    /// URL of layer 1 Ethereum JSON-RPC provider.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_L1_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    l1_provider: Url,
+
+    /// Address of LightClient contract on layer 1.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_LIGHTCLIENT_ADDRESS")]
+    light_client_address: Option<Address>,
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    match Command::parse() {
+        Command::Generate(opt) => {
+            let keys = ProverKeys::generate(opt.stake_table_capacity);
+            let hash = keys.save(&opt.out)?;
+            println!("{hash}");
+        }
+        Command::Inspect(opt) if opt.path.is_dir() => {
+            let (registry, hashes) = ProverKeyRegistry::load_dir(&opt.path)?;
+            for (capacity, hash) in hashes {
+                println!("stake table capacity {capacity}: hash {hash}");
+                if let Some(address) = opt.light_client_address {
+                    let vk = &registry.get(capacity).unwrap().verifying_key;
+                    match check_verifying_key(vk, &opt.l1_provider, address).await {
+                        Ok(()) => println!(
+                            "  verifying key matches light client contract at {address:#x}"
+                        ),
+                        Err(err) => println!("  {err:#}"),
+                    }
+                }
+            }
+        }
+        Command::Inspect(opt) => {
+            let (keys, hash) = ProverKeys::load(&opt.path)?;
+            println!("hash: {hash}");
+            println!("stake table capacity: {}", keys.stake_table_capacity);
+            println!(
+                "verifying key: {}",
+                ParsedVerifyingKey::from(keys.verifying_key.clone()).encode_hex()
+            );
+            if let Some(address) = opt.light_client_address {
+                check_verifying_key(&keys.verifying_key, &opt.l1_provider, address).await?;
+                println!("verifying key matches light client contract at {address:#x}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::{str::FromStr as _, time::Duration};
+use std::{path::PathBuf, str::FromStr as _, time::Duration};
 
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use clap::Parser;
@@ -10,7 +10,10 @@ use ethers::{
     types::Address,
 };
 use hotshot_stake_table::config::STAKE_TABLE_CAPACITY;
-use hotshot_state_prover::service::{run_prover_once, run_prover_service, StateProverConfig};
+use hotshot_state_prover::{
+    coordination::ProverRotation,
+    service::{run_prover_once, run_prover_service, StateProverConfig},
+};
 use snafu::Snafu;
 use url::Url;
 
@@ -61,7 +64,8 @@ struct Args {
     eth_account_index: u32,
 
     /// URL of a sequencer node that is currently providing the HotShot config.
-    /// This is used to initialize the stake table.
+    /// This is used to initialize the stake table, and is polled for stake table changes unless
+    /// --stake-table-address is provided.
     #[clap(
         long,
         env = "ESPRESSO_SEQUENCER_URL",
@@ -69,15 +73,94 @@ struct Args {
     )]
     pub sequencer_url: Url,
 
+    /// Address of a StakeTable contract on layer 1 to read stake table changes from, instead of
+    /// the HotShot config.
+    ///
+    /// Only set this if the sequencers derive their stake table from the same contract: the prover
+    /// can only prove states signed by the stake table it reads.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_STAKE_TABLE_ADDRESS")]
+    pub stake_table_address: Option<Address>,
+
+    /// The L1 block in which the StakeTable contract was deployed.
+    ///
+    /// Registrations are only searched for from this block onwards.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_STAKE_TABLE_DEPLOYMENT_BLOCK",
+        default_value = "0"
+    )]
+    pub stake_table_deployment_block: u64,
+
     /// If daemon and provided, the service will run a basic HTTP server on the given port.
     ///
-    /// The server provides healthcheck and version endpoints.
+    /// The server provides healthcheck, version, status and metrics endpoints.
     #[clap(short, long, env = "ESPRESSO_PROVER_SERVICE_PORT")]
     pub port: Option<u16>,
 
-    /// Stake table capacity for the prover circuit
+    /// Stake table capacity for the prover circuit, if the proving key is generated on startup.
     #[clap(short, long, env = "ESPRESSO_SEQUENCER_STAKE_TABLE_CAPACITY", default_value_t = STAKE_TABLE_CAPACITY)]
     pub stake_table_capacity: usize,
+
+    /// File containing the proving key, as generated by `prover-key generate`.
+    ///
+    /// This may also be a directory of key files for several stake table capacities. The prover
+    /// then uses the key matching the capacity of the stake table committed to by the light client,
+    /// which lets it keep working when the stake table outgrows a capacity and the light client
+    /// contract is upgraded to the verifier for a larger one.
+    ///
+    /// If not provided, the proving key is generated on startup, which takes several minutes.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_PROVING_KEY_PATH")]
+    pub proving_key_path: Option<PathBuf>,
+
+    /// Number of L1 blocks without a light client update after which rollups may enter escape
+    /// hatch mode.
+    ///
+    /// If provided, the prover reports whether the light client has fallen this far behind in its
+    /// status API and metrics, and logs an error when it has.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_ESCAPE_HATCH_THRESHOLD")]
+    pub escape_hatch_threshold: Option<u64>,
+
+    /// Directory in which to keep generated proofs until they are finalized on L1.
+    ///
+    /// This allows proofs to be resubmitted after a restart rather than generated again. If not
+    /// provided, pending proofs are only kept in memory.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_PROOF_QUEUE_PATH")]
+    pub proof_queue_path: Option<PathBuf>,
+
+    /// How long to wait for a submitted transaction to be mined before resubmitting it with a
+    /// higher gas price.
+    #[clap(long, value_parser = parse_duration, default_value = "2m", env = "ESPRESSO_STATE_PROVER_SUBMISSION_TIMEOUT")]
+    pub submission_timeout: Duration,
+
+    /// Position of this prover in a rotation of provers which take turns updating the light
+    /// client, starting from 0.
+    ///
+    /// Without a rotation, the prover updates the light client whenever an update is due, backing
+    /// off only when another prover has updated it recently.
+    #[clap(
+        long,
+        env = "ESPRESSO_STATE_PROVER_ROTATION_INDEX",
+        requires_all = ["rotation_size", "rotation_window"]
+    )]
+    pub rotation_index: Option<u64>,
+
+    /// Number of provers in the rotation.
+    #[clap(
+        long,
+        env = "ESPRESSO_STATE_PROVER_ROTATION_SIZE",
+        requires = "rotation_index"
+    )]
+    pub rotation_size: Option<u64>,
+
+    /// Number of L1 blocks in each prover's turn.
+    ///
+    /// This should leave enough time to generate and submit a proof.
+    #[clap(
+        long,
+        env = "ESPRESSO_STATE_PROVER_ROTATION_WINDOW",
+        requires = "rotation_index"
+    )]
+    pub rotation_window: Option<u64>,
 }
 
 #[derive(Clone, Debug, Snafu)]
@@ -103,6 +186,16 @@ async fn main() {
     // prepare config for state prover from user options
     let provider = Provider::<Http>::try_from(args.l1_provider.to_string()).unwrap();
     let chain_id = provider.get_chainid().await.unwrap().as_u64();
+    let rotation = match (
+        args.rotation_index,
+        args.rotation_size,
+        args.rotation_window,
+    ) {
+        (Some(index), Some(size), Some(window)) => {
+            Some(ProverRotation::new(index, size, window).expect("invalid prover rotation"))
+        }
+        _ => None,
+    };
     let config = StateProverConfig {
         relay_server: args.relay_server,
         update_interval: args.update_interval,
@@ -119,8 +212,15 @@ async fn main() {
             .signer()
             .clone(),
         sequencer_url: args.sequencer_url,
+        stake_table_address: args.stake_table_address,
+        stake_table_deployment_block: args.stake_table_deployment_block,
         port: args.port,
         stake_table_capacity: args.stake_table_capacity,
+        proving_key_path: args.proving_key_path,
+        escape_hatch_threshold: args.escape_hatch_threshold,
+        proof_queue_path: args.proof_queue_path,
+        submission_timeout: args.submission_timeout,
+        rotation,
     };
 
     if args.daemon {
@@ -42,6 +42,21 @@ pub struct StakeTableCommVar {
     pub stake_amount_comm: Variable,
 }
 
+impl StakeTableCommVar {
+    /// # Errors
+    /// if unable to create any of the public variables
+    pub fn new_public<F: PrimeField>(
+        circuit: &mut PlonkCircuit<F>,
+        comm: &(F, F, F),
+    ) -> Result<Self, CircuitError> {
+        Ok(Self {
+            qc_keys_comm: circuit.create_public_variable(comm.0)?,
+            state_keys_comm: circuit.create_public_variable(comm.1)?,
+            stake_amount_comm: circuit.create_public_variable(comm.2)?,
+        })
+    }
+}
+
 /// Light client state Variable
 #[derive(Clone, Debug)]
 pub struct LightClientStateVar {
@@ -122,17 +137,21 @@ impl AsRef<[Variable]> for LightClientStateVar {
 /// - a bit vector indicates the signers
 /// - a list of schnorr signatures of the updated states (`Vec<SchnorrSignature>`), default if the node doesn't sign the state
 /// - updated light client state (`(view_number, block_height, block_comm_root, fee_ledger_comm, stake_table_comm)`)
+/// - the commitment of the stake table which signed the update, i.e. the one committed in the
+///   previous light client state (differs from the `stake_table_comm` of the updated state when the
+///   stake table rotates)
 /// - a quorum threshold
 /// Lengths of input vectors should not exceed the `stake_table_capacity`.
 /// The list of stake table entries, bit indicators and signatures will be padded to the `stake_table_capacity`.
 /// It checks that
 /// - the vector that indicates who signed is a bit vector
 /// - the signers' accumulated weight exceeds the quorum threshold
-/// - the stake table corresponds to the one committed in the light client state
+/// - the stake table corresponds to the signers' stake table commitment
 /// - all Schnorr signatures over the light client state are valid
 /// and returns
 /// - A circuit for proof generation
-/// - A list of public inputs for verification
+/// - A list of public inputs for verification: the threshold, the fields of the updated state
+///   (including its stake table commitment) and the signers' stake table commitment, in that order
 /// - A `PlonkError` if any error happens when building the circuit
 #[allow(clippy::too_many_lines)]
 pub(crate) fn build<F, P, STIter, BitIter, SigIter>(
@@ -140,6 +159,7 @@ pub(crate) fn build<F, P, STIter, BitIter, SigIter>(
     signer_bit_vec: BitIter,
     signatures: SigIter,
     lightclient_state: &GenericLightClientState<F>,
+    signers_stake_table_comm: &(F, F, F),
     threshold: &U256,
     stake_table_capacity: usize,
 ) -> Result<(PlonkCircuit<F>, GenericPublicInput<F>), PlonkError>
@@ -246,7 +266,9 @@ where
     let threshold = u256_to_field::<F>(threshold);
     let threshold_pub_var = circuit.create_public_variable(threshold)?;
 
-    let lightclient_state_pub_var = LightClientStateVar::new(&mut circuit, lightclient_state)?;
+    let lightclient_state_var = LightClientStateVar::new(&mut circuit, lightclient_state)?;
+    let signers_stake_table_comm_pub_var =
+        StakeTableCommVar::new_public(&mut circuit, signers_stake_table_comm)?;
 
     let view_number_f = F::from(lightclient_state.view_number as u64);
     let block_height_f = F::from(lightclient_state.block_height as u64);
@@ -259,6 +281,9 @@ where
         lightclient_state.stake_table_comm.0,
         lightclient_state.stake_table_comm.1,
         lightclient_state.stake_table_comm.2,
+        signers_stake_table_comm.0,
+        signers_stake_table_comm.1,
+        signers_stake_table_comm.2,
     ];
 
     // Checking whether the accumulated weight exceeds the quorum threshold
@@ -297,7 +322,7 @@ where
     )?[0];
     circuit.enforce_equal(
         state_ver_key_comm,
-        lightclient_state_pub_var.stake_table_comm().state_keys_comm,
+        signers_stake_table_comm_pub_var.state_keys_comm,
     )?;
 
     // checking the commitment for the list of stake amounts
@@ -312,9 +337,7 @@ where
     )?[0];
     circuit.enforce_equal(
         stake_amount_comm,
-        lightclient_state_pub_var
-            .stake_table_comm()
-            .stake_amount_comm,
+        signers_stake_table_comm_pub_var.stake_amount_comm,
     )?;
 
     // checking all signatures
@@ -325,7 +348,7 @@ where
             SignatureGadget::<_, P>::check_signature_validity(
                 &mut circuit,
                 &entry.state_ver_key,
-                lightclient_state_pub_var.as_ref(),
+                lightclient_state_var.as_ref(),
                 &sig,
             )
         })
@@ -365,6 +388,7 @@ where
         &[],
         &[],
         &lightclient_state,
+        &(F::default(), F::default(), F::default()),
         &U256::zero(),
         stake_table_capacity,
     )
@@ -452,6 +476,7 @@ mod tests {
             &bit_vec,
             &bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(26u32),
             ST_CAPACITY,
         )
@@ -465,6 +490,7 @@ mod tests {
             &bit_vec,
             &bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(10u32),
             ST_CAPACITY,
         )
@@ -473,6 +499,58 @@ mod tests {
             .check_circuit_satisfiability(public_inputs.as_ref())
             .is_ok());
 
+        // good path: the signed state commits to a new stake table, but is proven against the
+        // stake table which signed it
+        let mut rotated_lightclient_state = lightclient_state.clone();
+        rotated_lightclient_state.stake_table_comm =
+            (F::from(7u32), F::from(11u32), F::from(13u32));
+        let rotated_state_msg: [F; 7] = rotated_lightclient_state.clone().into();
+        let rotated_sigs = state_keys
+            .iter()
+            .map(|(key, _)| {
+                SchnorrSignatureScheme::<Config>::sign(&(), key, rotated_state_msg, &mut prng)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let (circuit, public_inputs) = build(
+            &entries,
+            &bit_vec,
+            &rotated_sigs,
+            &rotated_lightclient_state,
+            &lightclient_state.stake_table_comm,
+            &U256::from(26u32),
+            ST_CAPACITY,
+        )
+        .unwrap();
+        assert!(circuit
+            .check_circuit_satisfiability(public_inputs.as_ref())
+            .is_ok());
+        // the public input contains both the new and the signers' stake table commitments
+        let public_inputs: &[F] = public_inputs.as_ref();
+        assert_eq!(public_inputs.len(), 11);
+        assert_eq!(
+            (public_inputs[5], public_inputs[6], public_inputs[7]),
+            rotated_lightclient_state.stake_table_comm
+        );
+        assert_eq!(
+            (public_inputs[8], public_inputs[9], public_inputs[10]),
+            lightclient_state.stake_table_comm
+        );
+        // bad path: the rotated state proven against its own, unrelated stake table commitment
+        let (bad_circuit, public_inputs) = build(
+            &entries,
+            &bit_vec,
+            &rotated_sigs,
+            &rotated_lightclient_state,
+            &rotated_lightclient_state.stake_table_comm,
+            &U256::from(26u32),
+            ST_CAPACITY,
+        )
+        .unwrap();
+        assert!(bad_circuit
+            .check_circuit_satisfiability(public_inputs.as_ref())
+            .is_err());
+
         // bad path: feeding non-bit vector
         let bit_vec = [F::from(2u64); 10];
         let (circuit, public_inputs) = build(
@@ -480,6 +558,7 @@ mod tests {
             &bit_vec,
             &bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(26u32),
             ST_CAPACITY,
         )
@@ -514,6 +593,7 @@ mod tests {
             &bad_bit_vec,
             &bad_bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(25u32),
             ST_CAPACITY,
         )
@@ -538,6 +618,7 @@ mod tests {
             &bit_vec,
             &sig_for_bad_state,
             &bad_lightclient_state,
+            &bad_lightclient_state.stake_table_comm,
             &U256::from(26u32),
             ST_CAPACITY,
         )
@@ -563,6 +644,7 @@ mod tests {
             &bit_vec,
             &wrong_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(26u32),
             ST_CAPACITY,
         )
@@ -577,6 +659,7 @@ mod tests {
             &bit_vec,
             &bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(26u32),
             9
         )
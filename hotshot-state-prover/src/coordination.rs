@@ -0,0 +1,182 @@
+//! Coordination between multiple provers updating the same light client contract.
+//!
+//! Provers do not communicate with each other directly. Instead, each prover decides whether to
+//! update the light client based on the state of the contract: it does not compete with a
+//! permissioned prover, it backs off when another prover has updated the contract recently, and,
+//! optionally, it only updates during its turn in a rotation determined by the L1 block number.
+
+use std::time::Duration;
+
+use anyhow::ensure;
+use contract_bindings::light_client::LightClient;
+use ethers::{
+    providers::Middleware,
+    types::{BlockNumber, U256},
+};
+
+use crate::service::{L1Wallet, ProverError};
+
+/// Deterministic rotation of a set of provers, which take turns updating the light client.
+///
+/// L1 blocks are divided into windows of `window` blocks, and the windows are assigned to the
+/// provers round robin. A prover only starts an update during its own windows, so provers
+/// configured with the same `num_provers` and `window` never compete for the same update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProverRotation {
+    /// Position of this prover in the rotation, starting from 0.
+    pub index: u64,
+    /// Number of provers in the rotation.
+    pub num_provers: u64,
+    /// Number of L1 blocks in each prover's turn.
+    pub window: u64,
+}
+
+impl ProverRotation {
+    pub fn new(index: u64, num_provers: u64, window: u64) -> anyhow::Result<Self> {
+        ensure!(num_provers > 0, "rotation must have at least one prover");
+        ensure!(window > 0, "rotation window must be at least one block");
+        ensure!(
+            index < num_provers,
+            "rotation index {index} out of range for {num_provers} provers"
+        );
+        Ok(Self {
+            index,
+            num_provers,
+            window,
+        })
+    }
+
+    /// The index of the prover whose turn it is at `l1_block`.
+    pub fn leader(&self, l1_block: u64) -> u64 {
+        (l1_block / self.window) % self.num_provers
+    }
+
+    /// Whether it is this prover's turn at `l1_block`.
+    pub fn is_leader(&self, l1_block: u64) -> bool {
+        self.leader(l1_block) == self.index
+    }
+}
+
+/// Check whether the contract only accepts updates from a permissioned prover other than this one.
+///
+/// Returns the reason this prover may not update the light client, or `None` if it may.
+pub async fn permissioned_prover_reason(
+    contract: &LightClient<L1Wallet>,
+) -> Result<Option<String>, ProverError> {
+    if contract
+        .permissioned_prover_enabled()
+        .call()
+        .await
+        .map_err(|err| ProverError::ContractError(err.into()))?
+    {
+        let prover = contract
+            .permissioned_prover()
+            .call()
+            .await
+            .map_err(|err| ProverError::ContractError(err.into()))?;
+        if prover != contract.client().address() {
+            return Ok(Some(format!(
+                "only the permissioned prover {prover:#x} may update the light client"
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// Decide whether this prover should hold off on updating the light client.
+///
+/// Returns the reason to hold off, or `None` if the prover should go ahead.
+///
+/// A prover holds off if the contract only accepts updates from a different permissioned prover,
+/// if it is not this prover's turn in `rotation`, or if the light client was updated less than half
+/// of `update_interval` ago. A prover waits a full update interval after each of its own updates, so
+/// a more recent update must have come from another prover.
+pub async fn defer_reason(
+    contract: &LightClient<L1Wallet>,
+    rotation: Option<&ProverRotation>,
+    update_interval: Duration,
+) -> Result<Option<String>, ProverError> {
+    let client = contract.client();
+
+    if let Some(reason) = permissioned_prover_reason(contract).await? {
+        return Ok(Some(reason));
+    }
+
+    let latest = client
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|err| ProverError::ContractError(err.into()))?
+        .ok_or_else(|| ProverError::Internal("latest L1 block not found".into()))?;
+    let latest_number = latest
+        .number
+        .ok_or_else(|| ProverError::Internal("latest L1 block has no number".into()))?
+        .as_u64();
+
+    if let Some(rotation) = rotation {
+        if !rotation.is_leader(latest_number) {
+            return Ok(Some(format!(
+                "it is prover {}'s turn at L1 block {latest_number}",
+                rotation.leader(latest_number)
+            )));
+        }
+    }
+
+    let updates = contract
+        .get_state_update_block_numbers_count()
+        .call()
+        .await
+        .map_err(|err| ProverError::ContractError(err.into()))?;
+    if updates.is_zero() {
+        return Ok(None);
+    }
+    let last_update_block = contract
+        .state_update_block_numbers(updates - U256::one())
+        .call()
+        .await
+        .map_err(|err| ProverError::ContractError(err.into()))?;
+    let last_update = client
+        .get_block(last_update_block.as_u64())
+        .await
+        .map_err(|err| ProverError::ContractError(err.into()))?
+        .ok_or_else(|| ProverError::Internal(format!("L1 block {last_update_block} not found")))?;
+    let since_last_update = Duration::from_secs(
+        latest
+            .timestamp
+            .saturating_sub(last_update.timestamp)
+            .as_u64(),
+    );
+    if since_last_update < update_interval / 2 {
+        return Ok(Some(format!(
+            "the light client was updated {since_last_update:?} ago, at L1 block \
+             {last_update_block}"
+        )));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_prover_rotation() {
+        let rotations = (0..3)
+            .map(|i| ProverRotation::new(i, 3, 10).unwrap())
+            .collect::<Vec<_>>();
+
+        // Exactly one prover is the leader at every block, and turns last `window` blocks.
+        for block in 0..100 {
+            let leaders = rotations
+                .iter()
+                .filter(|rotation| rotation.is_leader(block))
+                .collect::<Vec<_>>();
+            assert_eq!(leaders.len(), 1);
+            assert_eq!(leaders[0].index, (block / 10) % 3);
+        }
+
+        ProverRotation::new(3, 3, 10).unwrap_err();
+        ProverRotation::new(0, 0, 10).unwrap_err();
+        ProverRotation::new(0, 1, 0).unwrap_err();
+    }
+}
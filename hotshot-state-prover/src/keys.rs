@@ -0,0 +1,291 @@
+//! Generating, storing and checking the keys of the state update circuit.
+//!
+//! Generating a proving key requires downloading the SRS and preprocessing the circuit, which takes
+//! minutes. Keys can instead be generated once (for example with the `prover-key` binary), written
+//! to a file, and loaded by provers which may not have network access.
+//!
+//! The circuit, and thus the keys, depend on the stake table capacity: the stake table commitment in
+//! a light client state is computed over the stake table padded to the capacity. To let the stake
+//! table grow, a prover can hold keys for several capacities in a [`ProverKeyRegistry`]. The light
+//! client contract verifies proofs with the single verifying key linked into its implementation, so
+//! growing the stake table means upgrading the contract to an implementation linked with the
+//! verifier for the larger capacity (generated by `gen-vk-contract`).
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::{ensure, Context};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use contract_bindings::light_client::LightClient;
+use ethers::{
+    abi::AbiEncode,
+    providers::{Http, Provider},
+    types::Address,
+};
+use hotshot_contract_adapter::jellyfish::ParsedVerifyingKey;
+use hotshot_types::light_client::CircuitField;
+use jf_pcs::prelude::UnivariateUniversalParams;
+use jf_relation::Circuit as _;
+use time::ext::InstantExt;
+use url::Url;
+
+use crate::snark::{ProvingKey, VerifyingKey};
+
+/// The proving and verifying keys for a given stake table capacity.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProverKeys {
+    pub stake_table_capacity: u64,
+    pub proving_key: ProvingKey,
+    pub verifying_key: VerifyingKey,
+}
+
+impl ProverKeys {
+    /// Generate keys for a circuit supporting `stake_table_capacity` stakers.
+    ///
+    /// This loads the SRS from Aztec's ceremony, which is downloaded if not already cached.
+    pub fn generate(stake_table_capacity: usize) -> Self {
+        let srs = {
+            let num_gates = crate::circuit::build_for_preprocessing::<
+                CircuitField,
+                ark_ed_on_bn254::EdwardsConfig,
+            >(stake_table_capacity)
+            .unwrap()
+            .0
+            .num_gates();
+
+            tracing::info!("loading SRS from Aztec's ceremony");
+            let srs_timer = Instant::now();
+            let srs =
+                ark_srs::kzg10::aztec20::setup(num_gates + 2).expect("Aztec SRS fail to load");
+            let srs_elapsed = Instant::now().signed_duration_since(srs_timer);
+            tracing::info!("loaded SRS in {srs_elapsed:.3}");
+
+            // convert to Jellyfish type
+            // TODO: (alex) use constructor instead https://github.com/EspressoSystems/jellyfish/issues/440
+            UnivariateUniversalParams {
+                powers_of_g: srs.powers_of_g,
+                h: srs.h,
+                beta_h: srs.beta_h,
+                powers_of_h: vec![srs.h, srs.beta_h],
+            }
+        };
+
+        tracing::info!("generating proving key and verifying key");
+        let key_gen_timer = Instant::now();
+        let (proving_key, verifying_key) = crate::snark::preprocess(&srs, stake_table_capacity)
+            .expect("Fail to preprocess state prover circuit");
+        let key_gen_elapsed = Instant::now().signed_duration_since(key_gen_timer);
+        tracing::info!("generated keys in {key_gen_elapsed:.3}");
+
+        Self {
+            stake_table_capacity: stake_table_capacity as u64,
+            proving_key,
+            verifying_key,
+        }
+    }
+
+    /// Write the keys to `path`, returning the hash of their contents.
+    ///
+    /// The file starts with the hash, so that [`load`](Self::load) can detect corrupted or
+    /// truncated files.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<blake3::Hash> {
+        let path = path.as_ref();
+        let mut contents = vec![];
+        self.serialize_uncompressed(&mut contents)?;
+        let hash = blake3::hash(&contents);
+
+        // Write to a temporary file and rename, so that a crash never leaves a partial key behind.
+        // The suffix is appended rather than replacing the extension, so that keys whose names only
+        // differ in extension never share a temporary file, nor overwrite a file named like one.
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, [hash.as_bytes().as_slice(), &contents].concat())
+            .context(format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, path).context(format!(
+            "renaming {} to {}",
+            tmp.display(),
+            path.display()
+        ))?;
+        Ok(hash)
+    }
+
+    /// Read keys written by [`save`](Self::save), returning the keys and the hash of their
+    /// contents.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<(Self, blake3::Hash)> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).context(format!("reading {}", path.display()))?;
+        ensure!(
+            bytes.len() > blake3::OUT_LEN,
+            "{} is too short to contain prover keys",
+            path.display()
+        );
+        let (expected, contents) = bytes.split_at(blake3::OUT_LEN);
+        let hash = blake3::hash(contents);
+        ensure!(
+            hash.as_bytes() == expected,
+            "{} is corrupted: content hash {hash} does not match",
+            path.display()
+        );
+
+        // The hash already guarantees the contents are what we wrote, so skip the (slow) point
+        // validation.
+        let keys = Self::deserialize_uncompressed_unchecked(contents)
+            .context(format!("deserializing {}", path.display()))?;
+        Ok((keys, hash))
+    }
+}
+
+/// Prover keys for several stake table capacities.
+#[derive(Clone, Default)]
+pub struct ProverKeyRegistry {
+    keys: BTreeMap<usize, Arc<ProverKeys>>,
+}
+
+impl ProverKeyRegistry {
+    /// Add keys to the registry, replacing any keys for the same capacity.
+    pub fn insert(&mut self, keys: ProverKeys) {
+        self.keys
+            .insert(keys.stake_table_capacity as usize, Arc::new(keys));
+    }
+
+    /// Load the keys in every file of `dir`, as written by [`ProverKeys::save`].
+    ///
+    /// Returns the registry and the content hash of each file, by capacity.
+    pub fn load_dir(
+        dir: impl AsRef<Path>,
+    ) -> anyhow::Result<(Self, BTreeMap<usize, blake3::Hash>)> {
+        let dir = dir.as_ref();
+        let mut registry = Self::default();
+        let mut hashes = BTreeMap::new();
+        for entry in fs::read_dir(dir).context(format!("reading {}", dir.display()))? {
+            let path = entry?.path();
+            // Skip subdirectories and temporary files left behind by an interrupted save.
+            if !path.is_file() || path.extension().is_some_and(|ext| ext == "tmp") {
+                continue;
+            }
+            let (keys, hash) = ProverKeys::load(&path)?;
+            let capacity = keys.stake_table_capacity as usize;
+            ensure!(
+                hashes.insert(capacity, hash).is_none(),
+                "{} contains more than one key file for stake table capacity {capacity}",
+                dir.display()
+            );
+            registry.insert(keys);
+        }
+        ensure!(!registry.is_empty(), "no key files in {}", dir.display());
+        Ok((registry, hashes))
+    }
+
+    /// The keys for stake table capacity `capacity`, if any.
+    pub fn get(&self, capacity: usize) -> Option<Arc<ProverKeys>> {
+        self.keys.get(&capacity).cloned()
+    }
+
+    /// The stake table capacities with keys in the registry, in increasing order.
+    pub fn capacities(&self) -> impl Iterator<Item = usize> + Clone + '_ {
+        self.keys.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+impl From<ProverKeys> for ProverKeyRegistry {
+    fn from(keys: ProverKeys) -> Self {
+        let mut registry = Self::default();
+        registry.insert(keys);
+        registry
+    }
+}
+
+/// Check that `vk` is the verifying key the light client contract at `light_client_address` uses.
+///
+/// The key is compared with the one returned by the contract's `getVk` function.
+pub async fn check_verifying_key(
+    vk: &VerifyingKey,
+    l1_provider: &Url,
+    light_client_address: Address,
+) -> anyhow::Result<()> {
+    let provider = Provider::<Http>::try_from(l1_provider.to_string())?;
+    let deployed = LightClient::new(light_client_address, Arc::new(provider))
+        .get_vk()
+        .call()
+        .await
+        .context(format!(
+            "reading verifying key of light client at {light_client_address:#x}"
+        ))?;
+    ensure!(
+        deployed.encode() == ParsedVerifyingKey::from(vk.clone()).encode(),
+        "verifying key does not match the one deployed in the light client contract at \
+         {light_client_address:#x}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_prover_keys_file() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("keys");
+
+        let keys = ProverKeys::generate(4);
+        let hash = keys.save(&path).unwrap();
+        let (loaded, loaded_hash) = ProverKeys::load(&path).unwrap();
+        assert_eq!(hash, loaded_hash);
+        assert_eq!(loaded.stake_table_capacity, 4);
+        assert_eq!(loaded.verifying_key, keys.verifying_key);
+
+        // Corrupted keys are refused.
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, bytes).unwrap();
+        ProverKeys::load(&path).unwrap_err();
+
+        // Saving leaves files named like the temporary file of another key alone.
+        let path = tmp.path().join("pk.bin");
+        fs::write(tmp.path().join("pk.tmp"), [1, 2, 3]).unwrap();
+        keys.save(&path).unwrap();
+        ProverKeys::load(&path).unwrap();
+        assert_eq!(fs::read(tmp.path().join("pk.tmp")).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_prover_key_registry() {
+        let tmp = TempDir::new().unwrap();
+        let small = ProverKeys::generate(2);
+        let large = ProverKeys::generate(4);
+        small.save(tmp.path().join("small")).unwrap();
+        let large_hash = large.save(tmp.path().join("large")).unwrap();
+
+        let (registry, hashes) = ProverKeyRegistry::load_dir(tmp.path()).unwrap();
+        assert_eq!(registry.capacities().collect::<Vec<_>>(), [2, 4]);
+        assert_eq!(hashes[&4], large_hash);
+        assert_eq!(registry.get(2).unwrap().verifying_key, small.verifying_key);
+        assert_eq!(registry.get(4).unwrap().verifying_key, large.verifying_key);
+        assert!(registry.get(3).is_none());
+
+        // Two key files for the same capacity are ambiguous.
+        small.save(tmp.path().join("small-copy")).unwrap();
+        ProverKeyRegistry::load_dir(tmp.path()).unwrap_err();
+
+        // A directory without keys is refused.
+        ProverKeyRegistry::load_dir(TempDir::new().unwrap().path()).unwrap_err();
+    }
+}
@@ -2,12 +2,22 @@
 
 /// State verifier circuit builder
 pub mod circuit;
+/// Coordination between multiple provers
+pub mod coordination;
+/// Proving key generation and storage
+pub mod keys;
+/// Prometheus exporter for the prover's metrics
+pub mod metrics;
 /// Utilities for test
 pub mod mock_ledger;
+/// Queue of proofs waiting to be submitted to L1
+pub mod queue;
 /// Prover service related functionalities
 pub mod service;
 /// SNARK proof generation
 pub mod snark;
+/// Prover service status and metrics
+pub mod status;
 
 #[cfg(test)]
 mod test_utils;
@@ -0,0 +1,184 @@
+//! A minimal Prometheus implementation of the HotShot metrics traits.
+//!
+//! The prover only needs to export a handful of metrics, so rather than depending on the query
+//! service for its metrics registry, this wraps a [`prometheus::Registry`] directly.
+
+use hotshot_types::traits::metrics::{
+    Counter, CounterFamily, Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics, MetricsFamily,
+    TextFamily,
+};
+use prometheus::{
+    core::Collector, Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// A group of metrics exported in the Prometheus text format.
+///
+/// Metrics created in a [`subgroup`](Metrics::subgroup) share the registry of their parent, with
+/// the subgroup name prepended to their names.
+#[derive(Clone, Debug, Default)]
+pub struct PrometheusMetrics {
+    registry: Registry,
+    prefix: Option<String>,
+}
+
+impl PrometheusMetrics {
+    fn name(&self, name: String) -> String {
+        match &self.prefix {
+            Some(prefix) => format!("{prefix}_{name}"),
+            None => name,
+        }
+    }
+
+    fn opts(&self, name: String, unit_label: Option<String>) -> Opts {
+        let name = self.name(name);
+        // Prometheus requires a help string; use the unit if there is one.
+        let help = unit_label.unwrap_or_else(|| name.clone());
+        Opts::new(name, help)
+    }
+
+    fn register<C: Collector + Clone + 'static>(&self, collector: C) -> C {
+        if let Err(err) = self.registry.register(Box::new(collector.clone())) {
+            tracing::warn!("failed to register metric: {err}");
+        }
+        collector
+    }
+}
+
+impl tide_disco::metrics::Metrics for PrometheusMetrics {
+    type Error = prometheus::Error;
+
+    fn export(&self) -> Result<String, Self::Error> {
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|err| prometheus::Error::Msg(err.to_string()))
+    }
+}
+
+impl Metrics for PrometheusMetrics {
+    fn create_counter(&self, name: String, unit_label: Option<String>) -> Box<dyn Counter> {
+        let counter = IntCounter::with_opts(self.opts(name, unit_label)).unwrap();
+        Box::new(PrometheusCounter(self.register(counter)))
+    }
+
+    fn create_gauge(&self, name: String, unit_label: Option<String>) -> Box<dyn Gauge> {
+        let gauge = IntGauge::with_opts(self.opts(name, unit_label)).unwrap();
+        Box::new(PrometheusGauge(self.register(gauge)))
+    }
+
+    fn create_histogram(&self, name: String, unit_label: Option<String>) -> Box<dyn Histogram> {
+        let histogram =
+            prometheus::Histogram::with_opts(HistogramOpts::from(self.opts(name, unit_label)))
+                .unwrap();
+        Box::new(PrometheusHistogram(self.register(histogram)))
+    }
+
+    fn create_text(&self, name: String) {
+        // Prometheus has no text metrics, so record the text as the name of a constant gauge.
+        self.create_gauge(name, None).set(1);
+    }
+
+    fn counter_family(&self, name: String, labels: Vec<String>) -> Box<dyn CounterFamily> {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        let family = IntCounterVec::new(self.opts(name, None), &labels).unwrap();
+        Box::new(PrometheusCounterFamily(self.register(family)))
+    }
+
+    fn gauge_family(&self, name: String, labels: Vec<String>) -> Box<dyn GaugeFamily> {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        let family = IntGaugeVec::new(self.opts(name, None), &labels).unwrap();
+        Box::new(PrometheusGaugeFamily(self.register(family)))
+    }
+
+    fn histogram_family(&self, name: String, labels: Vec<String>) -> Box<dyn HistogramFamily> {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        let family =
+            HistogramVec::new(HistogramOpts::from(self.opts(name, None)), &labels).unwrap();
+        Box::new(PrometheusHistogramFamily(self.register(family)))
+    }
+
+    fn text_family(&self, name: String, labels: Vec<String>) -> Box<dyn TextFamily> {
+        // As with single text metrics, texts are recorded as labels of a constant gauge.
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        let family = IntGaugeVec::new(self.opts(name, None), &labels).unwrap();
+        Box::new(PrometheusTextFamily(self.register(family)))
+    }
+
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(Self {
+            registry: self.registry.clone(),
+            prefix: Some(self.name(subgroup_name)),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusCounter(IntCounter);
+
+impl Counter for PrometheusCounter {
+    fn add(&self, amount: usize) {
+        self.0.inc_by(amount as u64);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusGauge(IntGauge);
+
+impl Gauge for PrometheusGauge {
+    fn set(&self, amount: usize) {
+        self.0.set(amount as i64);
+    }
+
+    fn update(&self, delta: i64) {
+        self.0.add(delta);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusHistogram(prometheus::Histogram);
+
+impl Histogram for PrometheusHistogram {
+    fn add_point(&self, point: f64) {
+        self.0.observe(point);
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusCounterFamily(IntCounterVec);
+
+impl MetricsFamily<Box<dyn Counter>> for PrometheusCounterFamily {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        Box::new(PrometheusCounter(self.0.with_label_values(&labels)))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusGaugeFamily(IntGaugeVec);
+
+impl MetricsFamily<Box<dyn Gauge>> for PrometheusGaugeFamily {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Gauge> {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        Box::new(PrometheusGauge(self.0.with_label_values(&labels)))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusHistogramFamily(HistogramVec);
+
+impl MetricsFamily<Box<dyn Histogram>> for PrometheusHistogramFamily {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Histogram> {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        Box::new(PrometheusHistogram(self.0.with_label_values(&labels)))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PrometheusTextFamily(IntGaugeVec);
+
+impl MetricsFamily<()> for PrometheusTextFamily {
+    fn create(&self, labels: Vec<String>) {
+        let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+        self.0.with_label_values(&labels).set(1);
+    }
+}
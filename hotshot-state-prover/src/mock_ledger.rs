@@ -38,7 +38,8 @@ use jf_signature::{
 use jf_utils::test_rng;
 
 use crate::{
-    generate_state_update_proof, preprocess, service::one_honest_threshold, Proof, VerifyingKey,
+    generate_state_update_proof, preprocess, service::one_honest_threshold, Proof, ProvingKey,
+    VerifyingKey,
 };
 
 type F = ark_ed_on_bn254::Fq;
@@ -78,6 +79,10 @@ pub struct MockLedger {
     pub(crate) qc_keys: Vec<BLSVerKey>,
     pub(crate) state_keys: Vec<(SchnorrSignKey, SchnorrVerKey)>,
     key_archive: HashMap<BLSVerKey, SchnorrSignKey>,
+    /// whether the last state of each epoch commits to the stake table of the next epoch
+    rotate_stake_table: bool,
+    /// proving and verifying keys, generated on first use
+    keys: Option<(ProvingKey, VerifyingKey)>,
 }
 
 impl MockLedger {
@@ -115,11 +120,54 @@ impl MockLedger {
             qc_keys,
             state_keys,
             key_archive,
+            rotate_stake_table: false,
+            keys: None,
         }
     }
 
+    /// Commit to the stake table of the next epoch in the last state of each epoch, as a real
+    /// ledger does when the stake table changes.
+    ///
+    /// Without this, every state commits to the genesis stake table.
+    pub fn with_stake_table_rotation(mut self) -> Self {
+        self.rotate_stake_table = true;
+        self
+    }
+
+    /// The verifying key for state proofs generated by this ledger
+    pub fn verifying_key(&mut self) -> VerifyingKey {
+        self.keys().1.clone()
+    }
+
+    fn keys(&mut self) -> &(ProvingKey, VerifyingKey) {
+        self.keys.get_or_insert_with(|| {
+            let srs = {
+                // load SRS from Aztec's ceremony
+                let srs = ark_srs::kzg10::aztec20::setup(2u64.pow(16) as usize + 2)
+                    .expect("Aztec SRS fail to load");
+                // convert to Jellyfish type
+                // TODO: (alex) use constructor instead https://github.com/EspressoSystems/jellyfish/issues/440
+                UnivariateUniversalParams {
+                    powers_of_g: srs.powers_of_g,
+                    h: srs.h,
+                    beta_h: srs.beta_h,
+                    powers_of_h: vec![srs.h, srs.beta_h],
+                }
+            };
+            preprocess(&srs, STAKE_TABLE_CAPACITY).expect("Fail to preprocess state prover circuit")
+        })
+    }
+
     /// Elapse a view with a new finalized block
     pub fn elapse_with_block(&mut self) {
+        let new_root = self.new_dummy_comm();
+        let new_fee_ledger_comm = self.new_dummy_comm();
+        self.elapse_with_block_roots(new_root, new_fee_ledger_comm);
+    }
+
+    /// Elapse a view with a new finalized block, whose header has the given block and fee Merkle
+    /// tree commitments
+    pub fn elapse_with_block_roots(&mut self, block_comm_root: F, fee_ledger_comm: F) {
         // if the new block is the first block of an epoch, update epoch
         if self.state.block_height != 0
             && self.state.block_height % self.pp.blk_per_epoch as usize == 0
@@ -133,13 +181,16 @@ impl MockLedger {
             );
         }
 
-        let new_root = self.new_dummy_comm();
-        let new_fee_ledger_comm = self.new_dummy_comm();
-
         self.state.view_number += 1;
         self.state.block_height += 1;
-        self.state.block_comm_root = new_root;
-        self.state.fee_ledger_comm = new_fee_ledger_comm;
+        self.state.block_comm_root = block_comm_root;
+        self.state.fee_ledger_comm = fee_ledger_comm;
+
+        // the last block of an epoch commits to the stake table frozen for the next epoch
+        if self.rotate_stake_table && self.state.block_height % self.pp.blk_per_epoch as usize == 0
+        {
+            self.state.stake_table_comm = self.st.commitment(SnapshotVersion::EpochStart).unwrap();
+        }
     }
 
     /// Elapse a view without a new finalized block
@@ -247,21 +298,7 @@ impl MockLedger {
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
 
-        let srs = {
-            // load SRS from Aztec's ceremony
-            let srs = ark_srs::kzg10::aztec20::setup(2u64.pow(16) as usize + 2)
-                .expect("Aztec SRS fail to load");
-            // convert to Jellyfish type
-            // TODO: (alex) use constructor instead https://github.com/EspressoSystems/jellyfish/issues/440
-            UnivariateUniversalParams {
-                powers_of_g: srs.powers_of_g,
-                h: srs.h,
-                beta_h: srs.beta_h,
-                powers_of_h: vec![srs.h, srs.beta_h],
-            }
-        };
-        let (pk, _) = preprocess(&srs, STAKE_TABLE_CAPACITY)
-            .expect("Fail to preprocess state prover circuit");
+        let pk = self.keys().0.clone();
         let stake_table_entries = self
             .st
             .try_iter(SnapshotVersion::LastEpochStart)
@@ -275,6 +312,7 @@ impl MockLedger {
             &bit_vec,
             &sigs,
             &self.state,
+            &self.st.commitment(SnapshotVersion::LastEpochStart).unwrap(),
             &self.threshold,
             STAKE_TABLE_CAPACITY,
         )
@@ -306,21 +344,7 @@ impl MockLedger {
             .collect::<Result<Vec<_>, _>>()
             .unwrap();
 
-        let srs = {
-            // load SRS from Aztec's ceremony
-            let srs = ark_srs::kzg10::aztec20::setup(2u64.pow(16) as usize + 2)
-                .expect("Aztec SRS fail to load");
-            // convert to Jellyfish type
-            // TODO: (alex) use constructor instead https://github.com/EspressoSystems/jellyfish/issues/440
-            UnivariateUniversalParams {
-                powers_of_g: srs.powers_of_g,
-                h: srs.h,
-                beta_h: srs.beta_h,
-                powers_of_h: vec![srs.h, srs.beta_h],
-            }
-        };
-        let (pk, _) = preprocess(&srs, STAKE_TABLE_CAPACITY)
-            .expect("Fail to preprocess state prover circuit");
+        let pk = self.keys().0.clone();
         let stake_table_entries = adv_st
             .try_iter(SnapshotVersion::LastEpochStart)
             .unwrap()
@@ -333,6 +357,7 @@ impl MockLedger {
             &bit_vec,
             &sigs,
             &new_state,
+            &new_state.stake_table_comm,
             &self.threshold, // it's fine to use the old threshold
             STAKE_TABLE_CAPACITY,
         )
@@ -343,6 +368,8 @@ impl MockLedger {
     /// Returns the `LightClientState` for solidity
     pub fn get_state(&self) -> ParsedLightClientState {
         // The ugly conversion due to slight difference of `LightClientState` in solidity containing `threshold`
+        let (bls_key_comm, schnorr_key_comm, amount_comm) =
+            self.st.commitment(SnapshotVersion::LastEpochStart).unwrap();
         let pi = vec![
             u256_to_field(self.threshold),
             F::from(self.state.view_number as u64),
@@ -352,6 +379,9 @@ impl MockLedger {
             self.state.stake_table_comm.0,
             self.state.stake_table_comm.1,
             self.state.stake_table_comm.2,
+            bls_key_comm,
+            schnorr_key_comm,
+            amount_comm,
         ];
         let pi: GenericPublicInput<F> = pi.into();
         pi.into()
@@ -542,3 +572,37 @@ pub fn gen_circuit_for_test<F: PrimeField>(m: usize, a0: usize) -> Result<PlonkC
 
     Ok(cs)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stake_table_rotation() {
+        let pp = MockSystemParam::init(4);
+        let mut ledger = MockLedger::init(pp, STAKE_TABLE_CAPACITY / 2).with_stake_table_rotation();
+        let vk = ledger.verifying_key();
+
+        // the stake table commitment of the last proven state, which the light client contract
+        // expects the next state to be signed by
+        let mut finalized_comm = ledger.state.stake_table_comm;
+        let mut rotations = 0;
+        for _ in 0..4 {
+            ledger.elapse_epoch(2, 1);
+            let (pi, proof) = ledger.gen_state_proof();
+            PlonkKzgSnark::<Bn254>::verify::<SolidityTranscript>(&vk, pi.as_ref(), &proof, None)
+                .unwrap();
+
+            // the proof is against the stake table of the finalized state, even though the new
+            // state commits to a different one, and both are public
+            let pi: &[F] = pi.as_ref();
+            assert_eq!((pi[5], pi[6], pi[7]), ledger.state.stake_table_comm);
+            assert_eq!((pi[8], pi[9], pi[10]), finalized_comm);
+            if ledger.state.stake_table_comm != finalized_comm {
+                rotations += 1;
+            }
+            finalized_comm = ledger.state.stake_table_comm;
+        }
+        assert!(rotations > 0);
+    }
+}
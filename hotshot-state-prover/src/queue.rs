@@ -0,0 +1,331 @@
+//! A queue of generated proofs waiting to be submitted to the light client contract.
+//!
+//! Proofs are kept until the state they prove is finalized on L1, so that a failed submission (for
+//! example, due to a gas spike) can be retried without generating the proof again. If the queue is
+//! backed by a directory, pending proofs also survive restarts of the prover.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_std::future::timeout;
+use contract_bindings::light_client::LightClient;
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    contract::{EthAbiCodec, EthAbiType},
+    providers::Middleware,
+    types::{BlockId, BlockNumber, H256, U256},
+};
+use hotshot_contract_adapter::{jellyfish::ParsedPlonkProof, light_client::ParsedLightClientState};
+
+use crate::{
+    service::{L1Wallet, ProverError},
+    snark::Proof,
+};
+
+/// Percentage by which the gas price is raised when replacing a transaction which was not mined.
+///
+/// Nodes only accept a replacement transaction if it raises the gas price by at least 10%.
+const GAS_PRICE_BUMP_PERCENT: u64 = 25;
+
+/// Number of times a transaction is sent, with increasing gas price, before giving up.
+const MAX_SUBMISSION_ATTEMPTS: usize = 5;
+
+/// A proof of a new light client state, ready to be submitted.
+#[derive(Clone, Debug, EthAbiType, EthAbiCodec)]
+pub struct PendingProof {
+    pub state: ParsedLightClientState,
+    pub proof: ParsedPlonkProof,
+}
+
+impl PendingProof {
+    pub fn new(proof: Proof, state: ParsedLightClientState) -> Self {
+        Self {
+            state,
+            proof: proof.into(),
+        }
+    }
+
+    /// The block height of the proven state.
+    pub fn height(&self) -> u64 {
+        self.state.block_height
+    }
+}
+
+/// Proofs which have not yet been finalized on L1, by block height.
+#[derive(Debug, Default)]
+pub struct ProofQueue {
+    dir: Option<PathBuf>,
+    proofs: BTreeMap<u64, PendingProof>,
+    /// Nonce and gas price of the last transaction sent, if it may still be pending.
+    last_tx: Option<(U256, U256)>,
+}
+
+impl ProofQueue {
+    /// A queue which is not persisted.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Open a queue persisted in `dir`, loading any proofs saved there.
+    pub fn open(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).context(format!("creating {}", dir.display()))?;
+
+        let mut proofs = BTreeMap::new();
+        for entry in fs::read_dir(&dir).context(format!("reading {}", dir.display()))? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("proof") {
+                continue;
+            }
+            let proof = match fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Ok(PendingProof::decode(bytes)?))
+            {
+                Ok(proof) => proof,
+                Err(err) => {
+                    tracing::warn!("skipping unreadable proof {}: {err:#}", path.display());
+                    continue;
+                }
+            };
+            proofs.insert(proof.height(), proof);
+        }
+        tracing::info!(
+            "loaded {} pending proofs from {}",
+            proofs.len(),
+            dir.display()
+        );
+
+        Ok(Self {
+            dir: Some(dir),
+            proofs,
+            last_tx: None,
+        })
+    }
+
+    /// The number of pending proofs.
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// The pending proof of the most recent state.
+    pub fn latest(&self) -> Option<&PendingProof> {
+        self.proofs.values().next_back()
+    }
+
+    /// Add a proof to the queue.
+    pub fn push(&mut self, proof: PendingProof) -> anyhow::Result<()> {
+        if let Some(dir) = &self.dir {
+            // Write to a temporary file and rename, so that a crash never leaves a partial proof
+            // behind.
+            let path = proof_path(dir, proof.height());
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, proof.clone().encode())
+                .context(format!("writing {}", tmp.display()))?;
+            fs::rename(&tmp, &path).context(format!("renaming {}", tmp.display()))?;
+        }
+        self.proofs.insert(proof.height(), proof);
+        Ok(())
+    }
+
+    /// Remove the proof for the state at `height`.
+    pub fn remove(&mut self, height: u64) -> anyhow::Result<()> {
+        if self.proofs.remove(&height).is_some() {
+            if let Some(dir) = &self.dir {
+                let path = proof_path(dir, height);
+                fs::remove_file(&path).context(format!("removing {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove proofs which are made obsolete by a finalized state at `finalized_height`.
+    pub fn prune(&mut self, finalized_height: u64) -> anyhow::Result<()> {
+        let obsolete = self
+            .proofs
+            .range(..=finalized_height)
+            .map(|(height, _)| *height)
+            .collect::<Vec<_>>();
+        for height in obsolete {
+            self.remove(height)?;
+        }
+        Ok(())
+    }
+
+    /// Submit the pending proof for the state at `height` to the light client contract.
+    ///
+    /// If the transaction is not mined within `mining_timeout`, it is replaced by one with a higher
+    /// gas price. A transaction still pending from a previous call is replaced as well, rather than
+    /// queued behind. The proof is removed from the queue once the transaction is mined, even if
+    /// it reverted, since the contract would reject the proof again. If submission fails for any
+    /// other reason, the proof is kept to be retried.
+    ///
+    /// Returns the hash of the transaction which finalized the state, or `None` if another prover
+    /// finalized it (or a later state) first.
+    pub async fn submit(
+        &mut self,
+        height: u64,
+        contract: &LightClient<L1Wallet>,
+        mining_timeout: Duration,
+    ) -> Result<Option<H256>, ProverError> {
+        let pending = self
+            .proofs
+            .get(&height)
+            .ok_or_else(|| ProverError::Internal(format!("no pending proof for height {height}")))?
+            .clone();
+        let client = contract.client();
+
+        // Use the nonce of the first transaction not yet mined, so that a transaction left pending
+        // by a previous attempt is replaced.
+        let nonce = client
+            .get_transaction_count(client.address(), Some(BlockId::Number(BlockNumber::Latest)))
+            .await
+            .map_err(|err| ProverError::ContractError(err.into()))?;
+        let mut gas_price = client
+            .get_gas_price()
+            .await
+            .map_err(|err| ProverError::ContractError(err.into()))?;
+        if let Some((last_nonce, last_gas_price)) = self.last_tx {
+            if last_nonce == nonce {
+                gas_price = gas_price.max(bump_gas_price(last_gas_price));
+            }
+        }
+
+        let mut tx = contract
+            .new_finalized_state(pending.state.into(), pending.proof.into())
+            .tx;
+        tx.set_nonce(nonce);
+        let mut sent = vec![];
+        for attempt in 1..=MAX_SUBMISSION_ATTEMPTS {
+            tx.set_gas_price(gas_price);
+            let pending_tx = client
+                .send_transaction(tx.clone(), None)
+                .await
+                .map_err(|err| ProverError::ContractError(err.into()))?;
+            let hash = pending_tx.tx_hash();
+            sent.push(hash);
+            self.last_tx = Some((nonce, gas_price));
+            tracing::info!(
+                height,
+                %nonce,
+                %gas_price,
+                attempt,
+                "submitted state update in transaction {hash:#x}"
+            );
+
+            match timeout(mining_timeout, pending_tx).await {
+                Ok(Ok(Some(receipt))) => {
+                    self.last_tx = None;
+                    self.remove(height)
+                        .map_err(|err| ProverError::Internal(format!("{err:#}")))?;
+                    if receipt.status != Some(1.into()) {
+                        return Err(ProverError::ContractError(anyhow::anyhow!(
+                            "transaction {hash:#x} reverted; dropped the proof for height {height}"
+                        )));
+                    }
+                    return Ok(Some(receipt.transaction_hash));
+                }
+                Ok(Ok(None)) => {
+                    tracing::warn!("transaction {hash:#x} was dropped, resubmitting");
+                }
+                Ok(Err(err)) => return Err(ProverError::ContractError(err.into())),
+                Err(_) => {
+                    tracing::warn!(
+                        "transaction {hash:#x} not mined after {mining_timeout:?}, raising gas \
+                         price"
+                    );
+                }
+            }
+
+            // Before paying more, check whether the state has been finalized in the meantime,
+            // either by one of our transactions or by another prover.
+            let finalized = contract
+                .get_finalized_state()
+                .call()
+                .await
+                .map_err(|err| ProverError::ContractError(err.into()))?;
+            if finalized.block_height >= height {
+                let mut ours = None;
+                for hash in &sent {
+                    if let Ok(Some(receipt)) = client.get_transaction_receipt(*hash).await {
+                        if receipt.status == Some(1.into()) {
+                            ours = Some(*hash);
+                            self.last_tx = None;
+                        }
+                    }
+                }
+                if ours.is_none() {
+                    tracing::info!(
+                        height,
+                        finalized_height = finalized.block_height,
+                        "state was finalized by another prover"
+                    );
+                }
+                self.remove(height)
+                    .map_err(|err| ProverError::Internal(format!("{err:#}")))?;
+                return Ok(ours);
+            }
+            gas_price = bump_gas_price(gas_price);
+        }
+
+        Err(ProverError::ContractError(anyhow::anyhow!(
+            "state update for height {height} not mined after {MAX_SUBMISSION_ATTEMPTS} attempts"
+        )))
+    }
+}
+
+fn proof_path(dir: &Path, height: u64) -> PathBuf {
+    dir.join(format!("{height}.proof"))
+}
+
+fn bump_gas_price(gas_price: U256) -> U256 {
+    gas_price * (100 + GAS_PRICE_BUMP_PERCENT) / 100 + 1
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn proof(height: u64) -> PendingProof {
+        let mut state = ParsedLightClientState::dummy_genesis();
+        state.block_height = height;
+        PendingProof {
+            state,
+            proof: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_proof_queue_persistence() {
+        let tmp = TempDir::new().unwrap();
+
+        let mut queue = ProofQueue::open(tmp.path()).unwrap();
+        assert!(queue.is_empty());
+        for height in [3, 1, 2] {
+            queue.push(proof(height)).unwrap();
+        }
+        assert_eq!(queue.latest().unwrap().height(), 3);
+
+        // Proofs are restored when the queue is reopened.
+        let mut queue = ProofQueue::open(tmp.path()).unwrap();
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.latest().unwrap().state, proof(3).state);
+
+        // Pruning removes proofs up to the finalized height, also from storage.
+        queue.prune(2).unwrap();
+        assert_eq!(queue.len(), 1);
+        let queue = ProofQueue::open(tmp.path()).unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.latest().unwrap().height(), 3);
+    }
+}
@@ -1,35 +1,40 @@
 //! A light client prover service
 
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, VecDeque},
     iter,
+    path::PathBuf,
     time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use async_std::{
     io,
-    sync::Arc,
+    sync::{Arc, RwLock},
     task::{sleep, spawn, spawn_blocking},
 };
-use contract_bindings::light_client::{LightClient, LightClientErrors};
+use contract_bindings::{
+    light_client::{LightClient, LightClientErrors},
+    stake_table::StakeTable as StakeTableContract,
+};
 use displaydoc::Display;
 use ethers::{
     core::k256::ecdsa::SigningKey,
     middleware::SignerMiddleware,
     providers::{Http, Middleware, Provider, ProviderError},
     signers::{LocalWallet, Signer, Wallet},
-    types::{Address, U256},
+    types::{Address, H256, U256},
 };
 use futures::FutureExt;
 use hotshot_contract_adapter::{
     jellyfish::{u256_to_field, ParsedPlonkProof},
-    light_client::ParsedLightClientState,
+    light_client::{ParsedLightClientState, StateUpdateHistory},
 };
 use hotshot_stake_table::vec_based::{config::FieldType, StakeTable};
 use hotshot_types::{
     light_client::{
-        CircuitField, GenericPublicInput, LightClientState, PublicInput, StateSignaturesBundle,
-        StateVerKey,
+        CircuitField, GenericPublicInput, LightClientState, StateSignaturesBundle, StateVerKey,
     },
     signature_key::BLSPubKey,
     traits::{
@@ -38,10 +43,9 @@ use hotshot_types::{
     },
     PeerConfig,
 };
-use jf_pcs::prelude::UnivariateUniversalParams;
 use jf_plonk::errors::PlonkError;
-use jf_relation::Circuit as _;
 use jf_signature::constants::CS_ID_SCHNORR;
+use sequencer_utils::stake_table::{registered_nodes, stake_table_entries};
 use serde::Deserialize;
 use surf_disco::Client;
 use tide_disco::{error::ServerError, Api};
@@ -49,7 +53,13 @@ use time::ext::InstantExt;
 use url::Url;
 use vbs::version::StaticVersionType;
 
-use crate::snark::{generate_state_update_proof, Proof, ProvingKey};
+use crate::{
+    coordination::{defer_reason, permissioned_prover_reason, ProverRotation},
+    keys::{check_verifying_key, ProverKeyRegistry, ProverKeys},
+    queue::{PendingProof, ProofQueue},
+    snark::{generate_state_update_proof, Proof, ProvingKey},
+    status::{ProverMonitor, SignatureWait, StateUpdate},
+};
 
 type F = ark_ed_on_bn254::Fq;
 
@@ -72,14 +82,46 @@ pub struct StateProverConfig {
     /// Transaction signing key for Ethereum
     pub eth_signing_key: SigningKey,
     /// URL of a node that is currently providing the HotShot config.
-    /// This is used to initialize the stake table.
+    /// This is used to initialize the stake table, and is polled for stake table changes unless
+    /// `stake_table_address` is provided.
     pub sequencer_url: Url,
+    /// Address of a StakeTable contract on layer 1 to read stake table changes from, instead of the
+    /// HotShot config.
+    ///
+    /// Only set this if the sequencers derive their stake table from the same contract, since the
+    /// light client states they sign commit to their stake table, and the prover cannot prove
+    /// states signed by any other stake table.
+    pub stake_table_address: Option<Address>,
+    /// The L1 block in which the StakeTable contract was deployed.
+    ///
+    /// Registrations are only searched for from this block onwards.
+    pub stake_table_deployment_block: u64,
     /// If daemon and provided, the service will run a basic HTTP server on the given port.
     ///
-    /// The server provides healthcheck and version endpoints.
+    /// The server provides healthcheck, version, status and metrics endpoints.
     pub port: Option<u16>,
-    /// Stake table capacity for the prover circuit.
+    /// Stake table capacity for the prover circuit, if the keys are generated on startup.
     pub stake_table_capacity: usize,
+    /// Path to proving keys generated ahead of time.
+    ///
+    /// This is either a key file, or a directory of key files for several stake table capacities.
+    /// If not provided, the keys are generated on startup, which requires downloading the SRS.
+    pub proving_key_path: Option<PathBuf>,
+    /// Number of L1 blocks without a light client update after which rollups may enter escape
+    /// hatch mode.
+    ///
+    /// If provided, the prover reports (and logs an error) when the light client falls this far
+    /// behind.
+    pub escape_hatch_threshold: Option<u64>,
+    /// Directory in which to keep proofs until they are finalized on L1.
+    ///
+    /// If not provided, pending proofs are only kept in memory, and are lost on restart.
+    pub proof_queue_path: Option<PathBuf>,
+    /// How long to wait for a submitted transaction to be mined before replacing it with one with
+    /// a higher gas price.
+    pub submission_timeout: Duration,
+    /// Take turns with other provers, rather than updating the light client whenever possible.
+    pub rotation: Option<ProverRotation>,
 }
 
 #[inline]
@@ -121,39 +163,63 @@ async fn init_stake_table_from_sequencer(
 ) -> Result<StakeTable<BLSPubKey, StateVerKey, CircuitField>> {
     tracing::info!("Initializing stake table from node at {sequencer_url}");
 
+    // Request the stake table until it is successful
+    loop {
+        match fetch_stake_table_from_sequencer(sequencer_url, stake_table_capacity).await {
+            Ok(st) => break Ok(st),
+            Err(e) => {
+                tracing::error!("Failed to fetch the stake table: {e:#}");
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
+
+/// Fetch the current stake table from a sequencer node that
+/// is currently providing the HotShot config.
+pub async fn fetch_stake_table_from_sequencer(
+    sequencer_url: &Url,
+    stake_table_capacity: usize,
+) -> Result<StakeTable<BLSPubKey, StateVerKey, CircuitField>> {
+    let nodes = fetch_known_nodes_from_sequencer(sequencer_url).await?;
+    stake_table_from_nodes(&nodes, stake_table_capacity)
+}
+
+/// Fetch the nodes in the current stake table from a sequencer node that
+/// is currently providing the HotShot config.
+async fn fetch_known_nodes_from_sequencer(
+    sequencer_url: &Url,
+) -> Result<Vec<PeerConfig<BLSPubKey>>> {
     // Construct the URL to fetch the network config
     let config_url = sequencer_url
         .join("/v0/config/hotshot")
         .with_context(|| "Invalid URL")?;
 
-    // Request the configuration until it is successful
-    let network_config: PublicHotShotConfig = loop {
-        match reqwest::get(config_url.clone()).await {
-            Ok(resp) => match resp.json::<PublicHotShotConfig>().await {
-                Ok(config) => break config,
-                Err(e) => {
-                    tracing::error!("Failed to parse the network config: {e}");
-                    sleep(Duration::from_secs(5)).await;
-                }
-            },
-            Err(e) => {
-                tracing::error!("Failed to fetch the network config: {e}");
-                sleep(Duration::from_secs(5)).await;
-            }
-        }
-    };
+    let network_config: PublicHotShotConfig = reqwest::get(config_url)
+        .await
+        .context("fetching the network config")?
+        .json()
+        .await
+        .context("parsing the network config")?;
+    Ok(network_config.known_nodes_with_stake)
+}
 
+/// Build a stake table with capacity `stake_table_capacity` from `nodes`.
+fn stake_table_from_nodes(
+    nodes: &[PeerConfig<BLSPubKey>],
+    stake_table_capacity: usize,
+) -> Result<StakeTable<BLSPubKey, StateVerKey, CircuitField>> {
     // Create empty stake table
     let mut st = StakeTable::<BLSPubKey, StateVerKey, CircuitField>::new(stake_table_capacity);
 
     // Populate the stake table
-    for node in network_config.known_nodes_with_stake.into_iter() {
+    for node in nodes {
         st.register(
             *node.stake_table_entry.key(),
             node.stake_table_entry.stake(),
-            node.state_ver_key,
+            node.state_ver_key.clone(),
         )
-        .expect("Key registration shouldn't fail.");
+        .context("registering key")?;
     }
 
     // Advance the stake table
@@ -163,6 +229,189 @@ async fn init_stake_table_from_sequencer(
     Ok(st)
 }
 
+/// Record the stake table made up of `nodes` in `stake_tables`, for each of `capacities` large
+/// enough to hold it.
+///
+/// Fails if the stake table does not fit in any of the capacities.
+fn record_stake_table(
+    nodes: &[PeerConfig<BLSPubKey>],
+    capacities: impl IntoIterator<Item = usize>,
+    stake_tables: &mut StakeTableHistory,
+) -> Result<()> {
+    let mut recorded = false;
+    for capacity in capacities {
+        if nodes.len() > capacity {
+            tracing::debug!(
+                "Stake table with {} nodes exceeds capacity {capacity}",
+                nodes.len()
+            );
+            continue;
+        }
+        stake_tables.update(capacity, stake_table_from_nodes(nodes, capacity)?)?;
+        recorded = true;
+    }
+    ensure!(
+        recorded,
+        "stake table with {} nodes exceeds the capacity of every proving key",
+        nodes.len()
+    );
+    Ok(())
+}
+
+/// Fetch the latest stake tables, and record them in `stake_tables` for each of `capacities` large
+/// enough to hold them.
+///
+/// The stake tables come from the same source as the sequencers' stake table: the HotShot config,
+/// or the StakeTable contract if one is configured (see [`StateProverConfig::stake_table_address`]).
+async fn refresh_stake_tables(
+    config: &StateProverConfig,
+    capacities: impl IntoIterator<Item = usize> + Clone,
+    stake_tables: &mut StakeTableHistory,
+) -> Result<()> {
+    match config.stake_table_address {
+        Some(address) => {
+            refresh_stake_tables_from_contract(config, address, capacities, stake_tables).await
+        }
+        None => {
+            let nodes = fetch_known_nodes_from_sequencer(&config.sequencer_url).await?;
+            record_stake_table(&nodes, capacities, stake_tables)
+        }
+    }
+}
+
+/// Read the stake tables of the epochs around the light client's current epoch from the StakeTable
+/// contract at `address`, and record them in `stake_tables` for each of `capacities` large enough to
+/// hold them.
+///
+/// The voting stake table lags behind the current epoch, while signed states may already commit to
+/// the stake table of the next epoch, so the stake tables from two epochs ago up to the next epoch
+/// are recorded.
+async fn refresh_stake_tables_from_contract(
+    config: &StateProverConfig,
+    address: Address,
+    capacities: impl IntoIterator<Item = usize> + Clone,
+    stake_tables: &mut StakeTableHistory,
+) -> Result<()> {
+    let provider = Provider::<Http>::try_from(config.l1_provider.to_string())?;
+    let contract = StakeTableContract::new(address, Arc::new(provider));
+    let nodes = registered_nodes(&contract, config.stake_table_deployment_block).await?;
+    let epoch = contract
+        .current_epoch()
+        .call()
+        .await
+        .context("reading the current epoch")?;
+    for epoch in epoch.saturating_sub(2)..=epoch + 1 {
+        record_stake_table(
+            &stake_table_entries(&nodes, epoch),
+            capacities.clone(),
+            stake_tables,
+        )
+        .context(format!("recording the stake table for epoch {epoch}"))?;
+    }
+    Ok(())
+}
+
+/// Initialize the stake tables for each of `capacities` from a sequencer node that is currently
+/// providing the HotShot config.
+///
+/// Does not error, runs until the stake table is provided.
+async fn init_stake_tables_from_sequencer(
+    sequencer_url: &Url,
+    capacities: impl IntoIterator<Item = usize> + Clone,
+) -> StakeTableHistory {
+    tracing::info!("Initializing stake tables from node at {sequencer_url}");
+
+    let mut stake_tables = StakeTableHistory::default();
+    loop {
+        let res = async {
+            let nodes = fetch_known_nodes_from_sequencer(sequencer_url).await?;
+            record_stake_table(&nodes, capacities.clone(), &mut stake_tables)
+        };
+        match res.await {
+            Ok(()) => break stake_tables,
+            Err(e) => {
+                tracing::error!("Failed to fetch the stake table: {e:#}");
+                sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
+
+/// Fetch the height of the latest block from a sequencer node.
+pub async fn fetch_sequencer_block_height(sequencer_url: &Url) -> Result<u64> {
+    let url = sequencer_url
+        .join("/v0/status/block-height")
+        .with_context(|| "Invalid URL")?;
+    let block_height: u64 = reqwest::get(url)
+        .await
+        .context("fetching the block height")?
+        .json()
+        .await
+        .context("parsing the block height")?;
+    // The block height is the number of blocks, so the latest block is one below it.
+    Ok(block_height.saturating_sub(1))
+}
+
+/// Number of past stake tables kept by a [`StakeTableHistory`] for each capacity.
+const STAKE_TABLE_HISTORY_LEN: usize = 8;
+
+/// The stake tables a prover has seen for each capacity, most recent last.
+///
+/// The light client contract checks each new state against its voting stake table, not the latest
+/// one. After the stake table rotates, the prover must keep proving with the old table until the new
+/// table starts voting, so a few past tables are kept and looked up by commitment.
+///
+/// The commitment also depends on the capacity the stake table is padded to, and thus determines
+/// which circuit (and proving key) the next proof must use. Every rotation records a table for each
+/// capacity, so the history is kept separately per capacity, and the number of rotations it covers
+/// does not depend on how many proving keys are loaded.
+#[derive(Default)]
+pub struct StakeTableHistory {
+    tables: BTreeMap<usize, VecDeque<StakeTable<BLSPubKey, StateVerKey, CircuitField>>>,
+}
+
+impl StakeTableHistory {
+    pub fn new(capacity: usize, st: StakeTable<BLSPubKey, StateVerKey, CircuitField>) -> Self {
+        Self {
+            tables: [(capacity, [st].into())].into(),
+        }
+    }
+
+    /// Record the current stake table with capacity `capacity`, if it is not already known.
+    pub fn update(
+        &mut self,
+        capacity: usize,
+        st: StakeTable<BLSPubKey, StateVerKey, CircuitField>,
+    ) -> Result<(), StakeTableError> {
+        let comm = st.commitment(SnapshotVersion::LastEpochStart)?;
+        if self.get(&comm).is_some() {
+            return Ok(());
+        }
+
+        tracing::info!("Stake table changed, new commitment for capacity {capacity}: {comm:?}");
+        let tables = self.tables.entry(capacity).or_default();
+        tables.push_back(st);
+        if tables.len() > STAKE_TABLE_HISTORY_LEN {
+            tables.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Find the stake table with the given commitment, and its capacity.
+    pub fn get(
+        &self,
+        comm: &(CircuitField, CircuitField, CircuitField),
+    ) -> Option<(usize, &StakeTable<BLSPubKey, StateVerKey, CircuitField>)> {
+        self.tables.iter().find_map(|(capacity, tables)| {
+            tables.iter().rev().find_map(|st| {
+                st.commitment(SnapshotVersion::LastEpochStart)
+                    .is_ok_and(|st_comm| &st_comm == comm)
+                    .then_some((*capacity, st))
+            })
+        })
+    }
+}
+
 pub async fn light_client_genesis(
     sequencer_url: &Url,
     stake_table_capacity: usize,
@@ -190,44 +439,75 @@ pub fn light_client_genesis_from_stake_table(
         bls_comm,
         schnorr_comm,
         stake_comm,
+        // The genesis stake table also signs the first state update.
+        bls_comm,
+        schnorr_comm,
+        stake_comm,
     ];
     let pi: GenericPublicInput<F> = pi.into();
     Ok(pi.into())
 }
 
 pub fn load_proving_key(stake_table_capacity: usize) -> ProvingKey {
-    let srs = {
-        let num_gates = crate::circuit::build_for_preprocessing::<
-            CircuitField,
-            ark_ed_on_bn254::EdwardsConfig,
-        >(stake_table_capacity)
-        .unwrap()
-        .0
-        .num_gates();
-
-        std::println!("Loading SRS from Aztec's ceremony...");
-        let srs_timer = Instant::now();
-        let srs = ark_srs::kzg10::aztec20::setup(num_gates + 2).expect("Aztec SRS fail to load");
-        let srs_elapsed = Instant::now().signed_duration_since(srs_timer);
-        std::println!("Done in {srs_elapsed:.3}");
-
-        // convert to Jellyfish type
-        // TODO: (alex) use constructor instead https://github.com/EspressoSystems/jellyfish/issues/440
-        UnivariateUniversalParams {
-            powers_of_g: srs.powers_of_g,
-            h: srs.h,
-            beta_h: srs.beta_h,
-            powers_of_h: vec![srs.h, srs.beta_h],
+    ProverKeys::generate(stake_table_capacity).proving_key
+}
+
+/// Load the proving keys configured for the prover, generating them if no key file is given.
+///
+/// Keys for several stake table capacities can be loaded from a directory. At least one of them
+/// must match the verifying key deployed in the light client contract; the others are kept for use
+/// after the contract is upgraded to a verifier for a different capacity.
+pub async fn prover_keys(config: &StateProverConfig) -> Result<Arc<ProverKeyRegistry>> {
+    let stake_table_capacity = config.stake_table_capacity;
+    let keys = match &config.proving_key_path {
+        Some(path) if path.is_dir() => {
+            let path = path.clone();
+            let (keys, hashes) = spawn_blocking(move || ProverKeyRegistry::load_dir(path)).await?;
+            for (capacity, hash) in hashes {
+                tracing::info!(
+                    "Loaded proving key for stake table capacity {capacity} with content hash \
+                     {hash}"
+                );
+            }
+            keys
         }
+        Some(path) => {
+            let path = path.clone();
+            let (keys, hash) = spawn_blocking(move || ProverKeys::load(path)).await?;
+            tracing::info!(
+                "Loaded proving key for stake table capacity {} with content hash {hash}",
+                keys.stake_table_capacity
+            );
+            keys.into()
+        }
+        None => spawn_blocking(move || ProverKeys::generate(stake_table_capacity))
+            .await
+            .into(),
     };
 
-    std::println!("Generating proving key and verification key.");
-    let key_gen_timer = Instant::now();
-    let (pk, _) = crate::snark::preprocess(&srs, stake_table_capacity)
-        .expect("Fail to preprocess state prover circuit");
-    let key_gen_elapsed = Instant::now().signed_duration_since(key_gen_timer);
-    std::println!("Done in {key_gen_elapsed:.3}");
-    pk
+    let mut deployed = None;
+    for capacity in keys.capacities() {
+        let vk = &keys.get(capacity).unwrap().verifying_key;
+        match check_verifying_key(vk, &config.l1_provider, config.light_client_address).await {
+            Ok(()) => deployed = Some(capacity),
+            Err(err) => {
+                tracing::info!("Proving key for capacity {capacity} is not deployed: {err:#}")
+            }
+        }
+    }
+    let deployed = deployed.context(
+        "none of the proving keys matches the verifying key deployed in the light client contract",
+    )?;
+    tracing::info!("Light client contract verifies proofs for stake table capacity {deployed}");
+    Ok(Arc::new(keys))
+}
+
+/// Open the proof queue configured for the prover.
+fn open_proof_queue(config: &StateProverConfig) -> Result<ProofQueue> {
+    match &config.proof_queue_path {
+        Some(path) => ProofQueue::open(path),
+        None => Ok(ProofQueue::in_memory()),
+    }
 }
 
 pub async fn fetch_latest_state<Ver: StaticVersionType>(
@@ -271,17 +551,72 @@ pub async fn read_contract_state(
     Ok(state)
 }
 
+/// get the state committing to the stake table which must sign the next update from the LightClient
+/// contract on L1
+///
+/// The contract checks the next proof against both the commitment and the threshold of this state.
+/// If the finalized state ends the current epoch, the next update starts a new epoch, and the
+/// contract rotates the frozen stake table into voting before checking the proof.
+pub async fn read_contract_voting_state(
+    config: &StateProverConfig,
+) -> Result<ParsedLightClientState, ProverError> {
+    let contract = prepare_contract(config).await?;
+    let state = async {
+        let finalized = contract.get_finalized_state().call().await?;
+        let epoch = contract.current_epoch().call().await?;
+        let blocks_per_epoch = contract.blocks_per_epoch().call().await?;
+        let state = if finalized.block_height == epoch * blocks_per_epoch as u64 {
+            contract.get_frozen_state().call().await?
+        } else {
+            contract.get_voting_state().call().await?
+        };
+        anyhow::Ok(state)
+    }
+    .await
+    .map_err(|e| {
+        tracing::error!("unable to read voting state from contract: {e:#}");
+        ProverError::ContractError(e)
+    })?;
+    Ok(state.into())
+}
+
+/// Read the current L1 block number, and the light client update history needed to tell how far
+/// the light client has lagged behind at that block.
+///
+/// The lag is computed from the LightClient contract's `stateUpdateBlockNumbers` with the same logic
+/// as its `lagOverEscapeHatchThreshold`, which rollups use to decide whether to enter escape hatch
+/// mode.
+pub async fn read_state_update_history(
+    config: &StateProverConfig,
+) -> Result<(u64, StateUpdateHistory), ProverError> {
+    let contract = prepare_contract(config).await?;
+    let l1_block = contract
+        .client()
+        .get_block_number()
+        .await
+        .map_err(|e| ProverError::ContractError(e.into()))?
+        .as_u64();
+    let history = StateUpdateHistory::fetch(&contract, l1_block)
+        .await
+        .map_err(|e| {
+            tracing::error!("unable to read state update history from contract: {e:#}");
+            ProverError::ContractError(e)
+        })?;
+    Ok((l1_block, history))
+}
+
 /// submit the latest finalized state along with a proof to the L1 LightClient contract
+///
+/// Returns the hash of the submitted transaction.
 pub async fn submit_state_and_proof(
     proof: Proof,
-    public_input: PublicInput,
+    new_state: ParsedLightClientState,
     config: &StateProverConfig,
-) -> Result<(), ProverError> {
+) -> Result<H256, ProverError> {
     let contract = prepare_contract(config).await?;
 
     // prepare the input the contract call and the tx itself
     let proof: ParsedPlonkProof = proof.into();
-    let new_state: ParsedLightClientState = public_input.into();
     let tx = contract.new_finalized_state(new_state.into(), proof.into());
 
     // send the tx
@@ -294,17 +629,106 @@ pub async fn submit_state_and_proof(
         receipt.transaction_hash,
     );
 
-    Ok(())
+    Ok(receipt.transaction_hash)
 }
 
+/// Submit the most recent proof in `queue` which is still valid.
+///
+/// Proofs of states which are already finalized are dropped from the queue, as are proofs which
+/// the contract would no longer accept (for example, because the finalized state has changed since
+/// they were generated). If the contract cannot be reached, the queue is left as is and an error is
+/// returned. Returns the submitted state, or `None` if there is no valid proof or another
+/// prover finalized the state first.
+pub async fn submit_queued_proof(
+    queue: &mut ProofQueue,
+    config: &StateProverConfig,
+) -> Result<Option<(LightClientState, H256)>, ProverError> {
+    let finalized = read_contract_state(config).await?;
+    queue
+        .prune(finalized.block_height as u64)
+        .map_err(|err| ProverError::Internal(format!("{err:#}")))?;
+
+    let contract = prepare_contract(config).await?;
+    while let Some(pending) = queue.latest().cloned() {
+        let height = pending.height();
+        // Simulate the update, to check that the proof is still valid before paying for it. Only a
+        // revert shows that the contract rejects the proof; any other error (for example, an L1
+        // RPC failure) says nothing about the proof, which is kept to be retried.
+        match contract
+            .new_finalized_state(pending.state.clone().into(), pending.proof.into())
+            .call()
+            .await
+        {
+            Ok(()) => {}
+            Err(err) if err.is_revert() => {
+                let reason = err
+                    .decode_contract_revert::<LightClientErrors>()
+                    .map_or_else(|| err.to_string(), |err| format!("{err:?}"));
+                tracing::warn!(
+                    "Dropping pending proof for height {height}, which is invalid: {reason}"
+                );
+                queue
+                    .remove(height)
+                    .map_err(|err| ProverError::Internal(format!("{err:#}")))?;
+                continue;
+            }
+            Err(err) => return Err(ProverError::ContractError(err.into())),
+        }
+
+        tracing::info!("Submitting pending proof for height {height}.");
+        return Ok(queue
+            .submit(height, &contract, config.submission_timeout)
+            .await?
+            .map(|tx_hash| (pending.state.into(), tx_hash)));
+    }
+    Ok(None)
+}
+
+/// Prove and submit the latest signed state, if it is newer than the finalized state.
+///
+/// Proofs are added to `queue` before they are submitted. If the queue already holds a valid proof,
+/// it is submitted instead of proving a new state, unless the contract only accepts updates from
+/// another permissioned prover. Returns the submitted update, or `None` if the light client is
+/// already up to date, or if the prover defers to other provers (see [`defer_reason`]).
+///
+/// `signature_wait` keeps track of how long the prover has been waiting for enough signatures on a
+/// new state, across calls.
 pub async fn sync_state<Ver: StaticVersionType>(
-    st: &StakeTable<BLSPubKey, StateVerKey, CircuitField>,
-    proving_key: Arc<ProvingKey>,
+    stake_tables: &mut StakeTableHistory,
+    signature_wait: &mut SignatureWait,
+    keys: &ProverKeyRegistry,
     relay_server_client: &Client<ServerError, Ver>,
+    queue: &mut ProofQueue,
     config: &StateProverConfig,
-) -> Result<(), ProverError> {
+) -> Result<Option<StateUpdate>, ProverError> {
     tracing::info!("Start syncing light client state.");
 
+    let contract = prepare_contract(config).await?;
+    if let Some(reason) = permissioned_prover_reason(&contract).await? {
+        tracing::info!("Not updating the light client: {reason}.");
+        return Ok(None);
+    }
+
+    // A queued proof was generated when this prover was allowed to update, and may be the only
+    // proof of its state if submission failed, so only the permissioned prover check applies to it.
+    // The rotation and back-off govern whether to generate a new proof.
+    if let Some((state, tx_hash)) = submit_queued_proof(queue, config).await? {
+        tracing::info!("Successfully synced light client state from a pending proof.");
+        return Ok(Some(StateUpdate {
+            state,
+            tx_hash,
+            proof_generation_time: None,
+            signature_wait: None,
+        }));
+    }
+
+    if let Some(reason) =
+        defer_reason(&contract, config.rotation.as_ref(), config.update_interval).await?
+    {
+        tracing::info!("Not updating the light client: {reason}.");
+        return Ok(None);
+    }
+
     let bundle = fetch_latest_state(relay_server_client).await?;
     tracing::info!("Bundle accumulated weight: {}", bundle.accumulated_weight);
     tracing::info!("Latest HotShot block height: {}", bundle.state.block_height);
@@ -315,12 +739,65 @@ pub async fn sync_state<Ver: StaticVersionType>(
     );
     if old_state.block_height >= bundle.state.block_height {
         tracing::info!("No update needed.");
-        return Ok(());
+        signature_wait.reset();
+        return Ok(None);
     }
+    signature_wait.start();
     tracing::debug!("Old state: {old_state:?}");
     tracing::debug!("New state: {:?}", bundle.state);
 
-    let threshold = one_honest_threshold(st.total_stake(SnapshotVersion::LastEpochStart)?);
+    // The new state must be signed by the voting stake table, which may differ from the one
+    // committed to by the new state. The proof is checked against the commitment and the threshold
+    // of that same stake table. The stake table rotates exactly when the signers commit to a
+    // different one, so we only need to look for new stake tables when the signed state commits to
+    // one we don't know yet.
+    let voting_state = read_contract_voting_state(config).await?;
+    let threshold = voting_state.threshold;
+    let signers_stake_table_comm = LightClientState::from(voting_state).stake_table_comm;
+    let new_stake_table_comm = bundle.state.stake_table_comm;
+    if stake_tables.get(&signers_stake_table_comm).is_none()
+        || stake_tables.get(&new_stake_table_comm).is_none()
+    {
+        tracing::info!("Signed state commits to an unknown stake table, fetching stake table.");
+        if let Err(err) = refresh_stake_tables(config, keys.capacities(), stake_tables).await {
+            tracing::warn!("Cannot refresh the stake table: {err:#}");
+        }
+    }
+    let (stake_table_capacity, st) =
+        stake_tables.get(&signers_stake_table_comm).ok_or_else(|| {
+            ProverError::InvalidState(format!(
+                "No known stake table matches the voting commitment \
+                 {signers_stake_table_comm:?}."
+            ))
+        })?;
+    // The new state also records the threshold for the stake table it commits to, which the
+    // contract uses once that stake table starts voting.
+    let (_, new_st) = stake_tables.get(&new_stake_table_comm).ok_or_else(|| {
+        ProverError::InvalidState(format!(
+            "No known stake table matches the signed commitment {new_stake_table_comm:?}."
+        ))
+    })?;
+    let new_threshold = one_honest_threshold(new_st.total_stake(SnapshotVersion::LastEpochStart)?);
+
+    // The capacity of that stake table determines the circuit, which the contract must be able to
+    // verify proofs for.
+    let keys = keys.get(stake_table_capacity).ok_or_else(|| {
+        ProverError::InvalidState(format!(
+            "No proving key for stake table capacity {stake_table_capacity}."
+        ))
+    })?;
+    check_verifying_key(
+        &keys.verifying_key,
+        &config.l1_provider,
+        config.light_client_address,
+    )
+    .await
+    .map_err(|err| {
+        ProverError::InvalidState(format!(
+            "The light client contract cannot verify proofs for stake table capacity \
+             {stake_table_capacity}, it must be upgraded to the matching verifier: {err:#}"
+        ))
+    })?;
     tracing::info!("Threshold before syncing state: {}", threshold);
     let entries = st
         .try_iter(SnapshotVersion::LastEpochStart)
@@ -349,45 +826,119 @@ pub async fn sync_state<Ver: StaticVersionType>(
     }
 
     tracing::info!("Collected latest state and signatures. Start generating SNARK proof.");
+    let signature_wait = signature_wait.stop();
     let proof_gen_start = Instant::now();
-    let stake_table_capacity = config.stake_table_capacity;
-    let (proof, public_input) = spawn_blocking(move || {
+    let new_state = ParsedLightClientState::new(&bundle.state, new_threshold);
+    let (proof, _) = spawn_blocking(move || {
         generate_state_update_proof::<_, _, _, _>(
             &mut ark_std::rand::thread_rng(),
-            &proving_key,
+            &keys.proving_key,
             &entries,
             signer_bit_vec,
             signatures,
             &bundle.state,
+            &signers_stake_table_comm,
             &threshold,
             stake_table_capacity,
         )
     })
     .await?;
+    let proof_generation_time = proof_gen_start.elapsed();
     let proof_gen_elapsed = Instant::now().signed_duration_since(proof_gen_start);
     tracing::info!("Proof generation completed. Elapsed: {proof_gen_elapsed:.3}");
 
-    submit_state_and_proof(proof, public_input, config).await?;
+    // Queue the proof before submitting it, so that it is not lost if submission fails. The queue
+    // checks that the proof is still needed: another prover may have updated the light client while
+    // this one was proving.
+    queue
+        .push(PendingProof::new(proof, new_state))
+        .map_err(|err| ProverError::Internal(format!("{err:#}")))?;
+    let Some((state, tx_hash)) = submit_queued_proof(queue, config).await? else {
+        tracing::info!("Another prover updated the light client first.");
+        return Ok(None);
+    };
 
     tracing::info!("Successfully synced light client state.");
-    Ok(())
+    Ok(Some(StateUpdate {
+        state,
+        tx_hash,
+        proof_generation_time: Some(proof_generation_time),
+        signature_wait,
+    }))
+}
+
+/// Report how far the light client contract is behind the sequencer.
+async fn update_light_client_status(config: &StateProverConfig, monitor: &ProverMonitor) {
+    let light_client_height = match read_contract_state(config).await {
+        Ok(state) => state.block_height as u64,
+        Err(err) => {
+            tracing::warn!("Cannot read the light client state: {err}");
+            return;
+        }
+    };
+    let sequencer_height = match fetch_sequencer_block_height(&config.sequencer_url).await {
+        Ok(height) => Some(height),
+        Err(err) => {
+            tracing::warn!("Cannot fetch the sequencer's block height: {err:#}");
+            None
+        }
+    };
+    let (escape_hatch_lag, lag_over_escape_hatch_threshold) =
+        match read_state_update_history(config).await {
+            Ok((l1_block, history)) => (
+                history.lag(l1_block),
+                config.escape_hatch_threshold.and_then(|threshold| {
+                    history.lag_over_escape_hatch_threshold(l1_block, threshold)
+                }),
+            ),
+            Err(err) => {
+                tracing::warn!("Cannot check the escape hatch lag: {err}");
+                (None, None)
+            }
+        };
+    if lag_over_escape_hatch_threshold == Some(true) {
+        tracing::error!(
+            light_client_height,
+            ?sequencer_height,
+            ?escape_hatch_lag,
+            "The light client has fallen behind the escape hatch threshold of {} L1 blocks",
+            config.escape_hatch_threshold.unwrap_or_default()
+        );
+    }
+    monitor
+        .record_light_client(
+            light_client_height,
+            sequencer_height,
+            escape_hatch_lag,
+            lag_over_escape_hatch_threshold,
+        )
+        .await;
 }
 
 fn start_http_server<Ver: StaticVersionType + 'static>(
     port: u16,
     lightclient_address: Address,
+    monitor: ProverMonitor,
     bind_version: Ver,
 ) -> io::Result<()> {
-    let mut app = tide_disco::App::<(), ServerError>::with_state(());
+    let mut app = tide_disco::App::<_, ServerError>::with_state(RwLock::new(monitor));
     let toml = toml::from_str::<toml::value::Value>(include_str!("../api/prover-service.toml"))
         .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
-    let mut api = Api::<(), ServerError, Ver>::new(toml)
+    let mut api = Api::<RwLock<ProverMonitor>, ServerError, Ver>::new(toml)
         .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
     api.get("getlightclientcontract", move |_, _| {
         async move { Ok(lightclient_address) }.boxed()
     })
+    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+    .get("getstatus", |_, monitor| {
+        async move { Ok(monitor.status().await) }.boxed()
+    })
+    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+    .metrics("metrics", |_, monitor| {
+        async move { Ok(Cow::Borrowed(monitor.registry())) }.boxed()
+    })
     .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
     app.register_module("api", api)
@@ -401,35 +952,56 @@ pub async fn run_prover_service<Ver: StaticVersionType + 'static>(
     config: StateProverConfig,
     bind_version: Ver,
 ) -> Result<()> {
-    let stake_table_capacity = config.stake_table_capacity;
-    tracing::info!("Stake table capacity: {}", stake_table_capacity);
-    // TODO(#1022): maintain the following stake table
-    let st = Arc::new(
-        init_stake_table_from_sequencer(&config.sequencer_url, stake_table_capacity)
-            .await
-            .with_context(|| "Failed to initialize stake table")?,
-    );
-
     tracing::info!("Light client address: {:?}", config.light_client_address);
     let relay_server_client =
         Arc::new(Client::<ServerError, Ver>::new(config.relay_server.clone()));
 
     // Start the HTTP server to get a functioning healthcheck before any heavy computations.
+    let monitor = ProverMonitor::default();
     if let Some(port) = config.port {
-        if let Err(err) = start_http_server(port, config.light_client_address, bind_version) {
+        if let Err(err) = start_http_server(
+            port,
+            config.light_client_address,
+            monitor.clone(),
+            bind_version,
+        ) {
             tracing::error!("Error starting http server: {}", err);
         }
     }
 
-    let proving_key =
-        spawn_blocking(move || Arc::new(load_proving_key(stake_table_capacity))).await;
+    let keys = prover_keys(&config).await?;
+    tracing::info!(
+        "Stake table capacities: {:?}",
+        keys.capacities().collect::<Vec<_>>()
+    );
+    let mut stake_tables =
+        init_stake_tables_from_sequencer(&config.sequencer_url, keys.capacities()).await;
+    let mut queue = open_proof_queue(&config)?;
 
     let update_interval = config.update_interval;
     let retry_interval = config.retry_interval;
+    let mut signature_wait = SignatureWait::default();
     loop {
-        if let Err(err) = sync_state(&st, proving_key.clone(), &relay_server_client, &config).await
-        {
-            tracing::error!("Cannot sync the light client state, will retry: {}", err);
+        let result = sync_state(
+            &mut stake_tables,
+            &mut signature_wait,
+            &keys,
+            &relay_server_client,
+            &mut queue,
+            &config,
+        )
+        .await;
+        match &result {
+            Ok(Some(update)) => monitor.record_update(update).await,
+            Ok(None) => monitor.record_no_update().await,
+            Err(err) => {
+                tracing::error!("Cannot sync the light client state, will retry: {}", err);
+                monitor.record_error(err).await;
+            }
+        }
+        update_light_client_status(&config, &monitor).await;
+
+        if result.is_err() {
             sleep(retry_interval).await;
         } else {
             tracing::info!("Sleeping for {:?}", update_interval);
@@ -443,17 +1015,22 @@ pub async fn run_prover_once<Ver: StaticVersionType>(
     config: StateProverConfig,
     _: Ver,
 ) -> Result<()> {
-    let st = init_stake_table_from_sequencer(&config.sequencer_url, config.stake_table_capacity)
-        .await
-        .with_context(|| "Failed to initialize stake table")?;
-    let stake_table_capacity = config.stake_table_capacity;
-    let proving_key =
-        spawn_blocking(move || Arc::new(load_proving_key(stake_table_capacity))).await;
+    let keys = prover_keys(&config).await?;
+    let mut stake_tables =
+        init_stake_tables_from_sequencer(&config.sequencer_url, keys.capacities()).await;
+    let mut queue = open_proof_queue(&config)?;
     let relay_server_client = Client::<ServerError, Ver>::new(config.relay_server.clone());
 
-    sync_state(&st, proving_key, &relay_server_client, &config)
-        .await
-        .expect("Error syncing the light client state.");
+    sync_state(
+        &mut stake_tables,
+        &mut SignatureWait::default(),
+        &keys,
+        &relay_server_client,
+        &mut queue,
+        &config,
+    )
+    .await
+    .expect("Error syncing the light client state.");
 
     Ok(())
 }
@@ -506,15 +1083,18 @@ mod test {
     use anyhow::Result;
     use ark_ed_on_bn254::EdwardsConfig;
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+    use contract_bindings::light_client_mock::LightClientMock;
     use ethers::{
         abi::AbiEncode,
         utils::{Anvil, AnvilInstance},
     };
     use hotshot_stake_table::vec_based::StakeTable;
     use hotshot_types::light_client::StateSignKey;
+    use jf_pcs::prelude::UnivariateUniversalParams;
     use jf_signature::{schnorr::SchnorrSignatureScheme, SignatureScheme};
     use jf_utils::test_rng;
     use sequencer_utils::deployer;
+    use tempfile::TempDir;
 
     use super::*;
     use crate::mock_ledger::{MockLedger, MockSystemParam};
@@ -566,7 +1146,7 @@ mod test {
         new_state: ParsedLightClientState,
         state_keypairs: &[(StateSignKey, StateVerKey)],
         st: &StakeTable<BLSPubKey, StateVerKey, CircuitField>,
-    ) -> (PublicInput, Proof) {
+    ) -> Proof {
         let mut rng = test_rng();
 
         let new_state_msg: [CircuitField; 7] = {
@@ -603,19 +1183,20 @@ mod test {
             .unwrap()
             .map(|(_, stake_amount, schnorr_key)| (schnorr_key, stake_amount))
             .collect::<Vec<_>>();
-        let (proof, pi) = crate::generate_state_update_proof::<_, _, _, _>(
+        let (proof, _) = crate::generate_state_update_proof::<_, _, _, _>(
             &mut rng,
             &pk,
             &stake_table_entries,
             &bit_vec,
             &sigs,
             &new_state.into(),
+            &st.commitment(SnapshotVersion::LastEpochStart).unwrap(),
             &old_state.threshold,
             STAKE_TABLE_CAPACITY_FOR_TEST,
         )
         .expect("Fail to generate state proof");
 
-        (pi, proof)
+        proof
     }
 
     /// deploy LightClientMock.sol on local blockchain (via `anvil`) for testing
@@ -661,10 +1242,105 @@ mod test {
                 light_client_address: Address::default(),
                 eth_signing_key: SigningKey::random(&mut test_rng()),
                 sequencer_url: Url::parse("http://localhost").unwrap(),
+                stake_table_address: None,
+                stake_table_deployment_block: 0,
                 port: None,
                 stake_table_capacity: 10,
+                proving_key_path: None,
+                escape_hatch_threshold: None,
+                proof_queue_path: None,
+                submission_timeout: Duration::from_secs(60),
+                rotation: None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_stake_table_history() {
+        let (_, qc_keys, state_keys, _) = init_ledger_for_test();
+        let state_keys = state_keys.into_iter().map(|(_, vk)| vk).collect::<Vec<_>>();
+        let tables = (1..=qc_keys.len())
+            .map(|n| {
+                init_stake_table(
+                    &qc_keys[..n],
+                    &state_keys[..n],
+                    STAKE_TABLE_CAPACITY_FOR_TEST,
+                )
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        let comms = tables
+            .iter()
+            .map(|st| st.commitment(SnapshotVersion::LastEpochStart).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut tables = tables.into_iter();
+        let mut history =
+            StakeTableHistory::new(STAKE_TABLE_CAPACITY_FOR_TEST, tables.next().unwrap());
+        for st in tables {
+            history.update(STAKE_TABLE_CAPACITY_FOR_TEST, st).unwrap();
+        }
+
+        // Older tables can still be found by commitment.
+        for comm in &comms {
+            let (capacity, st) = history.get(comm).unwrap();
+            assert_eq!(capacity, STAKE_TABLE_CAPACITY_FOR_TEST);
+            assert_eq!(
+                st.commitment(SnapshotVersion::LastEpochStart).unwrap(),
+                *comm
+            );
+        }
+
+        // An unchanged stake table is not recorded twice.
+        let len = history.tables[&STAKE_TABLE_CAPACITY_FOR_TEST].len();
+        history
+            .update(
+                STAKE_TABLE_CAPACITY_FOR_TEST,
+                init_stake_table(&qc_keys, &state_keys, STAKE_TABLE_CAPACITY_FOR_TEST).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(history.tables[&STAKE_TABLE_CAPACITY_FOR_TEST].len(), len);
+
+        // The same stake table padded to a larger capacity has a different commitment, which
+        // identifies the capacity of the circuit to prove with.
+        let large =
+            init_stake_table(&qc_keys, &state_keys, 2 * STAKE_TABLE_CAPACITY_FOR_TEST).unwrap();
+        let large_comm = large.commitment(SnapshotVersion::LastEpochStart).unwrap();
+        assert_ne!(large_comm, *comms.last().unwrap());
+        history
+            .update(2 * STAKE_TABLE_CAPACITY_FOR_TEST, large)
+            .unwrap();
+        assert_eq!(
+            history.get(&large_comm).unwrap().0,
+            2 * STAKE_TABLE_CAPACITY_FOR_TEST
+        );
+        assert_eq!(
+            history.get(comms.last().unwrap()).unwrap().0,
+            STAKE_TABLE_CAPACITY_FOR_TEST
+        );
+
+        // Recording each rotation for several capacities does not shorten the history kept for
+        // any one of them.
+        let capacities = [
+            STAKE_TABLE_CAPACITY_FOR_TEST,
+            2 * STAKE_TABLE_CAPACITY_FOR_TEST,
+        ];
+        let mut history = StakeTableHistory::default();
+        let mut comms = vec![];
+        for n in 1..=qc_keys.len().min(STAKE_TABLE_HISTORY_LEN) {
+            for capacity in capacities {
+                let st = init_stake_table(&qc_keys[..n], &state_keys[..n], capacity).unwrap();
+                comms.push((
+                    capacity,
+                    st.commitment(SnapshotVersion::LastEpochStart).unwrap(),
+                ));
+                history.update(capacity, st).unwrap();
             }
         }
+        assert!(comms.len() > STAKE_TABLE_HISTORY_LEN);
+        for (capacity, comm) in &comms {
+            assert_eq!(history.get(comm).unwrap().0, *capacity);
+        }
     }
 
     #[async_std::test]
@@ -687,6 +1363,65 @@ mod test {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn test_read_state_update_history() -> Result<()> {
+        setup_logging();
+        setup_backtrace();
+        let anvil = Anvil::new().spawn();
+        let (wallet, contract) =
+            deploy_contract_for_test(&anvil, ParsedLightClientState::dummy_genesis()).await?;
+
+        // A freshly deployed contract does not have enough history to tell.
+        let mut config = StateProverConfig::default();
+        config.update_l1_info(&anvil, contract.address());
+        let (l1_block, history) = super::read_state_update_history(&config).await?;
+        assert_eq!(history.lag(l1_block), None);
+
+        // With enough updates, the lag agrees with the contract.
+        let mock = LightClientMock::new(contract.address(), wallet);
+        mock.set_state_update_block_numbers(vec![1.into(), 2.into(), 3.into()])
+            .send()
+            .await?
+            .await?;
+        let (l1_block, history) = super::read_state_update_history(&config).await?;
+        assert_eq!(history.count(), 3);
+        let lag = history.lag(l1_block).unwrap();
+        assert_eq!(lag, l1_block - 3);
+        for threshold in [lag - 1, lag] {
+            assert_eq!(
+                history.lag_over_escape_hatch_threshold(l1_block, threshold),
+                Some(
+                    contract
+                        .lag_over_escape_hatch_threshold(l1_block.into(), threshold.into())
+                        .call()
+                        .await?
+                )
+            );
+        }
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_check_verifying_key() -> Result<()> {
+        setup_logging();
+        setup_backtrace();
+        let anvil = Anvil::new().spawn();
+        let (_wallet, contract) =
+            deploy_contract_for_test(&anvil, ParsedLightClientState::dummy_genesis()).await?;
+        let l1_provider = Url::parse(&anvil.endpoint())?;
+
+        // The mock contract verifies proofs for the test stake table capacity.
+        let keys = ProverKeys::generate(STAKE_TABLE_CAPACITY_FOR_TEST);
+        check_verifying_key(&keys.verifying_key, &l1_provider, contract.address()).await?;
+
+        // Keys for a different circuit are refused.
+        let keys = ProverKeys::generate(STAKE_TABLE_CAPACITY_FOR_TEST / 2);
+        check_verifying_key(&keys.verifying_key, &l1_provider, contract.address())
+            .await
+            .unwrap_err();
+        Ok(())
+    }
+
     // This test is temporarily ignored. We are unifying the contract deployment in #1071.
     #[async_std::test]
     async fn test_submit_state_and_proof() -> Result<()> {
@@ -707,14 +1442,117 @@ mod test {
         new_state.view_num = 5;
         new_state.block_height = 1;
 
-        let (pi, proof) = gen_state_proof(&genesis, new_state.clone(), &state_keys, &st);
+        let proof = gen_state_proof(&genesis, new_state.clone(), &state_keys, &st);
         tracing::info!("Successfully generated proof for new state.");
 
-        super::submit_state_and_proof(proof, pi, &config).await?;
+        super::submit_state_and_proof(proof, new_state.clone(), &config).await?;
         tracing::info!("Successfully submitted new finalized state to L1.");
         // test if new state is updated in l1
         let finalized_l1: ParsedLightClientState = contract.get_finalized_state().await?.into();
         assert_eq!(finalized_l1, new_state);
         Ok(())
     }
+    #[async_std::test]
+    async fn test_defer_to_other_provers() -> Result<()> {
+        setup_logging();
+        setup_backtrace();
+        let anvil = Anvil::new().spawn();
+        let (_wallet, contract) =
+            deploy_contract_for_test(&anvil, ParsedLightClientState::dummy_genesis()).await?;
+
+        assert_eq!(defer_reason(&contract, None, Duration::ZERO).await?, None);
+
+        // The light client was just initialized, which counts as a recent update. This only
+        // prevents generating new proofs; queued proofs are still subject to the permissioned
+        // prover check alone.
+        assert!(defer_reason(&contract, None, Duration::from_secs(3600))
+            .await?
+            .is_some());
+        assert_eq!(permissioned_prover_reason(&contract).await?, None);
+
+        // In a rotation, only the leader updates. With a long enough window, the first prover is
+        // the leader.
+        let leader = ProverRotation::new(0, 2, 1_000_000)?;
+        let follower = ProverRotation::new(1, 2, 1_000_000)?;
+        assert_eq!(
+            defer_reason(&contract, Some(&leader), Duration::ZERO).await?,
+            None
+        );
+        assert!(defer_reason(&contract, Some(&follower), Duration::ZERO)
+            .await?
+            .is_some());
+
+        // Provers other than the permissioned prover defer.
+        sequencer_utils::contract_send::<_, _, LightClientErrors>(
+            &contract.set_permissioned_prover(Address::repeat_byte(1)),
+        )
+        .await?;
+        assert!(defer_reason(&contract, None, Duration::ZERO)
+            .await?
+            .is_some());
+        assert!(permissioned_prover_reason(&contract).await?.is_some());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn test_proof_survives_failed_submission() -> Result<()> {
+        setup_logging();
+        setup_backtrace();
+
+        let (genesis, _qc_keys, state_keys, st) = init_ledger_for_test();
+
+        let anvil = Anvil::new().spawn();
+        let (_wallet, contract) = deploy_contract_for_test(&anvil, genesis.clone()).await?;
+        let mut config = StateProverConfig::default();
+        config.update_l1_info(&anvil, contract.address());
+
+        let mut new_state = genesis.clone();
+        new_state.view_num = 5;
+        new_state.block_height = 1;
+        let proof = gen_state_proof(&genesis, new_state.clone(), &state_keys, &st);
+
+        let tmp = TempDir::new()?;
+        let mut queue = ProofQueue::open(tmp.path())?;
+        queue.push(PendingProof::new(proof.clone(), new_state.clone()))?;
+
+        // If L1 cannot be reached, the proof is kept.
+        let mut unreachable = config.clone();
+        unreachable.l1_provider = "http://localhost:1".parse().unwrap();
+        super::submit_queued_proof(&mut queue, &unreachable)
+            .await
+            .unwrap_err();
+        assert_eq!(queue.len(), 1);
+
+        // Submitting from an account with no funds fails, but the proof is kept.
+        let mut unfunded = config.clone();
+        unfunded.eth_signing_key = SigningKey::random(&mut test_rng());
+        super::submit_queued_proof(&mut queue, &unfunded)
+            .await
+            .unwrap_err();
+        assert_eq!(queue.len(), 1);
+
+        // After a restart, the pending proof is submitted without being generated again.
+        let mut queue = ProofQueue::open(tmp.path())?;
+        assert_eq!(queue.len(), 1);
+        let (state, _) = super::submit_queued_proof(&mut queue, &config)
+            .await?
+            .unwrap();
+        assert_eq!(state.block_height, 1);
+        let finalized_l1: ParsedLightClientState = contract.get_finalized_state().await?.into();
+        assert_eq!(finalized_l1, new_state);
+
+        // The finalized proof is removed from the queue.
+        assert!(queue.is_empty());
+        assert!(ProofQueue::open(tmp.path())?.is_empty());
+        assert_eq!(super::submit_queued_proof(&mut queue, &config).await?, None);
+
+        // A proof which the contract rejects is dropped.
+        let mut bad_state = new_state.clone();
+        bad_state.view_num = 10;
+        bad_state.block_height = 2;
+        queue.push(PendingProof::new(proof, bad_state))?;
+        assert_eq!(super::submit_queued_proof(&mut queue, &config).await?, None);
+        assert!(queue.is_empty());
+        Ok(())
+    }
 }
@@ -41,11 +41,13 @@ pub fn preprocess(
 /// - a list of stake table entries (`Vec<(BLSVerKey, Amount, SchnorrVerKey)>`)
 /// - a list of schnorr signatures of the updated states (`Vec<SchnorrSignature>`), default if the node doesn't sign the state
 /// - updated light client state (`(view_number, block_height, block_comm_root, fee_ledger_comm, stake_table_comm)`)
+/// - the commitment of the stake table which signed the update, i.e. the `stake_table_comm` of the
+///   previous light client state
 /// - a bit vector indicates the signers
 /// - a quorum threshold
 /// Returns error or a pair `(proof, public_inputs)` asserting that
 /// - the signer's accumulated weight exceeds the quorum threshold
-/// - the stake table corresponds to the one committed in the light client state
+/// - the stake table corresponds to the signers' stake table commitment
 /// - all schnorr signatures over the light client state are valid
 /// # Errors
 /// Errors if unable to generate proof
@@ -59,6 +61,7 @@ pub fn generate_state_update_proof<STIter, R, BitIter, SigIter>(
     signer_bit_vec: BitIter,
     signatures: SigIter,
     lightclient_state: &LightClientState,
+    signers_stake_table_comm: &(CircuitField, CircuitField, CircuitField),
     threshold: &U256,
     stake_table_capacity: usize,
 ) -> Result<(Proof, PublicInput), PlonkError>
@@ -86,6 +89,7 @@ where
         signer_bit_vec,
         signatures,
         lightclient_state,
+        signers_stake_table_comm,
         threshold,
         stake_table_capacity,
     )?;
@@ -93,6 +97,17 @@ where
     Ok((proof, public_inputs))
 }
 
+/// Verify a state update proof against its public inputs, as the light client contract does.
+/// # Errors
+/// Errors if the proof is invalid
+pub fn verify(
+    vk: &VerifyingKey,
+    public_input: &PublicInput,
+    proof: &Proof,
+) -> Result<(), PlonkError> {
+    PlonkKzgSnark::<Bn254>::verify::<SolidityTranscript>(vk, public_input.as_ref(), proof, None)
+}
+
 #[cfg(test)]
 mod tests {
     use ark_bn254::Bn254;
@@ -108,10 +123,6 @@ mod tests {
         traits::stake_table::{SnapshotVersion, StakeTableScheme},
     };
     use jf_crhf::CRHF;
-    use jf_plonk::{
-        proof_system::{PlonkKzgSnark, UniversalSNARK},
-        transcript::SolidityTranscript,
-    };
     use jf_relation::Circuit;
     use jf_rescue::crhf::VariableLengthRescueCRHF;
     use jf_signature::{
@@ -120,7 +131,7 @@ mod tests {
     };
     use jf_utils::test_rng;
 
-    use super::{generate_state_update_proof, preprocess, CircuitField, UniversalSrs};
+    use super::{generate_state_update_proof, preprocess, verify, CircuitField, UniversalSrs};
     use crate::{
         circuit::build_for_preprocessing,
         test_utils::{key_pairs_for_testing, stake_table_for_testing},
@@ -259,19 +270,14 @@ mod tests {
             &bit_vec,
             &bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(26u32),
             ST_CAPACITY,
         );
         assert!(result.is_ok());
 
         let (proof, public_inputs) = result.unwrap();
-        assert!(PlonkKzgSnark::<Bn254>::verify::<SolidityTranscript>(
-            &vk,
-            public_inputs.as_ref(),
-            &proof,
-            None
-        )
-        .is_ok());
+        assert!(verify(&vk, &public_inputs, &proof).is_ok());
 
         // minimum bad path, other bad cases are checked inside `circuit.rs`
         let result = generate_state_update_proof::<_, _, _, _>(
@@ -281,6 +287,7 @@ mod tests {
             &bit_vec,
             &bit_masked_sigs,
             &lightclient_state,
+            &lightclient_state.stake_table_comm,
             &U256::from(100u32),
             ST_CAPACITY,
         );
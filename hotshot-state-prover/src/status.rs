@@ -0,0 +1,319 @@
+//! Status and metrics reported by the prover service.
+
+use std::time::{Duration, Instant};
+
+use async_std::sync::{Arc, RwLock};
+use ethers::types::H256;
+use hotshot_types::{
+    light_client::LightClientState,
+    traits::metrics::{Counter, Gauge, Histogram, Metrics},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::PrometheusMetrics;
+
+/// A light client state update submitted by the prover.
+#[derive(Clone, Debug)]
+pub struct StateUpdate {
+    /// The newly finalized state.
+    pub state: LightClientState,
+    /// Hash of the L1 transaction which submitted the update.
+    pub tx_hash: H256,
+    /// Time spent generating the proof, if it was generated for this update rather than taken
+    /// from the queue of pending proofs.
+    pub proof_generation_time: Option<Duration>,
+    /// Time from when the prover first saw a state newer than the finalized one until a state
+    /// with enough signatures was obtained from the relay server, if the proof was generated for
+    /// this update.
+    pub signature_wait: Option<Duration>,
+}
+
+/// Measures how long the prover waits for enough signatures on a new state.
+///
+/// The wait starts when the prover first sees a state newer than the finalized state, and ends
+/// when the relay server has a state with enough signatures, which may take several attempts.
+#[derive(Clone, Debug, Default)]
+pub struct SignatureWait {
+    since: Option<Instant>,
+}
+
+impl SignatureWait {
+    /// Start waiting, unless already waiting for an earlier new state.
+    pub fn start(&mut self) {
+        self.since.get_or_insert_with(Instant::now);
+    }
+
+    /// Stop waiting, because a state with enough signatures was collected, and return how long the
+    /// wait was.
+    pub fn stop(&mut self) -> Option<Duration> {
+        self.since.take().map(|since| since.elapsed())
+    }
+
+    /// Stop waiting without a result, because there is no new state to wait for.
+    pub fn reset(&mut self) {
+        self.since = None;
+    }
+}
+
+/// The status of the prover, as served by its HTTP API.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProverStatus {
+    /// Block height of the last state proven and submitted by this prover.
+    pub last_proven_height: Option<u64>,
+    /// View number of the last state proven and submitted by this prover.
+    pub last_proven_view: Option<u64>,
+    /// Hash of the L1 transaction which submitted the last proof.
+    pub last_l1_tx: Option<H256>,
+    /// Time spent generating the last proof, in seconds.
+    pub proof_generation_secs: Option<f64>,
+    /// Time spent waiting for enough signatures on the last proven state, in seconds.
+    pub signature_wait_secs: Option<f64>,
+    /// Block height of the state currently finalized in the light client contract.
+    pub light_client_height: Option<u64>,
+    /// Height of the latest block reported by the sequencer.
+    pub sequencer_height: Option<u64>,
+    /// Number of blocks the light client contract is behind the sequencer.
+    pub lag: Option<u64>,
+    /// Number of L1 blocks since the last light client update.
+    ///
+    /// This is computed like the light client contract's `lagOverEscapeHatchThreshold`, which rollups
+    /// use to decide whether to enter escape hatch mode. It is unknown if the contract does not yet
+    /// have enough history to tell.
+    pub escape_hatch_lag: Option<u64>,
+    /// Whether the light client contract has fallen behind the configured escape hatch threshold.
+    ///
+    /// This is unknown if no threshold is configured, or if the contract does not yet have enough
+    /// history to tell.
+    pub lag_over_escape_hatch_threshold: Option<bool>,
+    /// The error which prevented the last attempted update, if it failed.
+    pub last_error: Option<String>,
+}
+
+/// Tracks the status of the prover and reports it through metrics.
+#[derive(Clone)]
+pub struct ProverMonitor {
+    status: Arc<RwLock<ProverStatus>>,
+    metrics: Arc<ProverMetrics>,
+    registry: PrometheusMetrics,
+}
+
+impl Default for ProverMonitor {
+    fn default() -> Self {
+        let registry = PrometheusMetrics::default();
+        Self {
+            status: Default::default(),
+            metrics: Arc::new(ProverMetrics::new(&registry)),
+            registry,
+        }
+    }
+}
+
+impl ProverMonitor {
+    /// The current status of the prover.
+    pub async fn status(&self) -> ProverStatus {
+        self.status.read().await.clone()
+    }
+
+    /// The Prometheus registry containing the prover's metrics.
+    pub fn registry(&self) -> &PrometheusMetrics {
+        &self.registry
+    }
+
+    /// Record a successful state update.
+    pub async fn record_update(&self, update: &StateUpdate) {
+        let height = update.state.block_height as u64;
+        let view = update.state.view_number as u64;
+        self.metrics.last_proven_height.set(height as usize);
+        self.metrics.last_proven_view.set(view as usize);
+        if let Some(time) = update.proof_generation_time {
+            self.metrics
+                .proof_generation_time
+                .add_point(time.as_secs_f64());
+        }
+        if let Some(wait) = update.signature_wait {
+            self.metrics
+                .signature_wait_time
+                .add_point(wait.as_secs_f64());
+        }
+        self.metrics.updates.add(1);
+
+        let mut status = self.status.write().await;
+        status.last_proven_height = Some(height);
+        status.last_proven_view = Some(view);
+        status.last_l1_tx = Some(update.tx_hash);
+        status.proof_generation_secs = update.proof_generation_time.map(|time| time.as_secs_f64());
+        status.signature_wait_secs = update.signature_wait.map(|wait| wait.as_secs_f64());
+        status.last_error = None;
+    }
+
+    /// Record an attempted update which turned out to be unnecessary.
+    pub async fn record_no_update(&self) {
+        self.status.write().await.last_error = None;
+    }
+
+    /// Record a failed update.
+    pub async fn record_error(&self, err: impl std::fmt::Display) {
+        self.metrics.failures.add(1);
+        self.status.write().await.last_error = Some(err.to_string());
+    }
+
+    /// Record the state of the light client contract relative to the sequencer.
+    pub async fn record_light_client(
+        &self,
+        light_client_height: u64,
+        sequencer_height: Option<u64>,
+        escape_hatch_lag: Option<u64>,
+        lag_over_escape_hatch_threshold: Option<bool>,
+    ) {
+        let lag = sequencer_height.map(|height| height.saturating_sub(light_client_height));
+        self.metrics
+            .light_client_height
+            .set(light_client_height as usize);
+        if let Some(height) = sequencer_height {
+            self.metrics.sequencer_height.set(height as usize);
+        }
+        if let Some(lag) = lag {
+            self.metrics.lag.set(lag as usize);
+        }
+        if let Some(lag) = escape_hatch_lag {
+            self.metrics.escape_hatch_lag.set(lag as usize);
+        }
+        if let Some(over) = lag_over_escape_hatch_threshold {
+            self.metrics
+                .lag_over_escape_hatch_threshold
+                .set(over as usize);
+        }
+
+        let mut status = self.status.write().await;
+        status.light_client_height = Some(light_client_height);
+        status.sequencer_height = sequencer_height;
+        status.lag = lag;
+        status.escape_hatch_lag = escape_hatch_lag;
+        status.lag_over_escape_hatch_threshold = lag_over_escape_hatch_threshold;
+    }
+}
+
+struct ProverMetrics {
+    last_proven_height: Box<dyn Gauge>,
+    last_proven_view: Box<dyn Gauge>,
+    light_client_height: Box<dyn Gauge>,
+    sequencer_height: Box<dyn Gauge>,
+    lag: Box<dyn Gauge>,
+    escape_hatch_lag: Box<dyn Gauge>,
+    lag_over_escape_hatch_threshold: Box<dyn Gauge>,
+    proof_generation_time: Box<dyn Histogram>,
+    signature_wait_time: Box<dyn Histogram>,
+    updates: Box<dyn Counter>,
+    failures: Box<dyn Counter>,
+}
+
+impl ProverMetrics {
+    fn new(metrics: &dyn Metrics) -> Self {
+        let metrics = metrics.subgroup("state_prover".into());
+        Self {
+            last_proven_height: metrics.create_gauge("last_proven_height".into(), None),
+            last_proven_view: metrics.create_gauge("last_proven_view".into(), None),
+            light_client_height: metrics.create_gauge("light_client_height".into(), None),
+            sequencer_height: metrics.create_gauge("sequencer_height".into(), None),
+            lag: metrics.create_gauge("lag".into(), None),
+            escape_hatch_lag: metrics.create_gauge("escape_hatch_lag".into(), None),
+            lag_over_escape_hatch_threshold: metrics
+                .create_gauge("lag_over_escape_hatch_threshold".into(), None),
+            proof_generation_time: metrics
+                .create_histogram("proof_generation_time".into(), Some("s".into())),
+            signature_wait_time: metrics
+                .create_histogram("signature_wait_time".into(), Some("s".into())),
+            updates: metrics.create_counter("updates".into(), None),
+            failures: metrics.create_counter("failed_updates".into(), None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ark_ff::Zero;
+    use hotshot_types::light_client::CircuitField;
+    use tide_disco::metrics::Metrics as _;
+
+    use super::*;
+
+    #[async_std::test]
+    async fn test_prover_monitor() {
+        let monitor = ProverMonitor::default();
+        assert_eq!(monitor.status().await, ProverStatus::default());
+
+        monitor.record_error("no signatures").await;
+        assert_eq!(
+            monitor.status().await.last_error.as_deref(),
+            Some("no signatures")
+        );
+
+        let update = StateUpdate {
+            state: LightClientState {
+                view_number: 12,
+                block_height: 10,
+                block_comm_root: CircuitField::zero(),
+                fee_ledger_comm: CircuitField::zero(),
+                stake_table_comm: (
+                    CircuitField::zero(),
+                    CircuitField::zero(),
+                    CircuitField::zero(),
+                ),
+            },
+            tx_hash: H256::repeat_byte(1),
+            proof_generation_time: Some(Duration::from_secs(30)),
+            signature_wait: Some(Duration::from_secs(5)),
+        };
+        monitor.record_update(&update).await;
+        monitor
+            .record_light_client(10, Some(15), Some(3), Some(false))
+            .await;
+
+        let status = monitor.status().await;
+        assert_eq!(
+            status,
+            ProverStatus {
+                last_proven_height: Some(10),
+                last_proven_view: Some(12),
+                last_l1_tx: Some(H256::repeat_byte(1)),
+                proof_generation_secs: Some(30.),
+                signature_wait_secs: Some(5.),
+                light_client_height: Some(10),
+                sequencer_height: Some(15),
+                lag: Some(5),
+                escape_hatch_lag: Some(3),
+                lag_over_escape_hatch_threshold: Some(false),
+                last_error: None,
+            }
+        );
+
+        let exported = monitor.registry().export().unwrap();
+        assert!(exported.contains("state_prover_lag 5"), "{exported}");
+        assert!(
+            exported.contains("state_prover_escape_hatch_lag 3"),
+            "{exported}"
+        );
+        assert!(
+            exported.contains("state_prover_last_proven_height 10"),
+            "{exported}"
+        );
+    }
+
+    #[test]
+    fn test_signature_wait() {
+        let mut wait = SignatureWait::default();
+        assert_eq!(wait.stop(), None);
+
+        // The wait runs from the first new state seen, across attempts.
+        wait.start();
+        std::thread::sleep(Duration::from_millis(10));
+        wait.start();
+        assert!(wait.stop().unwrap() >= Duration::from_millis(10));
+        assert_eq!(wait.stop(), None);
+
+        // Without a new state, there is nothing to wait for.
+        wait.start();
+        wait.reset();
+        assert_eq!(wait.stop(), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Verification of fee account balances.
+//!
+//! The account and amount types hash and serialize exactly like their counterparts in
+//! `espresso-types`, so that proofs served by the sequencer can be verified here.
+
+use anyhow::{bail, ensure, Context};
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalSerialize, Compress, Read, SerializationError, Valid, Validate,
+    Write,
+};
+use ethers::{
+    types::{Address, U256},
+    utils::{parse_units, ParseUnits},
+};
+use jf_merkle_tree::{
+    prelude::{Sha3Digest, Sha3Node},
+    universal_merkle_tree::UniversalMerkleTree,
+    MerkleCommitment, MerkleTreeScheme, ToTraversalPath, UniversalMerkleTreeScheme,
+};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+pub type FeeMerkleTree = UniversalMerkleTree<FeeAmount, Sha3Digest, FeeAccount, 256, Sha3Node>;
+pub type FeeMerkleCommitment = <FeeMerkleTree as MerkleTreeScheme>::Commitment;
+
+/// A balance in the fee ledger, in WEI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FeeAmount(pub U256);
+
+impl FeeAmount {
+    /// Parse an amount from its human-readable string representation.
+    fn from_string(s: &str) -> anyhow::Result<Self> {
+        // Amounts may be represented as hex strings of WEI, or as decimal strings with an optional
+        // unit.
+        if let Some(s) = s.strip_prefix("0x") {
+            return Ok(Self(s.parse()?));
+        }
+        let (base, unit) = s.split_once(char::is_whitespace).unwrap_or((s, "wei"));
+        match parse_units(base, unit)? {
+            ParseUnits::U256(n) => Ok(Self(n)),
+            ParseUnits::I256(_) => bail!("amount cannot be negative"),
+        }
+    }
+}
+
+// This matches the string-or-integer serialization `espresso-types` gets from `sequencer-utils`,
+// which we do not depend on, since it depends on the contract adapter, which depends on us. In
+// human-readable formats, amounts serialize as decimal strings and deserialize from either strings
+// (with an optional unit) or integers. Otherwise they serialize as the underlying `U256`.
+impl Serialize for FeeAmount {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if s.is_human_readable() {
+            self.0.to_string().serialize(s)
+        } else {
+            self.0.serialize(s)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FeeAmount {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum StringOrInteger {
+            String(String),
+            Integer(u64),
+        }
+
+        if d.is_human_readable() {
+            match StringOrInteger::deserialize(d)? {
+                StringOrInteger::String(s) => Self::from_string(&s).map_err(D::Error::custom),
+                StringOrInteger::Integer(i) => Ok(Self(i.into())),
+            }
+        } else {
+            U256::deserialize(d).map(Self)
+        }
+    }
+}
+
+impl Valid for FeeAmount {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalSerialize for FeeAmount {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        _compress: Compress,
+    ) -> Result<(), SerializationError> {
+        let mut bytes = [0u8; core::mem::size_of::<U256>()];
+        self.0.to_little_endian(&mut bytes);
+        Ok(writer.write_all(&bytes)?)
+    }
+
+    fn serialized_size(&self, _compress: Compress) -> usize {
+        core::mem::size_of::<U256>()
+    }
+}
+
+impl CanonicalDeserialize for FeeAmount {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        _compress: Compress,
+        _validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let mut bytes = [0u8; core::mem::size_of::<U256>()];
+        reader.read_exact(&mut bytes)?;
+        Ok(Self(U256::from_little_endian(&bytes)))
+    }
+}
+
+/// An account in the fee ledger.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct FeeAccount(pub Address);
+
+impl Valid for FeeAccount {
+    fn check(&self) -> Result<(), SerializationError> {
+        Ok(())
+    }
+}
+
+impl CanonicalSerialize for FeeAccount {
+    fn serialize_with_mode<W: Write>(
+        &self,
+        mut writer: W,
+        _compress: Compress,
+    ) -> Result<(), SerializationError> {
+        Ok(writer.write_all(&self.0.to_fixed_bytes())?)
+    }
+
+    fn serialized_size(&self, _compress: Compress) -> usize {
+        core::mem::size_of::<Address>()
+    }
+}
+
+impl CanonicalDeserialize for FeeAccount {
+    fn deserialize_with_mode<R: Read>(
+        mut reader: R,
+        _compress: Compress,
+        _validate: Validate,
+    ) -> Result<Self, SerializationError> {
+        let mut bytes = [0u8; core::mem::size_of::<Address>()];
+        reader.read_exact(&mut bytes)?;
+        Ok(Self(Address::from_slice(&bytes)))
+    }
+}
+
+impl ToTraversalPath<256> for FeeAccount {
+    fn to_traversal_path(&self, height: usize) -> Vec<usize> {
+        self.0
+            .to_fixed_bytes()
+            .into_iter()
+            .take(height)
+            .map(|i| i as usize)
+            .collect()
+    }
+}
+
+/// A proof of the balance of an account in the fee ledger.
+///
+/// If the account of interest does not exist in the fee state, this is a Merkle non-membership
+/// proof, and the balance is implicitly zero. Otherwise, this is a normal Merkle membership proof.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FeeAccountProof {
+    pub account: Address,
+    pub proof: FeeMerkleProof,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum FeeMerkleProof {
+    Presence(<FeeMerkleTree as MerkleTreeScheme>::MembershipProof),
+    Absence(<FeeMerkleTree as UniversalMerkleTreeScheme>::NonMembershipProof),
+}
+
+impl FeeAccountProof {
+    /// Verify the proof against the `fee_merkle_tree_root` of a block header.
+    ///
+    /// Returns the balance of the account.
+    pub fn verify(&self, comm: &FeeMerkleCommitment) -> anyhow::Result<U256> {
+        match &self.proof {
+            FeeMerkleProof::Presence(proof) => {
+                ensure!(
+                    FeeMerkleTree::verify(comm.digest(), FeeAccount(self.account), proof)?.is_ok(),
+                    "invalid proof"
+                );
+                Ok(proof
+                    .elem()
+                    .context("presence proof is missing account balance")?
+                    .0)
+            }
+            FeeMerkleProof::Absence(proof) => {
+                let tree = FeeMerkleTree::from_commitment(comm);
+                ensure!(
+                    tree.non_membership_verify(FeeAccount(self.account), proof)?,
+                    "invalid proof"
+                );
+                Ok(0.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::{
+        FeeAccount as EspressoFeeAccount, FeeAccountProof as EspressoFeeAccountProof,
+        FeeAmount as EspressoFeeAmount, FeeMerkleTree as EspressoFeeMerkleTree,
+        FEE_MERKLE_TREE_HEIGHT,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_verify_fee_account_proof() {
+        let mut tree = EspressoFeeMerkleTree::new(FEE_MERKLE_TREE_HEIGHT);
+        for i in 1..=3u8 {
+            tree.update(
+                EspressoFeeAccount::from(Address::repeat_byte(i)),
+                EspressoFeeAmount::from(i as u64 * 1000),
+            )
+            .unwrap();
+        }
+        let comm = tree.commitment();
+
+        // Presence proofs.
+        for i in 1..=3u8 {
+            let (proof, balance) =
+                EspressoFeeAccountProof::prove(&tree, Address::repeat_byte(i)).unwrap();
+            assert_eq!(balance, U256::from(i as u64 * 1000));
+
+            let proof: FeeAccountProof =
+                serde_json::from_value(serde_json::to_value(&proof).unwrap()).unwrap();
+            assert_eq!(proof.verify(&comm).unwrap(), balance);
+        }
+
+        // Absence proof.
+        let (proof, _) = EspressoFeeAccountProof::prove(&tree, Address::repeat_byte(4)).unwrap();
+        let proof: FeeAccountProof =
+            serde_json::from_value(serde_json::to_value(&proof).unwrap()).unwrap();
+        assert_eq!(proof.verify(&comm).unwrap(), U256::zero());
+
+        // Proofs do not verify against a different fee ledger.
+        let other = EspressoFeeMerkleTree::new(FEE_MERKLE_TREE_HEIGHT).commitment();
+        let (proof, _) = EspressoFeeAccountProof::prove(&tree, Address::repeat_byte(1)).unwrap();
+        let proof: FeeAccountProof =
+            serde_json::from_value(serde_json::to_value(&proof).unwrap()).unwrap();
+        proof.verify(&other).unwrap_err();
+    }
+}
@@ -0,0 +1,34 @@
+//! Verification of Espresso data against light client states.
+//!
+//! The `LightClient` contract on L1 stores a [`LightClientState`] for each finalized HotShot
+//! snapshot. This crate checks data obtained from an untrusted Espresso query service against
+//! such a state:
+//! * a block header's `block_merkle_tree_root` and `fee_merkle_tree_root` against the state's
+//!   `block_comm_root` and `fee_ledger_comm` ([`verify_block_merkle_root`],
+//!   [`verify_fee_merkle_root`]);
+//! * the header commitment of an earlier block against the block Merkle tree of a state, given
+//!   its [`BlockMerkleProof`] (see `verify_block_merkle_proof` in `hotshot-contract-adapter`, which
+//!   checks such proofs against states read from the contract);
+//! * the transactions in a namespace against a header's `payload_commitment` and `ns_table`
+//!   ([`NsProof::verify`]);
+//! * the balance of a fee account against a header's `fee_merkle_tree_root`
+//!   ([`FeeAccountProof::verify`]).
+//!
+//! All types deserialize from the same JSON as the corresponding types served by the sequencer
+//! API. Unlike `espresso-types`, this crate does not depend on the HotShot runtime or the query
+//! service, so it is cheap to include in a rollup.
+//!
+//! [`LightClientState`]: hotshot_types::light_client::LightClientState
+
+mod fee;
+mod namespace;
+mod state;
+
+pub use fee::{
+    FeeAccount, FeeAccountProof, FeeAmount, FeeMerkleCommitment, FeeMerkleProof, FeeMerkleTree,
+};
+pub use namespace::{NamespaceId, NsIndex, NsProof, NsTable};
+pub use state::{
+    block_comm_root, fee_ledger_comm, verify_block_merkle_root, verify_fee_merkle_root,
+    BlockMerkleCommitment, BlockMerkleProof, BlockMerkleTree,
+};
@@ -0,0 +1,258 @@
+//! Verification of the transactions in a namespace of a block payload.
+//!
+//! This mirrors the namespace table, namespace payload and namespace proof types of
+//! `espresso-types`, including their binary formats, but only supports reading and verifying them.
+//! See the documentation of `NsTable` and `NsPayload` in `espresso-types` for a full specification
+//! of the binary formats.
+
+use std::ops::Range;
+
+use anyhow::{bail, Context};
+use hotshot_types::vid::{
+    vid_scheme, LargeRangeProofType, VidCommitment, VidCommon, VidSchemeType,
+};
+use jf_vid::{
+    payload_prover::{PayloadProver, Statement},
+    VidScheme,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Byte lengths of the items in a namespace table.
+const NUM_NSS_BYTE_LEN: usize = 4;
+const NS_OFFSET_BYTE_LEN: usize = 4;
+const NS_ID_BYTE_LEN: usize = 4;
+
+/// Byte lengths of the items in the transaction table of a namespace payload.
+const NUM_TXS_BYTE_LEN: usize = 4;
+const TX_OFFSET_BYTE_LEN: usize = 4;
+
+pub type NamespaceId = u64;
+
+/// The namespace table from a block header.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct NsTable {
+    #[serde(with = "base64_bytes")]
+    bytes: Vec<u8>,
+}
+
+impl NsTable {
+    /// Number of entries in the namespace table.
+    ///
+    /// Defined as the maximum number of entries that could fit in the namespace table, ignoring
+    /// what's declared in the table header.
+    pub fn len(&self) -> usize {
+        self.bytes.len().saturating_sub(NUM_NSS_BYTE_LEN) / (NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Search the namespace table for the index of the namespace `ns_id`.
+    pub fn find_ns_id(&self, ns_id: NamespaceId) -> Option<NsIndex> {
+        (0..self.len())
+            .map(NsIndex)
+            .find(|index| self.read_ns_id_unchecked(index) == ns_id)
+    }
+
+    /// Read the namespace ID of the `index`th entry, or `None` if `index` is out of bounds.
+    pub fn read_ns_id(&self, index: &NsIndex) -> Option<NamespaceId> {
+        if index.0 < self.len() {
+            Some(self.read_ns_id_unchecked(index))
+        } else {
+            None
+        }
+    }
+
+    fn read_ns_id_unchecked(&self, index: &NsIndex) -> NamespaceId {
+        let start = index.0 * (NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN) + NUM_NSS_BYTE_LEN;
+        usize_from_bytes(&self.bytes[start..start + NS_ID_BYTE_LEN]) as NamespaceId
+    }
+
+    fn read_ns_offset_unchecked(&self, index: &NsIndex) -> usize {
+        let start =
+            index.0 * (NS_ID_BYTE_LEN + NS_OFFSET_BYTE_LEN) + NUM_NSS_BYTE_LEN + NS_ID_BYTE_LEN;
+        usize_from_bytes(&self.bytes[start..start + NS_OFFSET_BYTE_LEN])
+    }
+
+    /// The byte range of the `index`th namespace in a payload of `payload_byte_len` bytes.
+    fn ns_range(&self, index: &NsIndex, payload_byte_len: usize) -> Range<usize> {
+        let end = self.read_ns_offset_unchecked(index).min(payload_byte_len);
+        let start = if index.0 == 0 {
+            0
+        } else {
+            self.read_ns_offset_unchecked(&NsIndex(index.0 - 1))
+        }
+        .min(end);
+        start..end
+    }
+}
+
+/// Index of an entry in a namespace table.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct NsIndex(pub usize);
+
+impl Serialize for NsIndex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = [0; NUM_NSS_BYTE_LEN];
+        bytes.copy_from_slice(&self.0.to_le_bytes()[..NUM_NSS_BYTE_LEN]);
+        bytes.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NsIndex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = <[u8; NUM_NSS_BYTE_LEN]>::deserialize(deserializer)?;
+        Ok(Self(usize_from_bytes(&bytes)))
+    }
+}
+
+/// Proof of the payload bytes of a namespace, as served by the sequencer's `availability` API.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NsProof {
+    ns_index: NsIndex,
+    #[serde(with = "base64_bytes")]
+    ns_payload: Vec<u8>,
+    ns_proof: Option<LargeRangeProofType>, // `None` if ns_payload is empty
+}
+
+impl NsProof {
+    /// Verify a namespace proof against the `payload_commitment` and `ns_table` of a block header.
+    ///
+    /// `common` is the VID common data of the block, which is served along with the proof. On
+    /// success, returns the ID of the namespace and the payloads of its transactions.
+    pub fn verify(
+        &self,
+        ns_table: &NsTable,
+        commit: &VidCommitment,
+        common: &VidCommon,
+    ) -> anyhow::Result<(NamespaceId, Vec<Vec<u8>>)> {
+        VidSchemeType::is_consistent(commit, common).map_err(|err| {
+            anyhow::anyhow!("VID common data is inconsistent with payload commitment: {err}")
+        })?;
+        let ns_id = ns_table
+            .read_ns_id(&self.ns_index)
+            .context(format!("namespace index {} out of bounds", self.ns_index.0))?;
+
+        let payload_byte_len = VidSchemeType::get_payload_byte_len(common) as usize;
+        let range = ns_table.ns_range(&self.ns_index, payload_byte_len);
+        match (&self.ns_proof, range.is_empty()) {
+            (Some(proof), false) => {
+                let vid = vid_scheme(
+                    VidSchemeType::get_num_storage_nodes(common)
+                        .try_into()
+                        .context("number of storage nodes out of range")?,
+                );
+                vid.payload_verify(
+                    Statement {
+                        payload_subslice: &self.ns_payload,
+                        range,
+                        commit,
+                        common,
+                    },
+                    proof,
+                )
+                .map_err(|err| anyhow::anyhow!("verifying namespace proof: {err}"))?
+                .map_err(|_| anyhow::anyhow!("invalid namespace proof"))?;
+            }
+            // 0-length namespace, nothing to verify
+            (None, true) => {}
+            (None, false) => bail!("missing proof for nonempty namespace {ns_id}"),
+            (Some(_), true) => bail!("unexpected proof for empty namespace {ns_id}"),
+        }
+
+        Ok((ns_id, self.transactions()))
+    }
+
+    /// The payloads of the transactions in this namespace.
+    fn transactions(&self) -> Vec<Vec<u8>> {
+        let payload = &self.ns_payload;
+        let num_txs_declared = usize_from_bytes(&payload[..NUM_TXS_BYTE_LEN.min(payload.len())]);
+        let num_txs = num_txs_declared
+            .min(payload.len().saturating_sub(NUM_TXS_BYTE_LEN) / TX_OFFSET_BYTE_LEN);
+
+        // Transaction offsets are relative to the end of the transaction table as declared in the
+        // table itself.
+        let tx_table_byte_len = num_txs_declared
+            .saturating_mul(TX_OFFSET_BYTE_LEN)
+            .saturating_add(NUM_TXS_BYTE_LEN);
+        let mut prev = 0;
+        (0..num_txs)
+            .map(|i| {
+                let entry = NUM_TXS_BYTE_LEN + i * TX_OFFSET_BYTE_LEN;
+                let cur = usize_from_bytes(&payload[entry..entry + TX_OFFSET_BYTE_LEN]);
+                let end = cur.saturating_add(tx_table_byte_len).min(payload.len());
+                let start = prev.saturating_add(tx_table_byte_len).min(end);
+                prev = cur;
+                payload[start..end].to_vec()
+            })
+            .collect()
+    }
+}
+
+/// Deserialize little-endian `bytes`, of length at most `size_of::<usize>()`, into a `usize`.
+fn usize_from_bytes(bytes: &[u8]) -> usize {
+    let mut usize_bytes = [0; std::mem::size_of::<usize>()];
+    usize_bytes[..bytes.len()].copy_from_slice(bytes);
+    usize::from_le_bytes(usize_bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::{
+        NamespaceId as EspressoNamespaceId, NsProof as EspressoNsProof, Payload, Transaction,
+    };
+    use hotshot_types::traits::{BlockPayload, EncodeBytes};
+
+    use super::*;
+
+    #[async_std::test]
+    async fn test_verify_ns_proof() {
+        let txs = [
+            (1u32, vec![1, 2, 3]),
+            (1, vec![]),
+            (2, vec![4; 100]),
+            (3, vec![5]),
+        ]
+        .into_iter()
+        .map(|(ns, payload)| Transaction::new(EspressoNamespaceId::from(ns), payload))
+        .collect::<Vec<_>>();
+        let (payload, _) =
+            Payload::from_transactions(txs.clone(), &Default::default(), &Default::default())
+                .await
+                .unwrap();
+        let vid = vid_scheme(10).disperse(payload.encode()).unwrap();
+
+        // Namespace tables and proofs served by the sequencer deserialize into our types.
+        let ns_table: NsTable =
+            serde_json::from_value(serde_json::to_value(payload.ns_table()).unwrap()).unwrap();
+        assert_eq!(ns_table.len(), 3);
+
+        for ns in [1, 2, 3] {
+            let index = payload
+                .ns_table()
+                .find_ns_id(&EspressoNamespaceId::from(ns))
+                .unwrap();
+            let proof = EspressoNsProof::new(&payload, &index, &vid.common).unwrap();
+            let proof: NsProof =
+                serde_json::from_value(serde_json::to_value(&proof).unwrap()).unwrap();
+
+            let (ns_id, ns_txs) = proof.verify(&ns_table, &vid.commit, &vid.common).unwrap();
+            assert_eq!(ns_id, ns as NamespaceId);
+            assert_eq!(
+                ns_txs,
+                txs.iter()
+                    .filter(|tx| tx.namespace() == EspressoNamespaceId::from(ns))
+                    .map(|tx| tx.payload().to_vec())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(ns_table.find_ns_id(ns_id), Some(proof.ns_index));
+
+            // The proof does not verify against a different block.
+            let other = vid_scheme(10).disperse(vec![0; 100]).unwrap();
+            proof
+                .verify(&ns_table, &other.commit, &vid.common)
+                .unwrap_err();
+        }
+    }
+}
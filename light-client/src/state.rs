@@ -0,0 +1,131 @@
+//! Checking Merkle roots from block headers against light client states.
+
+use anyhow::{ensure, Context};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use hotshot_types::light_client::{CircuitField, LightClientState};
+use jf_crhf::CRHF;
+use jf_merkle_tree::{prelude::LightWeightSHA3MerkleTree, MerkleTreeScheme};
+use jf_rescue::{crhf::VariableLengthRescueCRHF, RescueError};
+
+use crate::FeeMerkleCommitment;
+
+/// The Merkle tree of block header commitments, whose root is committed to by `block_comm_root`.
+///
+/// Leaves are the raw bytes of header commitments, so the same tree works across header versions.
+pub type BlockMerkleTree = LightWeightSHA3MerkleTree<[u8; 32]>;
+pub type BlockMerkleCommitment = <BlockMerkleTree as MerkleTreeScheme>::Commitment;
+pub type BlockMerkleProof = <BlockMerkleTree as MerkleTreeScheme>::MembershipProof;
+
+/// The `block_comm_root` of the light client state for a header with the given
+/// `block_merkle_tree_root`.
+pub fn block_comm_root(root: &BlockMerkleCommitment) -> anyhow::Result<CircuitField> {
+    let mut bytes = vec![];
+    root.serialize_compressed(&mut bytes)?;
+    Ok(hash_bytes_to_field(&bytes)?)
+}
+
+/// The `fee_ledger_comm` of the light client state for a header with the given
+/// `fee_merkle_tree_root`.
+pub fn fee_ledger_comm(root: &FeeMerkleCommitment) -> anyhow::Result<CircuitField> {
+    let mut bytes = vec![];
+    root.serialize_compressed(&mut bytes)?;
+    Ok(hash_bytes_to_field(&bytes)?)
+}
+
+/// Check that `root` is the block Merkle tree root committed to by a light client state.
+///
+/// `state` may be a [`LightClientState`] or a
+/// `ParsedLightClientState` read from the `LightClient` contract with `hotshot-contract-adapter`.
+pub fn verify_block_merkle_root(
+    state: impl Into<LightClientState>,
+    root: &BlockMerkleCommitment,
+) -> anyhow::Result<()> {
+    let state = state.into();
+    let comm = block_comm_root(root).context("computing block commitment root")?;
+    ensure!(
+        comm == state.block_comm_root,
+        "block Merkle root does not match light client state at height {}",
+        state.block_height
+    );
+    Ok(())
+}
+
+/// Check that `root` is the fee Merkle tree root committed to by a light client state.
+///
+/// `state` may be a [`LightClientState`] or a
+/// `ParsedLightClientState` read from the `LightClient` contract with `hotshot-contract-adapter`.
+pub fn verify_fee_merkle_root(
+    state: impl Into<LightClientState>,
+    root: &FeeMerkleCommitment,
+) -> anyhow::Result<()> {
+    let state = state.into();
+    let comm = fee_ledger_comm(root).context("computing fee ledger commitment")?;
+    ensure!(
+        comm == state.fee_ledger_comm,
+        "fee Merkle root does not match light client state at height {}",
+        state.block_height
+    );
+    Ok(())
+}
+
+fn hash_bytes_to_field(bytes: &[u8]) -> Result<CircuitField, RescueError> {
+    // make sure that `mod_order` won't happen.
+    let bytes_len = ((<CircuitField as PrimeField>::MODULUS_BIT_SIZE + 7) / 8 - 1) as usize;
+    let elem = bytes
+        .chunks(bytes_len)
+        .map(CircuitField::from_le_bytes_mod_order)
+        .collect::<Vec<_>>();
+    Ok(VariableLengthRescueCRHF::<_, 1>::evaluate(elem)?[0])
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::{
+        FeeAccount as EspressoFeeAccount, FeeAmount as EspressoFeeAmount,
+        FeeMerkleTree as EspressoFeeMerkleTree, BLOCK_MERKLE_TREE_HEIGHT, FEE_MERKLE_TREE_HEIGHT,
+    };
+    use ethers::types::Address;
+    use hotshot_state_prover::mock_ledger::{MockLedger, MockSystemParam};
+    use jf_merkle_tree::{AppendableMerkleTreeScheme, UniversalMerkleTreeScheme};
+
+    use super::*;
+
+    #[test]
+    fn test_verify_merkle_roots() {
+        let mut ledger = MockLedger::init(MockSystemParam::init(10), 5);
+        let mut block_tree = BlockMerkleTree::new(BLOCK_MERKLE_TREE_HEIGHT);
+        let mut fee_tree = EspressoFeeMerkleTree::new(FEE_MERKLE_TREE_HEIGHT);
+
+        for i in 0..3u8 {
+            block_tree.push([i; 32]).unwrap();
+            fee_tree
+                .update(
+                    EspressoFeeAccount::from(Address::repeat_byte(i)),
+                    EspressoFeeAmount::from(100u64),
+                )
+                .unwrap();
+            ledger.elapse_with_block_roots(
+                block_comm_root(&block_tree.commitment()).unwrap(),
+                fee_ledger_comm(&fee_tree.commitment()).unwrap(),
+            );
+
+            // The state as stored in the contract commits to the current roots.
+            let state = ledger.get_state();
+            verify_block_merkle_root(state.clone(), &block_tree.commitment()).unwrap();
+            verify_fee_merkle_root(state.clone(), &fee_tree.commitment()).unwrap();
+
+            // The roots don't verify against the wrong commitment.
+            verify_block_merkle_root(
+                state.clone(),
+                &BlockMerkleTree::new(BLOCK_MERKLE_TREE_HEIGHT).commitment(),
+            )
+            .unwrap_err();
+            verify_fee_merkle_root(
+                state,
+                &EspressoFeeMerkleTree::new(FEE_MERKLE_TREE_HEIGHT).commitment(),
+            )
+            .unwrap_err();
+        }
+    }
+}
@@ -0,0 +1,10 @@
+-- Consensus storage entries which failed an integrity check and were removed by `check-storage`.
+-- The raw contents of each entry are preserved here for inspection.
+CREATE TABLE quarantine (
+    id     SERIAL PRIMARY KEY,
+    kind   TEXT NOT NULL,
+    view   BIGINT,
+    data   BYTEA NOT NULL,
+    reason TEXT NOT NULL,
+    time   TIMESTAMP NOT NULL DEFAULT now()
+);
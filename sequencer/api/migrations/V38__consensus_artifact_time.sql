@@ -0,0 +1,5 @@
+-- Record when each consensus artifact was stored, so that artifacts can be retained for a fixed
+-- duration. Existing artifacts are treated as if they were stored at the time of the migration.
+ALTER TABLE da_proposal ADD COLUMN created TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE vid_share ADD COLUMN created TIMESTAMPTZ NOT NULL DEFAULT now();
+ALTER TABLE quorum_proposals ADD COLUMN created TIMESTAMPTZ NOT NULL DEFAULT now();
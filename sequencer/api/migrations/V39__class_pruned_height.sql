@@ -0,0 +1,6 @@
+-- The height up to which each class of query data (leaves, payloads, VID) has been pruned,
+-- separately from the whole blocks deleted by the query service pruner.
+CREATE TABLE class_pruned_height (
+    class  TEXT PRIMARY KEY,
+    height BIGINT NOT NULL
+);
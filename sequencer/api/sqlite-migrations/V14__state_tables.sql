@@ -0,0 +1,35 @@
+-- SQLite equivalent of the Postgres migration V14.
+--
+-- Each row is one version of a node in a Merkle tree. `path` is the sequence of branch indices
+-- from the root to the node, as a comma-separated string (the root has an empty path), and
+-- `created` is the block height at which this version of the node was written. The state of the
+-- tree at height `h` consists of, for each path, the version with the greatest `created <= h`.
+--
+-- Postgres deduplicates hashes into a separate table and stores children as an array of
+-- references. SQLite has no arrays, so branch nodes instead store the serialized list of their
+-- children's hashes, and leaf nodes store their serialized index and entry.
+CREATE TABLE fee_merkle_tree (
+    path     TEXT NOT NULL,
+    created  BIGINT NOT NULL,
+    hash     BLOB NOT NULL,
+    children BLOB,
+    idx      BLOB,
+    entry    BLOB,
+    PRIMARY KEY (path, created)
+);
+
+CREATE TABLE block_merkle_tree (
+    path     TEXT NOT NULL,
+    created  BIGINT NOT NULL,
+    hash     BLOB NOT NULL,
+    children BLOB,
+    idx      BLOB,
+    entry    BLOB,
+    PRIMARY KEY (path, created)
+);
+
+CREATE TABLE last_merklized_state_height (
+    -- The ID is always set to 0, so there is only a single entry in this table.
+    id     INT PRIMARY KEY,
+    height BIGINT NOT NULL
+);
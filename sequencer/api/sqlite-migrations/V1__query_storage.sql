@@ -0,0 +1,66 @@
+-- Tables for the availability and node APIs of the query service.
+--
+-- In the Postgres backend these tables are created by the migrations bundled with the query
+-- service. The embedded backend implements query service storage itself, so it defines its own
+-- schema. Query data structures are stored in their binary serialization, with only the fields
+-- needed for lookups broken out into separate, indexed columns.
+
+CREATE TABLE header (
+    height    BIGINT PRIMARY KEY,
+    hash      TEXT NOT NULL UNIQUE,
+    payload_hash TEXT NOT NULL,
+    timestamp BIGINT NOT NULL,
+
+    -- The header is stored as JSON so that we can index fields of the header which are needed for
+    -- merklized state queries (see the generated columns below).
+    data      TEXT NOT NULL,
+
+    -- Post 0.1, the header serialization nests these fields one level deeper, under `fields`. We
+    -- extract the value from whichever path is present, as the Postgres migration V36 does.
+    block_merkle_tree_root TEXT GENERATED ALWAYS AS (
+        coalesce(
+            json_extract(data, '$.fields.block_merkle_tree_root'),
+            json_extract(data, '$.block_merkle_tree_root')
+        )
+    ) STORED NOT NULL,
+    fee_merkle_tree_root TEXT GENERATED ALWAYS AS (
+        coalesce(
+            json_extract(data, '$.fields.fee_merkle_tree_root'),
+            json_extract(data, '$.fee_merkle_tree_root')
+        )
+    ) STORED NOT NULL
+);
+
+CREATE INDEX header_payload_hash_idx ON header (payload_hash);
+CREATE INDEX header_timestamp_idx ON header (timestamp);
+CREATE INDEX header_block_merkle_tree_root_idx ON header (block_merkle_tree_root);
+CREATE INDEX header_fee_merkle_tree_root_idx ON header (fee_merkle_tree_root);
+
+CREATE TABLE leaf (
+    height     BIGINT PRIMARY KEY REFERENCES header (height) ON DELETE CASCADE,
+    hash       TEXT NOT NULL UNIQUE,
+    block_hash TEXT NOT NULL,
+    data       BLOB NOT NULL
+);
+
+CREATE TABLE payload (
+    height           BIGINT PRIMARY KEY REFERENCES header (height) ON DELETE CASCADE,
+    size             BIGINT NOT NULL,
+    num_transactions BIGINT NOT NULL,
+    data             BLOB NOT NULL
+);
+
+CREATE TABLE vid (
+    height BIGINT PRIMARY KEY REFERENCES header (height) ON DELETE CASCADE,
+    common BLOB NOT NULL,
+    share  BLOB
+);
+
+CREATE TABLE transactions (
+    hash         TEXT NOT NULL,
+    block_height BIGINT NOT NULL REFERENCES header (height) ON DELETE CASCADE,
+    idx          BLOB NOT NULL,
+    PRIMARY KEY (block_height, idx)
+);
+
+CREATE INDEX transactions_hash_idx ON transactions (hash);
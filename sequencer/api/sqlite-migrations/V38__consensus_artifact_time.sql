@@ -0,0 +1,11 @@
+-- Record when each consensus artifact was stored (in seconds since the Unix epoch), so that
+-- artifacts can be retained for a fixed duration. SQLite does not allow a non-constant default in
+-- `ADD COLUMN`, so new rows set `created` explicitly, and existing rows are treated as if they
+-- were stored at the time of the migration.
+ALTER TABLE da_proposal ADD COLUMN created INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE vid_share ADD COLUMN created INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE quorum_proposals ADD COLUMN created INTEGER NOT NULL DEFAULT 0;
+
+UPDATE da_proposal SET created = unixepoch();
+UPDATE vid_share SET created = unixepoch();
+UPDATE quorum_proposals SET created = unixepoch();
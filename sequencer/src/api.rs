@@ -35,9 +35,14 @@ use crate::{
 
 pub mod data_source;
 pub mod endpoints;
+mod explorer;
 pub mod fs;
+mod merkle_nodes;
 pub mod options;
+pub mod pruning;
 pub mod sql;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 mod update;
 
 pub use options::Options;
@@ -819,15 +824,27 @@ pub mod test_helpers {
 #[cfg(test)]
 #[espresso_macros::generic_tests]
 mod api_tests {
+    use std::time::Duration;
+
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
-    use committable::Committable;
+    use async_std::task::sleep;
+    use committable::{Commitment, Committable};
     use data_source::testing::TestableSequencerDataSource;
-    use endpoints::NamespaceProofQueryData;
+    use endpoints::{BlockMerkleProofQueryData, NamespaceProofQueryData};
     use es_version::SequencerVersion;
-    use espresso_types::{Header, NamespaceId};
+    use espresso_light_client::block_comm_root;
+    use espresso_types::{FeeAccount, FeeAmount, Header, NamespaceId};
     use ethers::utils::Anvil;
-    use futures::stream::StreamExt;
-    use hotshot_query_service::availability::{LeafQueryData, VidCommonQueryData};
+    use futures::stream::{StreamExt, TryStreamExt};
+    use hotshot_contract_adapter::{
+        jellyfish::field_to_u256,
+        light_client::{verify_block_merkle_proof, ParsedLightClientState},
+    };
+    use hotshot_query_service::{
+        availability::{BlockQueryData, LeafQueryData, VidCommonQueryData},
+        types::HeightIndexed,
+    };
+    use jf_merkle_tree::prelude::{MerkleProof, Sha3Node};
     use portpicker::pick_unused_port;
     use surf_disco::Client;
     use test_helpers::{
@@ -836,9 +853,9 @@ mod api_tests {
     };
     use tide_disco::error::ServerError;
 
-    use self::options::HotshotEvents;
+    use self::{options::HotshotEvents, pruning::PruningStatus};
     use super::*;
-    use crate::testing::{wait_for_decide_on_handle, TestConfigBuilder};
+    use crate::testing::{wait_for_decide_on_handle, TestConfig, TestConfigBuilder};
 
     #[async_std::test]
     pub(crate) async fn submit_test_with_query_module<D: TestableSequencerDataSource>() {
@@ -956,6 +973,167 @@ mod api_tests {
         assert!(found_empty_block);
     }
 
+    #[async_std::test]
+    pub(crate) async fn test_pruning_status<D: TestableSequencerDataSource>() {
+        setup_logging();
+        setup_backtrace();
+
+        // Start query service.
+        let port = pick_unused_port().expect("No ports free");
+        let storage = D::create_storage().await;
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let network_config = TestConfigBuilder::default().l1_url(l1).build();
+        let config = TestNetworkConfigBuilder::default()
+            .api_config(D::options(&storage, Options::with_port(port)))
+            .network_config(network_config)
+            .build();
+        let _network = TestNetwork::new(config).await;
+
+        // Connect client.
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        // Wait for a block to be sequenced.
+        client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+
+        // Pruning is not enabled, so nothing has been pruned.
+        let status: PruningStatus = client.get("availability/pruning").send().await.unwrap();
+        assert_eq!(status.pruned_height, None);
+        for (class, class_status) in status.classes {
+            assert_eq!(class_status.pruned_height, None, "{class}");
+        }
+
+        // Requests for data which has not been pruned still succeed.
+        let ns_id = NamespaceId::from(42_u32);
+        client
+            .get::<NamespaceProofQueryData>(&format!("availability/block/0/namespace/{ns_id}"))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    #[async_std::test]
+    pub(crate) async fn test_merklized_state_api<D: TestableSequencerDataSource>() {
+        setup_logging();
+        setup_backtrace();
+
+        let port = pick_unused_port().expect("No ports free");
+
+        let storage = D::create_storage().await;
+        let options = D::options(
+            &storage,
+            Options::with_port(port)
+                .state(Default::default())
+                .status(Default::default()),
+        );
+
+        let anvil: ethers::utils::AnvilInstance = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let network_config = TestConfigBuilder::default().l1_url(l1).build();
+        let config = TestNetworkConfigBuilder::default()
+            .api_config(options)
+            .network_config(network_config)
+            .build();
+        let mut network = TestNetwork::new(config).await;
+        let url = format!("http://localhost:{port}").parse().unwrap();
+        let client: Client<ServerError, SequencerVersion> = Client::new(url);
+
+        client.connect(None).await;
+
+        // Wait until some blocks have been decided.
+        tracing::info!("waiting for blocks");
+        let blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap()
+            .take(4)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        // sleep for few seconds so that state data is upserted
+        tracing::info!("waiting for state to be inserted");
+        sleep(Duration::from_secs(5)).await;
+        network.stop_consensus().await;
+
+        for block in &blocks {
+            let i = block.height();
+            tracing::info!(i, "get block state");
+            let path = client
+                .get::<MerkleProof<Commitment<Header>, u64, Sha3Node, 3>>(&format!(
+                    "block-state/{}/{i}",
+                    i + 1
+                ))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(*path.elem().unwrap(), block.hash());
+
+            tracing::info!(i, "get fee state");
+            let account = TestConfig::<5>::builder_key().fee_account();
+            let path = client
+                .get::<MerkleProof<FeeAmount, FeeAccount, Sha3Node, 256>>(&format!(
+                    "fee-state/{}/{}",
+                    i + 1,
+                    account
+                ))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(*path.index(), account);
+            assert!(*path.elem().unwrap() > 0.into(), "{:?}", path.elem());
+        }
+
+        // Earlier blocks can be proven against the block Merkle tree of a light client snapshot.
+        let snapshot = blocks.last().unwrap().height();
+        let header: Header = client
+            .get(&format!("availability/header/{snapshot}"))
+            .send()
+            .await
+            .unwrap();
+        let state = ParsedLightClientState {
+            block_height: snapshot,
+            block_comm_root: field_to_u256(
+                block_comm_root(&header.block_merkle_tree_root()).unwrap(),
+            ),
+            ..ParsedLightClientState::dummy_genesis()
+        };
+        for block in &blocks[..blocks.len() - 1] {
+            let i = block.height();
+            tracing::info!(i, snapshot, "get block proof for light client");
+            let res = client
+                .get::<BlockMerkleProofQueryData>(&format!(
+                    "block-state/light-client/{snapshot}/{i}"
+                ))
+                .send()
+                .await
+                .unwrap();
+            let comm = verify_block_merkle_proof(&state, &res.root, i, &res.proof).unwrap();
+            let expected: &[u8; 32] = block.hash().as_ref();
+            assert_eq!(&comm, expected);
+        }
+
+        // The snapshot block is not in its own block Merkle tree.
+        client
+            .get::<BlockMerkleProofQueryData>(&format!(
+                "block-state/light-client/{snapshot}/{snapshot}"
+            ))
+            .send()
+            .await
+            .unwrap_err();
+    }
+
     #[async_std::test]
     pub(crate) async fn catchup_test_with_query_module<D: TestableSequencerDataSource>() {
         let storage = D::create_storage().await;
@@ -1024,27 +1202,21 @@ mod api_tests {
 
 #[cfg(test)]
 mod test {
-    use std::time::Duration;
-
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
-    use async_std::task::sleep;
-    use committable::{Commitment, Committable};
+    use committable::Committable;
     use es_version::{SequencerVersion, SEQUENCER_VERSION};
     use espresso_types::{
         mock::MockStateCatchup,
         v0_1::{UpgradeMode, ViewBasedUpgrade},
-        FeeAccount, FeeAmount, Header, Upgrade, UpgradeType, ValidatedState,
+        Upgrade, UpgradeType, ValidatedState,
     };
     use ethers::utils::Anvil;
     use futures::{
         future::{self, join_all},
-        stream::{StreamExt, TryStreamExt},
+        stream::StreamExt,
     };
     use hotshot::types::EventType;
-    use hotshot_query_service::{
-        availability::{BlockQueryData, LeafQueryData},
-        types::HeightIndexed,
-    };
+    use hotshot_query_service::availability::{BlockQueryData, LeafQueryData};
     use hotshot_types::{
         event::LeafInfo,
         traits::{
@@ -1053,7 +1225,6 @@ mod test {
         },
         ValidatorConfig,
     };
-    use jf_merkle_tree::prelude::{MerkleProof, Sha3Node};
     use portpicker::pick_unused_port;
     use surf_disco::Client;
     use test_helpers::{
@@ -1068,11 +1239,7 @@ mod test {
         sql::DataSource as SqlDataSource,
     };
     use super::*;
-    use crate::{
-        catchup::StatePeers,
-        persistence::no_storage,
-        testing::{TestConfig, TestConfigBuilder},
-    };
+    use crate::{catchup::StatePeers, persistence::no_storage, testing::TestConfigBuilder};
 
     #[async_std::test]
     async fn test_healthcheck() {
@@ -1117,80 +1284,6 @@ mod test {
         catchup_test_helper(|opt| opt).await
     }
 
-    #[async_std::test]
-    async fn test_merklized_state_api() {
-        setup_logging();
-        setup_backtrace();
-
-        let port = pick_unused_port().expect("No ports free");
-
-        let storage = SqlDataSource::create_storage().await;
-        let options = SqlDataSource::options(
-            &storage,
-            Options::with_port(port)
-                .state(Default::default())
-                .status(Default::default()),
-        );
-
-        let anvil: ethers::utils::AnvilInstance = Anvil::new().spawn();
-        let l1 = anvil.endpoint().parse().unwrap();
-        let network_config = TestConfigBuilder::default().l1_url(l1).build();
-        let config = TestNetworkConfigBuilder::default()
-            .api_config(options)
-            .network_config(network_config)
-            .build();
-        let mut network = TestNetwork::new(config).await;
-        let url = format!("http://localhost:{port}").parse().unwrap();
-        let client: Client<ServerError, SequencerVersion> = Client::new(url);
-
-        client.connect(None).await;
-
-        // Wait until some blocks have been decided.
-        tracing::info!("waiting for blocks");
-        let blocks = client
-            .socket("availability/stream/blocks/0")
-            .subscribe::<BlockQueryData<SeqTypes>>()
-            .await
-            .unwrap()
-            .take(4)
-            .try_collect::<Vec<_>>()
-            .await
-            .unwrap();
-
-        // sleep for few seconds so that state data is upserted
-        tracing::info!("waiting for state to be inserted");
-        sleep(Duration::from_secs(5)).await;
-        network.stop_consensus().await;
-
-        for block in blocks {
-            let i = block.height();
-            tracing::info!(i, "get block state");
-            let path = client
-                .get::<MerkleProof<Commitment<Header>, u64, Sha3Node, 3>>(&format!(
-                    "block-state/{}/{i}",
-                    i + 1
-                ))
-                .send()
-                .await
-                .unwrap();
-            assert_eq!(*path.elem().unwrap(), block.hash());
-
-            tracing::info!(i, "get fee state");
-            let account = TestConfig::<5>::builder_key().fee_account();
-            let path = client
-                .get::<MerkleProof<FeeAmount, FeeAccount, Sha3Node, 256>>(&format!(
-                    "fee-state/{}/{}",
-                    i + 1,
-                    account
-                ))
-                .send()
-                .await
-                .unwrap();
-            assert_eq!(*path.index(), account);
-            assert!(*path.elem().unwrap() > 0.into(), "{:?}", path.elem());
-        }
-    }
-
     #[async_std::test]
     async fn test_catchup() {
         setup_logging();
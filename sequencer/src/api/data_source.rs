@@ -31,6 +31,7 @@ use vec1::Vec1;
 use super::{
     fs,
     options::{Options, Query},
+    pruning::{DataClass, PruningStatus},
     sql, AccountQueryData, BlocksFrontier,
 };
 use crate::{
@@ -60,6 +61,15 @@ impl DataSourceOptions for persistence::fs::Options {
     }
 }
 
+#[cfg(feature = "sqlite")]
+impl DataSourceOptions for persistence::sqlite::Options {
+    type DataSource = super::sqlite::DataSource;
+
+    fn enable_query_module(&self, opt: Options, query: Query) -> Options {
+        opt.query_sqlite(query, self.clone())
+    }
+}
+
 /// A data source with sequencer-specific functionality.
 ///
 /// This trait extends the generic [`AvailabilityDataSource`] with some additional data needed to
@@ -77,6 +87,18 @@ pub trait SequencerDataSource:
 
     /// Instantiate a data source from command line options.
     async fn create(opt: Self::Options, provider: Provider, reset: bool) -> anyhow::Result<Self>;
+
+    /// The height up to which `class` data has been pruned, if any.
+    ///
+    /// Data sources which do not support pruning never prune anything.
+    async fn pruned_height(&self, _class: DataClass) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Report what has been pruned from this data source.
+    async fn pruning_status(&self) -> anyhow::Result<PruningStatus> {
+        Ok(Default::default())
+    }
 }
 
 /// Provider for fetching missing data for the query service.
@@ -394,6 +416,11 @@ impl From<NetworkConfig<PubKey>> for PublicNetworkConfig {
 }
 
 impl PublicNetworkConfig {
+    /// The stake table of the network.
+    pub fn stake_table(&self) -> &[PeerConfig<PubKey>] {
+        &self.config.known_nodes_with_stake
+    }
+
     pub fn into_network_config(
         self,
         my_own_validator_config: ValidatorConfig<PubKey>,
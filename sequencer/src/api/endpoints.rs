@@ -3,19 +3,23 @@
 use std::{
     collections::{BTreeSet, HashMap},
     env,
+    str::FromStr,
 };
 
 use anyhow::Result;
 use async_std::sync::{Arc, RwLock};
 use committable::Committable;
-use espresso_types::{NamespaceId, NsProof, PubKey, Transaction};
+use espresso_types::{
+    BlockMerkleCommitment, BlockMerkleTree, NamespaceId, NsProof, PubKey, Transaction,
+};
 use futures::{try_join, FutureExt};
+use hotshot_contract_adapter::light_client::BlockMerkleProof;
 use hotshot_query_service::{
     availability::{self, AvailabilityDataSource, CustomSnafu, FetchBlockSnafu},
     data_source::storage::ExplorerStorage,
     explorer::{self},
     merklized_state::{
-        self, MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence,
+        self, MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot,
     },
     node, Error,
 };
@@ -23,6 +27,7 @@ use hotshot_types::{
     data::ViewNumber,
     traits::{network::ConnectedNetwork, node_implementation::ConsensusTime},
 };
+use jf_merkle_tree::MerkleTreeScheme;
 use serde::{de::Error as _, Deserialize, Serialize};
 use snafu::OptionExt;
 use tagged_base64::TaggedBase64;
@@ -37,6 +42,7 @@ use super::{
         CatchupDataSource, HotShotConfigDataSource, SequencerDataSource, StateSignatureDataSource,
         SubmitDataSource,
     },
+    pruning::{ensure_retained, ensure_state_retained, DataClass, PrunedHeights},
     StorageState,
 };
 use crate::{SeqTypes, SequencerPersistence};
@@ -47,11 +53,21 @@ pub struct NamespaceProofQueryData {
     pub transactions: Vec<Transaction>,
 }
 
+/// A proof of an earlier block against the block Merkle tree of a light client snapshot.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockMerkleProofQueryData {
+    /// The block Merkle tree root in the header at the snapshot height.
+    pub root: BlockMerkleCommitment,
+    /// Membership proof for the commitment of the requested block.
+    pub proof: BlockMerkleProof,
+}
+
 pub(super) type AvailState<N, P, D, Ver> = Arc<RwLock<StorageState<N, P, D, Ver>>>;
 
 type AvailabilityApi<N, P, D, Ver> = Api<AvailState<N, P, D, Ver>, availability::Error, Ver>;
 
 pub(super) fn availability<N, P, D, Ver: StaticVersionType + 'static>(
+    pruned: PrunedHeights,
     bind_version: Ver,
 ) -> Result<AvailabilityApi<N, P, D, Ver>>
 where
@@ -73,6 +89,18 @@ where
         async move {
             let height: usize = req.integer_param("height")?;
             let ns_id = NamespaceId::from(req.integer_param::<_, u32>("namespace")?);
+
+            // Fail fast if the data we need has been pruned, rather than waiting for a fetch which
+            // will never complete.
+            for class in [DataClass::Payload, DataClass::VidCommon] {
+                let pruned_height = state
+                    .inner()
+                    .pruned_height(class)
+                    .await
+                    .map_err(internal_error)?;
+                ensure_retained(class, height as u64, pruned_height)?;
+            }
+
             let (block, common) = try_join!(
                 async move {
                     state
@@ -117,9 +145,21 @@ where
             }
         }
         .boxed()
+    })?
+    .get("getpruningstatus", |_, state| {
+        async move { state.inner().pruning_status().await.map_err(internal_error) }.boxed()
     })?;
 
-    Ok(api)
+    // Requests for pruned data through the standard routes fail with 410 (Gone), like the
+    // namespace proof route.
+    Ok(api.map_err(move |err| pruned.map_error(err)))
+}
+
+fn internal_error(err: anyhow::Error) -> availability::Error {
+    availability::Error::Custom {
+        message: format!("{err:#}"),
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+    }
 }
 
 type ExplorerApi<N, P, D, Ver> = Api<AvailState<N, P, D, Ver>, explorer::Error, Ver>;
@@ -283,18 +323,170 @@ pub(super) fn merklized_state<N, P, D, S, Ver: StaticVersionType + 'static, cons
 ) -> Result<MerklizedStateApi<N, P, D, Ver>>
 where
     N: ConnectedNetwork<PubKey>,
-    D: MerklizedStateDataSource<SeqTypes, S, ARITY>
+    D: SequencerDataSource
+        + MerklizedStateDataSource<SeqTypes, S, ARITY>
         + Send
         + Sync
         + MerklizedStateHeightPersistence
         + 'static,
     S: MerklizedState<SeqTypes, ARITY>,
+    S::Key: FromStr,
     P: SequencerPersistence,
     for<'a> <S::Commit as TryFrom<&'a TaggedBase64>>::Error: std::fmt::Display,
 {
-    let api = merklized_state::define_api::<AvailState<N, P, D, Ver>, SeqTypes, S, Ver, ARITY>(
-        &Default::default(),
-    )?;
+    let mut options = merklized_state::Options::default();
+    let extension = toml::from_str(include_str!("../../api/merklized_state.toml"))?;
+    options.extensions.push(extension);
+
+    let mut api =
+        merklized_state::define_api::<AvailState<N, P, D, Ver>, SeqTypes, S, Ver, ARITY>(&options)?;
+    path_at_height::<N, P, D, S, Ver, ARITY>(&mut api)?;
+    Ok(api)
+}
+
+/// Serve Merkle paths by height, failing with 410 (Gone) if the state has been pruned.
+///
+/// This replaces the query service handler for queries by height (see `merklized_state.toml`),
+/// which would otherwise look up a snapshot whose nodes may have been partially deleted.
+fn path_at_height<N, P, D, S, Ver: StaticVersionType + 'static, const ARITY: usize>(
+    api: &mut MerklizedStateApi<N, P, D, Ver>,
+) -> Result<()>
+where
+    N: ConnectedNetwork<PubKey>,
+    D: SequencerDataSource + MerklizedStateDataSource<SeqTypes, S, ARITY> + Send + Sync + 'static,
+    S: MerklizedState<SeqTypes, ARITY>,
+    S::Key: FromStr,
+    P: SequencerPersistence,
+{
+    api.get("getpathatheight", |req, state| {
+        async move {
+            let height: u64 = req
+                .integer_param("height")
+                .map_err(merklized_state::Error::from_request_error)?;
+            let key = req
+                .string_param("key")
+                .map_err(merklized_state::Error::from_request_error)?;
+            let key = key.parse::<S::Key>().map_err(|_| {
+                merklized_state::Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("invalid key {key}"),
+                )
+            })?;
+
+            ensure_state_at_height_retained(state, height).await?;
+            state
+                .get_path(Snapshot::<SeqTypes, S, ARITY>::Index(height), key)
+                .await
+                .map_err(|err| {
+                    merklized_state::Error::catch_all(StatusCode::NOT_FOUND, format!("{err}"))
+                })
+        }
+        .boxed()
+    })?;
+    Ok(())
+}
+
+/// Fail with 410 (Gone) if the merklized state at `height` has been pruned.
+async fn ensure_state_at_height_retained<N, P, D, Ver>(
+    state: &StorageState<N, P, D, Ver>,
+    height: u64,
+) -> Result<(), merklized_state::Error>
+where
+    N: ConnectedNetwork<PubKey>,
+    D: SequencerDataSource + Send + Sync,
+    P: SequencerPersistence,
+    Ver: StaticVersionType,
+{
+    let pruned_height = state
+        .inner()
+        .pruned_height(DataClass::MerklizedState)
+        .await
+        .map_err(|err| {
+            merklized_state::Error::catch_all(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+        })?;
+    ensure_state_retained(height, pruned_height)
+}
+
+pub(super) fn block_state<N, P, D, Ver: StaticVersionType + 'static>(
+    _: Ver,
+) -> Result<MerklizedStateApi<N, P, D, Ver>>
+where
+    N: ConnectedNetwork<PubKey>,
+    D: SequencerDataSource
+        + MerklizedStateDataSource<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>
+        + MerklizedStateHeightPersistence
+        + Send
+        + Sync
+        + 'static,
+    P: SequencerPersistence,
+{
+    let mut options = merklized_state::Options::default();
+    for extension in [
+        include_str!("../../api/merklized_state.toml"),
+        include_str!("../../api/block_state.toml"),
+    ] {
+        options.extensions.push(toml::from_str(extension)?);
+    }
+    let timeout = availability::Options::default().fetch_timeout;
+
+    let mut api = merklized_state::define_api::<
+        AvailState<N, P, D, Ver>,
+        SeqTypes,
+        BlockMerkleTree,
+        Ver,
+        { BlockMerkleTree::ARITY },
+    >(&options)?;
+    path_at_height::<N, P, D, BlockMerkleTree, Ver, { BlockMerkleTree::ARITY }>(&mut api)?;
+
+    api.get("getlightclientproof", move |req, state| {
+        async move {
+            let snapshot: u64 = req
+                .integer_param("snapshot")
+                .map_err(merklized_state::Error::from_request_error)?;
+            let height: u64 = req
+                .integer_param("height")
+                .map_err(merklized_state::Error::from_request_error)?;
+            if height >= snapshot {
+                return Err(merklized_state::Error::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!("block {height} is not before snapshot {snapshot}"),
+                ));
+            }
+            ensure_state_at_height_retained(state, snapshot).await?;
+
+            let proof = state
+                .get_path(
+                    Snapshot::<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>::Index(
+                        snapshot,
+                    ),
+                    height,
+                )
+                .await
+                .map_err(|err| {
+                    merklized_state::Error::catch_all(StatusCode::NOT_FOUND, format!("{err}"))
+                })?;
+            // The merklized state at `snapshot` is derived from the leaf at `snapshot`, so the
+            // leaf is available locally.
+            let leaf = state
+                .get_leaf(snapshot as usize)
+                .await
+                .with_timeout(timeout)
+                .await
+                .ok_or_else(|| {
+                    merklized_state::Error::catch_all(
+                        StatusCode::NOT_FOUND,
+                        format!("leaf {snapshot} not available"),
+                    )
+                })?;
+
+            Ok(BlockMerkleProofQueryData {
+                root: leaf.header().block_merkle_tree_root(),
+                proof,
+            })
+        }
+        .boxed()
+    })?;
+
     Ok(api)
 }
 
@@ -0,0 +1,644 @@
+//! Block explorer support for the embedded storage backends.
+//!
+//! The Postgres backend gets explorer storage from the query service, which answers explorer
+//! queries with SQL over its own schema. The SQLite and file system backends instead keep in-memory
+//! indexes of block and transaction hashes, and of transactions by namespace, which are rebuilt
+//! from the stored blocks on startup and updated as new blocks are committed. The summaries
+//! themselves are built from the stored blocks using the same conversions as the Postgres backend,
+//! so the responses are identical. This module contains the explorer logic, which is independent
+//! of how the blocks are actually stored.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use committable::Committable;
+use espresso_types::{NamespaceId, Transaction};
+use hotshot_query_service::{
+    availability::{AvailabilityStorage, BlockId, BlockQueryData},
+    data_source::storage::NodeStorage,
+    explorer::{
+        BlockDetail, BlockIdentifier, BlockSummary, ExplorerHistograms, ExplorerSummary,
+        GenesisOverview, GetBlockDetailError, GetBlockSummariesError, GetBlockSummariesRequest,
+        GetExplorerSummaryError, GetSearchResultsError, GetTransactionDetailError,
+        GetTransactionSummariesError, GetTransactionSummariesRequest, SearchResult,
+        TransactionDetailResponse, TransactionIdentifier, TransactionSummary,
+        TransactionSummaryFilter,
+    },
+    types::HeightIndexed,
+    QueryError, QueryResult,
+};
+use tagged_base64::TaggedBase64;
+
+use super::merkle_nodes::query_error;
+use crate::SeqTypes;
+
+/// The number of recent blocks and transactions included in the explorer summary.
+const SUMMARY_LENGTH: usize = 10;
+
+/// The number of recent blocks included in the explorer histograms.
+const HISTOGRAM_LENGTH: usize = 50;
+
+/// The maximum number of blocks or transactions returned for a search.
+const SEARCH_RESULTS_LENGTH: usize = 5;
+
+/// The position of a transaction in the chain: block height and offset within the block.
+type Position = (u64, usize);
+
+/// In-memory indexes over the blocks in an embedded storage backend.
+///
+/// Newly inserted blocks are staged and only indexed on [`commit`](Self::commit), so that the
+/// indexes stay consistent with the underlying storage.
+#[derive(Debug, Default)]
+pub(crate) struct ExplorerIndex {
+    blocks: BTreeMap<Vec<u8>, u64>,
+    transactions: BTreeMap<Vec<u8>, Position>,
+    namespaces: BTreeMap<NamespaceId, BTreeSet<Position>>,
+    num_transactions: u64,
+    pending: Vec<BlockQueryData<SeqTypes>>,
+}
+
+impl ExplorerIndex {
+    /// Stage a new block to be indexed.
+    pub(crate) fn insert(&mut self, block: BlockQueryData<SeqTypes>) {
+        self.pending.push(block);
+    }
+
+    /// Index all staged blocks.
+    pub(crate) fn commit(&mut self) {
+        for block in std::mem::take(&mut self.pending) {
+            self.index(&block);
+        }
+    }
+
+    /// Discard all staged blocks.
+    pub(crate) fn revert(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Add a committed block to the indexes.
+    pub(crate) fn index(&mut self, block: &BlockQueryData<SeqTypes>) {
+        let height = block.height();
+        if self
+            .blocks
+            .insert(block.hash().as_ref().to_vec(), height)
+            .is_some()
+        {
+            // We have already indexed this block.
+            return;
+        }
+
+        for (offset, (_, txn)) in block.enumerate().enumerate() {
+            let pos = (height, offset);
+            self.transactions
+                .insert(txn.commit().as_ref().to_vec(), pos);
+            self.namespaces
+                .entry(txn.namespace())
+                .or_default()
+                .insert(pos);
+            self.num_transactions += 1;
+        }
+    }
+
+    fn search_blocks(&self, prefix: &[u8]) -> Vec<u64> {
+        self.blocks
+            .range(prefix.to_vec()..)
+            .take_while(|(hash, _)| hash.starts_with(prefix))
+            .map(|(_, height)| *height)
+            .take(SEARCH_RESULTS_LENGTH)
+            .collect()
+    }
+
+    fn search_transactions(&self, prefix: &[u8]) -> Vec<Position> {
+        self.transactions
+            .range(prefix.to_vec()..)
+            .take_while(|(hash, _)| hash.starts_with(prefix))
+            .map(|(_, pos)| *pos)
+            .take(SEARCH_RESULTS_LENGTH)
+            .collect()
+    }
+}
+
+/// A storage backend which answers explorer queries from an [`ExplorerIndex`].
+pub(crate) trait IndexedStorage:
+    AvailabilityStorage<SeqTypes> + NodeStorage<SeqTypes> + Sync
+{
+    /// The index over the committed blocks in this storage.
+    fn explorer_index(&self) -> &ExplorerIndex;
+}
+
+async fn load_block(
+    storage: &impl IndexedStorage,
+    height: u64,
+) -> QueryResult<BlockQueryData<SeqTypes>> {
+    storage.get_block(BlockId::Number(height as usize)).await
+}
+
+async fn latest_height(storage: &impl IndexedStorage) -> QueryResult<u64> {
+    match storage.block_height().await? {
+        0 => Err(QueryError::NotFound),
+        height => Ok(height as u64 - 1),
+    }
+}
+
+async fn block_height_for(
+    storage: &impl IndexedStorage,
+    id: BlockIdentifier<SeqTypes>,
+) -> QueryResult<u64> {
+    match id {
+        BlockIdentifier::Latest => latest_height(storage).await,
+        BlockIdentifier::Height(height) => Ok(height as u64),
+        BlockIdentifier::Hash(hash) => Ok(storage.get_block(BlockId::Hash(hash)).await?.height()),
+    }
+}
+
+/// Resolve a transaction identifier to a position in the chain.
+///
+/// [`TransactionIdentifier::Latest`] resolves to a position after every transaction in the latest
+/// block, so that ranges ending at the target include the whole block.
+async fn transaction_position(
+    storage: &impl IndexedStorage,
+    id: TransactionIdentifier<SeqTypes>,
+) -> QueryResult<Position> {
+    match id {
+        TransactionIdentifier::Latest => Ok((latest_height(storage).await?, usize::MAX)),
+        TransactionIdentifier::HeightAndOffset(height, offset) => Ok((height as u64, offset)),
+        TransactionIdentifier::Hash(hash) => storage
+            .explorer_index()
+            .transactions
+            .get(hash.as_ref())
+            .copied()
+            .ok_or(QueryError::NotFound),
+    }
+}
+
+/// Load the transactions at `positions`, in order.
+async fn load_transactions(
+    storage: &impl IndexedStorage,
+    positions: impl IntoIterator<Item = Position>,
+) -> QueryResult<Vec<(BlockQueryData<SeqTypes>, usize, Transaction)>> {
+    let mut block: Option<BlockQueryData<SeqTypes>> = None;
+    let mut txns = vec![];
+    for (height, offset) in positions {
+        if block.as_ref().map(|block| block.height()) != Some(height) {
+            block = Some(load_block(storage, height).await?);
+        }
+        let block = block.as_ref().unwrap();
+        let (_, txn) = block.enumerate().nth(offset).ok_or(QueryError::NotFound)?;
+        txns.push((block.clone(), offset, txn));
+    }
+    Ok(txns)
+}
+
+/// Find the positions of the `count` transactions at or before `target`, newest first.
+async fn transactions_before(
+    storage: &impl IndexedStorage,
+    target: Position,
+    count: usize,
+) -> QueryResult<Vec<Position>> {
+    let mut positions = vec![];
+    let mut height = Some(target.0);
+    while let Some(h) = height {
+        if positions.len() >= count {
+            break;
+        }
+        let block = load_block(storage, h).await?;
+        let num_transactions = block.num_transactions() as usize;
+        let end = if h == target.0 {
+            num_transactions.min(target.1.saturating_add(1))
+        } else {
+            num_transactions
+        };
+        positions.extend((0..end).rev().map(|offset| (h, offset)));
+        height = h.checked_sub(1);
+    }
+    positions.truncate(count);
+    Ok(positions)
+}
+
+fn block_summary(block: BlockQueryData<SeqTypes>) -> QueryResult<BlockSummary<SeqTypes>> {
+    BlockSummary::try_from(block).map_err(query_error)
+}
+
+fn transaction_summary(
+    (block, offset, txn): (BlockQueryData<SeqTypes>, usize, Transaction),
+) -> QueryResult<TransactionSummary<SeqTypes>> {
+    TransactionSummary::try_from((&block, offset, txn)).map_err(query_error)
+}
+
+pub(crate) async fn get_block_detail(
+    storage: &impl IndexedStorage,
+    request: BlockIdentifier<SeqTypes>,
+) -> Result<BlockDetail<SeqTypes>, GetBlockDetailError> {
+    let height = block_height_for(storage, request).await?;
+    let block = load_block(storage, height).await?;
+    Ok(BlockDetail::try_from(block).map_err(query_error)?)
+}
+
+pub(crate) async fn get_block_summaries(
+    storage: &impl IndexedStorage,
+    request: GetBlockSummariesRequest<SeqTypes>,
+) -> Result<Vec<BlockSummary<SeqTypes>>, GetBlockSummariesError> {
+    let range = request.0;
+    let last = block_height_for(storage, range.target).await?;
+    let first = (last + 1).saturating_sub(range.num_blocks.get() as u64);
+
+    let mut summaries = vec![];
+    for height in (first..=last).rev() {
+        summaries.push(block_summary(load_block(storage, height).await?)?);
+    }
+    Ok(summaries)
+}
+
+pub(crate) async fn get_transaction_detail(
+    storage: &impl IndexedStorage,
+    request: TransactionIdentifier<SeqTypes>,
+) -> Result<TransactionDetailResponse<SeqTypes>, GetTransactionDetailError> {
+    let target = transaction_position(storage, request).await?;
+    let position = match target {
+        // For the latest transaction, find the last transaction in the chain.
+        (_, usize::MAX) => *transactions_before(storage, target, 1)
+            .await?
+            .first()
+            .ok_or(QueryError::NotFound)?,
+        position => position,
+    };
+    let (block, offset, txn) = load_transactions(storage, [position])
+        .await?
+        .pop()
+        .ok_or(QueryError::NotFound)?;
+    Ok(TransactionDetailResponse::try_from((&block, offset, txn)).map_err(query_error)?)
+}
+
+pub(crate) async fn get_transaction_summaries(
+    storage: &impl IndexedStorage,
+    request: GetTransactionSummariesRequest<SeqTypes>,
+) -> Result<Vec<TransactionSummary<SeqTypes>>, GetTransactionSummariesError> {
+    let count = request.range.num_transactions.get();
+    let target = transaction_position(storage, request.range.target).await?;
+
+    let positions = match request.filter {
+        TransactionSummaryFilter::None => transactions_before(storage, target, count).await?,
+        TransactionSummaryFilter::RollUp(namespace) => storage
+            .explorer_index()
+            .namespaces
+            .get(&namespace)
+            .map(|positions| {
+                positions
+                    .range(..=target)
+                    .rev()
+                    .take(count)
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default(),
+        TransactionSummaryFilter::Block(height) => {
+            let height = height as u64;
+            let num_transactions = load_block(storage, height).await?.num_transactions() as usize;
+            (0..num_transactions)
+                .rev()
+                .map(|offset| (height, offset))
+                .take(count)
+                .collect()
+        }
+    };
+
+    Ok(load_transactions(storage, positions)
+        .await?
+        .into_iter()
+        .map(transaction_summary)
+        .collect::<QueryResult<_>>()?)
+}
+
+pub(crate) async fn get_explorer_summary(
+    storage: &impl IndexedStorage,
+) -> Result<ExplorerSummary<SeqTypes>, GetExplorerSummaryError> {
+    let latest_height = latest_height(storage).await?;
+    let latest_block = load_block(storage, latest_height).await?;
+
+    // Collect statistics about recent blocks, oldest first. For the block time of the oldest block
+    // in the window, we need the timestamp of its parent.
+    let first = latest_height.saturating_sub(HISTOGRAM_LENGTH as u64 - 1);
+    let mut histograms = ExplorerHistograms {
+        block_time: VecDeque::new(),
+        block_size: VecDeque::new(),
+        block_transactions: VecDeque::new(),
+        block_heights: VecDeque::new(),
+    };
+    let mut latest_blocks = vec![];
+    let mut prev_timestamp = match first.checked_sub(1) {
+        Some(height) => Some(load_block(storage, height).await?.header().timestamp()),
+        None => None,
+    };
+    for height in first..=latest_height {
+        let block = load_block(storage, height).await?;
+        let timestamp = block.header().timestamp();
+        histograms
+            .block_time
+            .push_back(prev_timestamp.map(|prev| timestamp.saturating_sub(prev)));
+        histograms.block_size.push_back(Some(block.size()));
+        histograms
+            .block_transactions
+            .push_back(block.num_transactions());
+        histograms.block_heights.push_back(height);
+        prev_timestamp = Some(timestamp);
+
+        if height + SUMMARY_LENGTH as u64 > latest_height {
+            latest_blocks.push(block_summary(block)?);
+        }
+    }
+    latest_blocks.reverse();
+
+    let latest_transactions = load_transactions(
+        storage,
+        transactions_before(storage, (latest_height, usize::MAX), SUMMARY_LENGTH).await?,
+    )
+    .await?
+    .into_iter()
+    .map(transaction_summary)
+    .collect::<QueryResult<_>>()?;
+
+    let index = storage.explorer_index();
+    Ok(ExplorerSummary {
+        latest_block: BlockDetail::try_from(latest_block).map_err(query_error)?,
+        genesis_overview: GenesisOverview {
+            rollups: index.namespaces.len() as u64,
+            transactions: index.num_transactions,
+            blocks: latest_height + 1,
+        },
+        latest_blocks,
+        latest_transactions,
+        histograms,
+    })
+}
+
+pub(crate) async fn get_search_results(
+    storage: &impl IndexedStorage,
+    query: TaggedBase64,
+) -> Result<SearchResult<SeqTypes>, GetSearchResultsError> {
+    let prefix = query.value();
+
+    let mut blocks = vec![];
+    for height in storage.explorer_index().search_blocks(&prefix) {
+        blocks.push(block_summary(load_block(storage, height).await?)?);
+    }
+
+    let transactions = load_transactions(
+        storage,
+        storage.explorer_index().search_transactions(&prefix),
+    )
+    .await?
+    .into_iter()
+    .map(transaction_summary)
+    .collect::<QueryResult<_>>()?;
+
+    Ok(SearchResult {
+        blocks,
+        transactions,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::NonZeroUsize;
+
+    use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+    use committable::Commitment;
+    use es_version::SequencerVersion;
+    use ethers::utils::Anvil;
+    use futures::StreamExt;
+    use hotshot_query_service::{
+        availability::{LeafQueryData, UpdateAvailabilityData},
+        data_source::{
+            sql::Config,
+            storage::{sql::testing::TmpDb, ExplorerStorage, SqlStorage},
+            VersionedDataSource,
+        },
+        explorer::{BlockRange, TransactionRange},
+    };
+    use portpicker::pick_unused_port;
+    use serde::Serialize;
+    use surf_disco::Client;
+    use tempfile::TempDir;
+    use tide_disco::error::ServerError;
+
+    use super::*;
+    #[cfg(feature = "sqlite")]
+    use crate::api::sqlite::SqliteStorage;
+    use crate::{
+        api::{
+            fs::FsStorage,
+            test_helpers::{TestNetwork, TestNetworkConfigBuilder},
+            Options,
+        },
+        persistence,
+        testing::{wait_for_decide_on_handle, TestConfigBuilder},
+    };
+
+    /// Assert that two explorer responses are identical.
+    #[track_caller]
+    fn assert_same<T: Serialize>(embedded: T, sql: T) {
+        assert_eq!(
+            serde_json::to_value(embedded).unwrap(),
+            serde_json::to_value(sql).unwrap()
+        );
+    }
+
+    /// Assert that `storage` answers explorer queries exactly like the Postgres storage `sql`.
+    ///
+    /// Both storages must contain the blocks up to `block_height`, including `txns` in namespace
+    /// `ns_id`.
+    async fn assert_matches_sql(
+        storage: &(impl IndexedStorage + ExplorerStorage<SeqTypes>),
+        sql: &SqlStorage,
+        block_height: u64,
+        txns: &[Transaction],
+        ns_id: NamespaceId,
+    ) {
+        assert_same(
+            storage.get_explorer_summary().await.unwrap(),
+            sql.get_explorer_summary().await.unwrap(),
+        );
+        for target in [BlockIdentifier::Latest, BlockIdentifier::Height(1)] {
+            let request = || {
+                GetBlockSummariesRequest(BlockRange {
+                    target: target.clone(),
+                    num_blocks: NonZeroUsize::new(10).unwrap(),
+                })
+            };
+            assert_same(
+                storage.get_block_summaries(request()).await.unwrap(),
+                sql.get_block_summaries(request()).await.unwrap(),
+            );
+        }
+        for height in 0..=block_height as usize {
+            let block = storage.get_block(BlockId::Number(height)).await.unwrap();
+            assert_same(
+                storage
+                    .get_block_detail(BlockIdentifier::Height(height))
+                    .await
+                    .unwrap(),
+                sql.get_block_detail(BlockIdentifier::Height(height))
+                    .await
+                    .unwrap(),
+            );
+            let query = TaggedBase64::parse(&block.hash().to_string()).unwrap();
+            assert_same(
+                storage.get_search_results(query.clone()).await.unwrap(),
+                sql.get_search_results(query).await.unwrap(),
+            );
+        }
+        for filter in [
+            TransactionSummaryFilter::None,
+            TransactionSummaryFilter::RollUp(ns_id),
+        ] {
+            let request = || GetTransactionSummariesRequest {
+                range: TransactionRange {
+                    target: TransactionIdentifier::Latest,
+                    num_transactions: NonZeroUsize::new(10).unwrap(),
+                },
+                filter: filter.clone(),
+            };
+            assert_same(
+                storage.get_transaction_summaries(request()).await.unwrap(),
+                sql.get_transaction_summaries(request()).await.unwrap(),
+            );
+        }
+        for txn in txns {
+            let id = || TransactionIdentifier::Hash(txn.commit());
+            assert_same(
+                storage.get_transaction_detail(id()).await.unwrap(),
+                sql.get_transaction_detail(id()).await.unwrap(),
+            );
+            let query = TaggedBase64::parse(&txn.commit().to_string()).unwrap();
+            assert_same(
+                storage.get_search_results(query.clone()).await.unwrap(),
+                sql.get_search_results(query).await.unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_prefix() {
+        let mut index = ExplorerIndex::default();
+        index.blocks.insert(vec![1, 2, 3], 0);
+        index.blocks.insert(vec![1, 2, 4], 1);
+        index.blocks.insert(vec![1, 3, 0], 2);
+        index.blocks.insert(vec![2, 0, 0], 3);
+
+        assert_eq!(index.search_blocks(&[1, 2]), vec![0, 1]);
+        assert_eq!(index.search_blocks(&[1]), vec![0, 1, 2]);
+        assert_eq!(index.search_blocks(&[1, 3, 0]), vec![2]);
+        assert_eq!(index.search_blocks(&[3]), Vec::<u64>::new());
+    }
+
+    #[async_std::test]
+    async fn test_explorer_matches_sql() {
+        setup_logging();
+        setup_backtrace();
+
+        // Run a network to produce some blocks with transactions in them.
+        let port = pick_unused_port().expect("No ports free");
+        let tmp = TempDir::new().unwrap();
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let config = TestNetworkConfigBuilder::default()
+            .api_config(
+                Options::with_port(port)
+                    .query_fs(
+                        Default::default(),
+                        persistence::fs::Options::new(tmp.path().into()),
+                    )
+                    .submit(Default::default()),
+            )
+            .network_config(TestConfigBuilder::default().l1_url(l1).build())
+            .build();
+        let network = TestNetwork::new(config).await;
+        let mut events = network.server.event_stream().await;
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        let ns_id = NamespaceId::from(42_u32);
+        let mut txns = vec![];
+        let mut block_height = 0;
+        for i in 0..3u8 {
+            let txn = Transaction::new(ns_id, vec![i; 4]);
+            let hash: Commitment<Transaction> = client
+                .post("submit/submit")
+                .body_json(&txn)
+                .unwrap()
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(txn.commit(), hash);
+            block_height = wait_for_decide_on_handle(&mut events, &txn).await;
+            txns.push(txn);
+        }
+
+        // Copy the chain into fresh file system, SQLite and Postgres storage.
+        let fs_dir = TempDir::new().unwrap();
+        let mut fs = FsStorage::create(fs_dir.path()).await.unwrap();
+        #[cfg(feature = "sqlite")]
+        let sqlite_dir = TempDir::new().unwrap();
+        #[cfg(feature = "sqlite")]
+        let sqlite_opt = persistence::sqlite::Options::new(sqlite_dir.path().join("sequencer.db"));
+        #[cfg(feature = "sqlite")]
+        let mut sqlite = SqliteStorage::open(sqlite_opt.connect().await.unwrap())
+            .await
+            .unwrap();
+        let db = TmpDb::init().await;
+        let mut sql = SqlStorage::connect(
+            Config::try_from(persistence::sql::Options {
+                port: Some(db.port()),
+                host: Some(db.host()),
+                user: Some("postgres".into()),
+                password: Some("password".into()),
+                ..Default::default()
+            })
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+        let mut leaves = client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .unwrap();
+        let mut blocks = client
+            .socket("availability/stream/blocks/0")
+            .subscribe::<BlockQueryData<SeqTypes>>()
+            .await
+            .unwrap();
+        for _ in 0..=block_height {
+            let leaf = leaves.next().await.unwrap().unwrap();
+            let block = blocks.next().await.unwrap().unwrap();
+            fs.insert_leaf(leaf.clone()).await.unwrap();
+            fs.insert_block(block.clone()).await.unwrap();
+            #[cfg(feature = "sqlite")]
+            {
+                sqlite.insert_leaf(leaf.clone()).await.unwrap();
+                sqlite.insert_block(block.clone()).await.unwrap();
+            }
+            sql.insert_leaf(leaf).await.unwrap();
+            sql.insert_block(block).await.unwrap();
+        }
+        fs.commit().await.unwrap();
+        sql.commit().await.unwrap();
+
+        // Reopen the embedded storage, so that we also check the indexes rebuilt on startup.
+        drop(fs);
+        let fs = FsStorage::open(fs_dir.path()).await.unwrap();
+        assert_matches_sql(&fs, &sql, block_height, &txns, ns_id).await;
+
+        #[cfg(feature = "sqlite")]
+        {
+            // Check the index maintained as blocks are committed, and the one rebuilt on startup.
+            sqlite.commit().await.unwrap();
+            assert_matches_sql(&sqlite, &sql, block_height, &txns, ns_id).await;
+            drop(sqlite);
+            let sqlite = SqliteStorage::open(sqlite_opt.connect().await.unwrap())
+                .await
+                .unwrap();
+            assert_matches_sql(&sqlite, &sql, block_height, &txns, ns_id).await;
+        }
+    }
+}
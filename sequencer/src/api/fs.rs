@@ -1,32 +1,121 @@
 use std::path::Path;
 
+use anyhow::ensure;
 use async_trait::async_trait;
-use hotshot_query_service::data_source::FileSystemDataSource;
+use committable::Commitment;
+use espresso_types::ChainConfig;
+use ethers::prelude::Address;
+use hotshot_query_service::data_source::FetchingDataSource;
+use hotshot_types::data::ViewNumber;
 
-use super::data_source::{CatchupDataSource, Provider, SequencerDataSource};
-use crate::{persistence::fs::Options, SeqTypes};
+use super::{
+    data_source::{CatchupDataSource, Provider, SequencerDataSource},
+    pruning::{ClassStatus, DataClass, PruningStatus},
+    AccountQueryData, BlocksFrontier,
+};
+use crate::{
+    persistence::{fs::Options, ChainConfigPersistence},
+    SeqTypes,
+};
 
-pub type DataSource = FileSystemDataSource<SeqTypes, Provider>;
+mod node_log;
+mod storage;
+
+pub use storage::FsStorage;
+
+pub type DataSource = FetchingDataSource<SeqTypes, FsStorage, Provider>;
 
 #[async_trait]
 impl SequencerDataSource for DataSource {
     type Options = Options;
 
     async fn create(opt: Self::Options, provider: Provider, reset: bool) -> anyhow::Result<Self> {
+        if opt.is_encrypted() {
+            tracing::warn!(
+                "consensus storage is encrypted, but query service storage is not encrypted at rest"
+            );
+        }
+        let state_retention = opt.merklized_state_retention();
+        ensure!(
+            state_retention != Some(0),
+            "merklized state retention must be at least one block"
+        );
         let path = Path::new(opt.path());
-        let data_source = {
+        let storage = {
             if reset {
-                FileSystemDataSource::create(path, provider).await?
+                FsStorage::create(path).await?
             } else {
-                FileSystemDataSource::open(path, provider).await?
+                FsStorage::open(path).await?
             }
         };
 
-        Ok(data_source)
+        FetchingDataSource::builder(storage.with_state_retention(state_retention), provider)
+            .build()
+            .await
+    }
+
+    async fn pruned_height(&self, class: DataClass) -> anyhow::Result<Option<u64>> {
+        // Only merklized state is pruned by this data source.
+        if class != DataClass::MerklizedState {
+            return Ok(None);
+        }
+        Ok(self.storage().await.state_pruned_height())
+    }
+
+    async fn pruning_status(&self) -> anyhow::Result<PruningStatus> {
+        let pruned_height = self.storage().await.state_pruned_height();
+        let mut status = PruningStatus::default();
+        status.classes.insert(
+            DataClass::MerklizedState,
+            ClassStatus {
+                pruned_height,
+                oldest_height: Some(pruned_height.map_or(0, |h| h + 1)),
+            },
+        );
+        Ok(status)
+    }
+}
+
+impl CatchupDataSource for DataSource {
+    async fn get_account(
+        &self,
+        height: u64,
+        view: ViewNumber,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        self.storage()
+            .await
+            .get_account(height, view, account)
+            .await
+    }
+
+    async fn get_frontier(&self, height: u64, view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
+        self.storage().await.get_frontier(height, view).await
+    }
+
+    async fn get_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        self.storage().await.get_chain_config(commitment).await
     }
 }
 
-impl CatchupDataSource for DataSource {}
+#[async_trait]
+impl ChainConfigPersistence for DataSource {
+    async fn insert_chain_config(&mut self, chain_config: ChainConfig) -> anyhow::Result<()> {
+        (*self.storage_mut().await)
+            .insert_chain_config(chain_config)
+            .await
+    }
+
+    async fn load_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        self.storage().await.load_chain_config(commitment).await
+    }
+}
 
 #[cfg(test)]
 mod impl_testable_data_source {
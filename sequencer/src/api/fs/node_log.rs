@@ -0,0 +1,485 @@
+//! Append-only file storage for versioned Merkle tree nodes.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context};
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+
+use super::super::merkle_nodes::StoredNode;
+
+/// A record in a node log: one version of one node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Record {
+    path: Vec<usize>,
+    created: u64,
+    node: StoredNode,
+}
+
+/// The versions of the nodes of a single Merkle tree, stored in an append-only file.
+///
+/// Each record is stored as a little-endian `u32` length followed by the `bincode` serialization
+/// of the record. The offset of each record is indexed in memory by path and version, so reading a
+/// node takes a single positioned read. Since the index holds an entry for every version of every
+/// node, it is kept compact: paths are packed into variable-length byte strings and the versions of
+/// each node are a sorted vector of `(height, offset)` pairs. Appended records are buffered until
+/// [`commit`] is called, so that a failed state update can be discarded with [`revert`].
+///
+/// Without pruning, the log and its index grow with every version of every node. [`prune`]
+/// rewrites the log without the versions which are not needed for recent snapshots, which bounds
+/// both the memory used by the index and the time taken to rebuild it when the log is opened.
+///
+/// [`commit`]: Self::commit
+/// [`revert`]: Self::revert
+/// [`prune`]: Self::prune
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub(super) struct NodeLog {
+    path: PathBuf,
+    #[derivative(Debug = "ignore")]
+    file: File,
+    len: u64,
+    #[derivative(Debug = "ignore")]
+    index: HashMap<Box<[u8]>, Vec<(u64, u64)>>,
+    #[derivative(Debug = "ignore")]
+    pending: Vec<Record>,
+}
+
+impl NodeLog {
+    /// Open the log at `path`, creating it if it does not exist.
+    ///
+    /// If the log ends with a partially written record (for example, because we crashed while
+    /// committing) the partial record is discarded. A corrupt record anywhere else in the log is an
+    /// error, since discarding it would also discard every committed record after it.
+    pub(super) fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .context(format!("opening node log {}", path.display()))?;
+
+        let file_len = file.metadata()?.len();
+        let mut index = HashMap::new();
+        let mut len = 0;
+        {
+            let mut reader = BufReader::new(&file);
+            loop {
+                match read_record(&mut reader)? {
+                    ReadRecord::Record(record, size) => {
+                        insert_version(&mut index, &record.path, record.created, len);
+                        len += size;
+                    }
+                    ReadRecord::End | ReadRecord::Partial => break,
+                    // A record that was only partially synced before a crash may have the right
+                    // length but garbage contents. This can only happen to the last record in the
+                    // file; anywhere else, the log itself is damaged.
+                    ReadRecord::Corrupt(size) if len + size == file_len => break,
+                    ReadRecord::Corrupt(_) => {
+                        bail!("corrupt record at offset {len} in {}", path.display());
+                    }
+                }
+            }
+        }
+        for versions in index.values_mut() {
+            versions.shrink_to_fit();
+        }
+
+        if len < file_len {
+            tracing::warn!(
+                path = %path.display(),
+                len,
+                "discarding partial record at end of node log"
+            );
+            file.set_len(len)?;
+        }
+
+        Ok(Self {
+            path: path.into(),
+            file,
+            len,
+            index,
+            pending: vec![],
+        })
+    }
+
+    /// Stage a new version of the node at `path`, created at block height `created`.
+    pub(super) fn insert(&mut self, path: Vec<usize>, created: u64, node: StoredNode) {
+        self.pending.push(Record {
+            path,
+            created,
+            node,
+        });
+    }
+
+    /// Load the latest committed version of the node at `path` created at or before `height`.
+    pub(super) fn load(&self, path: &[usize], height: u64) -> anyhow::Result<Option<StoredNode>> {
+        let Some(versions) = self.index.get(pack_path(path).as_slice()) else {
+            return Ok(None);
+        };
+        // The number of versions created at or before `height`.
+        let count = versions.partition_point(|&(created, _)| created <= height);
+        let Some(&(_, offset)) = count.checked_sub(1).map(|i| &versions[i]) else {
+            return Ok(None);
+        };
+
+        let mut len = [0; 4];
+        self.file.read_exact_at(&mut len, offset)?;
+        let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+        self.file.read_exact_at(&mut bytes, offset + 4)?;
+        let record: Record = bincode::deserialize(&bytes).context(format!(
+            "corrupt record at offset {offset} in {}",
+            self.path.display()
+        ))?;
+        Ok(Some(record.node))
+    }
+
+    /// Write all staged nodes to disk.
+    pub(super) fn commit(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut buf = vec![];
+        let mut offsets = vec![];
+        for record in &self.pending {
+            let bytes = bincode::serialize(record)?;
+            offsets.push(self.len + buf.len() as u64);
+            buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&bytes);
+        }
+        if let Err(err) = self
+            .file
+            .write_all(&buf)
+            .and_then(|()| self.file.sync_data())
+        {
+            // Roll back any partial write, so that the file stays consistent with our offsets.
+            self.file.set_len(self.len)?;
+            return Err(err.into());
+        }
+        self.len += buf.len() as u64;
+
+        // Only update the index once the records are durable, so that readers never observe nodes
+        // which might be lost.
+        for (record, offset) in self.pending.drain(..).zip(offsets) {
+            insert_version(&mut self.index, &record.path, record.created, offset);
+        }
+        Ok(())
+    }
+
+    /// Discard all staged nodes.
+    pub(super) fn revert(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Delete the versions of nodes which are not needed to load nodes at `height` or later.
+    ///
+    /// A version can be deleted if it was replaced by a newer version at or before `height`. The
+    /// latest version of each node is never deleted. The retained records are copied to a new
+    /// file, which atomically replaces the log, so a crash while pruning leaves the log intact.
+    /// Staged nodes must be committed or reverted first.
+    pub(super) fn prune(&mut self, height: u64) -> anyhow::Result<()> {
+        ensure!(
+            self.pending.is_empty(),
+            "cannot prune with uncommitted nodes"
+        );
+
+        // Collect the offsets of the retained records, in the order they appear in the log.
+        let mut offsets = vec![];
+        for versions in self.index.values() {
+            let count = versions.partition_point(|&(created, _)| created <= height);
+            let first = count.saturating_sub(1);
+            offsets.extend(versions[first..].iter().map(|&(_, offset)| offset));
+        }
+        offsets.sort_unstable();
+
+        let mut swap_path = self.path.clone();
+        swap_path.set_extension("swp");
+        let mut swap = BufWriter::new(File::create(&swap_path)?);
+        let mut index = HashMap::new();
+        let mut len = 0;
+        for offset in offsets {
+            let mut size = [0; 4];
+            self.file.read_exact_at(&mut size, offset)?;
+            let mut bytes = vec![0; u32::from_le_bytes(size) as usize];
+            self.file.read_exact_at(&mut bytes, offset + 4)?;
+            let record: Record = bincode::deserialize(&bytes).context(format!(
+                "corrupt record at offset {offset} in {}",
+                self.path.display()
+            ))?;
+            insert_version(&mut index, &record.path, record.created, len);
+            swap.write_all(&size)?;
+            swap.write_all(&bytes)?;
+            len += 4 + bytes.len() as u64;
+        }
+        swap.into_inner()?.sync_all()?;
+        fs::rename(&swap_path, &self.path)?;
+        for versions in index.values_mut() {
+            versions.shrink_to_fit();
+        }
+
+        tracing::info!(
+            path = %self.path.display(),
+            height,
+            old_len = self.len,
+            new_len = len,
+            "pruned node log"
+        );
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .context(format!("opening node log {}", self.path.display()))?;
+        self.len = len;
+        self.index = index;
+        Ok(())
+    }
+}
+
+/// Record that the version of the node at `path` created at `created` is stored at `offset`.
+fn insert_version(
+    index: &mut HashMap<Box<[u8]>, Vec<(u64, u64)>>,
+    path: &[usize],
+    created: u64,
+    offset: u64,
+) {
+    let versions = index.entry(pack_path(path).into()).or_default();
+    // Versions are almost always appended in order, so this is usually a push.
+    match versions.binary_search_by_key(&created, |&(created, _)| created) {
+        Ok(i) => versions[i].1 = offset,
+        Err(i) => versions.insert(i, (created, offset)),
+    }
+}
+
+/// Pack a traversal path into a compact index key.
+///
+/// Each branch index is encoded as an LEB128 varint, which takes a single byte for any tree with a
+/// branching factor of at most 128.
+fn pack_path(path: &[usize]) -> Vec<u8> {
+    let mut key = Vec::with_capacity(path.len());
+    for &branch in path {
+        let mut branch = branch;
+        while branch >= 0x80 {
+            key.push((branch as u8) | 0x80);
+            branch >>= 7;
+        }
+        key.push(branch as u8);
+    }
+    key
+}
+
+/// The result of reading the next record from a log.
+enum ReadRecord {
+    /// A valid record, and its size on disk.
+    Record(Record, u64),
+    /// The end of the log.
+    End,
+    /// A record which was cut off by the end of the log.
+    Partial,
+    /// A complete record, with the given size on disk, which could not be deserialized.
+    Corrupt(u64),
+}
+
+/// Read the next record from a log.
+fn read_record(reader: &mut impl Read) -> anyhow::Result<ReadRecord> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+            // `read_exact` does not tell us how many bytes it read before hitting the end of the
+            // file, but [`NodeLog::open`] treats a partial length prefix the same as the end of
+            // the log: either way, everything from here on is discarded.
+            return Ok(ReadRecord::End);
+        }
+        Err(err) => return Err(err.into()),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+
+    let mut bytes = vec![0; len];
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(ReadRecord::Partial),
+        Err(err) => return Err(err.into()),
+    }
+    let size = 4 + len as u64;
+    match bincode::deserialize(&bytes) {
+        Ok(record) => Ok(ReadRecord::Record(record, size)),
+        Err(_) => Ok(ReadRecord::Corrupt(size)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Seek;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn node(byte: u8) -> StoredNode {
+        StoredNode {
+            hash: vec![byte],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_node_log_versions() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tree.log");
+
+        let mut log = NodeLog::open(&path).unwrap();
+        log.insert(vec![], 0, node(0));
+        log.insert(vec![1], 0, node(1));
+        log.commit().unwrap();
+        log.insert(vec![], 2, node(2));
+        log.commit().unwrap();
+
+        // Uncommitted nodes are not visible, and are dropped on revert.
+        log.insert(vec![], 3, node(3));
+        assert_eq!(log.load(&[], 3).unwrap(), Some(node(2)));
+        log.revert();
+        log.commit().unwrap();
+
+        assert_eq!(log.load(&[], 0).unwrap(), Some(node(0)));
+        assert_eq!(log.load(&[], 1).unwrap(), Some(node(0)));
+        assert_eq!(log.load(&[], 5).unwrap(), Some(node(2)));
+        assert_eq!(log.load(&[1], 5).unwrap(), Some(node(1)));
+        assert_eq!(log.load(&[2], 5).unwrap(), None);
+
+        // The log survives a restart.
+        drop(log);
+        let log = NodeLog::open(&path).unwrap();
+        assert_eq!(log.load(&[], 1).unwrap(), Some(node(0)));
+        assert_eq!(log.load(&[], 5).unwrap(), Some(node(2)));
+        assert_eq!(log.load(&[1], 5).unwrap(), Some(node(1)));
+    }
+
+    #[test]
+    fn test_node_log_partial_record() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tree.log");
+
+        let mut log = NodeLog::open(&path).unwrap();
+        log.insert(vec![], 0, node(0));
+        log.commit().unwrap();
+        drop(log);
+
+        // Simulate a crash in the middle of writing a record.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        let len = file.seek(std::io::SeekFrom::End(0)).unwrap();
+        file.write_all(&100u32.to_le_bytes()).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+        drop(file);
+
+        let mut log = NodeLog::open(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+        assert_eq!(log.load(&[], 0).unwrap(), Some(node(0)));
+
+        // We can continue appending after recovering.
+        log.insert(vec![], 1, node(1));
+        log.commit().unwrap();
+        drop(log);
+        let log = NodeLog::open(&path).unwrap();
+        assert_eq!(log.load(&[], 1).unwrap(), Some(node(1)));
+    }
+
+    #[test]
+    fn test_node_log_corrupt_record() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tree.log");
+
+        let mut log = NodeLog::open(&path).unwrap();
+        log.insert(vec![], 0, node(0));
+        log.commit().unwrap();
+        let first = std::fs::metadata(&path).unwrap().len();
+        log.insert(vec![], 1, node(1));
+        log.commit().unwrap();
+        drop(log);
+
+        // Overwrite the contents of the last record with garbage of the same length. Since it is
+        // the last record, it could have been partially synced, and it is discarded.
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.write_all_at(&vec![0xff; (len - first - 4) as usize], first + 4)
+            .unwrap();
+        drop(file);
+        let log = NodeLog::open(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), first);
+        assert_eq!(log.load(&[], 1).unwrap(), Some(node(0)));
+        drop(log);
+
+        // Corrupt a record in the middle of the log. Truncating would lose the committed records
+        // after it, so opening the log fails instead.
+        let mut log = NodeLog::open(&path).unwrap();
+        log.insert(vec![], 1, node(1));
+        log.commit().unwrap();
+        drop(log);
+        let len = std::fs::metadata(&path).unwrap().len();
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.write_all_at(&vec![0xff; (first - 4) as usize], 4)
+            .unwrap();
+        drop(file);
+        NodeLog::open(&path).unwrap_err();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+    }
+
+    #[test]
+    fn test_node_log_prune() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tree.log");
+
+        // Node `[]` is modified at heights 0, 5 and 10, and node `[1]` only at height 0.
+        let mut log = NodeLog::open(&path).unwrap();
+        log.insert(vec![], 0, node(0));
+        log.insert(vec![1], 0, node(1));
+        log.commit().unwrap();
+        log.insert(vec![], 5, node(5));
+        log.commit().unwrap();
+        log.insert(vec![], 10, node(10));
+        log.commit().unwrap();
+        let len = std::fs::metadata(&path).unwrap().len();
+
+        // Keep the nodes needed from height 8 on. The version of `[]` from height 0 is superseded
+        // at height 5, which is still in effect at height 8. The only version of `[1]` is kept, no
+        // matter how old.
+        log.prune(8).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() < len);
+        assert_eq!(log.index[pack_path(&[]).as_slice()].len(), 2);
+        for log in [log, NodeLog::open(&path).unwrap()] {
+            assert_eq!(log.load(&[], 8).unwrap(), Some(node(5)));
+            assert_eq!(log.load(&[], 10).unwrap(), Some(node(10)));
+            assert_eq!(log.load(&[1], 8).unwrap(), Some(node(1)));
+        }
+
+        // We can continue appending after pruning.
+        let mut log = NodeLog::open(&path).unwrap();
+        log.insert(vec![], 11, node(11));
+        log.commit().unwrap();
+        drop(log);
+        let log = NodeLog::open(&path).unwrap();
+        assert_eq!(log.load(&[], 11).unwrap(), Some(node(11)));
+        assert_eq!(log.load(&[], 10).unwrap(), Some(node(10)));
+
+        // Pruning with staged nodes fails, and leaves them staged.
+        let mut log = log;
+        log.insert(vec![], 12, node(12));
+        log.prune(12).unwrap_err();
+        log.commit().unwrap();
+        assert_eq!(log.load(&[], 12).unwrap(), Some(node(12)));
+    }
+
+    #[test]
+    fn test_pack_path() {
+        assert_eq!(pack_path(&[]), Vec::<u8>::new());
+        assert_eq!(pack_path(&[0, 1, 2]), vec![0, 1, 2]);
+        assert_eq!(pack_path(&[255]), vec![0xff, 0x01]);
+        assert_ne!(pack_path(&[128]), pack_path(&[0, 1]));
+    }
+}
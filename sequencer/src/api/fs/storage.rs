@@ -0,0 +1,640 @@
+//! File system query storage extended with merklized state.
+
+use std::{
+    cmp::min,
+    collections::HashMap,
+    fs::{self, File},
+    io::Write,
+    ops::RangeBounds,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use async_std::task::spawn_blocking;
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use espresso_types::{ChainConfig, Header};
+use ethers::prelude::Address;
+use hotshot_query_service::{
+    availability::{
+        AvailabilityStorage, BlockId, BlockQueryData, LeafId, LeafQueryData, PayloadQueryData,
+        TransactionHash, UpdateAvailabilityData, VidCommonQueryData,
+    },
+    data_source::{
+        storage::{ExplorerStorage, FileSystemStorage, NodeStorage, PruneStorage},
+        VersionedDataSource,
+    },
+    explorer::{
+        BlockDetail, BlockIdentifier, BlockSummary, ExplorerSummary, GetBlockDetailError,
+        GetBlockSummariesError, GetBlockSummariesRequest, GetExplorerSummaryError,
+        GetSearchResultsError, GetTransactionDetailError, GetTransactionSummariesError,
+        GetTransactionSummariesRequest, SearchResult, TransactionDetailResponse,
+        TransactionIdentifier, TransactionSummary,
+    },
+    merklized_state::{
+        MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot,
+        UpdateStateData,
+    },
+    node::{TimeWindowQueryData, WindowStart},
+    QueryError, QueryResult, VidShare,
+};
+use hotshot_types::data::ViewNumber;
+use jf_merkle_tree::prelude::MerkleProof;
+use tagged_base64::TaggedBase64;
+
+use super::{
+    super::{
+        data_source::CatchupDataSource,
+        explorer::{self, ExplorerIndex, IndexedStorage},
+        merkle_nodes::{self, proof_nodes, query_error, reconstruct_proof, NodeLoader, StoredNode},
+        pruning::{pruned_message, DataClass},
+        AccountQueryData, BlocksFrontier,
+    },
+    node_log::NodeLog,
+};
+use crate::{persistence::ChainConfigPersistence, SeqTypes};
+
+/// The number of blocks loaded at a time when rebuilding in-memory indexes on startup.
+const INDEX_BATCH_SIZE: usize = 1000;
+
+/// Query service storage in the file system, with support for merklized state.
+///
+/// Availability and node data is stored by the query service's [`FileSystemStorage`]. Alongside
+/// it, in the same directory, we keep a [`NodeLog`] for each Merkle tree and a directory of chain
+/// configs, which together provide the state needed for the merklized state and catchup APIs. We
+/// also keep an in-memory [`ExplorerIndex`] to support the explorer API, and an in-memory index of
+/// block heights by state commitment to support looking up merklized state snapshots by
+/// commitment.
+///
+/// If a merklized state retention is set, the node logs are pruned as the state advances, so that
+/// they only hold the versions needed for the retained snapshots.
+#[derive(Debug)]
+pub struct FsStorage {
+    inner: FileSystemStorage<SeqTypes>,
+    explorer: ExplorerIndex,
+    state_dir: PathBuf,
+    chain_config_dir: PathBuf,
+    trees: HashMap<String, NodeLog>,
+    snapshots: HashMap<String, u64>,
+    pending_snapshots: Vec<Header>,
+    last_state_height: u64,
+    pending_state_height: Option<u64>,
+    state_retention: Option<u64>,
+    state_pruned_height: Option<u64>,
+}
+
+impl FsStorage {
+    /// Create new storage at `path`, deleting any existing data.
+    pub(crate) async fn create(path: &Path) -> anyhow::Result<Self> {
+        let inner = FileSystemStorage::create(path).await?;
+        for dir in [state_dir(path), chain_config_dir(path)] {
+            if dir.exists() {
+                fs::remove_dir_all(&dir).context(format!("removing {}", dir.display()))?;
+            }
+        }
+        Self::init(path, inner).await
+    }
+
+    /// Open existing storage at `path`.
+    pub(crate) async fn open(path: &Path) -> anyhow::Result<Self> {
+        let inner = FileSystemStorage::open(path).await?;
+        Self::init(path, inner).await
+    }
+
+    async fn init(path: &Path, inner: FileSystemStorage<SeqTypes>) -> anyhow::Result<Self> {
+        let state_dir = state_dir(path);
+        let chain_config_dir = chain_config_dir(path);
+        fs::create_dir_all(&state_dir)?;
+        fs::create_dir_all(&chain_config_dir)?;
+
+        // Load the logs for any trees we have already stored.
+        let mut trees = HashMap::new();
+        for entry in fs::read_dir(&state_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "log") {
+                let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                trees.insert(name.to_string(), NodeLog::open(&path)?);
+            }
+        }
+
+        let height_path = state_dir.join("last_height");
+        let last_state_height = if height_path.is_file() {
+            bincode::deserialize(&fs::read(&height_path)?).context("reading last state height")?
+        } else {
+            0
+        };
+        let pruned_path = state_dir.join("pruned_height");
+        let state_pruned_height = if pruned_path.is_file() {
+            Some(
+                bincode::deserialize(&fs::read(&pruned_path)?)
+                    .context("reading pruned state height")?,
+            )
+        } else {
+            None
+        };
+
+        // Rebuild the explorer and snapshot indexes from the blocks we have. Blocks we are missing
+        // will be indexed when they are fetched. We load the blocks in bounded batches, so that we
+        // never hold more than a batch in memory at once.
+        let mut explorer = ExplorerIndex::default();
+        let mut snapshots = HashMap::new();
+        let block_height = inner.block_height().await?;
+        for start in (0..block_height).step_by(INDEX_BATCH_SIZE) {
+            let end = min(start + INDEX_BATCH_SIZE, block_height);
+            for block in inner
+                .get_block_range(start..end)
+                .await?
+                .into_iter()
+                .flatten()
+            {
+                explorer.index(&block);
+                index_snapshots(&mut snapshots, block.header());
+            }
+        }
+
+        Ok(Self {
+            inner,
+            explorer,
+            state_dir,
+            chain_config_dir,
+            trees,
+            snapshots,
+            pending_snapshots: vec![],
+            last_state_height,
+            pending_state_height: None,
+            state_retention: None,
+            state_pruned_height,
+        })
+    }
+
+    /// Retain all versions of merklized state for the last `retention` blocks.
+    ///
+    /// If [`None`], all versions are kept forever.
+    pub(crate) fn with_state_retention(mut self, retention: Option<u64>) -> Self {
+        self.state_retention = retention;
+        self
+    }
+
+    /// The height up to which merklized state has been pruned, if any.
+    pub(crate) fn state_pruned_height(&self) -> Option<u64> {
+        self.state_pruned_height
+    }
+
+    /// Get the log for the tree `name`, creating it if necessary.
+    fn tree_mut(&mut self, name: &str) -> anyhow::Result<&mut NodeLog> {
+        if !self.trees.contains_key(name) {
+            let log = NodeLog::open(&self.state_dir.join(format!("{name}.log")))?;
+            self.trees.insert(name.to_string(), log);
+        }
+        Ok(self.trees.get_mut(name).unwrap())
+    }
+
+    async fn commit_state(&mut self) -> anyhow::Result<()> {
+        for tree in self.trees.values_mut() {
+            tree.commit()?;
+        }
+        for header in std::mem::take(&mut self.pending_snapshots) {
+            index_snapshots(&mut self.snapshots, &header);
+        }
+        if let Some(height) = self.pending_state_height.take() {
+            write_atomic(
+                &self.state_dir.join("last_height"),
+                &bincode::serialize(&height)?,
+            )?;
+            self.last_state_height = height;
+
+            // The new state is durable, so a failure to prune does not fail the commit; we will
+            // try again when the state next advances.
+            if let Err(err) = self.prune_state().await {
+                tracing::warn!("error pruning merklized state: {err:#}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Prune versions of merklized state which are not needed to serve the retained snapshots.
+    ///
+    /// Pruning rewrites every node log, so rather than pruning on every block, we wait until the
+    /// logs hold a full retention window of versions which can be pruned. This keeps the logs, and
+    /// their in-memory indexes, within about twice the retained window. The logs are rewritten on a
+    /// blocking thread, so that a pruning pass does not stall the executor.
+    async fn prune_state(&mut self) -> anyhow::Result<()> {
+        let Some(retention) = self.state_retention else {
+            return Ok(());
+        };
+        // Keep every snapshot from `height` on.
+        let Some(height) = self.last_state_height.checked_sub(retention - 1) else {
+            return Ok(());
+        };
+        let unpruned = self.state_pruned_height.map_or(0, |h| h + 1);
+        if height < unpruned + retention {
+            return Ok(());
+        }
+
+        // Record the pruned height before deleting anything, so that queries for snapshots which
+        // are about to lose nodes fail explicitly instead of returning incomplete paths.
+        write_atomic(
+            &self.state_dir.join("pruned_height"),
+            &bincode::serialize(&(height - 1))?,
+        )?;
+        self.state_pruned_height = Some(height - 1);
+        let mut trees = std::mem::take(&mut self.trees);
+        let (trees, res) = spawn_blocking(move || {
+            let res = trees.values_mut().try_for_each(|tree| tree.prune(height));
+            (trees, res)
+        })
+        .await;
+        self.trees = trees;
+        res
+    }
+
+    /// Fail if merklized state at `height` has been pruned.
+    fn ensure_state_retained(&self, height: u64) -> anyhow::Result<()> {
+        match pruned_message(DataClass::MerklizedState, height, self.state_pruned_height) {
+            Some(message) => bail!(message),
+            None => Ok(()),
+        }
+    }
+
+    /// Find the block height corresponding to a merklized state snapshot.
+    fn snapshot_height<State, const ARITY: usize>(
+        &self,
+        snapshot: Snapshot<SeqTypes, State, ARITY>,
+    ) -> QueryResult<u64>
+    where
+        State: MerklizedState<SeqTypes, ARITY>,
+    {
+        let height = match snapshot {
+            Snapshot::Index(height) => height,
+            Snapshot::Commit(commit) => *self
+                .snapshots
+                .get(&commit.to_string())
+                .ok_or(QueryError::NotFound)?,
+        };
+
+        if let Err(err) = self.ensure_state_retained(height) {
+            return Err(QueryError::Error {
+                message: err.to_string(),
+            });
+        }
+        if height > self.last_state_height {
+            return Err(QueryError::Error {
+                message: format!(
+                    "state at height {height} is not yet available (last state height is {})",
+                    self.last_state_height
+                ),
+            });
+        }
+        Ok(height)
+    }
+}
+
+#[async_trait]
+impl VersionedDataSource for FsStorage {
+    type Error = QueryError;
+
+    async fn commit(&mut self) -> Result<(), Self::Error> {
+        self.inner.commit().await.map_err(query_error)?;
+        self.explorer.commit();
+        self.commit_state().await.map_err(query_error)
+    }
+
+    async fn revert(&mut self) {
+        self.inner.revert().await;
+        self.explorer.revert();
+        self.pending_snapshots.clear();
+        for tree in self.trees.values_mut() {
+            tree.revert();
+        }
+        self.pending_state_height = None;
+    }
+}
+
+#[async_trait]
+impl AvailabilityStorage<SeqTypes> for FsStorage {
+    async fn get_leaf(&self, id: LeafId<SeqTypes>) -> QueryResult<LeafQueryData<SeqTypes>> {
+        self.inner.get_leaf(id).await
+    }
+
+    async fn get_block(&self, id: BlockId<SeqTypes>) -> QueryResult<BlockQueryData<SeqTypes>> {
+        self.inner.get_block(id).await
+    }
+
+    async fn get_header(&self, id: BlockId<SeqTypes>) -> QueryResult<Header> {
+        self.inner.get_header(id).await
+    }
+
+    async fn get_payload(&self, id: BlockId<SeqTypes>) -> QueryResult<PayloadQueryData<SeqTypes>> {
+        self.inner.get_payload(id).await
+    }
+
+    async fn get_vid_common(
+        &self,
+        id: BlockId<SeqTypes>,
+    ) -> QueryResult<VidCommonQueryData<SeqTypes>> {
+        self.inner.get_vid_common(id).await
+    }
+
+    async fn get_leaf_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<LeafQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.inner.get_leaf_range(range).await
+    }
+
+    async fn get_block_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<BlockQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.inner.get_block_range(range).await
+    }
+
+    async fn get_payload_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<PayloadQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.inner.get_payload_range(range).await
+    }
+
+    async fn get_vid_common_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<VidCommonQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.inner.get_vid_common_range(range).await
+    }
+
+    async fn get_transaction(
+        &self,
+        hash: TransactionHash<SeqTypes>,
+    ) -> QueryResult<BlockQueryData<SeqTypes>> {
+        self.inner.get_transaction(hash).await
+    }
+}
+
+#[async_trait]
+impl UpdateAvailabilityData<SeqTypes> for FsStorage {
+    type Error = QueryError;
+
+    async fn insert_leaf(&mut self, leaf: LeafQueryData<SeqTypes>) -> Result<(), Self::Error> {
+        self.pending_snapshots.push(leaf.header().clone());
+        self.inner.insert_leaf(leaf).await.map_err(query_error)
+    }
+
+    async fn insert_block(&mut self, block: BlockQueryData<SeqTypes>) -> Result<(), Self::Error> {
+        self.explorer.insert(block.clone());
+        self.inner.insert_block(block).await.map_err(query_error)
+    }
+
+    async fn insert_vid(
+        &mut self,
+        common: VidCommonQueryData<SeqTypes>,
+        share: Option<VidShare>,
+    ) -> Result<(), Self::Error> {
+        self.inner
+            .insert_vid(common, share)
+            .await
+            .map_err(query_error)
+    }
+}
+
+#[async_trait]
+impl NodeStorage<SeqTypes> for FsStorage {
+    async fn block_height(&self) -> QueryResult<usize> {
+        self.inner.block_height().await
+    }
+
+    async fn count_transactions(&self) -> QueryResult<usize> {
+        self.inner.count_transactions().await
+    }
+
+    async fn payload_size(&self) -> QueryResult<usize> {
+        self.inner.payload_size().await
+    }
+
+    async fn vid_share<ID>(&self, id: ID) -> QueryResult<VidShare>
+    where
+        ID: Into<BlockId<SeqTypes>> + Send + Sync,
+    {
+        self.inner.vid_share(id).await
+    }
+
+    async fn get_header_window(
+        &self,
+        start: impl Into<WindowStart<SeqTypes>> + Send + Sync,
+        end: u64,
+    ) -> QueryResult<TimeWindowQueryData<Header>> {
+        self.inner.get_header_window(start, end).await
+    }
+}
+
+impl PruneStorage for FsStorage {}
+
+#[async_trait]
+impl ExplorerStorage<SeqTypes> for FsStorage {
+    async fn get_block_detail(
+        &self,
+        request: BlockIdentifier<SeqTypes>,
+    ) -> Result<BlockDetail<SeqTypes>, GetBlockDetailError> {
+        explorer::get_block_detail(self, request).await
+    }
+
+    async fn get_block_summaries(
+        &self,
+        request: GetBlockSummariesRequest<SeqTypes>,
+    ) -> Result<Vec<BlockSummary<SeqTypes>>, GetBlockSummariesError> {
+        explorer::get_block_summaries(self, request).await
+    }
+
+    async fn get_transaction_detail(
+        &self,
+        request: TransactionIdentifier<SeqTypes>,
+    ) -> Result<TransactionDetailResponse<SeqTypes>, GetTransactionDetailError> {
+        explorer::get_transaction_detail(self, request).await
+    }
+
+    async fn get_transaction_summaries(
+        &self,
+        request: GetTransactionSummariesRequest<SeqTypes>,
+    ) -> Result<Vec<TransactionSummary<SeqTypes>>, GetTransactionSummariesError> {
+        explorer::get_transaction_summaries(self, request).await
+    }
+
+    async fn get_explorer_summary(
+        &self,
+    ) -> Result<ExplorerSummary<SeqTypes>, GetExplorerSummaryError> {
+        explorer::get_explorer_summary(self).await
+    }
+
+    async fn get_search_results(
+        &self,
+        query: TaggedBase64,
+    ) -> Result<SearchResult<SeqTypes>, GetSearchResultsError> {
+        explorer::get_search_results(self, query).await
+    }
+}
+
+impl IndexedStorage for FsStorage {
+    fn explorer_index(&self) -> &ExplorerIndex {
+        &self.explorer
+    }
+}
+
+#[async_trait]
+impl NodeLoader for FsStorage {
+    async fn load_node(
+        &self,
+        tree: &str,
+        path: &[usize],
+        height: u64,
+    ) -> QueryResult<Option<StoredNode>> {
+        match self.trees.get(tree) {
+            Some(log) => log.load(path, height).map_err(query_error),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl MerklizedStateHeightPersistence for FsStorage {
+    async fn set_last_state_height(&mut self, height: usize) -> QueryResult<()> {
+        self.pending_state_height = Some(height as u64);
+        Ok(())
+    }
+
+    async fn get_last_state_height(&self) -> QueryResult<usize> {
+        Ok(self.last_state_height as usize)
+    }
+}
+
+#[async_trait]
+impl<State, const ARITY: usize> UpdateStateData<SeqTypes, State, ARITY> for FsStorage
+where
+    State: MerklizedState<SeqTypes, ARITY> + 'static,
+{
+    async fn insert_merkle_nodes(
+        &mut self,
+        proof: MerkleProof<State::Entry, State::Key, State::T, ARITY>,
+        traversal_path: Vec<usize>,
+        block_number: u64,
+    ) -> QueryResult<()> {
+        let nodes = proof_nodes::<State, ARITY>(proof, &traversal_path)?;
+        let tree = self.tree_mut(State::state_type()).map_err(query_error)?;
+        for (path, node) in nodes {
+            tree.insert(path, block_number, node);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<State, const ARITY: usize> MerklizedStateDataSource<SeqTypes, State, ARITY> for FsStorage
+where
+    State: MerklizedState<SeqTypes, ARITY> + 'static,
+{
+    async fn get_path(
+        &self,
+        snapshot: Snapshot<SeqTypes, State, ARITY>,
+        key: State::Key,
+    ) -> QueryResult<MerkleProof<State::Entry, State::Key, State::T, ARITY>> {
+        let height = self.snapshot_height(snapshot)?;
+        reconstruct_proof::<State, ARITY>(self, height, key).await
+    }
+}
+
+#[async_trait]
+impl ChainConfigPersistence for FsStorage {
+    async fn insert_chain_config(&mut self, chain_config: ChainConfig) -> anyhow::Result<()> {
+        // Chain configs are immutable and content-addressed, so there is no need to wait for the
+        // next commit; we can write them right away.
+        let path = self
+            .chain_config_dir
+            .join(chain_config.commitment().to_string());
+        if !path.exists() {
+            write_atomic(&path, &bincode::serialize(&chain_config)?)?;
+        }
+        Ok(())
+    }
+
+    async fn load_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        let path = self.chain_config_dir.join(commitment.to_string());
+        let bytes = fs::read(&path).context(format!("chain config {commitment} not found"))?;
+        bincode::deserialize(&bytes).context("failed to deserialize")
+    }
+}
+
+impl CatchupDataSource for FsStorage {
+    async fn get_account(
+        &self,
+        height: u64,
+        _view: ViewNumber,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        self.ensure_state_retained(height)?;
+        merkle_nodes::get_account(self, height, account).await
+    }
+
+    async fn get_frontier(&self, height: u64, _view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
+        self.ensure_state_retained(height)?;
+        merkle_nodes::get_frontier(self, height).await
+    }
+
+    async fn get_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        self.load_chain_config(commitment).await
+    }
+}
+
+fn state_dir(path: &Path) -> PathBuf {
+    path.join("merklized_state")
+}
+
+fn chain_config_dir(path: &Path) -> PathBuf {
+    path.join("chain_config")
+}
+
+/// Index the state commitments in `header` by block height.
+///
+/// A tree which is not modified by a block has the same commitment at several heights. The state
+/// is identical at each of them, so we keep the earliest, which is the first to become available.
+fn index_snapshots(snapshots: &mut HashMap<String, u64>, header: &Header) {
+    let height = header.height();
+    for commit in [
+        header.block_merkle_tree_root().to_string(),
+        header.fee_merkle_tree_root().to_string(),
+    ] {
+        snapshots
+            .entry(commit)
+            .and_modify(|h| *h = min(*h, height))
+            .or_insert(height);
+    }
+}
+
+/// Replace the contents of the file at `path`, atomically.
+fn write_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut swap_path = path.to_owned();
+    swap_path.set_extension("swp");
+    let mut swap = File::create(&swap_path)?;
+    swap.write_all(bytes)?;
+    swap.sync_all()?;
+    fs::rename(swap_path, path)?;
+    Ok(())
+}
@@ -0,0 +1,364 @@
+//! Versioned storage of Merkle tree nodes for the embedded storage backends.
+//!
+//! The Postgres backend gets merklized state storage from the query service. The SQLite and file
+//! system backends instead store each version of each node separately, keyed by its path from the
+//! root of the tree and the block height at which it was created. The state of a tree at height
+//! `h` consists of, for each path, the latest version created at or before `h`. This module
+//! contains the logic for converting between Merkle proofs and stored nodes, which is independent
+//! of how the nodes are actually stored.
+
+use std::{fmt::Display, sync::Arc};
+
+use anyhow::{bail, Context};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use async_trait::async_trait;
+use espresso_types::{BlockMerkleTree, FeeAccountProof, FeeMerkleTree};
+use ethers::prelude::Address;
+use hotshot_query_service::{
+    merklized_state::{MerklizedState, MerklizedStateDataSource, Snapshot},
+    QueryError, QueryResult,
+};
+use jf_merkle_tree::{
+    prelude::{MerkleNode, MerkleProof},
+    DigestAlgorithm, MerkleTreeScheme, ToTraversalPath,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{AccountQueryData, BlocksFrontier};
+use crate::SeqTypes;
+
+/// A single version of a node in a Merkle tree.
+///
+/// Branch nodes store the serialized list of their children's hashes. Leaf nodes store their
+/// serialized index and entry. Empty subtrees store neither.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StoredNode {
+    pub(crate) hash: Vec<u8>,
+    pub(crate) children: Option<Vec<u8>>,
+    pub(crate) idx: Option<Vec<u8>>,
+    pub(crate) entry: Option<Vec<u8>>,
+}
+
+/// A storage backend which can look up versions of Merkle tree nodes.
+#[async_trait]
+pub(crate) trait NodeLoader: Sync {
+    /// Load the latest version of the node at `path` in `tree` created at or before `height`.
+    ///
+    /// `path` is the sequence of branch indices from the root to the node; the root has an empty
+    /// path. `tree` is the [`MerklizedState::state_type`] of the tree.
+    async fn load_node(
+        &self,
+        tree: &str,
+        path: &[usize],
+        height: u64,
+    ) -> QueryResult<Option<StoredNode>>;
+}
+
+/// Convert a Merkle proof into the nodes which need to be stored to reconstruct it.
+///
+/// `traversal_path` lists branch indices from the leaf up to the root, as returned by
+/// [`ToTraversalPath::to_traversal_path`]. The result pairs each node with its path from the root.
+pub(crate) fn proof_nodes<State, const ARITY: usize>(
+    proof: MerkleProof<State::Entry, State::Key, State::T, ARITY>,
+    traversal_path: &[usize],
+) -> QueryResult<Vec<(Vec<usize>, StoredNode)>>
+where
+    State: MerklizedState<SeqTypes, ARITY>,
+{
+    // The proof lists nodes from the leaf (or the first empty subtree) up to the root. The node at
+    // depth `d` below the root is addressed by the first `d` branch indices going down from the
+    // root.
+    let path_from_root = traversal_path.iter().rev().copied().collect::<Vec<_>>();
+    let num_nodes = proof.proof.len();
+
+    proof
+        .proof
+        .into_iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let depth = num_nodes - 1 - i;
+            let path = path_from_root
+                .get(..depth)
+                .ok_or_else(|| QueryError::Error {
+                    message: format!(
+                        "proof of length {num_nodes} is too long for tree of height {}",
+                        traversal_path.len()
+                    ),
+                })?;
+            let node = match node {
+                MerkleNode::Leaf { value, pos, elem } => StoredNode {
+                    hash: serialize_value(&value)?,
+                    children: None,
+                    idx: Some(bincode::serialize(&pos).map_err(query_error)?),
+                    entry: Some(bincode::serialize(&elem).map_err(query_error)?),
+                },
+                MerkleNode::Branch { children, .. } => {
+                    let children = children.iter().map(|c| c.value()).collect::<Vec<_>>();
+                    let hash = State::Digest::digest(&children).map_err(query_error)?;
+                    StoredNode {
+                        hash: serialize_value(&hash)?,
+                        children: Some(serialize_value(&children)?),
+                        idx: None,
+                        entry: None,
+                    }
+                }
+                MerkleNode::Empty => StoredNode {
+                    hash: serialize_value(&State::T::default())?,
+                    ..Default::default()
+                },
+                MerkleNode::ForgettenSubtree { .. } => {
+                    return Err(QueryError::Error {
+                        message: "cannot store forgotten subtree in merklized state".into(),
+                    });
+                }
+            };
+            Ok((path.to_vec(), node))
+        })
+        .collect()
+}
+
+/// Reconstruct a Merkle proof for `key` from the nodes stored in `loader` at `height`.
+pub(crate) async fn reconstruct_proof<State, const ARITY: usize>(
+    loader: &impl NodeLoader,
+    height: u64,
+    key: State::Key,
+) -> QueryResult<MerkleProof<State::Entry, State::Key, State::T, ARITY>>
+where
+    State: MerklizedState<SeqTypes, ARITY>,
+{
+    let tree = State::state_type();
+    let path_from_root =
+        <State::Key as ToTraversalPath<ARITY>>::to_traversal_path(&key, State::tree_height())
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>();
+
+    // Walk down from the root, collecting each node on the path to `key`. We stop early if we
+    // reach an empty subtree, in which case the proof is a non-membership proof.
+    let mut nodes = vec![];
+    for depth in 0..=path_from_root.len() {
+        let Some(node) = loader
+            .load_node(tree, &path_from_root[..depth], height)
+            .await?
+        else {
+            if depth == 0 {
+                // There is no root at all; we have no state for this snapshot.
+                return Err(QueryError::NotFound);
+            }
+            nodes.push(MerkleNode::Empty);
+            break;
+        };
+
+        let hash: State::T = deserialize_value(&node.hash)?;
+        if let Some(children) = node.children {
+            let children: Vec<State::T> = deserialize_value(&children)?;
+            let next_is_empty = path_from_root
+                .get(depth)
+                .map(|&i| children.get(i).copied().unwrap_or_default() == State::T::default())
+                .unwrap_or(true);
+            nodes.push(MerkleNode::Branch {
+                value: hash,
+                children: children
+                    .into_iter()
+                    .map(|value| {
+                        Arc::new(if value == State::T::default() {
+                            MerkleNode::Empty
+                        } else {
+                            MerkleNode::ForgettenSubtree { value }
+                        })
+                    })
+                    .collect(),
+            });
+            if next_is_empty {
+                nodes.push(MerkleNode::Empty);
+                break;
+            }
+        } else if let (Some(pos), Some(elem)) = (node.idx, node.entry) {
+            nodes.push(MerkleNode::Leaf {
+                value: hash,
+                pos: bincode::deserialize(&pos).map_err(query_error)?,
+                elem: bincode::deserialize(&elem).map_err(query_error)?,
+            });
+            break;
+        } else {
+            nodes.push(MerkleNode::Empty);
+            break;
+        }
+    }
+
+    // Proofs are ordered from the leaf up to the root.
+    nodes.reverse();
+    Ok(MerkleProof::new(key, nodes))
+}
+
+/// Look up a fee account in a merklized state data source, for catchup.
+pub(crate) async fn get_account<D>(
+    ds: &D,
+    height: u64,
+    account: Address,
+) -> anyhow::Result<AccountQueryData>
+where
+    D: MerklizedStateDataSource<SeqTypes, FeeMerkleTree, { FeeMerkleTree::ARITY }> + Sync,
+{
+    let proof = ds
+        .get_path(
+            Snapshot::<SeqTypes, FeeMerkleTree, { FeeMerkleTree::ARITY }>::Index(height),
+            account.into(),
+        )
+        .await
+        .context(format!("fetching account {account}; height {height}"))?;
+
+    match proof.proof.first().context(format!(
+        "empty proof for account {account}; height {height}"
+    ))? {
+        MerkleNode::Leaf { pos, elem, .. } => Ok(AccountQueryData {
+            balance: (*elem).into(),
+            proof: FeeAccountProof::presence(*pos, proof),
+        }),
+
+        MerkleNode::Empty => Ok(AccountQueryData {
+            balance: 0_u64.into(),
+            proof: FeeAccountProof::absence(account.into(), proof),
+        }),
+        _ => {
+            bail!("Invalid proof");
+        }
+    }
+}
+
+/// Look up the blocks frontier in a merklized state data source, for catchup.
+pub(crate) async fn get_frontier<D>(ds: &D, height: u64) -> anyhow::Result<BlocksFrontier>
+where
+    D: MerklizedStateDataSource<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }> + Sync,
+{
+    ds.get_path(
+        Snapshot::<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>::Index(height),
+        height - 1,
+    )
+    .await
+    .context(format!("fetching frontier at height {height}"))
+}
+
+/// Encode a path of branch indices from the root of a Merkle tree as a string key.
+pub(crate) fn encode_path(path: &[usize]) -> String {
+    path.iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub(crate) fn query_error(err: impl Display) -> QueryError {
+    QueryError::Error {
+        message: err.to_string(),
+    }
+}
+
+fn serialize_value(value: &impl CanonicalSerialize) -> QueryResult<Vec<u8>> {
+    let mut bytes = vec![];
+    value
+        .serialize_compressed(&mut bytes)
+        .map_err(query_error)?;
+    Ok(bytes)
+}
+
+fn deserialize_value<T: CanonicalDeserialize>(bytes: &[u8]) -> QueryResult<T> {
+    T::deserialize_compressed(bytes).map_err(query_error)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use espresso_types::{FeeAccount, FeeAmount};
+    use jf_merkle_tree::{LookupResult, UniversalMerkleTreeScheme};
+
+    use super::*;
+
+    /// An in-memory node store, for testing.
+    #[derive(Default)]
+    struct MemoryLoader(BTreeMap<(String, String), BTreeMap<u64, StoredNode>>);
+
+    impl MemoryLoader {
+        fn insert(&mut self, tree: &str, nodes: Vec<(Vec<usize>, StoredNode)>, height: u64) {
+            for (path, node) in nodes {
+                self.0
+                    .entry((tree.to_string(), encode_path(&path)))
+                    .or_default()
+                    .insert(height, node);
+            }
+        }
+    }
+
+    #[async_trait]
+    impl NodeLoader for MemoryLoader {
+        async fn load_node(
+            &self,
+            tree: &str,
+            path: &[usize],
+            height: u64,
+        ) -> QueryResult<Option<StoredNode>> {
+            Ok(self
+                .0
+                .get(&(tree.to_string(), encode_path(path)))
+                .and_then(|versions| versions.range(..=height).next_back())
+                .map(|(_, node)| node.clone()))
+        }
+    }
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path(&[]), "");
+        assert_eq!(encode_path(&[0]), "0");
+        assert_eq!(encode_path(&[1, 255, 3]), "1,255,3");
+    }
+
+    #[async_std::test]
+    async fn test_fee_tree_round_trip() {
+        let mut tree = FeeMerkleTree::new(20);
+        let mut loader = MemoryLoader::default();
+        let accounts = (1..=3)
+            .map(|i| FeeAccount::from(Address::from_low_u64_be(i)))
+            .collect::<Vec<_>>();
+
+        for (height, account) in accounts.iter().enumerate() {
+            tree.update(*account, FeeAmount::from(height as u64 + 1))
+                .unwrap();
+            let LookupResult::Ok(_, proof) = tree.universal_lookup(*account) else {
+                panic!("account {account} missing from tree");
+            };
+            let path = <FeeAccount as ToTraversalPath<{ FeeMerkleTree::ARITY }>>::to_traversal_path(
+                account,
+                tree.height(),
+            );
+            let nodes =
+                proof_nodes::<FeeMerkleTree, { FeeMerkleTree::ARITY }>(proof, &path).unwrap();
+            loader.insert(FeeMerkleTree::state_type(), nodes, height as u64);
+        }
+
+        // Every account is present in the final snapshot, with a proof against the final root.
+        let height = accounts.len() as u64 - 1;
+        for (i, account) in accounts.iter().enumerate() {
+            let proof = reconstruct_proof::<FeeMerkleTree, { FeeMerkleTree::ARITY }>(
+                &loader, height, *account,
+            )
+            .await
+            .unwrap();
+            let MerkleNode::Leaf { elem, .. } = &proof.proof[0] else {
+                panic!("expected membership proof for {account}");
+            };
+            assert_eq!(*elem, FeeAmount::from(i as u64 + 1));
+            assert!(
+                FeeMerkleTree::verify(tree.commitment().digest(), *account, proof)
+                    .unwrap()
+                    .is_ok()
+            );
+        }
+
+        // The last account is absent from the first snapshot.
+        let proof =
+            reconstruct_proof::<FeeMerkleTree, { FeeMerkleTree::ARITY }>(&loader, 0, accounts[2])
+                .await
+                .unwrap();
+        assert!(matches!(proof.proof[0], MerkleNode::Empty));
+    }
+}
@@ -3,7 +3,7 @@
 use anyhow::bail;
 use async_std::sync::{Arc, RwLock};
 use clap::Parser;
-use espresso_types::{v0::traits::SequencerPersistence, BlockMerkleTree, FeeMerkleTree, PubKey};
+use espresso_types::{v0::traits::SequencerPersistence, FeeMerkleTree, PubKey};
 use futures::{
     channel::oneshot,
     future::{BoxFuture, Future, FutureExt},
@@ -30,7 +30,9 @@ use super::{
         provider, CatchupDataSource, HotShotConfigDataSource, SequencerDataSource,
         StateSignatureDataSource, SubmitDataSource,
     },
-    endpoints, fs, sql,
+    endpoints, fs,
+    pruning::PrunedHeights,
+    sql,
     update::update_loop,
     ApiState, StorageState,
 };
@@ -53,6 +55,8 @@ pub struct Options {
     pub explorer: Option<Explorer>,
     pub storage_fs: Option<persistence::fs::Options>,
     pub storage_sql: Option<persistence::sql::Options>,
+    #[cfg(feature = "sqlite")]
+    pub storage_sqlite: Option<persistence::sqlite::Options>,
 }
 
 impl From<Http> for Options {
@@ -69,6 +73,8 @@ impl From<Http> for Options {
             explorer: None,
             storage_fs: None,
             storage_sql: None,
+            #[cfg(feature = "sqlite")]
+            storage_sqlite: None,
         }
     }
 }
@@ -93,6 +99,14 @@ impl Options {
         self
     }
 
+    /// Add a query API module backed by an embedded SQLite database.
+    #[cfg(feature = "sqlite")]
+    pub fn query_sqlite(mut self, query: Query, storage: persistence::sqlite::Options) -> Self {
+        self.query = Some(query);
+        self.storage_sqlite = Some(storage);
+        self
+    }
+
     /// Add a submit API module.
     pub fn submit(mut self, opt: Submit) -> Self {
         self.submit = Some(opt);
@@ -137,7 +151,13 @@ impl Options {
 
     /// Whether these options will run the query API.
     pub fn has_query_module(&self) -> bool {
-        self.query.is_some() && (self.storage_fs.is_some() || self.storage_sql.is_some())
+        #[cfg(feature = "sqlite")]
+        let has_sqlite = self.storage_sqlite.is_some();
+        #[cfg(not(feature = "sqlite"))]
+        let has_sqlite = false;
+
+        self.query.is_some()
+            && (self.storage_fs.is_some() || self.storage_sql.is_some() || has_sqlite)
     }
 
     /// Start the server.
@@ -199,6 +219,19 @@ impl Options {
                 )
                 .await?
             } else {
+                #[cfg(feature = "sqlite")]
+                if let Some(opt) = self.storage_sqlite.take() {
+                    let metrics = self
+                        .init_with_query_module_sqlite::<N, P, Ver>(
+                            query_opt,
+                            opt,
+                            state,
+                            &mut tasks,
+                            bind_version,
+                        )
+                        .await?;
+                    return Ok(init_context(metrics).await.with_task_list(tasks));
+                }
                 bail!("query module requested but not storage provided");
             }
         } else if self.status.is_some() {
@@ -285,7 +318,18 @@ impl Options {
         }
 
         // Initialize availability and node APIs (these both use the same data source).
-        app.register_module("availability", endpoints::availability(bind_version)?)?;
+        let pruned = PrunedHeights::default();
+        tasks.spawn("pruned height monitor", {
+            let ds = ds.clone();
+            pruned.clone().monitor(move |class| {
+                let ds = ds.clone();
+                async move { ds.read().await.inner().pruned_height(class).await }
+            })
+        });
+        app.register_module(
+            "availability",
+            endpoints::availability(pruned, bind_version)?,
+        )?;
         app.register_module("node", endpoints::node(bind_version)?)?;
 
         self.init_hotshot_modules::<_, _, _, Ver>(&mut app)?;
@@ -317,10 +361,34 @@ impl Options {
         )
         .await?;
 
-        let (metrics, _, app) = self
+        let (metrics, ds, mut app) = self
             .init_app_modules(ds, state.clone(), tasks, bind_version)
             .await?;
 
+        if self.explorer.is_some() {
+            app.register_module("explorer", endpoints::explorer(bind_version)?)?;
+        }
+
+        if self.state.is_some() {
+            // Initialize merklized state module for block merkle tree
+            app.register_module(
+                "block-state",
+                endpoints::block_state::<N, P, _, _>(bind_version)?,
+            )?;
+            // Initialize merklized state module for fee merkle tree
+            app.register_module(
+                "fee-state",
+                endpoints::merklized_state::<N, P, _, FeeMerkleTree, _, 256>(bind_version)?,
+            )?;
+
+            let state = state.clone();
+            let get_node_state = async move { state.node_state().await.clone() };
+            tasks.spawn(
+                "merklized state storage update loop",
+                update_state_storage_loop(ds, get_node_state, Ver::version()),
+            );
+        }
+
         if self.hotshot_events.is_some() {
             self.init_and_spawn_hotshot_event_streaming_module(state, tasks, bind_version)?;
         }
@@ -350,6 +418,67 @@ impl Options {
             false,
         )
         .await?;
+        if let Some(pruner) = sql::ClassPruner::new(&mod_opt).await? {
+            tasks.spawn("query data class pruner", pruner.run());
+        }
+        let (metrics, ds, mut app) = self
+            .init_app_modules(ds, state.clone(), tasks, bind_version)
+            .await?;
+
+        if self.explorer.is_some() {
+            app.register_module("explorer", endpoints::explorer(bind_version)?)?;
+        }
+
+        if self.state.is_some() {
+            // Initialize merklized state module for block merkle tree
+            app.register_module(
+                "block-state",
+                endpoints::block_state::<N, P, _, _>(bind_version)?,
+            )?;
+            // Initialize merklized state module for fee merkle tree
+            app.register_module(
+                "fee-state",
+                endpoints::merklized_state::<N, P, _, FeeMerkleTree, _, 256>(bind_version)?,
+            )?;
+
+            let state = state.clone();
+            let get_node_state = async move { state.node_state().await.clone() };
+            tasks.spawn(
+                "merklized state storage update loop",
+                update_state_storage_loop(ds, get_node_state, Ver::version()),
+            );
+        }
+
+        if self.hotshot_events.is_some() {
+            self.init_and_spawn_hotshot_event_streaming_module(state, tasks, bind_version)?;
+        }
+
+        tasks.spawn(
+            "API server",
+            self.listen(self.http.port, app, Ver::instance()),
+        );
+        Ok(metrics)
+    }
+
+    #[cfg(feature = "sqlite")]
+    async fn init_with_query_module_sqlite<N, P, Ver: StaticVersionType + 'static>(
+        self,
+        query_opt: Query,
+        mod_opt: persistence::sqlite::Options,
+        state: ApiState<N, P, Ver>,
+        tasks: &mut TaskList,
+        bind_version: Ver,
+    ) -> anyhow::Result<Box<dyn Metrics>>
+    where
+        N: ConnectedNetwork<PubKey>,
+        P: SequencerPersistence,
+    {
+        let ds = super::sqlite::DataSource::create(
+            mod_opt,
+            provider(query_opt.peers.clone(), bind_version),
+            false,
+        )
+        .await?;
         let (metrics, ds, mut app) = self
             .init_app_modules(ds, state.clone(), tasks, bind_version)
             .await?;
@@ -362,7 +491,7 @@ impl Options {
             // Initialize merklized state module for block merkle tree
             app.register_module(
                 "block-state",
-                endpoints::merklized_state::<N, P, _, BlockMerkleTree, _, 3>(bind_version)?,
+                endpoints::block_state::<N, P, _, _>(bind_version)?,
             )?;
             // Initialize merklized state module for fee merkle tree
             app.register_module(
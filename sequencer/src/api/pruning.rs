@@ -0,0 +1,243 @@
+//! Visibility into pruning of query service data.
+//!
+//! Besides the query service pruner, which deletes whole blocks once they are older than its target
+//! retention period, some storage backends can prune individual classes of data (such as payloads)
+//! on their own schedule. This module defines the types used to report what has been pruned, and to
+//! fail requests for pruned data explicitly instead of waiting for a fetch which will never succeed.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use async_std::task::sleep;
+use hotshot_query_service::{availability, merklized_state};
+use serde::{Deserialize, Serialize};
+use tide_disco::{Error as _, StatusCode};
+
+/// A class of query service data which can be pruned independently of whole blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataClass {
+    /// Leaves and the QCs which sign them.
+    Leaf,
+    /// Block payloads.
+    Payload,
+    /// VID common data and VID shares.
+    VidCommon,
+    /// Old versions of merklized state (the fee and block Merkle trees).
+    ///
+    /// Pruning this class removes Merkle nodes which are not needed to serve snapshots after the
+    /// pruned height. The latest version of every node, and therefore every account, is kept.
+    MerklizedState,
+}
+
+impl DataClass {
+    pub const ALL: [Self; 4] = [
+        Self::Leaf,
+        Self::Payload,
+        Self::VidCommon,
+        Self::MerklizedState,
+    ];
+}
+
+impl Display for DataClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Leaf => write!(f, "leaf"),
+            Self::Payload => write!(f, "payload"),
+            Self::VidCommon => write!(f, "vid_common"),
+            Self::MerklizedState => write!(f, "merklized_state"),
+        }
+    }
+}
+
+/// The pruning status of a single class of data.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClassStatus {
+    /// Data of this class has been pruned up to and including this height.
+    pub pruned_height: Option<u64>,
+    /// The oldest height for which data of this class is currently stored.
+    pub oldest_height: Option<u64>,
+}
+
+/// The pruning status of query service storage.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruningStatus {
+    /// Whole blocks have been pruned up to and including this height.
+    pub pruned_height: Option<u64>,
+    /// The status of each class of data which is pruned separately from whole blocks.
+    pub classes: BTreeMap<DataClass, ClassStatus>,
+}
+
+/// Describe why a request for `class` data at `height` cannot be served, if it has been pruned.
+///
+/// `pruned_height` is the height up to which `class` data has been pruned, if any.
+pub(crate) fn pruned_message(
+    class: DataClass,
+    height: u64,
+    pruned_height: Option<u64>,
+) -> Option<String> {
+    match pruned_height {
+        Some(pruned_height) if height <= pruned_height => Some(format!(
+            "{class} data at height {height} has been pruned (pruned up to height {pruned_height})"
+        )),
+        _ => None,
+    }
+}
+
+/// Fail with an explicit error if `class` data at `height` has been pruned.
+///
+/// `pruned_height` is the height up to which `class` data has been pruned, if any.
+pub(crate) fn ensure_retained(
+    class: DataClass,
+    height: u64,
+    pruned_height: Option<u64>,
+) -> Result<(), availability::Error> {
+    match pruned_message(class, height, pruned_height) {
+        Some(message) => Err(availability::Error::Custom {
+            message,
+            status: StatusCode::GONE,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Fail with an explicit error if merklized state at `height` has been pruned.
+///
+/// `pruned_height` is the height up to which merklized state has been pruned, if any.
+pub(crate) fn ensure_state_retained(
+    height: u64,
+    pruned_height: Option<u64>,
+) -> Result<(), merklized_state::Error> {
+    match pruned_message(DataClass::MerklizedState, height, pruned_height) {
+        Some(message) => Err(merklized_state::Error::catch_all(StatusCode::GONE, message)),
+        None => Ok(()),
+    }
+}
+
+/// A snapshot of the heights up to which each class of data has been pruned.
+///
+/// The handlers for the standard availability routes belong to the query service, and fail
+/// requests for missing data with a generic fetch error once their fetch times out. The
+/// availability API maps those errors through [`PrunedHeights::map_error`], so that requests for
+/// data which has been pruned fail with 410 (Gone) instead. Error mapping cannot wait on storage, so
+/// the snapshot is refreshed periodically by [`PrunedHeights::monitor`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PrunedHeights(Arc<RwLock<BTreeMap<DataClass, u64>>>);
+
+impl PrunedHeights {
+    /// How often the snapshot is refreshed.
+    pub(crate) const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// The height up to which `class` data had been pruned as of the last refresh.
+    pub(crate) fn get(&self, class: DataClass) -> Option<u64> {
+        self.0.read().unwrap().get(&class).copied()
+    }
+
+    fn set(&self, class: DataClass, height: Option<u64>) {
+        let mut heights = self.0.write().unwrap();
+        match height {
+            Some(height) => {
+                heights.insert(class, height);
+            }
+            None => {
+                heights.remove(&class);
+            }
+        }
+    }
+
+    /// Refresh the snapshot from `pruned_height` periodically, forever.
+    pub(crate) async fn monitor<F, Fut>(self, pruned_height: F)
+    where
+        F: Fn(DataClass) -> Fut,
+        Fut: std::future::Future<Output = anyhow::Result<Option<u64>>>,
+    {
+        loop {
+            for class in [DataClass::Leaf, DataClass::Payload, DataClass::VidCommon] {
+                match pruned_height(class).await {
+                    Ok(height) => self.set(class, height),
+                    Err(err) => tracing::warn!(%class, "error loading pruned height: {err:#}"),
+                }
+            }
+            sleep(Self::REFRESH_INTERVAL).await;
+        }
+    }
+
+    /// Replace a failure to fetch pruned data with an explicit 410 (Gone) error.
+    ///
+    /// Leaves are only fetched by the leaf routes, while a failure to fetch a block may be due to
+    /// its payload or its VID common data, depending on the route, so it is reported as gone if
+    /// either has been pruned. Only requests by height are affected: we cannot tell the height of
+    /// data requested by hash once it is gone.
+    pub(crate) fn map_error(&self, err: availability::Error) -> availability::Error {
+        let (classes, resource): (&[DataClass], _) = match &err {
+            availability::Error::FetchLeaf { resource } => (&[DataClass::Leaf], resource),
+            availability::Error::FetchBlock { resource } => {
+                (&[DataClass::Payload, DataClass::VidCommon], resource)
+            }
+            _ => return err,
+        };
+        let Ok(height) = resource.parse::<u64>() else {
+            return err;
+        };
+        let Some((class, pruned_height)) = classes
+            .iter()
+            .filter_map(|class| Some((*class, self.get(*class)?)))
+            .max_by_key(|(_, pruned_height)| *pruned_height)
+        else {
+            return err;
+        };
+        ensure_retained(class, height, Some(pruned_height))
+            .err()
+            .unwrap_or(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ensure_retained() {
+        ensure_retained(DataClass::Payload, 0, None).unwrap();
+        ensure_retained(DataClass::Payload, 11, Some(10)).unwrap();
+
+        let err = ensure_retained(DataClass::Payload, 10, Some(10)).unwrap_err();
+        let availability::Error::Custom { message, status } = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert_eq!(status, StatusCode::GONE);
+        assert!(message.contains("pruned"), "{message}");
+
+        ensure_state_retained(8, Some(7)).unwrap();
+        let err = ensure_state_retained(7, Some(7)).unwrap_err();
+        assert_eq!(err.status(), StatusCode::GONE);
+    }
+
+    #[test]
+    fn test_map_error() {
+        let pruned = PrunedHeights::default();
+        pruned.set(DataClass::Leaf, Some(10));
+        pruned.set(DataClass::VidCommon, Some(5));
+
+        let status = |err: availability::Error| match pruned.map_error(err) {
+            availability::Error::Custom { status, .. } => status,
+            _ => StatusCode::NOT_FOUND,
+        };
+        let leaf = |resource: &str| availability::Error::FetchLeaf {
+            resource: resource.into(),
+        };
+        let block = |resource: &str| availability::Error::FetchBlock {
+            resource: resource.into(),
+        };
+        assert_eq!(status(leaf("10")), StatusCode::GONE);
+        assert_eq!(status(leaf("11")), StatusCode::NOT_FOUND);
+        assert_eq!(status(block("5")), StatusCode::GONE);
+        assert_eq!(status(block("6")), StatusCode::NOT_FOUND);
+        // Data requested by hash is never reported as gone.
+        assert_eq!(status(leaf("LEAF~abc")), StatusCode::NOT_FOUND);
+    }
+}
@@ -17,6 +17,7 @@ use jf_merkle_tree::{prelude::MerkleNode, MerkleTreeScheme};
 
 use super::{
     data_source::{CatchupDataSource, Provider, SequencerDataSource},
+    pruning::{DataClass, PruningStatus},
     AccountQueryData, BlocksFrontier,
 };
 use crate::{
@@ -27,6 +28,10 @@ use crate::{
     SeqTypes,
 };
 
+mod pruner;
+
+pub(crate) use pruner::ClassPruner;
+
 pub type DataSource = SqlDataSource<SeqTypes, Provider>;
 
 #[async_trait]
@@ -37,6 +42,10 @@ impl SequencerDataSource for DataSource {
         let fetch_limit = opt.fetch_rate_limit;
         let active_fetch_delay = opt.active_fetch_delay;
         let chunk_fetch_delay = opt.chunk_fetch_delay;
+        let class_pruning = !opt.pruning.class_retention().is_empty();
+        if class_pruning && opt.archive {
+            bail!("per-class retention periods cannot be used in archive mode");
+        }
         let mut cfg = Config::try_from(opt)?;
 
         if reset {
@@ -54,9 +63,21 @@ impl SequencerDataSource for DataSource {
         if let Some(delay) = chunk_fetch_delay {
             builder = builder.with_chunk_fetch_delay(delay);
         }
+        if class_pruning {
+            // Proactive fetching would restore data as fast as the class pruner deletes it.
+            builder = builder.disable_proactive_fetching();
+        }
 
         builder.build().await
     }
+
+    async fn pruned_height(&self, class: DataClass) -> anyhow::Result<Option<u64>> {
+        pruner::pruned_height(&*self.storage().await, class).await
+    }
+
+    async fn pruning_status(&self) -> anyhow::Result<PruningStatus> {
+        pruner::pruning_status(&*self.storage().await).await
+    }
 }
 
 impl CatchupDataSource for SqlStorage {
@@ -66,6 +87,7 @@ impl CatchupDataSource for SqlStorage {
         _view: ViewNumber,
         account: Address,
     ) -> anyhow::Result<AccountQueryData> {
+        pruner::ensure_state_retained(self, height).await?;
         let proof = self
             .get_path(
                 Snapshot::<SeqTypes, FeeMerkleTree, { FeeMerkleTree::ARITY }>::Index(height),
@@ -93,6 +115,7 @@ impl CatchupDataSource for SqlStorage {
     }
 
     async fn get_frontier(&self, height: u64, _view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
+        pruner::ensure_state_retained(self, height).await?;
         self.get_path(
             Snapshot::<SeqTypes, BlockMerkleTree, { BlockMerkleTree::ARITY }>::Index(height),
             height - 1,
@@ -0,0 +1,566 @@
+//! Pruning of individual classes of query service data in Postgres.
+//!
+//! The query service pruner deletes whole blocks. This pruner runs alongside it, deleting leaves,
+//! payloads or VID data on their own, shorter, retention schedules, while keeping the headers (and
+//! block summaries) of the affected blocks. It also deletes old versions of merklized state, which
+//! the query service pruner never touches. The height up to which each class has been pruned is
+//! recorded in the `class_pruned_height` table, so that requests for pruned data can fail
+//! explicitly.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, ensure};
+use async_std::task::sleep;
+use futures::FutureExt;
+use hotshot_query_service::{
+    data_source::storage::sql::{Config, Query, SqlStorage},
+    merklized_state::MerklizedStateHeightPersistence,
+};
+
+use crate::{
+    api::pruning::{pruned_message, ClassStatus, DataClass, PruningStatus},
+    persistence::sql::{sql_param, transaction, Options},
+};
+
+/// Prunes classes of data which have their own retention period.
+pub(crate) struct ClassPruner {
+    db: SqlStorage,
+    retention: BTreeMap<DataClass, Duration>,
+    state_retention: Option<u64>,
+    batch_size: u64,
+    interval: Duration,
+}
+
+impl ClassPruner {
+    /// Connect a pruner for the classes of data which have a retention period in `opt`.
+    ///
+    /// Returns [`None`] if no class has its own retention period. The pruner uses its own
+    /// connection, so that it does not hold up the query service while deleting data.
+    pub(crate) async fn new(opt: &Options) -> anyhow::Result<Option<Self>> {
+        let retention = opt.pruning.class_retention();
+        let state_retention = opt.pruning.merklized_state_retention();
+        if retention.is_empty() && state_retention.is_none() {
+            return Ok(None);
+        }
+        ensure!(
+            state_retention != Some(0),
+            "merklized state retention must be at least one block"
+        );
+        Ok(Some(Self {
+            db: SqlStorage::connect(Config::try_from(opt.clone())?).await?,
+            retention,
+            state_retention,
+            batch_size: opt.pruning.batch_size(),
+            interval: opt.pruning.interval(),
+        }))
+    }
+
+    /// Prune periodically, forever.
+    pub(crate) async fn run(mut self) {
+        loop {
+            for data in &BLOCK_DATA {
+                let Some(retention) = self.retention.get(&data.class).copied() else {
+                    continue;
+                };
+                if let Err(err) = self.prune(data, retention).await {
+                    tracing::warn!(class = %data.class, "error pruning query data: {err:#}");
+                }
+            }
+            if let Some(retention) = self.state_retention {
+                if let Err(err) = self.prune_state(retention).await {
+                    tracing::warn!("error pruning merklized state: {err:#}");
+                }
+            }
+            sleep(self.interval).await;
+        }
+    }
+
+    /// Prune `data` in all blocks older than `retention`.
+    async fn prune(&mut self, data: &BlockData, retention: Duration) -> anyhow::Result<()> {
+        let class = data.class;
+        let Some(cutoff) = SystemTime::now().checked_sub(retention) else {
+            return Ok(());
+        };
+        let cutoff = cutoff.duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        let row = self
+            .db
+            .query_one(
+                "SELECT max(height) AS height FROM header WHERE timestamp < $1",
+                [&cutoff],
+            )
+            .await?;
+        let Some(target) = row.try_get::<_, Option<i64>>("height")? else {
+            return Ok(());
+        };
+
+        let start = pruned_height(&self.db, class).await?.map_or(0, |h| h + 1);
+        let stmt = data.prune;
+        for (from, to) in batches(start, target as u64, self.batch_size) {
+            transaction(&mut self.db, |mut tx| {
+                async move {
+                    tx.execute(stmt, [&(from as i64), &(to as i64)]).await?;
+                    tx.upsert(
+                        "class_pruned_height",
+                        ["class", "height"],
+                        ["class"],
+                        [[sql_param(&class.to_string()), sql_param(&(to as i64))]],
+                    )
+                    .await?;
+                    Ok(())
+                }
+                .boxed()
+            })
+            .await?;
+            tracing::info!(%class, from, to, "pruned query data");
+        }
+        Ok(())
+    }
+
+    /// Prune versions of merklized state which are not needed to serve the last `retention`
+    /// snapshots.
+    async fn prune_state(&mut self, retention: u64) -> anyhow::Result<()> {
+        let last_height = self.db.get_last_state_height().await? as u64;
+        // Keep every snapshot from `height` on.
+        let Some(height) = last_height.checked_sub(retention - 1) else {
+            return Ok(());
+        };
+        self.prune_state_before(height).await
+    }
+
+    /// Prune versions of merklized state which are not needed to serve snapshots from `height` on.
+    ///
+    /// The pruned height is recorded before anything is deleted, so that queries for snapshots
+    /// which are about to lose nodes fail explicitly instead of returning incomplete paths. Nodes,
+    /// and then the hashes which are no longer referenced by any node, are deleted in batches of
+    /// `batch_size` rows, each in its own transaction.
+    async fn prune_state_before(&mut self, height: u64) -> anyhow::Result<()> {
+        let pruned_height = pruned_height(&self.db, DataClass::MerklizedState).await?;
+        if height == 0 || pruned_height.is_some_and(|pruned| pruned + 1 >= height) {
+            return Ok(());
+        }
+
+        transaction(&mut self.db, |mut tx| {
+            async move {
+                tx.upsert(
+                    "class_pruned_height",
+                    ["class", "height"],
+                    ["class"],
+                    [[
+                        sql_param(&DataClass::MerklizedState.to_string()),
+                        sql_param(&(height as i64 - 1)),
+                    ]],
+                )
+                .await?;
+                Ok(())
+            }
+            .boxed()
+        })
+        .await?;
+
+        for table in STATE_TABLES {
+            // A version of a node can be deleted if it was replaced by a newer version at or
+            // before `height`: the newer version is the one in effect for every snapshot we keep.
+            // The latest version of each node is never deleted. There is no index on `created`
+            // alone (see migration V31), so this scans the table; that is acceptable for a
+            // background task which runs infrequently.
+            let stmt = format!(
+                "DELETE FROM {table} WHERE ctid IN (
+                    SELECT t.ctid FROM {table} AS t
+                     WHERE t.created < $1 AND EXISTS (
+                        SELECT 1 FROM {table} AS n
+                         WHERE n.path = t.path AND n.created > t.created AND n.created <= $1
+                     )
+                     LIMIT $2
+                )"
+            );
+            let deleted = self
+                .delete_in_batches(&stmt, [height as i64, self.batch_size as i64])
+                .await?;
+            tracing::info!(table, height, deleted, "pruned merklized state");
+        }
+
+        // Nodes refer to their own hash and to the hashes of their children. A hash which is
+        // referred to by neither in any remaining node can be deleted.
+        let referenced = STATE_TABLES
+            .iter()
+            .map(|table| {
+                format!(
+                    "SELECT hash_id AS id FROM {table}
+                     UNION SELECT unnest(children) AS id FROM {table}
+                      WHERE children IS NOT NULL"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" UNION ");
+        let stmt = format!(
+            "DELETE FROM hash WHERE id IN (
+                SELECT h.id FROM hash AS h WHERE h.id NOT IN ({referenced}) LIMIT $1
+            )"
+        );
+        let deleted = self
+            .delete_in_batches(&stmt, [self.batch_size as i64])
+            .await?;
+        tracing::info!(
+            height,
+            deleted,
+            "deleted unreferenced merklized state hashes"
+        );
+        Ok(())
+    }
+
+    /// Run a statement which deletes up to a batch of rows until it no longer deletes a full batch.
+    ///
+    /// The batch size must be the last parameter. Returns the total number of rows deleted.
+    async fn delete_in_batches<const N: usize>(
+        &mut self,
+        stmt: &str,
+        params: [i64; N],
+    ) -> anyhow::Result<u64> {
+        let batch_size = params[N - 1] as u64;
+        let mut total = 0;
+        loop {
+            let stmt = stmt.to_string();
+            let deleted = transaction(&mut self.db, |mut tx| {
+                async move { Ok(tx.execute(&stmt, params).await?) }.boxed()
+            })
+            .await?;
+            total += deleted;
+            if deleted < batch_size {
+                return Ok(total);
+            }
+        }
+    }
+}
+
+/// The tables which store merklized state.
+const STATE_TABLES: [&str; 2] = ["fee_merkle_tree", "block_merkle_tree"];
+
+/// A class of data which is stored with blocks, and how to prune it.
+struct BlockData {
+    class: DataClass,
+    /// The statement which prunes this data between two heights (inclusive).
+    prune: &'static str,
+    /// The query for the oldest height at which this data is stored, above a given height.
+    oldest: &'static str,
+}
+
+/// The classes of data which are stored with blocks and pruned by the timestamps of their blocks.
+///
+/// Merklized state is not stored by block, and is pruned by [`ClassPruner::prune_state_before`].
+const BLOCK_DATA: [BlockData; 3] = [
+    BlockData {
+        class: DataClass::Leaf,
+        prune: "DELETE FROM leaf WHERE height >= $1 AND height <= $2",
+        oldest: "SELECT min(height) AS height FROM leaf WHERE height > $1",
+    },
+    BlockData {
+        class: DataClass::Payload,
+        // Keep the payload row, which carries the block size and number of transactions used in
+        // block summaries.
+        prune: "UPDATE payload SET data = NULL WHERE height >= $1 AND height <= $2",
+        oldest: "SELECT min(height) AS height FROM payload WHERE height > $1 AND data IS NOT NULL",
+    },
+    BlockData {
+        class: DataClass::VidCommon,
+        prune: "DELETE FROM vid WHERE height >= $1 AND height <= $2",
+        oldest: "SELECT min(height) AS height FROM vid WHERE height > $1",
+    },
+];
+
+/// Split the heights from `start` to `end` (inclusive) into ranges of at most `batch_size`.
+fn batches(start: u64, end: u64, batch_size: u64) -> impl Iterator<Item = (u64, u64)> {
+    let batch_size = batch_size.max(1);
+    (start..=end)
+        .step_by(batch_size as usize)
+        .map(move |from| (from, end.min(from + batch_size - 1)))
+}
+
+/// The height up to which whole blocks have been pruned by the query service pruner.
+async fn block_pruned_height(db: &SqlStorage) -> anyhow::Result<Option<u64>> {
+    let Some(row) = db
+        .query_opt_static("SELECT last_height FROM pruned_height ORDER BY id DESC LIMIT 1")
+        .await?
+    else {
+        return Ok(None);
+    };
+    let height: i64 = row.try_get("last_height")?;
+    Ok(Some(height as u64))
+}
+
+/// The height up to which `class` data has been pruned, either on its own or with whole blocks.
+///
+/// Merklized state is not stored with blocks, so it is only ever pruned on its own.
+pub(crate) async fn pruned_height(
+    db: &SqlStorage,
+    class: DataClass,
+) -> anyhow::Result<Option<u64>> {
+    let class_height = db
+        .query_opt(
+            "SELECT height FROM class_pruned_height WHERE class = $1",
+            [&class.to_string()],
+        )
+        .await?
+        .map(|row| row.try_get::<_, i64>("height"))
+        .transpose()?
+        .map(|height| height as u64);
+    if class == DataClass::MerklizedState {
+        return Ok(class_height);
+    }
+    Ok(class_height.max(block_pruned_height(db).await?))
+}
+
+/// Fail if merklized state at `height` has been pruned.
+pub(crate) async fn ensure_state_retained(db: &SqlStorage, height: u64) -> anyhow::Result<()> {
+    let pruned_height = pruned_height(db, DataClass::MerklizedState).await?;
+    match pruned_message(DataClass::MerklizedState, height, pruned_height) {
+        Some(message) => bail!(message),
+        None => Ok(()),
+    }
+}
+
+/// Report the pruning status of each class of data.
+pub(crate) async fn pruning_status(db: &SqlStorage) -> anyhow::Result<PruningStatus> {
+    let mut status = PruningStatus {
+        pruned_height: block_pruned_height(db).await?,
+        classes: Default::default(),
+    };
+    for data in &BLOCK_DATA {
+        let pruned_height = pruned_height(db, data.class).await?;
+        let floor = pruned_height.map_or(-1, |h| h as i64);
+        let oldest_height = db
+            .query_one(data.oldest, [&floor])
+            .await?
+            .try_get::<_, Option<i64>>("height")?
+            .map(|h| h as u64);
+        status.classes.insert(
+            data.class,
+            ClassStatus {
+                pruned_height,
+                oldest_height,
+            },
+        );
+    }
+
+    // Nodes are not indexed by the height at which they were created, so rather than the oldest
+    // stored node, report the oldest snapshot which can be served.
+    let pruned_height = pruned_height(db, DataClass::MerklizedState).await?;
+    status.classes.insert(
+        DataClass::MerklizedState,
+        ClassStatus {
+            pruned_height,
+            oldest_height: Some(pruned_height.map_or(0, |h| h + 1)),
+        },
+    );
+    Ok(status)
+}
+
+#[cfg(test)]
+mod test {
+    use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+    use es_version::SequencerVersion;
+    use ethers::utils::Anvil;
+    use futures::StreamExt;
+    use hotshot_query_service::{
+        availability::LeafQueryData, data_source::storage::sql::testing::TmpDb,
+    };
+    use portpicker::pick_unused_port;
+    use surf_disco::Client;
+    use tide_disco::{error::ServerError, Error as _, StatusCode};
+
+    use super::*;
+    use crate::{
+        api::{
+            self,
+            pruning::PrunedHeights,
+            test_helpers::{TestNetwork, TestNetworkConfigBuilder},
+        },
+        testing::TestConfigBuilder,
+        SeqTypes,
+    };
+
+    fn tmp_options(db: &TmpDb) -> Options {
+        Options {
+            port: Some(db.port()),
+            host: Some(db.host()),
+            user: Some("postgres".into()),
+            password: Some("password".into()),
+            ..Default::default()
+        }
+    }
+
+    #[async_std::test]
+    async fn test_pruned_data_gone() {
+        setup_logging();
+        setup_backtrace();
+
+        // Start a query service backed by Postgres.
+        let tmp = TmpDb::init().await;
+        let opt = tmp_options(&tmp);
+        let port = pick_unused_port().expect("No ports free");
+        let anvil = Anvil::new().spawn();
+        let l1 = anvil.endpoint().parse().unwrap();
+        let config = TestNetworkConfigBuilder::default()
+            .api_config(api::Options::with_port(port).query_sql(Default::default(), opt.clone()))
+            .network_config(TestConfigBuilder::default().l1_url(l1).build())
+            .build();
+        let _network = TestNetwork::new(config).await;
+
+        let client: Client<ServerError, SequencerVersion> =
+            Client::new(format!("http://localhost:{port}").parse().unwrap());
+        client.connect(None).await;
+
+        // Wait for a few blocks, and for time to move on past their timestamps.
+        let mut leaves = client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            leaves.next().await.unwrap().unwrap();
+        }
+        sleep(Duration::from_secs(2)).await;
+
+        // Prune every class of data in all of those blocks.
+        let mut pruner = ClassPruner {
+            db: SqlStorage::connect(Config::try_from(opt).unwrap())
+                .await
+                .unwrap(),
+            retention: Default::default(),
+            state_retention: None,
+            batch_size: 1,
+            interval: Duration::from_secs(1),
+        };
+        for data in &BLOCK_DATA {
+            let class = data.class;
+            pruner.prune(data, Duration::ZERO).await.unwrap();
+            let pruned_height = pruned_height(&pruner.db, class).await.unwrap();
+            assert!(pruned_height >= Some(2), "{class} {pruned_height:?}");
+        }
+
+        // Once the API has picked up the new pruned heights, the standard routes report pruned
+        // data as gone.
+        sleep(PrunedHeights::REFRESH_INTERVAL * 2).await;
+        for path in ["leaf/1", "block/1", "payload/1", "vid/common/1"] {
+            let err = client
+                .get::<serde_json::Value>(&format!("availability/{path}"))
+                .send()
+                .await
+                .unwrap_err();
+            assert_eq!(err.status(), StatusCode::GONE, "{path}: {err}");
+        }
+
+        // Headers are kept.
+        client
+            .get::<serde_json::Value>("availability/header/1")
+            .send()
+            .await
+            .unwrap();
+
+        let status: PruningStatus = client.get("availability/pruning").send().await.unwrap();
+        for class in [DataClass::Leaf, DataClass::Payload, DataClass::VidCommon] {
+            assert!(
+                status.classes[&class].pruned_height >= Some(2),
+                "{status:?}"
+            );
+        }
+    }
+
+    #[async_std::test]
+    async fn test_prune_state() {
+        let tmp = TmpDb::init().await;
+        let opt = tmp_options(&tmp);
+        let mut pruner = ClassPruner {
+            db: SqlStorage::connect(Config::try_from(opt).unwrap())
+                .await
+                .unwrap(),
+            retention: Default::default(),
+            state_retention: None,
+            // Delete one row at a time, to exercise batching.
+            batch_size: 1,
+            interval: Duration::from_secs(1),
+        };
+
+        // Node `{1}` is modified at heights 0, 5 and 10, and node `{2}` only at height 0. Hash 2 is
+        // only used by the oldest version of `{1}`, while hash 3 is only used as a child of `{2}`.
+        transaction(&mut pruner.db, |mut tx| {
+            async move {
+                tx.execute(
+                    "INSERT INTO hash (id, value) VALUES (1, '\\x01'), (2, '\\x02'), (3, '\\x03')",
+                    [] as [i64; 0],
+                )
+                .await?;
+                tx.execute(
+                    "INSERT INTO fee_merkle_tree (path, created, hash_id, children)
+                     VALUES ('{1}', 0, 2, NULL), ('{1}', 5, 1, NULL), ('{1}', 10, 1, NULL),
+                            ('{2}', 0, 1, '{3}')",
+                    [] as [i64; 0],
+                )
+                .await?;
+                Ok(())
+            }
+            .boxed()
+        })
+        .await
+        .unwrap();
+
+        // Keep snapshots from height 8 on.
+        pruner.prune_state_before(8).await.unwrap();
+        let mut remaining = vec![];
+        for (path, created) in [("{1}", 0i64), ("{1}", 5), ("{1}", 10), ("{2}", 0)] {
+            let row = pruner
+                .db
+                .query_opt(
+                    "SELECT 1 FROM fee_merkle_tree WHERE path = $1::TEXT::INTEGER[] AND created = $2",
+                    [sql_param(&path), sql_param(&created)],
+                )
+                .await
+                .unwrap();
+            if row.is_some() {
+                remaining.push((path, created));
+            }
+        }
+        // The version of `{1}` from height 0 is superseded at height 5, which is still in effect at
+        // height 8. The only version of `{2}` is kept, no matter how old.
+        assert_eq!(remaining, [("{1}", 5), ("{1}", 10), ("{2}", 0)]);
+
+        // The hash of the deleted version is garbage collected, but hashes of remaining nodes and
+        // their children are kept.
+        let mut hashes = vec![];
+        for id in [1i64, 2, 3] {
+            let row = pruner
+                .db
+                .query_opt("SELECT 1 FROM hash WHERE id = $1", [&(id as i32)])
+                .await
+                .unwrap();
+            if row.is_some() {
+                hashes.push(id);
+            }
+        }
+        assert_eq!(hashes, [1, 3]);
+
+        assert_eq!(
+            pruned_height(&pruner.db, DataClass::MerklizedState)
+                .await
+                .unwrap(),
+            Some(7)
+        );
+        ensure_state_retained(&pruner.db, 8).await.unwrap();
+        ensure_state_retained(&pruner.db, 7).await.unwrap_err();
+
+        // Pruning again up to the same height is a no-op.
+        pruner.prune_state_before(8).await.unwrap();
+    }
+
+    #[test]
+    fn test_batches() {
+        assert_eq!(
+            batches(0, 9, 4).collect::<Vec<_>>(),
+            [(0, 3), (4, 7), (8, 9)]
+        );
+        assert_eq!(batches(5, 5, 4).collect::<Vec<_>>(), [(5, 5)]);
+        assert_eq!(batches(6, 5, 4).count(), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use committable::Commitment;
+use espresso_types::ChainConfig;
+use ethers::prelude::Address;
+use hotshot_query_service::data_source::FetchingDataSource;
+use hotshot_types::data::ViewNumber;
+
+use super::{
+    data_source::{CatchupDataSource, Provider, SequencerDataSource},
+    AccountQueryData, BlocksFrontier,
+};
+use crate::{
+    persistence::{sqlite::Options, ChainConfigPersistence},
+    SeqTypes,
+};
+
+mod storage;
+
+pub use storage::SqliteStorage;
+
+pub type DataSource = FetchingDataSource<SeqTypes, SqliteStorage, Provider>;
+
+#[async_trait]
+impl SequencerDataSource for DataSource {
+    type Options = Options;
+
+    async fn create(opt: Self::Options, provider: Provider, reset: bool) -> anyhow::Result<Self> {
+        if reset {
+            opt.reset_db()?;
+        }
+        let storage = SqliteStorage::open(opt.connect().await?).await?;
+
+        let mut builder = FetchingDataSource::builder(storage, provider);
+        if let Some(limit) = opt.fetch_rate_limit {
+            builder = builder.with_rate_limit(limit);
+        }
+        if let Some(delay) = opt.active_fetch_delay {
+            builder = builder.with_active_fetch_delay(delay);
+        }
+        if let Some(delay) = opt.chunk_fetch_delay {
+            builder = builder.with_chunk_fetch_delay(delay);
+        }
+
+        builder.build().await
+    }
+}
+
+impl CatchupDataSource for DataSource {
+    async fn get_account(
+        &self,
+        height: u64,
+        view: ViewNumber,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        self.storage()
+            .await
+            .get_account(height, view, account)
+            .await
+    }
+
+    async fn get_frontier(&self, height: u64, view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
+        self.storage().await.get_frontier(height, view).await
+    }
+
+    async fn get_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        self.storage().await.get_chain_config(commitment).await
+    }
+}
+
+#[async_trait]
+impl ChainConfigPersistence for DataSource {
+    async fn insert_chain_config(&mut self, chain_config: ChainConfig) -> anyhow::Result<()> {
+        (*self.storage_mut().await)
+            .insert_chain_config(chain_config)
+            .await
+    }
+
+    async fn load_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        self.storage().await.load_chain_config(commitment).await
+    }
+}
+
+#[cfg(test)]
+mod impl_testable_data_source {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::api::{self, data_source::testing::TestableSequencerDataSource};
+
+    fn tmp_options(dir: &TempDir) -> Options {
+        Options::new(dir.path().join("sequencer.db"))
+    }
+
+    #[async_trait]
+    impl TestableSequencerDataSource for DataSource {
+        type Storage = TempDir;
+
+        async fn create_storage() -> Self::Storage {
+            TempDir::new().unwrap()
+        }
+
+        fn persistence_options(storage: &Self::Storage) -> Self::Options {
+            tmp_options(storage)
+        }
+
+        fn options(storage: &Self::Storage, opt: api::Options) -> api::Options {
+            opt.query_sqlite(Default::default(), tmp_options(storage))
+        }
+    }
+}
+
+#[cfg(test)]
+mod generic_tests {
+    use super::{super::api_tests, DataSource};
+    // For some reason this is the only way to import the macro defined in another module of this
+    // crate.
+    use crate::*;
+
+    instantiate_api_tests!(DataSource);
+}
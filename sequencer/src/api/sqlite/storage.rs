@@ -0,0 +1,841 @@
+//! Query service storage backed by an embedded SQLite database.
+
+use std::{
+    cmp::min,
+    ops::{Bound, RangeBounds},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use derivative::Derivative;
+use espresso_types::{ChainConfig, Header};
+use ethers::prelude::Address;
+use hotshot_query_service::{
+    availability::{
+        AvailabilityStorage, BlockId, BlockQueryData, LeafId, LeafQueryData, PayloadQueryData,
+        TransactionHash, UpdateAvailabilityData, VidCommonQueryData,
+    },
+    data_source::{
+        storage::{ExplorerStorage, NodeStorage, PruneStorage},
+        VersionedDataSource,
+    },
+    explorer::{
+        BlockDetail, BlockIdentifier, BlockSummary, ExplorerSummary, GetBlockDetailError,
+        GetBlockSummariesError, GetBlockSummariesRequest, GetExplorerSummaryError,
+        GetSearchResultsError, GetTransactionDetailError, GetTransactionSummariesError,
+        GetTransactionSummariesRequest, SearchResult, TransactionDetailResponse,
+        TransactionIdentifier, TransactionSummary,
+    },
+    merklized_state::{
+        MerklizedState, MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot,
+        UpdateStateData,
+    },
+    node::{TimeWindowQueryData, WindowStart},
+    types::HeightIndexed,
+    QueryError, QueryResult, VidShare,
+};
+use hotshot_types::data::ViewNumber;
+use jf_merkle_tree::prelude::MerkleProof;
+use sqlx::{sqlite::SqliteRow, Row, Sqlite, SqlitePool, Transaction};
+use tagged_base64::TaggedBase64;
+
+use super::super::{
+    data_source::CatchupDataSource,
+    explorer::{self, ExplorerIndex, IndexedStorage},
+    merkle_nodes::{
+        self, encode_path, proof_nodes, query_error, reconstruct_proof, NodeLoader, StoredNode,
+    },
+    AccountQueryData, BlocksFrontier,
+};
+use crate::{persistence::ChainConfigPersistence, SeqTypes};
+
+/// The number of blocks loaded at a time when rebuilding the explorer index on startup.
+const INDEX_BATCH_SIZE: usize = 1000;
+
+/// Query service storage in an SQLite database.
+///
+/// Reads are served from a connection pool and only observe committed data. Writes are buffered in
+/// a single open transaction, which is committed or rolled back via [`VersionedDataSource`]. Like
+/// the file system storage, we keep an in-memory [`ExplorerIndex`] to support the explorer API.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+    #[derivative(Debug = "ignore")]
+    tx: Option<Transaction<'static, Sqlite>>,
+    explorer: ExplorerIndex,
+}
+
+impl SqliteStorage {
+    /// Storage over `pool` without an explorer index.
+    ///
+    /// This is enough to serve state catchup, but explorer queries will not find any blocks which
+    /// were stored before this storage was created. Use [`open`](Self::open) to serve the explorer.
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            tx: None,
+            explorer: Default::default(),
+        }
+    }
+
+    /// Storage over `pool`, with the explorer index rebuilt from the blocks in the database.
+    pub(crate) async fn open(pool: SqlitePool) -> anyhow::Result<Self> {
+        let mut storage = Self::new(pool);
+
+        // Blocks we are missing will be indexed when they are fetched. We load the blocks in
+        // bounded batches, so that we never hold more than a batch in memory at once.
+        let block_height = storage.block_height().await?;
+        for start in (0..block_height).step_by(INDEX_BATCH_SIZE) {
+            let end = min(start + INDEX_BATCH_SIZE, block_height);
+            for block in storage
+                .get_block_range(start..end)
+                .await?
+                .into_iter()
+                .flatten()
+            {
+                storage.explorer.index(&block);
+            }
+        }
+        Ok(storage)
+    }
+
+    /// Get the open write transaction, starting a new one if necessary.
+    async fn transaction(&mut self) -> Result<&mut Transaction<'static, Sqlite>, sqlx::Error> {
+        if self.tx.is_none() {
+            self.tx = Some(self.pool.begin().await?);
+        }
+        Ok(self.tx.as_mut().unwrap())
+    }
+
+    /// Resolve a block identifier to a block height.
+    async fn block_height_of(&self, id: BlockId<SeqTypes>) -> QueryResult<u64> {
+        let row = match id {
+            BlockId::Number(n) => return Ok(n as u64),
+            BlockId::Hash(hash) => sqlx::query("SELECT height FROM header WHERE hash = $1")
+                .bind(hash.to_string())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(query_error)?,
+            BlockId::PayloadHash(hash) => sqlx::query(
+                "SELECT height FROM header WHERE payload_hash = $1 ORDER BY height LIMIT 1",
+            )
+            .bind(hash.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(query_error)?,
+        };
+        let height: i64 = row
+            .ok_or(QueryError::NotFound)?
+            .try_get("height")
+            .map_err(query_error)?;
+        Ok(height as u64)
+    }
+
+    async fn leaf_height_of(&self, id: LeafId<SeqTypes>) -> QueryResult<u64> {
+        match id {
+            LeafId::Number(n) => Ok(n as u64),
+            LeafId::Hash(hash) => {
+                let height: i64 = sqlx::query("SELECT height FROM leaf WHERE hash = $1")
+                    .bind(hash.to_string())
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(query_error)?
+                    .ok_or(QueryError::NotFound)?
+                    .try_get("height")
+                    .map_err(query_error)?;
+                Ok(height as u64)
+            }
+        }
+    }
+
+    /// Load a single binary-encoded object from `table` at the given height.
+    async fn load_at<T: serde::de::DeserializeOwned>(
+        &self,
+        table: &str,
+        column: &str,
+        height: u64,
+    ) -> QueryResult<T> {
+        let row = sqlx::query(&format!("SELECT {column} FROM {table} WHERE height = $1"))
+            .bind(height as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(query_error)?
+            .ok_or(QueryError::Missing)?;
+        decode(&row, column)
+    }
+
+    /// Load a range of binary-encoded objects from `table`.
+    ///
+    /// The result contains an entry for every height in the range which is less than the current
+    /// block height. Heights for which we have no data are reported as missing.
+    async fn load_range<T, R>(
+        &self,
+        table: &str,
+        column: &str,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<T>>>
+    where
+        T: serde::de::DeserializeOwned,
+        R: RangeBounds<usize> + Send,
+    {
+        let (start, end) = bounds(&range, self.block_height().await?);
+        if start >= end {
+            return Ok(vec![]);
+        }
+        let rows = sqlx::query(&format!(
+            "SELECT height, {column} FROM {table}
+              WHERE height >= $1 AND height < $2
+              ORDER BY height"
+        ))
+        .bind(start as i64)
+        .bind(end as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(query_error)?;
+
+        let mut rows = rows.into_iter().peekable();
+        Ok((start..end)
+            .map(|height| {
+                let row = rows.next_if(|row| {
+                    row.try_get::<i64, _>("height")
+                        .is_ok_and(|h| h as usize == height)
+                });
+                match row {
+                    Some(row) => decode(&row, column),
+                    None => Err(QueryError::Missing),
+                }
+            })
+            .collect())
+    }
+
+    async fn upsert_header(&mut self, header: &Header) -> Result<(), sqlx::Error> {
+        let data =
+            serde_json::to_string(header).map_err(|err| sqlx::Error::Encode(Box::new(err)))?;
+        let tx = self.transaction().await?;
+        sqlx::query(
+            "INSERT INTO header (height, hash, payload_hash, timestamp, data)
+                  VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (height) DO NOTHING",
+        )
+        .bind(header.height() as i64)
+        .bind(header.commit().to_string())
+        .bind(header.payload_commitment().to_string())
+        .bind(header.timestamp() as i64)
+        .bind(data)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Find the block height corresponding to a merklized state snapshot.
+    async fn snapshot_height<State, const ARITY: usize>(
+        &self,
+        snapshot: Snapshot<SeqTypes, State, ARITY>,
+    ) -> QueryResult<u64>
+    where
+        State: MerklizedState<SeqTypes, ARITY>,
+    {
+        let height = match snapshot {
+            Snapshot::Index(height) => height,
+            Snapshot::Commit(commit) => {
+                let row = sqlx::query(&format!(
+                    "SELECT height FROM header WHERE {} = $1 ORDER BY height DESC LIMIT 1",
+                    State::header_state_commitment_field()
+                ))
+                .bind(commit.to_string())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(query_error)?
+                .ok_or(QueryError::NotFound)?;
+                row.try_get::<i64, _>("height").map_err(query_error)? as u64
+            }
+        };
+
+        let last_height = self.get_last_state_height().await? as u64;
+        if height > last_height {
+            return Err(QueryError::Error {
+                message: format!(
+                    "state at height {height} is not yet available (last state height is \
+                     {last_height})"
+                ),
+            });
+        }
+        Ok(height)
+    }
+}
+
+#[async_trait]
+impl VersionedDataSource for SqliteStorage {
+    type Error = sqlx::Error;
+
+    async fn commit(&mut self) -> Result<(), Self::Error> {
+        if let Some(tx) = self.tx.take() {
+            tx.commit().await?;
+        }
+        self.explorer.commit();
+        Ok(())
+    }
+
+    async fn revert(&mut self) {
+        if let Some(tx) = self.tx.take() {
+            if let Err(err) = tx.rollback().await {
+                tracing::error!("failed to roll back transaction: {err:#}");
+            }
+        }
+        self.explorer.revert();
+    }
+}
+
+#[async_trait]
+impl AvailabilityStorage<SeqTypes> for SqliteStorage {
+    async fn get_leaf(&self, id: LeafId<SeqTypes>) -> QueryResult<LeafQueryData<SeqTypes>> {
+        let height = self.leaf_height_of(id).await?;
+        self.load_at("leaf", "data", height).await
+    }
+
+    async fn get_block(&self, id: BlockId<SeqTypes>) -> QueryResult<BlockQueryData<SeqTypes>> {
+        let height = self.block_height_of(id).await?;
+        self.load_at("payload", "data", height).await
+    }
+
+    async fn get_header(&self, id: BlockId<SeqTypes>) -> QueryResult<Header> {
+        let height = self.block_height_of(id).await?;
+        let row = sqlx::query("SELECT data FROM header WHERE height = $1")
+            .bind(height as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(query_error)?
+            .ok_or(QueryError::Missing)?;
+        let data: String = row.try_get("data").map_err(query_error)?;
+        serde_json::from_str(&data).map_err(query_error)
+    }
+
+    async fn get_payload(&self, id: BlockId<SeqTypes>) -> QueryResult<PayloadQueryData<SeqTypes>> {
+        self.get_block(id).await.map(PayloadQueryData::from)
+    }
+
+    async fn get_vid_common(
+        &self,
+        id: BlockId<SeqTypes>,
+    ) -> QueryResult<VidCommonQueryData<SeqTypes>> {
+        let height = self.block_height_of(id).await?;
+        self.load_at("vid", "common", height).await
+    }
+
+    async fn get_leaf_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<LeafQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.load_range("leaf", "data", range).await
+    }
+
+    async fn get_block_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<BlockQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.load_range("payload", "data", range).await
+    }
+
+    async fn get_payload_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<PayloadQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        Ok(self
+            .get_block_range(range)
+            .await?
+            .into_iter()
+            .map(|block| block.map(PayloadQueryData::from))
+            .collect())
+    }
+
+    async fn get_vid_common_range<R>(
+        &self,
+        range: R,
+    ) -> QueryResult<Vec<QueryResult<VidCommonQueryData<SeqTypes>>>>
+    where
+        R: RangeBounds<usize> + Send + 'static,
+    {
+        self.load_range("vid", "common", range).await
+    }
+
+    async fn get_transaction(
+        &self,
+        hash: TransactionHash<SeqTypes>,
+    ) -> QueryResult<BlockQueryData<SeqTypes>> {
+        let row = sqlx::query(
+            "SELECT block_height FROM transactions WHERE hash = $1
+              ORDER BY block_height LIMIT 1",
+        )
+        .bind(hash.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(query_error)?
+        .ok_or(QueryError::NotFound)?;
+        let height: i64 = row.try_get("block_height").map_err(query_error)?;
+        self.load_at("payload", "data", height as u64).await
+    }
+}
+
+#[async_trait]
+impl UpdateAvailabilityData<SeqTypes> for SqliteStorage {
+    type Error = sqlx::Error;
+
+    async fn insert_leaf(&mut self, leaf: LeafQueryData<SeqTypes>) -> Result<(), Self::Error> {
+        self.upsert_header(leaf.header()).await?;
+
+        let data = bincode::serialize(&leaf).map_err(|err| sqlx::Error::Encode(err.into()))?;
+        let tx = self.transaction().await?;
+        sqlx::query(
+            "INSERT INTO leaf (height, hash, block_hash, data) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (height) DO NOTHING",
+        )
+        .bind(leaf.height() as i64)
+        .bind(leaf.hash().to_string())
+        .bind(leaf.block_hash().to_string())
+        .bind(data)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert_block(&mut self, block: BlockQueryData<SeqTypes>) -> Result<(), Self::Error> {
+        self.upsert_header(block.header()).await?;
+
+        let data = bincode::serialize(&block).map_err(|err| sqlx::Error::Encode(err.into()))?;
+        let height = block.height() as i64;
+        let tx = self.transaction().await?;
+        sqlx::query(
+            "INSERT INTO payload (height, size, num_transactions, data) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (height) DO NOTHING",
+        )
+        .bind(height)
+        .bind(block.size() as i64)
+        .bind(block.num_transactions() as i64)
+        .bind(data)
+        .execute(&mut **tx)
+        .await?;
+
+        for (index, txn) in block.enumerate() {
+            let index =
+                bincode::serialize(&index).map_err(|err| sqlx::Error::Encode(err.into()))?;
+            sqlx::query(
+                "INSERT INTO transactions (hash, block_height, idx) VALUES ($1, $2, $3)
+                 ON CONFLICT (block_height, idx) DO NOTHING",
+            )
+            .bind(txn.commit().to_string())
+            .bind(height)
+            .bind(index)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        self.explorer.insert(block);
+        Ok(())
+    }
+
+    async fn insert_vid(
+        &mut self,
+        common: VidCommonQueryData<SeqTypes>,
+        share: Option<VidShare>,
+    ) -> Result<(), Self::Error> {
+        let height = common.height() as i64;
+        let common = bincode::serialize(&common).map_err(|err| sqlx::Error::Encode(err.into()))?;
+        let share = share
+            .map(|share| bincode::serialize(&share))
+            .transpose()
+            .map_err(|err| sqlx::Error::Encode(err.into()))?;
+        let tx = self.transaction().await?;
+
+        // If we already have a VID share for this block, don't overwrite it with `None`.
+        sqlx::query(
+            "INSERT INTO vid (height, common, share) VALUES ($1, $2, $3)
+             ON CONFLICT (height) DO UPDATE SET share = coalesce(excluded.share, vid.share)",
+        )
+        .bind(height)
+        .bind(common)
+        .bind(share)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NodeStorage<SeqTypes> for SqliteStorage {
+    async fn block_height(&self) -> QueryResult<usize> {
+        let height: Option<i64> = sqlx::query("SELECT max(height) AS height FROM leaf")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(query_error)?
+            .try_get("height")
+            .map_err(query_error)?;
+        Ok(height.map(|h| h as usize + 1).unwrap_or(0))
+    }
+
+    async fn count_transactions(&self) -> QueryResult<usize> {
+        let count: i64 = sqlx::query("SELECT count(*) AS count FROM transactions")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(query_error)?
+            .try_get("count")
+            .map_err(query_error)?;
+        Ok(count as usize)
+    }
+
+    async fn payload_size(&self) -> QueryResult<usize> {
+        let size: i64 = sqlx::query("SELECT coalesce(sum(size), 0) AS size FROM payload")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(query_error)?
+            .try_get("size")
+            .map_err(query_error)?;
+        Ok(size as usize)
+    }
+
+    async fn vid_share<ID>(&self, id: ID) -> QueryResult<VidShare>
+    where
+        ID: Into<BlockId<SeqTypes>> + Send + Sync,
+    {
+        let height = self.block_height_of(id.into()).await?;
+        let row = sqlx::query("SELECT share FROM vid WHERE height = $1")
+            .bind(height as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(query_error)?
+            .ok_or(QueryError::Missing)?;
+        // The share column is NULL if we have the VID common data for this block but not our
+        // share.
+        let bytes: Option<Vec<u8>> = row.try_get("share").map_err(query_error)?;
+        bincode::deserialize(&bytes.ok_or(QueryError::Missing)?).map_err(query_error)
+    }
+
+    async fn get_header_window(
+        &self,
+        start: impl Into<WindowStart<SeqTypes>> + Send + Sync,
+        end: u64,
+    ) -> QueryResult<TimeWindowQueryData<Header>> {
+        let first_height = match start.into() {
+            WindowStart::Height(height) => height,
+            WindowStart::Hash(hash) => self.block_height_of(BlockId::Hash(hash)).await?,
+            WindowStart::Time(time) => {
+                match sqlx::query(
+                    "SELECT height FROM header WHERE timestamp >= $1 ORDER BY height LIMIT 1",
+                )
+                .bind(time as i64)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(query_error)?
+                {
+                    Some(row) => row.try_get::<i64, _>("height").map_err(query_error)? as u64,
+                    // No headers in the window yet; start after the last header we have.
+                    None => self.block_height().await? as u64,
+                }
+            }
+        };
+
+        let decode_header = |row: SqliteRow| -> QueryResult<Header> {
+            let data: String = row.try_get("data").map_err(query_error)?;
+            serde_json::from_str(&data).map_err(query_error)
+        };
+
+        let window = sqlx::query(
+            "SELECT data FROM header WHERE height >= $1 AND timestamp < $2 ORDER BY height",
+        )
+        .bind(first_height as i64)
+        .bind(end as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(decode_header)
+        .collect::<QueryResult<Vec<_>>>()?;
+
+        let prev = match first_height.checked_sub(1) {
+            Some(height) => sqlx::query("SELECT data FROM header WHERE height = $1")
+                .bind(height as i64)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(query_error)?
+                .map(decode_header)
+                .transpose()?,
+            None => None,
+        };
+        let next = sqlx::query(
+            "SELECT data FROM header WHERE height >= $1 AND timestamp >= $2
+              ORDER BY height LIMIT 1",
+        )
+        .bind(first_height as i64)
+        .bind(end as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(query_error)?
+        .map(decode_header)
+        .transpose()?;
+
+        Ok(TimeWindowQueryData { window, prev, next })
+    }
+}
+
+impl PruneStorage for SqliteStorage {}
+
+#[async_trait]
+impl ExplorerStorage<SeqTypes> for SqliteStorage {
+    async fn get_block_detail(
+        &self,
+        request: BlockIdentifier<SeqTypes>,
+    ) -> Result<BlockDetail<SeqTypes>, GetBlockDetailError> {
+        explorer::get_block_detail(self, request).await
+    }
+
+    async fn get_block_summaries(
+        &self,
+        request: GetBlockSummariesRequest<SeqTypes>,
+    ) -> Result<Vec<BlockSummary<SeqTypes>>, GetBlockSummariesError> {
+        explorer::get_block_summaries(self, request).await
+    }
+
+    async fn get_transaction_detail(
+        &self,
+        request: TransactionIdentifier<SeqTypes>,
+    ) -> Result<TransactionDetailResponse<SeqTypes>, GetTransactionDetailError> {
+        explorer::get_transaction_detail(self, request).await
+    }
+
+    async fn get_transaction_summaries(
+        &self,
+        request: GetTransactionSummariesRequest<SeqTypes>,
+    ) -> Result<Vec<TransactionSummary<SeqTypes>>, GetTransactionSummariesError> {
+        explorer::get_transaction_summaries(self, request).await
+    }
+
+    async fn get_explorer_summary(
+        &self,
+    ) -> Result<ExplorerSummary<SeqTypes>, GetExplorerSummaryError> {
+        explorer::get_explorer_summary(self).await
+    }
+
+    async fn get_search_results(
+        &self,
+        query: TaggedBase64,
+    ) -> Result<SearchResult<SeqTypes>, GetSearchResultsError> {
+        explorer::get_search_results(self, query).await
+    }
+}
+
+impl IndexedStorage for SqliteStorage {
+    fn explorer_index(&self) -> &ExplorerIndex {
+        &self.explorer
+    }
+}
+
+#[async_trait]
+impl NodeLoader for SqliteStorage {
+    async fn load_node(
+        &self,
+        tree: &str,
+        path: &[usize],
+        height: u64,
+    ) -> QueryResult<Option<StoredNode>> {
+        let Some(row) = sqlx::query(&format!(
+            "SELECT hash, children, idx, entry FROM {tree}
+              WHERE path = $1 AND created <= $2
+              ORDER BY created DESC LIMIT 1"
+        ))
+        .bind(encode_path(path))
+        .bind(height as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(query_error)?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(StoredNode {
+            hash: row.try_get("hash").map_err(query_error)?,
+            children: row.try_get("children").map_err(query_error)?,
+            idx: row.try_get("idx").map_err(query_error)?,
+            entry: row.try_get("entry").map_err(query_error)?,
+        }))
+    }
+}
+
+#[async_trait]
+impl MerklizedStateHeightPersistence for SqliteStorage {
+    async fn set_last_state_height(&mut self, height: usize) -> QueryResult<()> {
+        let tx = self.transaction().await.map_err(query_error)?;
+        sqlx::query(
+            "INSERT INTO last_merklized_state_height (id, height) VALUES (0, $1)
+             ON CONFLICT (id) DO UPDATE SET height = excluded.height",
+        )
+        .bind(height as i64)
+        .execute(&mut **tx)
+        .await
+        .map_err(query_error)?;
+        Ok(())
+    }
+
+    async fn get_last_state_height(&self) -> QueryResult<usize> {
+        let height = sqlx::query("SELECT height FROM last_merklized_state_height WHERE id = 0")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(query_error)?
+            .map(|row| row.try_get::<i64, _>("height"))
+            .transpose()
+            .map_err(query_error)?;
+        Ok(height.unwrap_or(0) as usize)
+    }
+}
+
+#[async_trait]
+impl<State, const ARITY: usize> UpdateStateData<SeqTypes, State, ARITY> for SqliteStorage
+where
+    State: MerklizedState<SeqTypes, ARITY> + 'static,
+{
+    async fn insert_merkle_nodes(
+        &mut self,
+        proof: MerkleProof<State::Entry, State::Key, State::T, ARITY>,
+        traversal_path: Vec<usize>,
+        block_number: u64,
+    ) -> QueryResult<()> {
+        let nodes = proof_nodes::<State, ARITY>(proof, &traversal_path)?;
+
+        let stmt = format!(
+            "INSERT INTO {} (path, created, hash, children, idx, entry)
+                  VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (path, created) DO UPDATE SET
+                hash = excluded.hash,
+                children = excluded.children,
+                idx = excluded.idx,
+                entry = excluded.entry",
+            State::state_type()
+        );
+        let tx = self.transaction().await.map_err(query_error)?;
+        for (path, node) in nodes {
+            sqlx::query(&stmt)
+                .bind(encode_path(&path))
+                .bind(block_number as i64)
+                .bind(node.hash)
+                .bind(node.children)
+                .bind(node.idx)
+                .bind(node.entry)
+                .execute(&mut **tx)
+                .await
+                .map_err(query_error)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<State, const ARITY: usize> MerklizedStateDataSource<SeqTypes, State, ARITY> for SqliteStorage
+where
+    State: MerklizedState<SeqTypes, ARITY> + 'static,
+{
+    async fn get_path(
+        &self,
+        snapshot: Snapshot<SeqTypes, State, ARITY>,
+        key: State::Key,
+    ) -> QueryResult<MerkleProof<State::Entry, State::Key, State::T, ARITY>> {
+        let height = self.snapshot_height(snapshot).await?;
+        reconstruct_proof::<State, ARITY>(self, height, key).await
+    }
+}
+
+#[async_trait]
+impl ChainConfigPersistence for SqliteStorage {
+    async fn insert_chain_config(&mut self, chain_config: ChainConfig) -> anyhow::Result<()> {
+        let commitment = chain_config.commitment();
+        let data = bincode::serialize(&chain_config)?;
+
+        // Chain configs are immutable and content-addressed, so there is no need to include this
+        // write in the pending transaction; we can commit it right away.
+        sqlx::query(
+            "INSERT INTO chain_config (commitment, data) VALUES ($1, $2)
+             ON CONFLICT (commitment) DO NOTHING",
+        )
+        .bind(commitment.to_string())
+        .bind(data)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        let row = sqlx::query("SELECT data FROM chain_config WHERE commitment = $1")
+            .bind(commitment.to_string())
+            .fetch_optional(&self.pool)
+            .await?
+            .context(format!("chain config {commitment} not found"))?;
+        let data: Vec<u8> = row.try_get("data")?;
+        bincode::deserialize(&data[..]).context("failed to deserialize")
+    }
+}
+
+impl CatchupDataSource for SqliteStorage {
+    async fn get_account(
+        &self,
+        height: u64,
+        _view: ViewNumber,
+        account: Address,
+    ) -> anyhow::Result<AccountQueryData> {
+        merkle_nodes::get_account(self, height, account).await
+    }
+
+    async fn get_frontier(&self, height: u64, _view: ViewNumber) -> anyhow::Result<BlocksFrontier> {
+        merkle_nodes::get_frontier(self, height).await
+    }
+
+    async fn get_chain_config(
+        &self,
+        commitment: Commitment<ChainConfig>,
+    ) -> anyhow::Result<ChainConfig> {
+        self.load_chain_config(commitment).await
+    }
+}
+
+fn decode<T: serde::de::DeserializeOwned>(row: &SqliteRow, column: &str) -> QueryResult<T> {
+    let bytes: Vec<u8> = row.try_get(column).map_err(query_error)?;
+    bincode::deserialize(&bytes).map_err(query_error)
+}
+
+/// Convert `range` to a half-open interval, truncated to `block_height`.
+fn bounds(range: &impl RangeBounds<usize>, block_height: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&n) => n,
+        Bound::Excluded(&n) => n + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&n) => n + 1,
+        Bound::Excluded(&n) => n,
+        Bound::Unbounded => block_height,
+    };
+    (start, end.min(block_height))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(bounds(&(..), 10), (0, 10));
+        assert_eq!(bounds(&(2..5), 10), (2, 5));
+        assert_eq!(bounds(&(2..=5), 10), (2, 6));
+        assert_eq!(bounds(&(8..20), 10), (8, 10));
+        assert_eq!(bounds(&(12..), 10), (12, 10));
+    }
+}
@@ -0,0 +1,114 @@
+//! Utility program to check the consensus storage of a sequencer for corruption.
+
+use std::process::exit;
+
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::{Parser, Subcommand};
+use espresso_types::v0::traits::PersistenceOptions;
+use sequencer::persistence::{
+    self,
+    integrity::{check, CheckablePersistence, Severity},
+};
+
+/// Check the consensus storage of a sequencer for corruption.
+///
+/// Every stored leaf, QC, DA proposal, VID share and quorum proposal is decoded and checked for
+/// consistency with its commitments, its signature and the view it is stored under. Any anomalies
+/// are reported, and the program exits with a non-zero status if any errors are found.
+///
+/// With --quarantine, entries with errors are removed from storage (but preserved for inspection:
+/// in the `quarantine` directory for file system storage, or the `quarantine` table for SQL
+/// storage), so that the node can start and recover the missing data from its peers.
+///
+/// Do not run this program while the sequencer is running.
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    /// Quarantine entries with errors.
+    #[clap(long, env = "ESPRESSO_CHECK_STORAGE_QUARANTINE")]
+    quarantine: bool,
+
+    /// Also quarantine entries with warnings, such as leftover temporary files.
+    #[clap(
+        long,
+        env = "ESPRESSO_CHECK_STORAGE_QUARANTINE_WARNINGS",
+        requires = "quarantine"
+    )]
+    quarantine_warnings: bool,
+
+    #[clap(subcommand)]
+    storage: Storage,
+}
+
+/// The storage to check.
+#[derive(Clone, Debug, Subcommand)]
+enum Storage {
+    /// Check file system storage.
+    Fs(persistence::fs::Options),
+    /// Check SQL storage.
+    Sql(Box<persistence::sql::Options>),
+    /// Check SQLite storage.
+    #[cfg(feature = "sqlite")]
+    Sqlite(persistence::sqlite::Options),
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    let ok = match opt.storage.clone() {
+        Storage::Fs(storage) => {
+            tracing::info!("checking file system storage {storage:?}");
+            check_storage(&opt, storage).await?
+        }
+        Storage::Sql(storage) => {
+            tracing::info!("checking SQL storage {storage:?}");
+            check_storage(&opt, *storage).await?
+        }
+        #[cfg(feature = "sqlite")]
+        Storage::Sqlite(storage) => {
+            tracing::info!("checking SQLite storage {storage:?}");
+            check_storage(&opt, storage).await?
+        }
+    };
+    if !ok {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// Check storage, returning whether it is free of errors once we are done.
+async fn check_storage<O>(opt: &Options, storage: O) -> anyhow::Result<bool>
+where
+    O: PersistenceOptions,
+    O::Persistence: CheckablePersistence,
+{
+    let mut storage = storage.create().await?;
+    let anomalies = check(&storage).await?;
+    tracing::info!(anomalies = anomalies.len(), "checked storage");
+
+    let mut ok = true;
+    for anomaly in anomalies {
+        match anomaly.severity {
+            Severity::Warning => tracing::warn!("{anomaly}"),
+            Severity::Error => tracing::error!("{anomaly}"),
+        }
+
+        let quarantine = match anomaly.severity {
+            Severity::Warning => opt.quarantine_warnings,
+            Severity::Error => opt.quarantine,
+        };
+        if quarantine {
+            let entry = storage.entry(&anomaly.id).await?;
+            storage.quarantine(&entry, &anomaly.reason).await?;
+        } else if anomaly.severity == Severity::Error {
+            ok = false;
+        }
+    }
+
+    if !ok {
+        tracing::error!("storage has errors, run with --quarantine to remove the affected entries");
+    }
+    Ok(ok)
+}
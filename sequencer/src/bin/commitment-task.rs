@@ -1,16 +1,20 @@
-use std::{io, time::Duration};
+use std::{io, sync::Arc, time::Duration};
 
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use async_std::task::spawn;
 use clap::Parser;
-use es_version::SEQUENCER_VERSION;
+use contract_bindings::light_client::LightClient;
+use es_version::{SequencerVersion, SEQUENCER_VERSION};
 use ethers::prelude::*;
 use futures::FutureExt;
 use sequencer::{
-    hotshot_commitment::{run_hotshot_commitment_task, CommitmentTaskOptions},
+    hotshot_commitment::{
+        run_hotshot_commitment_task, CommitmentTaskMode, CommitmentTaskOptions, HotShotClient,
+        LightClientReader,
+    },
     options::parse_duration,
 };
-use tide_disco::{error::ServerError, Api};
+use tide_disco::{error::ServerError, Api, Error as _, StatusCode};
 use url::Url;
 use vbs::version::StaticVersionType;
 
@@ -25,16 +29,48 @@ pub struct Options {
     #[clap(long, env = "ESPRESSO_SEQUENCER_L1_PROVIDER")]
     pub l1_provider: Url,
 
+    /// Where to publish block commitments.
+    ///
+    /// In `legacy` mode, the commitment of every block is posted to the HotShot contract. In
+    /// `light-client` mode, nothing is written to layer 1: snapshots are published only through the
+    /// LightClient contract, by the state prover.
+    #[clap(
+        long,
+        env = "ESPRESSO_COMMITMENT_TASK_MODE",
+        value_enum,
+        default_value_t = CommitmentTaskMode::Legacy
+    )]
+    pub mode: CommitmentTaskMode,
+
     /// Address of the HotShot contract on layer 1.
-    #[clap(long, env = "ESPRESSO_SEQUENCER_HOTSHOT_ADDRESS")]
-    pub hotshot_address: Address,
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_HOTSHOT_ADDRESS",
+        required_if_eq("mode", "legacy")
+    )]
+    pub hotshot_address: Option<Address>,
+
+    /// Address of the LightClient contract on layer 1.
+    ///
+    /// If provided, the HTTP server also answers the queries of the legacy HotShot contract (block
+    /// height and block commitments) from the LightClient contract.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_LIGHTCLIENT_ADDRESS",
+        required_if_eq("mode", "light-client")
+    )]
+    pub light_client_address: Option<Address>,
 
     /// Mnemonic phrase for the commitment task  wallet.
     ///
     /// This is the wallet that will be used to send commitments to the HotShot contract. It must be
-    /// funded with ETH on the layer 1.
-    #[clap(long, env = "ESPRESSO_SEQUENCER_ETH_MNEMONIC")]
-    pub eth_mnemonic: String,
+    /// funded with ETH on the layer 1. Required in `legacy` mode.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_ETH_MNEMONIC",
+        required_if_eq("mode", "legacy")
+    )]
+    pub eth_mnemonic: Option<String>,
 
     /// Index of a funded account derived from mnemonic, designating the account that will send
     /// commitments to the HotShot contract.
@@ -47,7 +83,9 @@ pub struct Options {
 
     /// If provided, the service will run a basic HTTP server on the given port.
     ///
-    /// The server provides healthcheck and version endpoints.
+    /// The server provides healthcheck and version endpoints, and the address of the HotShot
+    /// contract. If a LightClient contract is configured, it also answers legacy HotShot contract
+    /// queries.
     #[clap(short, long, env = "ESPRESSO_COMMITMENT_TASK_PORT")]
     pub port: Option<u16>,
 
@@ -67,11 +105,24 @@ async fn main() {
     let opt = Options::parse();
 
     if let Some(port) = opt.port {
-        start_http_server(port, opt.hotshot_address, SEQUENCER_VERSION).unwrap();
+        let reader = opt.light_client_address.map(|address| {
+            let provider = Provider::<Http>::try_from(opt.l1_provider.to_string()).unwrap();
+            let query_service =
+                HotShotClient::<SequencerVersion>::builder(opt.sequencer_url.clone())
+                    .set_timeout(Some(opt.request_timeout))
+                    .build();
+            Arc::new(LightClientReader::new(
+                LightClient::new(address, Arc::new(provider)),
+                query_service,
+            ))
+        });
+        start_http_server(port, opt.hotshot_address, reader, SEQUENCER_VERSION).unwrap();
     }
 
     let hotshot_contract_options = CommitmentTaskOptions {
+        mode: opt.mode,
         hotshot_address: opt.hotshot_address,
+        light_client_address: opt.light_client_address,
         l1_chain_id: None,
         l1_provider: opt.l1_provider.clone(),
         delay: opt.delay,
@@ -84,9 +135,12 @@ async fn main() {
     run_hotshot_commitment_task::<es_version::SequencerVersion>(&hotshot_contract_options).await;
 }
 
+type Reader = LightClientReader<Provider<Http>, HotShotClient<SequencerVersion>>;
+
 fn start_http_server<Ver: StaticVersionType + 'static>(
     port: u16,
-    hotshot_address: Address,
+    hotshot_address: Option<Address>,
+    reader: Option<Arc<Reader>>,
     bind_version: Ver,
 ) -> io::Result<()> {
     let mut app = tide_disco::App::<(), ServerError>::with_state(());
@@ -97,10 +151,45 @@ fn start_http_server<Ver: StaticVersionType + 'static>(
         .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
     api.get("gethotshotcontract", move |_, _| {
-        async move { Ok(hotshot_address) }.boxed()
+        async move {
+            hotshot_address.ok_or_else(|| {
+                ServerError::catch_all(
+                    StatusCode::NOT_FOUND,
+                    "no HotShot contract configured".into(),
+                )
+            })
+        }
+        .boxed()
     })
     .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
+    if let Some(reader) = reader {
+        let height_reader = reader.clone();
+        api.get("getblockheight", move |_, _| {
+            let reader = height_reader.clone();
+            async move {
+                reader.block_height().await.map_err(|err| {
+                    ServerError::catch_all(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+                })
+            }
+            .boxed()
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+        .get("getcommitment", move |req, _| {
+            let reader = reader.clone();
+            async move {
+                let height = req
+                    .integer_param("height")
+                    .map_err(ServerError::from_request_error)?;
+                reader.commitment(height).await.map_err(|err| {
+                    ServerError::catch_all(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+                })
+            }
+            .boxed()
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    }
+
     app.register_module("api", api)
         .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
 
@@ -126,7 +215,7 @@ mod test {
         let expected_addr = "0xED15E1FE0789c524398137a066ceb2EF9884E5D8"
             .parse::<Address>()
             .unwrap();
-        start_http_server(port, expected_addr, SEQUENCER_VERSION)
+        start_http_server(port, Some(expected_addr), None, SEQUENCER_VERSION)
             .expect("Failed to start the server");
 
         let client: Client<ServerError, SequencerVersion> =
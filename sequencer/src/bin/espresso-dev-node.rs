@@ -1,4 +1,4 @@
-use std::{sync::Arc, time::Duration};
+use std::time::Duration;
 
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use async_std::task::{sleep, spawn, spawn_blocking};
@@ -9,8 +9,11 @@ use ethers::{
     signers::{coins_bip39::English, MnemonicBuilder, Signer},
 };
 use futures::FutureExt;
-use hotshot_state_prover::service::{
-    load_proving_key, one_honest_threshold, sync_state, StateProverConfig,
+use hotshot_state_prover::{
+    keys::{ProverKeyRegistry, ProverKeys},
+    queue::ProofQueue,
+    service::{one_honest_threshold, sync_state, StakeTableHistory, StateProverConfig},
+    status::SignatureWait,
 };
 use hotshot_types::traits::stake_table::{SnapshotVersion, StakeTableScheme};
 use portpicker::pick_unused_port;
@@ -71,6 +74,14 @@ struct Args {
 
     #[clap(flatten)]
     sql: persistence::sql::Options,
+
+    /// Store query data in an embedded SQLite database at this path instead of in Postgres.
+    ///
+    /// When this is set, the dev node does not need a Postgres database, and the SQL options are
+    /// ignored.
+    #[cfg(feature = "sqlite")]
+    #[clap(long, env = "ESPRESSO_DEV_NODE_SQLITE_PATH")]
+    sqlite_path: Option<std::path::PathBuf>,
 }
 
 #[async_std::main]
@@ -85,8 +96,17 @@ async fn main() -> anyhow::Result<()> {
     })
     .status(Default::default())
     .state(Default::default())
-    .submit(Default::default())
-    .query_sql(Default::default(), cli_params.sql);
+    .submit(Default::default());
+
+    #[cfg(feature = "sqlite")]
+    let api_options = match cli_params.sqlite_path {
+        Some(path) => {
+            api_options.query_sqlite(Default::default(), persistence::sqlite::Options::new(path))
+        }
+        None => api_options.query_sql(Default::default(), cli_params.sql),
+    };
+    #[cfg(not(feature = "sqlite"))]
+    let api_options = api_options.query_sql(Default::default(), cli_params.sql);
 
     let (url, _anvil) = if let Some(url) = cli_params.rpc_url {
         (url, None)
@@ -147,13 +167,15 @@ async fn main() -> anyhow::Result<()> {
             .unwrap(),
         SEQUENCER_VERSION,
     ));
+    let mut stake_tables = StakeTableHistory::new(STAKE_TABLE_CAPACITY_FOR_TEST as usize, st);
 
     // Run the prover service. These code are basically from `hotshot-state-prover`. The difference
     // is that here we don't need to fetch the `stake table` from other entities.
     // TODO: Remove the redundant code.
-    let proving_key =
-        spawn_blocking(move || Arc::new(load_proving_key(STAKE_TABLE_CAPACITY_FOR_TEST as usize)))
-            .await;
+    let keys = spawn_blocking(move || {
+        ProverKeyRegistry::from(ProverKeys::generate(STAKE_TABLE_CAPACITY_FOR_TEST as usize))
+    })
+    .await;
     let relay_server_client =
         Client::<ServerError, SequencerVersion>::new(relay_server_url.clone());
 
@@ -180,12 +202,29 @@ async fn main() -> anyhow::Result<()> {
             .signer()
             .clone(),
         sequencer_url: "http://localhost".parse().unwrap(), // This should not be used in dev-node
+        stake_table_address: None,
+        stake_table_deployment_block: 0,
         port: None,
         stake_table_capacity: STAKE_TABLE_CAPACITY_FOR_TEST as usize,
+        proving_key_path: None,
+        escape_hatch_threshold: None,
+        proof_queue_path: None,
+        submission_timeout: Duration::from_secs(60),
+        rotation: None,
     };
 
+    let mut queue = ProofQueue::in_memory();
+    let mut signature_wait = SignatureWait::default();
     loop {
-        if let Err(err) = sync_state(&st, proving_key.clone(), &relay_server_client, &config).await
+        if let Err(err) = sync_state(
+            &mut stake_tables,
+            &mut signature_wait,
+            &keys,
+            &relay_server_client,
+            &mut queue,
+            &config,
+        )
+        .await
         {
             tracing::error!("Cannot sync the light client state, will retry: {}", err);
             sleep(retry_interval).await;
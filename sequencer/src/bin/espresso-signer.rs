@@ -0,0 +1,90 @@
+//! A signer which holds a node's keys in an isolated process.
+
+use std::{net::IpAddr, path::PathBuf};
+
+use anyhow::{ensure, Context};
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use es_version::SEQUENCER_VERSION;
+use sequencer::{
+    keystore::PassphraseOptions,
+    signer::{
+        server::{self, run_signer_server},
+        slashing::SlashingProtection,
+        LocalSigner, Signer,
+    },
+};
+
+/// Run a signer for the keys in a keystore.
+///
+/// The signer serves signing requests over HTTP, and refuses to sign anything which could be used
+/// as evidence of double signing. Its slashing protection database must be kept for as long as the
+/// keys are in use; deleting it removes the protection for views signed before it was deleted.
+#[derive(Parser)]
+struct Args {
+    /// Address to bind the server to.
+    ///
+    /// Anyone who can reach the signer can request signatures, so by default it only listens on the
+    /// loopback interface. Binding to any other address requires an auth token.
+    #[clap(
+        long,
+        env = "ESPRESSO_SIGNER_BIND_ADDRESS",
+        default_value = "127.0.0.1"
+    )]
+    bind_address: IpAddr,
+
+    /// Port to run the server on.
+    #[clap(short, long, env = "ESPRESSO_SIGNER_PORT", default_value = "8090")]
+    port: u16,
+
+    /// Keystore containing the keys to sign with.
+    #[clap(long, env = "ESPRESSO_SIGNER_KEYSTORE")]
+    keystore: PathBuf,
+
+    #[clap(flatten)]
+    passphrase: PassphraseOptions,
+
+    /// Path to the slashing protection database.
+    ///
+    /// The database is created if it does not exist.
+    #[clap(long, env = "ESPRESSO_SIGNER_SLASHING_PROTECTION_PATH")]
+    slashing_protection_path: PathBuf,
+
+    #[clap(flatten)]
+    server: server::Options,
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let args = Args::parse();
+    ensure!(
+        args.bind_address.is_loopback() || args.server.auth_token.is_some(),
+        "an auth token is required to bind the signer to non-loopback address {}",
+        args.bind_address
+    );
+    let keys = args
+        .passphrase
+        .unlock(&args.keystore)
+        .context(format!("unlocking keystore {}", args.keystore.display()))?;
+    let protection = SlashingProtection::open(args.slashing_protection_path)?;
+    let signer = LocalSigner::new(keys).with_slashing_protection(protection);
+
+    let keys = signer.keys().await?;
+    tracing::info!(
+        port = args.port,
+        state = ?keys.state,
+        fee_account = ?keys.fee_account,
+        "starting signer"
+    );
+    run_signer_server(
+        signer,
+        format!("http://{}:{}", args.bind_address, args.port).parse()?,
+        args.server,
+        SEQUENCER_VERSION,
+    )
+    .await?;
+    Ok(())
+}
@@ -1,20 +1,23 @@
 //! Utility program to generate keypairs
 
-use std::{
-    fs::{self, File},
-    io::Write,
-    path::PathBuf,
-};
+use std::{fs, io::Write, path::PathBuf};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, ensure};
 use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
 use clap::{Parser, ValueEnum};
 use derive_more::Display;
-use ethers::utils::hex;
+use espresso_types::eth_signature_key::EthKeyPair;
+use ethers::{core::k256::ecdsa::SigningKey, utils::hex};
 use hotshot::types::SignatureKey;
 use hotshot_types::{light_client::StateKeyPair, signature_key::BLSPubKey};
 use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use sequencer::{
+    keystore::{Keys, Keystore, PassphraseOptions},
+    persistence::encryption::{EncryptionOptions, KEY_FILE_CONTEXT},
+};
 use tracing::info_span;
+use zeroize::Zeroizing;
 
 #[derive(Clone, Copy, Debug, Display, Default, ValueEnum)]
 enum Scheme {
@@ -25,43 +28,69 @@ enum Scheme {
     Bls,
     #[display(fmt = "schnorr")]
     Schnorr,
+    /// An Ethereum key, as used by builders. This is only supported with --keystore.
+    #[display(fmt = "eth")]
+    Eth,
 }
 
 impl Scheme {
-    fn gen(self, seed: [u8; 32], index: u64, env_file: &mut impl Write) -> anyhow::Result<()> {
+    fn gen(self, seed: [u8; 32], index: u64, keys: &mut Keys) {
         match self {
             Self::All => {
-                Self::Bls.gen(seed, index, env_file)?;
-                Self::Schnorr.gen(seed, index, env_file)?;
+                Self::Bls.gen(seed, index, keys);
+                Self::Schnorr.gen(seed, index, keys);
             }
             Self::Bls => {
                 let (pub_key, priv_key) = BLSPubKey::generated_from_seed_indexed(seed, index);
-                writeln!(env_file, "ESPRESSO_SEQUENCER_PUBLIC_STAKING_KEY={pub_key}")?;
-                writeln!(
-                    env_file,
-                    "ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY={priv_key}"
-                )?;
+                keys.staking = Some(priv_key);
                 tracing::info!(%pub_key, "generated staking key")
             }
             Self::Schnorr => {
                 let key_pair = StateKeyPair::generate_from_seed_indexed(seed, index);
-                writeln!(
-                    env_file,
-                    "ESPRESSO_SEQUENCER_PUBLIC_STATE_KEY={}",
-                    key_pair.ver_key()
-                )?;
-                writeln!(
-                    env_file,
-                    "ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY={}",
-                    key_pair.sign_key_ref()
-                )?;
+                keys.state = Some(key_pair.sign_key_ref().clone());
                 tracing::info!(pub_key = %key_pair.ver_key(), "generated state key");
             }
+            Self::Eth => {
+                let mut rng = ChaChaRng::from_seed(seed);
+                rng.set_stream(index);
+                let key_pair = EthKeyPair::from(SigningKey::random(&mut rng));
+                tracing::info!(address = %key_pair.address(), "generated Ethereum key");
+                keys.eth = Some(key_pair);
+            }
         }
-        Ok(())
     }
 }
 
+/// Write keys to a file in .env format.
+fn write_env(keys: &Keys, env_file: &mut impl Write) -> anyhow::Result<()> {
+    if let Some(priv_key) = &keys.staking {
+        let pub_key = BLSPubKey::from_private(priv_key);
+        writeln!(env_file, "ESPRESSO_SEQUENCER_PUBLIC_STAKING_KEY={pub_key}")?;
+        writeln!(
+            env_file,
+            "ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY={priv_key}"
+        )?;
+    }
+    if let Some(sign_key) = &keys.state {
+        let key_pair = StateKeyPair::from_sign_key(sign_key.clone());
+        writeln!(
+            env_file,
+            "ESPRESSO_SEQUENCER_PUBLIC_STATE_KEY={}",
+            key_pair.ver_key()
+        )?;
+        writeln!(
+            env_file,
+            "ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY={}",
+            key_pair.sign_key_ref()
+        )?;
+    }
+    ensure!(
+        keys.eth.is_none(),
+        "Ethereum keys can only be written to a keystore (use --keystore)"
+    );
+    Ok(())
+}
+
 /// Utility program to generate keypairs
 ///
 /// With no options, this program generates the keys needed to run a single instance of the Espresso
@@ -69,6 +98,9 @@ impl Scheme {
 ///
 /// Generated secret keys are written to a file in .env format, which can directly be used to
 /// configure a sequencer node. Public information about the generated keys is printed to stdout.
+///
+/// Alternatively, with --keystore, each set of keys is written to an encrypted keystore, which can
+/// be unlocked with a passphrase. Keystores can also hold an Ethereum key for a builder.
 #[derive(Clone, Debug, Parser)]
 struct Options {
     /// Seed for generating keys.
@@ -97,9 +129,27 @@ struct Options {
     /// DIR must be a directory. If it does not exist, one will be created. Private key setups will
     /// be written to files immediately under DIR, with names like 0.env, 1.env, etc. for 0 through
     /// N - 1. The random seed used to generate the keys will also be written to a file in DIR
-    /// called .seed.
+    /// called .seed, unless the keys are written to keystores and the seed cannot be encrypted.
     #[clap(short, long, name = "OUT")]
     out: PathBuf,
+
+    /// Write each set of keys to an encrypted keystore instead of a .env file.
+    ///
+    /// Keystores are written to files like 0.json, 1.json, etc. under DIR, and are encrypted with
+    /// the keystore passphrase, which is required with this option.
+    #[clap(long)]
+    keystore: bool,
+
+    /// Passphrase for keystores created with --keystore.
+    #[clap(flatten)]
+    passphrase: PassphraseOptions,
+
+    /// Encrypt the generated files at rest.
+    ///
+    /// If an encryption key or passphrase is given, the private key files and the seed are
+    /// encrypted with it. The sequencer must then be given the same key to read the key file.
+    #[clap(flatten)]
+    encryption: EncryptionOptions,
 }
 
 fn parse_seed(s: &str) -> Result<[u8; 32], anyhow::Error> {
@@ -111,7 +161,7 @@ fn parse_seed(s: &str) -> Result<[u8; 32], anyhow::Error> {
 
 fn gen_default_seed() -> [u8; 32] {
     let mut seed = [0u8; 32];
-    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let mut rng = ChaChaRng::from_entropy();
     rng.fill_bytes(&mut seed);
 
     seed
@@ -131,25 +181,46 @@ fn main() -> anyhow::Result<()> {
 
     // Create output dir if necessary.
     fs::create_dir_all(&opts.out)?;
+    let encryption = opts.encryption.encryption()?;
+    let passphrase = if opts.keystore {
+        opts.passphrase.passphrase()?
+    } else {
+        Default::default()
+    };
 
     let seed = opts.seed.unwrap_or_else(|| {
         tracing::debug!("No seed provided, generating a random seed");
         gen_default_seed()
     });
-    fs::write(opts.out.join(".seed"), hex::encode(seed))?;
+    if opts.keystore && !encryption.is_enabled() {
+        // The seed would reveal the keys we are about to lock away in keystores.
+        tracing::warn!("not writing unencrypted seed alongside keystores");
+    } else {
+        fs::write(
+            opts.out.join(".seed"),
+            encryption.seal(KEY_FILE_CONTEXT, hex::encode(seed).as_bytes())?,
+        )?;
+    }
 
     for index in 0..opts.num {
         let span = info_span!("gen", index);
         let _enter = span.enter();
         tracing::info!("generating new key set");
 
-        let path = opts.out.join(format!("{index}.env"));
-        let mut file = File::options()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&path)?;
-        opts.scheme.gen(seed, index as u64, &mut file)?;
+        let mut keys = Keys::default();
+        opts.scheme.gen(seed, index as u64, &mut keys);
+
+        let path = if opts.keystore {
+            let path = opts.out.join(format!("{index}.json"));
+            Keystore::encrypt(&keys, &passphrase, format!("key set {index}"))?.save(&path)?;
+            path
+        } else {
+            let path = opts.out.join(format!("{index}.env"));
+            let mut env_file = Zeroizing::new(vec![]);
+            write_env(&keys, &mut *env_file)?;
+            fs::write(&path, encryption.seal(KEY_FILE_CONTEXT, &env_file)?)?;
+            path
+        };
 
         tracing::info!("private keys written to {}", path.display());
     }
@@ -0,0 +1,688 @@
+//! Utility program to migrate the persistent storage of a sequencer between backends.
+
+use anyhow::{bail, ensure, Context};
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::{Parser, Subcommand};
+use committable::Committable;
+use espresso_types::{
+    v0::traits::{PersistenceOptions, SequencerPersistence},
+    SeqTypes,
+};
+use hotshot_query_service::{
+    availability::{
+        AvailabilityDataSource, BlockQueryData, LeafQueryData, UpdateAvailabilityData,
+        VidCommonQueryData,
+    },
+    data_source::VersionedDataSource,
+    node::NodeDataSource,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    event::HotShotAction,
+    traits::{block_contents::BlockHeader, node_implementation::ConsensusTime, EncodeBytes},
+    vid::{vid_scheme, VidSchemeType},
+    vote::HasViewNumber,
+};
+use jf_vid::VidScheme;
+use sequencer::{
+    api::data_source::{DataSourceOptions, Provider, SequencerDataSource},
+    persistence,
+};
+
+/// Migrate the persistent storage of a sequencer from one backend to another.
+///
+/// This copies both the consensus storage (anchor leaf, highest voted view, undecided state, saved
+/// quorum proposals, DA proposals and VID shares) and the availability data of the query service.
+/// Leaves and blocks are verified against their commitments and against the chain of parent
+/// commitments as they are copied.
+///
+/// The migration can be interrupted and resumed: availability data is committed to the destination
+/// in batches, and a subsequent run continues from the block height of the destination, after
+/// checking that the destination is consistent with the source. Consensus storage is small and is
+/// always copied in full.
+///
+/// Merklized state is not copied. A node running with the destination storage will rebuild it from
+/// the migrated blocks.
+///
+/// Do not run this program while a sequencer is using either the source or the destination storage.
+///
+/// The source and destination are given as nested subcommands, for example:
+///
+///     migrate-storage fs --path /store/sequencer sql --postgres-host localhost
+#[derive(Clone, Debug, Parser)]
+struct Options {
+    /// Number of blocks to copy between commits to the destination.
+    ///
+    /// If the migration is interrupted, at most this many blocks will have to be copied again.
+    #[clap(
+        long,
+        env = "ESPRESSO_MIGRATE_STORAGE_BATCH_SIZE",
+        default_value = "1000"
+    )]
+    batch_size: usize,
+
+    /// Skip objects which are missing from the source, rather than failing.
+    ///
+    /// Missing blocks will be skipped and can be fetched from peers by a node running with the
+    /// destination storage.
+    #[clap(long, env = "ESPRESSO_MIGRATE_STORAGE_ALLOW_GAPS")]
+    allow_gaps: bool,
+
+    #[clap(subcommand)]
+    source: Source,
+}
+
+/// The storage to migrate from.
+#[derive(Clone, Debug, Subcommand)]
+enum Source {
+    /// Migrate from file system storage.
+    Fs {
+        #[clap(flatten)]
+        opt: persistence::fs::Options,
+        #[clap(subcommand)]
+        destination: Destination,
+    },
+    /// Migrate from SQL storage.
+    Sql {
+        #[clap(flatten)]
+        opt: Box<persistence::sql::Options>,
+        #[clap(subcommand)]
+        destination: Destination,
+    },
+    /// Migrate from SQLite storage.
+    #[cfg(feature = "sqlite")]
+    Sqlite {
+        #[clap(flatten)]
+        opt: persistence::sqlite::Options,
+        #[clap(subcommand)]
+        destination: Destination,
+    },
+}
+
+/// The storage to migrate to.
+#[derive(Clone, Debug, Subcommand)]
+enum Destination {
+    /// Migrate to file system storage.
+    Fs(persistence::fs::Options),
+    /// Migrate to SQL storage.
+    Sql(Box<persistence::sql::Options>),
+    /// Migrate to SQLite storage.
+    #[cfg(feature = "sqlite")]
+    Sqlite(persistence::sqlite::Options),
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    ensure!(opt.batch_size > 0, "batch size must be positive");
+    match opt.source.clone() {
+        Source::Fs {
+            opt: src,
+            destination,
+        } => {
+            tracing::info!("migrating from file system storage {src:?}");
+            migrate_to(&opt, src, destination).await
+        }
+        Source::Sql {
+            opt: src,
+            destination,
+        } => {
+            tracing::info!("migrating from SQL storage {src:?}");
+            migrate_to(&opt, *src, destination).await
+        }
+        #[cfg(feature = "sqlite")]
+        Source::Sqlite {
+            opt: src,
+            destination,
+        } => {
+            tracing::info!("migrating from SQLite storage {src:?}");
+            migrate_to(&opt, src, destination).await
+        }
+    }
+}
+
+async fn migrate_to<S: DataSourceOptions>(
+    opt: &Options,
+    src: S,
+    destination: Destination,
+) -> anyhow::Result<()> {
+    match destination {
+        Destination::Fs(dst) => {
+            tracing::info!("migrating to file system storage {dst:?}");
+            migrate(opt, src, dst).await
+        }
+        Destination::Sql(dst) => {
+            tracing::info!("migrating to SQL storage {dst:?}");
+            migrate(opt, src, *dst).await
+        }
+        #[cfg(feature = "sqlite")]
+        Destination::Sqlite(dst) => {
+            tracing::info!("migrating to SQLite storage {dst:?}");
+            migrate(opt, src, dst).await
+        }
+    }
+}
+
+async fn migrate<S: DataSourceOptions, D: DataSourceOptions>(
+    opt: &Options,
+    src: S,
+    dst: D,
+) -> anyhow::Result<()>
+where
+    D::DataSource: UpdateAvailabilityData<SeqTypes>,
+{
+    // Migrate consensus storage.
+    let src_persistence = src
+        .clone()
+        .create()
+        .await
+        .context("opening source storage")?;
+    let mut dst_persistence = dst
+        .clone()
+        .create()
+        .await
+        .context("opening destination storage")?;
+    migrate_consensus(&src_persistence, &mut dst_persistence).await?;
+
+    // Migrate query service storage. We don't want to fetch anything that is missing from the
+    // source, so we use an empty provider.
+    let src_ds = S::DataSource::create(src, Provider::default(), false)
+        .await
+        .context("opening source data source")?;
+    let mut dst_ds = D::DataSource::create(dst, Provider::default(), false)
+        .await
+        .context("opening destination data source")?;
+    migrate_availability(opt, &src_ds, &mut dst_ds).await?;
+
+    tracing::info!("migration complete");
+    Ok(())
+}
+
+async fn migrate_consensus<S: SequencerPersistence, D: SequencerPersistence>(
+    src: &S,
+    dst: &mut D,
+) -> anyhow::Result<()> {
+    if let Some(cfg) = src.load_config().await.context("loading config")? {
+        tracing::info!("migrating network config");
+        dst.save_config(&cfg).await.context("saving config")?;
+    }
+
+    let mut max_view = ViewNumber::genesis();
+    if let Some(view) = src
+        .load_latest_acted_view()
+        .await
+        .context("loading highest voted view")?
+    {
+        tracing::info!(?view, "migrating highest voted view");
+        dst.record_action(view, HotShotAction::Vote)
+            .await
+            .context("saving highest voted view")?;
+        max_view = max_view.max(view);
+    }
+
+    let mut min_view = ViewNumber::genesis();
+    if let Some((leaf, qc)) = src
+        .load_anchor_leaf()
+        .await
+        .context("loading anchor leaf")?
+    {
+        tracing::info!(height = leaf.height(), "migrating anchor leaf");
+        ensure!(
+            qc.data.leaf_commit == leaf.commit(),
+            "anchor leaf {} does not match QC for {}",
+            leaf.commit(),
+            qc.data.leaf_commit
+        );
+        ensure!(
+            qc.view_number == leaf.view_number(),
+            "anchor leaf is from view {:?}, but QC is from view {:?}",
+            leaf.view_number(),
+            qc.view_number
+        );
+        dst.save_anchor_leaf(&leaf, &qc)
+            .await
+            .context("saving anchor leaf")?;
+
+        // Make sure the destination did not already have a newer anchor leaf, in which case the
+        // destination is not a copy of the source.
+        let Some((saved, _)) = dst.load_anchor_leaf().await? else {
+            bail!("anchor leaf missing from destination after saving");
+        };
+        ensure!(
+            saved.commit() == leaf.commit(),
+            "destination has a different anchor leaf {} at height {} (expected {} at height {})",
+            saved.commit(),
+            saved.height(),
+            leaf.commit(),
+            leaf.height()
+        );
+
+        min_view = leaf.view_number();
+        max_view = max_view.max(min_view);
+    }
+
+    if let Some((leaves, state)) = src
+        .load_undecided_state()
+        .await
+        .context("loading undecided state")?
+    {
+        tracing::info!(
+            leaves = leaves.len(),
+            views = state.len(),
+            "migrating undecided state"
+        );
+        for (commit, leaf) in &leaves {
+            ensure!(
+                *commit == leaf.commit(),
+                "undecided leaf {} stored with commitment {commit}",
+                leaf.commit()
+            );
+        }
+        dst.update_undecided_state(leaves, state)
+            .await
+            .context("saving undecided state")?;
+    }
+
+    if let Some(proposals) = src
+        .load_quorum_proposals()
+        .await
+        .context("loading quorum proposals")?
+    {
+        tracing::info!(count = proposals.len(), "migrating quorum proposals");
+        for (view, proposal) in proposals {
+            ensure!(
+                proposal.data.view_number() == view,
+                "quorum proposal for view {:?} stored at view {view:?}",
+                proposal.data.view_number()
+            );
+            dst.append_quorum_proposal(&proposal)
+                .await
+                .context(format!("saving quorum proposal for view {view:?}"))?;
+            max_view = max_view.max(view);
+        }
+    }
+
+    // DA proposals and VID shares are garbage collected once decided, so we only need to look for
+    // them in views starting from the anchor leaf.
+    tracing::info!(
+        from = ?min_view,
+        to = ?max_view,
+        "migrating DA proposals and VID shares"
+    );
+    for view in min_view.u64()..=max_view.u64() {
+        let view = ViewNumber::new(view);
+        if let Some(proposal) = src
+            .load_da_proposal(view)
+            .await
+            .context(format!("loading DA proposal for view {view:?}"))?
+        {
+            ensure!(
+                proposal.data.view_number() == view,
+                "DA proposal for view {:?} stored at view {view:?}",
+                proposal.data.view_number()
+            );
+            dst.append_da(&proposal)
+                .await
+                .context(format!("saving DA proposal for view {view:?}"))?;
+        }
+        if let Some(share) = src
+            .load_vid_share(view)
+            .await
+            .context(format!("loading VID share for view {view:?}"))?
+        {
+            ensure!(
+                share.data.view_number() == view,
+                "VID share for view {:?} stored at view {view:?}",
+                share.data.view_number()
+            );
+            dst.append_vid(&share)
+                .await
+                .context(format!("saving VID share for view {view:?}"))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn migrate_availability<S, D>(opt: &Options, src: &S, dst: &mut D) -> anyhow::Result<()>
+where
+    S: SequencerDataSource,
+    D: SequencerDataSource + UpdateAvailabilityData<SeqTypes>,
+{
+    let src_height = NodeDataSource::block_height(src)
+        .await
+        .context("loading source block height")? as u64;
+    let dst_height = NodeDataSource::block_height(dst)
+        .await
+        .context("loading destination block height")? as u64;
+    tracing::info!(src_height, dst_height, "migrating availability data");
+
+    // If we are resuming an interrupted migration, make sure the data already in the destination
+    // matches the source, and pick up the chain where we left off.
+    let mut parent = None;
+    if dst_height > 0 {
+        let height = dst_height - 1;
+        let dst_leaf = load_leaf(dst, height)
+            .await
+            .context("destination is missing its latest leaf")?;
+        let src_leaf = load_leaf(src, height)
+            .await
+            .context(format!("source is missing leaf {height}"))?;
+        ensure!(
+            dst_leaf.hash() == src_leaf.hash(),
+            "destination leaf {height} is {}, but source leaf is {}",
+            dst_leaf.hash(),
+            src_leaf.hash()
+        );
+        tracing::info!(height, "resuming migration");
+        parent = Some(src_leaf);
+    }
+
+    let mut skipped = 0;
+    for height in dst_height..src_height {
+        let Some(leaf) = load_leaf(src, height).await else {
+            ensure!(opt.allow_gaps, "source is missing leaf {height}");
+            tracing::warn!(height, "skipping missing leaf");
+            skipped += 1;
+            parent = None;
+            continue;
+        };
+        verify_leaf(&leaf, parent.as_ref())?;
+        dst.insert_leaf(leaf.clone())
+            .await
+            .context(format!("inserting leaf {height}"))?;
+
+        let common = load_vid_common(src, height).await;
+        if let Some(common) = &common {
+            ensure!(
+                common.block_hash() == leaf.block_hash(),
+                "VID common {height} is for block {}, but leaf is for block {}",
+                common.block_hash(),
+                leaf.block_hash()
+            );
+        }
+
+        match load_block(src, height).await {
+            Some(block) => {
+                verify_block(&block, &leaf, common.as_ref())?;
+                dst.insert_block(block)
+                    .await
+                    .context(format!("inserting block {height}"))?;
+            }
+            None => {
+                ensure!(opt.allow_gaps, "source is missing block {height}");
+                tracing::warn!(height, "skipping missing block");
+                skipped += 1;
+            }
+        }
+
+        match common {
+            Some(common) => {
+                let share = src.vid_share(height as usize).await.ok();
+                dst.insert_vid(common, share)
+                    .await
+                    .context(format!("inserting VID data {height}"))?;
+            }
+            None => {
+                ensure!(opt.allow_gaps, "source is missing VID common {height}");
+                tracing::warn!(height, "skipping missing VID common");
+                skipped += 1;
+            }
+        }
+
+        parent = Some(leaf);
+        if (height + 1) % opt.batch_size as u64 == 0 {
+            dst.commit()
+                .await
+                .context(format!("committing batch at {height}"))?;
+            tracing::info!(height, src_height, "migrated batch");
+        }
+    }
+    dst.commit().await.context("committing final batch")?;
+
+    if skipped > 0 {
+        tracing::warn!(skipped, "some objects were missing from the source");
+    }
+    Ok(())
+}
+
+/// Check a leaf against its own commitment and the commitment of its parent.
+fn verify_leaf(
+    leaf: &LeafQueryData<SeqTypes>,
+    parent: Option<&LeafQueryData<SeqTypes>>,
+) -> anyhow::Result<()> {
+    let height = leaf.height();
+    ensure!(
+        leaf.leaf().commit() == leaf.hash(),
+        "leaf {height} has commitment {}, but is stored as {}",
+        leaf.leaf().commit(),
+        leaf.hash()
+    );
+    ensure!(
+        leaf.qc().data.leaf_commit == leaf.hash(),
+        "leaf {height} is {}, but its QC is for {}",
+        leaf.hash(),
+        leaf.qc().data.leaf_commit
+    );
+    if let Some(parent) = parent {
+        ensure!(
+            leaf.leaf().parent_commitment() == parent.hash(),
+            "leaf {height} has parent {}, but leaf {} is {}",
+            leaf.leaf().parent_commitment(),
+            parent.height(),
+            parent.hash()
+        );
+    }
+    Ok(())
+}
+
+/// Check a block against its header and the leaf that decided it.
+///
+/// The payload is checked by recomputing its VID commitment, which requires the VID common data
+/// for the block. If that is missing from the source (which can only happen with `--allow-gaps`)
+/// the payload cannot be checked.
+fn verify_block(
+    block: &BlockQueryData<SeqTypes>,
+    leaf: &LeafQueryData<SeqTypes>,
+    common: Option<&VidCommonQueryData<SeqTypes>>,
+) -> anyhow::Result<()> {
+    let height = block.height();
+    ensure!(
+        block.header().commit() == block.hash(),
+        "block {height} has commitment {}, but is stored as {}",
+        block.header().commit(),
+        block.hash()
+    );
+    ensure!(
+        block.hash() == leaf.block_hash(),
+        "block {height} is {}, but leaf is for block {}",
+        block.hash(),
+        leaf.block_hash()
+    );
+    ensure!(
+        block.payload().ns_table() == block.header().ns_table(),
+        "block {height} has a namespace table which does not match its header"
+    );
+
+    let Some(common) = common else {
+        tracing::warn!(height, "cannot verify payload without VID common data");
+        return Ok(());
+    };
+    let num_storage_nodes = VidSchemeType::get_num_storage_nodes(common.common());
+    let payload_commitment = vid_scheme(num_storage_nodes as usize)
+        .commit_only(block.payload().encode())
+        .context(format!("computing payload commitment for block {height}"))?;
+    ensure!(
+        payload_commitment == block.header().payload_commitment(),
+        "block {height} has payload {payload_commitment}, but header commits to {}",
+        block.header().payload_commitment()
+    );
+    Ok(())
+}
+
+async fn load_leaf<D: SequencerDataSource>(ds: &D, height: u64) -> Option<LeafQueryData<SeqTypes>> {
+    ds.get_leaf(height as usize).await.try_resolve().ok()
+}
+
+async fn load_block<D: SequencerDataSource>(
+    ds: &D,
+    height: u64,
+) -> Option<BlockQueryData<SeqTypes>> {
+    ds.get_block(height as usize).await.try_resolve().ok()
+}
+
+async fn load_vid_common<D: SequencerDataSource>(
+    ds: &D,
+    height: u64,
+) -> Option<VidCommonQueryData<SeqTypes>> {
+    ds.get_vid_common(height as usize).await.try_resolve().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use espresso_types::{Leaf, NodeState, ValidatedState};
+    use hotshot_query_service::availability::LeafQueryData;
+    use hotshot_types::{
+        data::QuorumProposal, simple_certificate::QuorumCertificate, traits::BlockPayload,
+        vid::VidCommitment,
+    };
+    use sequencer::persistence::fs;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    type Chain = Vec<(
+        LeafQueryData<SeqTypes>,
+        BlockQueryData<SeqTypes>,
+        VidCommonQueryData<SeqTypes>,
+    )>;
+
+    /// Build a chain of `len` empty blocks, each linked to its parent.
+    ///
+    /// If `payload_commitment` is given, it replaces the payload commitment in every header, which
+    /// makes the headers inconsistent with their payloads.
+    async fn mock_chain(len: u64, payload_commitment: Option<VidCommitment>) -> Chain {
+        let instance = NodeState::mock();
+        let genesis = Leaf::genesis(&ValidatedState::default(), &instance).await;
+        let payload = genesis.block_payload().unwrap();
+        let disperse = vid_scheme(2).disperse(payload.encode()).unwrap();
+
+        let mut chain = vec![];
+        let mut justify_qc =
+            QuorumCertificate::genesis(&ValidatedState::default(), &instance).await;
+        for height in 0..len {
+            let mut header = genesis.block_header().clone();
+            *header.height_mut() = height;
+            *header.payload_commitment_mut() = payload_commitment.unwrap_or(disperse.commit);
+            let leaf = if height == 0 {
+                let mut leaf = genesis.clone();
+                *leaf.block_header_mut() = header.clone();
+                leaf
+            } else {
+                Leaf::from_quorum_proposal(&QuorumProposal {
+                    block_header: header.clone(),
+                    view_number: ViewNumber::new(height),
+                    justify_qc: justify_qc.clone(),
+                    upgrade_certificate: None,
+                    proposal_certificate: None,
+                })
+            };
+            let mut qc = justify_qc.clone();
+            qc.view_number = ViewNumber::new(height);
+            qc.data.leaf_commit = leaf.commit();
+            justify_qc = qc.clone();
+
+            chain.push((
+                LeafQueryData::new(leaf, qc).unwrap(),
+                BlockQueryData::new(header.clone(), payload.clone()),
+                VidCommonQueryData::new(header, disperse.common.clone()),
+            ));
+        }
+        chain
+    }
+
+    async fn populate(opt: fs::Options, chain: &Chain) {
+        let mut ds =
+            <fs::Options as DataSourceOptions>::DataSource::create(opt, Provider::default(), true)
+                .await
+                .unwrap();
+        for (leaf, block, common) in chain {
+            ds.insert_leaf(leaf.clone()).await.unwrap();
+            ds.insert_block(block.clone()).await.unwrap();
+            ds.insert_vid(common.clone(), None).await.unwrap();
+        }
+        ds.commit().await.unwrap();
+    }
+
+    fn options(src: &fs::Options, dst: &fs::Options) -> Options {
+        Options {
+            batch_size: 2,
+            allow_gaps: false,
+            source: Source::Fs {
+                opt: src.clone(),
+                destination: Destination::Fs(dst.clone()),
+            },
+        }
+    }
+
+    #[async_std::test]
+    async fn test_migrate_round_trip() {
+        let src_dir = TempDir::new().unwrap();
+        let dst_dir = TempDir::new().unwrap();
+        let src = fs::Options::new(src_dir.path().into());
+        let dst = fs::Options::new(dst_dir.path().into());
+
+        let chain = mock_chain(5, None).await;
+        populate(src.clone(), &chain).await;
+        migrate(&options(&src, &dst), src.clone(), dst.clone())
+            .await
+            .unwrap();
+
+        let ds = <fs::Options as DataSourceOptions>::DataSource::create(
+            dst.clone(),
+            Provider::default(),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            NodeDataSource::block_height(&ds).await.unwrap(),
+            chain.len()
+        );
+        for (leaf, block, common) in &chain {
+            let height = leaf.height();
+            assert_eq!(load_leaf(&ds, height).await.unwrap(), *leaf);
+            assert_eq!(load_block(&ds, height).await.unwrap(), *block);
+            assert_eq!(load_vid_common(&ds, height).await.unwrap(), *common);
+        }
+        drop(ds);
+
+        // Migrating again resumes from the end of the destination, which is a no-op.
+        migrate(&options(&src, &dst), src, dst).await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_migrate_rejects_bad_payload() {
+        let src_dir = TempDir::new().unwrap();
+        let dst_dir = TempDir::new().unwrap();
+        let src = fs::Options::new(src_dir.path().into());
+        let dst = fs::Options::new(dst_dir.path().into());
+
+        // Commit each header to a payload other than the one stored with it. Everything is
+        // consistent except the payload itself, which is only caught by recomputing its
+        // commitment.
+        let (other, _) = <espresso_types::Payload as BlockPayload<SeqTypes>>::empty();
+        let mut bytes = other.encode().to_vec();
+        bytes.push(0);
+        let wrong = vid_scheme(2).commit_only(bytes).unwrap();
+        let chain = mock_chain(2, Some(wrong)).await;
+        let (leaf, block, common) = &chain[1];
+        verify_block(block, leaf, Some(common)).unwrap_err();
+
+        populate(src.clone(), &chain).await;
+        migrate(&options(&src, &dst), src, dst).await.unwrap_err();
+    }
+}
@@ -1,6 +1,6 @@
-use std::str::FromStr;
+use std::{path::PathBuf, str::FromStr};
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use clap::Parser;
 use espresso_types::{PrivKey, PubKey};
 use hotshot::{traits::implementations::derive_libp2p_peer_id, types::BLSPubKey};
@@ -8,6 +8,7 @@ use hotshot_types::{
     light_client::{StateKeyPair, StateSignKey},
     traits::signature_key::SignatureKey,
 };
+use sequencer::keystore::{Keystore, PassphraseOptions};
 
 #[derive(Clone, Debug)]
 enum PrivateKey {
@@ -33,17 +34,52 @@ impl FromStr for PrivateKey {
 #[derive(Clone, Debug, Parser)]
 struct Options {
     /// The private key to get the public key for.
-    key: PrivateKey,
+    #[clap(required_unless_present = "keystore")]
+    key: Option<PrivateKey>,
+
+    /// Get the public keys of the keys in a keystore instead.
+    ///
+    /// The public keys are stored in the clear, so the keystore is only unlocked (using the keystore
+    /// passphrase) if the libp2p peer ID is requested.
+    #[clap(long, conflicts_with = "key")]
+    keystore: Option<PathBuf>,
+
+    #[clap(flatten)]
+    passphrase: PassphraseOptions,
 
     // Whether or not to derive the libp2p peer ID from the private key.
     #[clap(long, short)]
     libp2p: bool,
 }
 
-fn main() {
+fn main() -> anyhow::Result<()> {
     let opt = Options::parse();
 
-    match (opt.libp2p, opt.key) {
+    let key = match (opt.key, &opt.keystore) {
+        (Some(key), _) => key,
+        (None, Some(path)) if opt.libp2p => PrivateKey::Bls(
+            opt.passphrase
+                .unlock(path)?
+                .staking
+                .context("keystore does not contain a staking key")?,
+        ),
+        (None, Some(path)) => {
+            let keys = Keystore::load(path)?.public_keys;
+            if let Some(key) = keys.staking {
+                println!("staking: {key}");
+            }
+            if let Some(key) = keys.state {
+                println!("state: {key}");
+            }
+            if let Some(address) = keys.eth {
+                println!("eth: {address:?}");
+            }
+            return Ok(());
+        }
+        (None, None) => bail!("either a private key or a keystore is required"),
+    };
+
+    match (opt.libp2p, key) {
         // Non-libp2p
         (false, PrivateKey::Bls(key)) => println!("{}", PubKey::from_private(&key)),
         (false, PrivateKey::Schnorr(key)) => {
@@ -61,4 +97,6 @@ fn main() {
             eprintln!("Key type unsupported for libp2p peer ID derivation");
         }
     }
+
+    Ok(())
 }
@@ -16,6 +16,9 @@ enum Options {
     Fs(persistence::fs::Options),
     /// Reset SQL storage.
     Sql(Box<persistence::sql::Options>),
+    /// Reset SQLite storage.
+    #[cfg(feature = "sqlite")]
+    Sqlite(persistence::sqlite::Options),
 }
 
 #[async_std::main]
@@ -33,6 +36,11 @@ async fn main() -> anyhow::Result<()> {
             tracing::warn!("resetting SQL storage {opt:?}");
             reset_storage(*opt).await
         }
+        #[cfg(feature = "sqlite")]
+        Options::Sqlite(opt) => {
+            tracing::warn!("resetting SQLite storage {opt:?}");
+            reset_storage(opt).await
+        }
     }
 }
 
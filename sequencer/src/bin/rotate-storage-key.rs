@@ -0,0 +1,161 @@
+//! Utility program to rotate the key used to encrypt file system storage and key files at rest.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context};
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use clap::Parser;
+use derivative::Derivative;
+use sequencer::persistence::{
+    self,
+    encryption::{is_sealed, Encryption, KeySource, KEY_FILE_CONTEXT},
+};
+use zeroize::Zeroizing;
+
+/// Rotate the key used to encrypt file system storage and key files at rest.
+///
+/// Every consensus storage file under the storage path, and every given key file, is decrypted with
+/// the old key and encrypted with the new key. If no old key is given, the files must not be
+/// encrypted yet, which can be used to enable encryption for existing storage. This is the only
+/// way to enable encryption for existing storage: once a key is configured, the sequencer rejects
+/// files which are not encrypted. If no new key is given, the files are left decrypted, which can
+/// be used to disable encryption.
+///
+/// Files which are already encrypted with the new key are skipped, so it is safe to run this
+/// program again if it is interrupted. Files which have been quarantined are left as they are,
+/// since they may not be readable with any key. Do not run this program while the sequencer is
+/// running.
+#[derive(Clone, Parser, Derivative)]
+#[derivative(Debug)]
+struct Options {
+    /// Storage path of the file system consensus storage to re-encrypt.
+    ///
+    /// Query service data stored under the same path is not encrypted, and is left untouched.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_STORAGE_PATH")]
+    storage_path: Option<PathBuf>,
+
+    /// Key files to re-encrypt.
+    #[clap(
+        long = "key-file",
+        env = "ESPRESSO_SEQUENCER_KEY_FILE",
+        value_delimiter = ','
+    )]
+    key_files: Vec<PathBuf>,
+
+    /// Path to a file containing the current hex-encoded 32-byte key.
+    #[clap(long, conflicts_with = "old_passphrase")]
+    old_key_file: Option<PathBuf>,
+
+    /// The current passphrase.
+    #[clap(long, env = "ESPRESSO_ROTATE_STORAGE_KEY_OLD_PASSPHRASE")]
+    #[derivative(Debug = "ignore")]
+    old_passphrase: Option<String>,
+
+    /// Path to a file containing the new hex-encoded 32-byte key.
+    #[clap(long, conflicts_with = "new_passphrase")]
+    new_key_file: Option<PathBuf>,
+
+    /// The new passphrase.
+    #[clap(long, env = "ESPRESSO_ROTATE_STORAGE_KEY_NEW_PASSPHRASE")]
+    #[derivative(Debug = "ignore")]
+    new_passphrase: Option<String>,
+}
+
+fn encryption(
+    key_file: &Option<PathBuf>,
+    passphrase: &Option<String>,
+) -> anyhow::Result<Encryption> {
+    if let Some(path) = key_file {
+        Encryption::new(KeySource::File(path.clone()))
+    } else if let Some(passphrase) = passphrase {
+        Encryption::new(KeySource::Passphrase(Zeroizing::new(passphrase.clone())))
+    } else {
+        Ok(Encryption::none())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    let opt = Options::parse();
+    tracing::info!("rotating storage key {opt:?}");
+    ensure!(
+        opt.storage_path.is_some() || !opt.key_files.is_empty(),
+        "nothing to do: no storage path or key files were given"
+    );
+
+    let old = encryption(&opt.old_key_file, &opt.old_passphrase)?;
+    let new = encryption(&opt.new_key_file, &opt.new_passphrase)?;
+
+    // Collect all the files, along with the context each one is sealed with, before rewriting any
+    // of them, so we fail early if the set of files cannot be determined.
+    let mut files = opt
+        .key_files
+        .iter()
+        .map(|path| (path.clone(), KEY_FILE_CONTEXT.to_vec()))
+        .collect::<Vec<_>>();
+    if let Some(path) = &opt.storage_path {
+        let storage = persistence::fs::Options::new(path.clone());
+        for path in storage.consensus_files()? {
+            let context = storage.sealing_context(&path);
+            files.push((path, context));
+        }
+    }
+    // Never touch the encryption keys themselves, in case they are kept alongside the storage.
+    let keys = [&opt.old_key_file, &opt.new_key_file]
+        .into_iter()
+        .flatten()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect::<Vec<_>>();
+    files.retain(|(path, _)| {
+        path.canonicalize()
+            .map_or(true, |path| !keys.contains(&path))
+    });
+
+    // Check that every file can be opened with the old key before rewriting any of them, so that a
+    // file which cannot be read does not leave the storage sealed under a mix of old and new keys.
+    let mut pending = vec![];
+    for (path, context) in &files {
+        let bytes = fs::read(path).context(format!("reading {}", path.display()))?;
+        if is_rotated(&bytes, &new) {
+            tracing::debug!("skipping {}", path.display());
+            continue;
+        }
+        old.open(context, bytes)
+            .context(format!("opening {}", path.display()))?;
+        pending.push((path, context));
+    }
+
+    for (path, context) in &pending {
+        rotate(path, context, &old, &new).context(format!("rotating {}", path.display()))?;
+    }
+    let rotated = pending.len();
+    tracing::info!(files = files.len(), rotated, "rotated storage key");
+    Ok(())
+}
+
+/// Whether a file with contents `bytes` is already in its final state, for example because we were
+/// interrupted on a previous run.
+fn is_rotated(bytes: &[u8], new: &Encryption) -> bool {
+    new.sealed_by(bytes) || (!new.is_enabled() && !is_sealed(bytes))
+}
+
+/// Re-encrypt a single file, sealed with `context`.
+fn rotate(path: &Path, context: &[u8], old: &Encryption, new: &Encryption) -> anyhow::Result<()> {
+    let bytes = fs::read(path)?;
+    let plaintext = Zeroizing::new(old.open(context, bytes)?);
+    let bytes = new.seal(context, &plaintext)?;
+
+    // Replace the file atomically, so that an interruption never leaves a partial file behind.
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".rotate");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)?;
+    tracing::debug!("rotated {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
+use async_std::sync::Arc;
+use clap::Parser;
+use contract_bindings::stake_table::{StakeTable, StakeTableErrors};
+use ethers::types::Address;
+use hotshot_contract_adapter::stake_table::bls_key_to_g2_point;
+use hotshot_types::{
+    light_client::{StateKeyPair, StateSignKey},
+    signature_key::{BLSPrivKey, BLSPubKey},
+    traits::signature_key::SignatureKey,
+};
+use sequencer::keystore::PassphraseOptions;
+use sequencer_utils::{
+    contract_send, init_signer,
+    stake_table::{self, registered_nodes, stake_table_entries},
+    Signer,
+};
+use url::Url;
+
+/// Command-line utility for working with the Espresso stake table contract.
+#[derive(Parser)]
+enum Command {
+    Register(Register),
+    Deposit(Deposit),
+    RequestExit(RequestExit),
+    Withdraw(Withdraw),
+    List(List),
+}
+
+/// Options for connecting to the stake table contract.
+#[derive(Debug, Parser)]
+struct L1Options {
+    /// L1 JSON-RPC provider.
+    #[clap(
+        short,
+        long,
+        env = "ESPRESSO_SEQUENCER_L1_PROVIDER",
+        default_value = "http://localhost:8545"
+    )]
+    rpc_url: Url,
+
+    /// The address of the stake table contract on the L1.
+    #[clap(short, long, env = "ESPRESSO_SEQUENCER_STAKE_TABLE_ADDRESS")]
+    contract_address: Address,
+
+    /// Mnemonic to generate the L1 account which controls the stake.
+    #[clap(
+        short,
+        long,
+        env = "ESPRESSO_SEQUENCER_ETH_MNEMONIC",
+        default_value = "test test test test test test test test test test test junk"
+    )]
+    mnemonic: String,
+
+    /// Account index when deriving an account from MNEMONIC.
+    #[clap(
+        short = 'i',
+        long,
+        env = "ESPRESSO_STAKE_TABLE_ACCOUNT_INDEX",
+        default_value = "0"
+    )]
+    account_index: u32,
+}
+
+impl L1Options {
+    async fn connect(&self) -> anyhow::Result<StakeTable<Signer>> {
+        let l1 = init_signer(&self.rpc_url, &self.mnemonic, self.account_index)
+            .await
+            .context(format!("connecting to L1 at {}", self.rpc_url))?;
+        Ok(StakeTable::new(self.contract_address, Arc::new(l1)))
+    }
+}
+
+/// Register a node in the stake table.
+///
+/// The stake is transferred from the L1 account, which must hold enough of the stake table's token.
+/// The same account must be used to deposit more stake, exit and withdraw later.
+#[derive(Parser)]
+struct Register {
+    #[clap(flatten)]
+    l1: L1Options,
+
+    /// Keystore containing the keys of the node to register.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_KEYSTORE",
+        conflicts_with_all = ["private_staking_key", "private_state_key"]
+    )]
+    keystore: Option<PathBuf>,
+
+    #[clap(flatten)]
+    passphrase: PassphraseOptions,
+
+    /// Private staking key of the node to register.
+    ///
+    /// This can be used as an alternative to KEYSTORE.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY",
+        requires = "private_state_key"
+    )]
+    private_staking_key: Option<BLSPrivKey>,
+
+    /// Private state signing key of the node to register.
+    ///
+    /// This can be used as an alternative to KEYSTORE.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY",
+        requires = "private_staking_key"
+    )]
+    private_state_key: Option<StateSignKey>,
+
+    /// Amount of the stake table's token to stake.
+    #[clap(short, long)]
+    amount: u64,
+
+    /// Abort if the node could not join the stake table by this epoch (default: the next epoch
+    /// with room for new registrations).
+    #[clap(long)]
+    valid_until_epoch: Option<u64>,
+}
+
+impl Register {
+    fn keys(&self) -> anyhow::Result<(BLSPrivKey, StateSignKey)> {
+        if let Some(path) = &self.keystore {
+            let keys = self.passphrase.unlock(path)?;
+            Ok((
+                keys.staking.context("keystore has no staking key")?,
+                keys.state.context("keystore has no state key")?,
+            ))
+        } else if let (Some(staking), Some(state)) =
+            (&self.private_staking_key, &self.private_state_key)
+        {
+            Ok((staking.clone(), state.clone()))
+        } else {
+            bail!("neither keystore nor full set of private keys was provided")
+        }
+    }
+}
+
+/// Deposit more stake for a registered node.
+#[derive(Debug, Parser)]
+struct Deposit {
+    #[clap(flatten)]
+    l1: L1Options,
+
+    /// Public staking key of the node.
+    #[clap(short, long)]
+    staking_key: BLSPubKey,
+
+    /// Amount of the stake table's token to deposit.
+    #[clap(short, long)]
+    amount: u64,
+}
+
+/// Request for a node to leave the stake table.
+///
+/// The node leaves at the next epoch with room for exits. Its stake can be withdrawn once the exit
+/// escrow period has passed.
+#[derive(Debug, Parser)]
+struct RequestExit {
+    #[clap(flatten)]
+    l1: L1Options,
+
+    /// Public staking key of the node.
+    #[clap(short, long)]
+    staking_key: BLSPubKey,
+}
+
+/// Withdraw the stake of a node which has exited.
+#[derive(Debug, Parser)]
+struct Withdraw {
+    #[clap(flatten)]
+    l1: L1Options,
+
+    /// Public staking key of the node.
+    #[clap(short, long)]
+    staking_key: BLSPubKey,
+}
+
+/// List the nodes in the stake table.
+#[derive(Debug, Parser)]
+struct List {
+    #[clap(flatten)]
+    l1: L1Options,
+
+    /// Only list nodes which are part of the stake table in this epoch (default: all registered
+    /// nodes).
+    #[clap(long)]
+    epoch: Option<u64>,
+
+    /// The L1 block in which the stake table contract was deployed.
+    ///
+    /// Registrations are only searched for from this block onwards.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_STAKE_TABLE_DEPLOYMENT_BLOCK",
+        default_value = "0"
+    )]
+    deployment_block: u64,
+}
+
+async fn register(opt: Register) -> anyhow::Result<()> {
+    let (staking_key, state_key) = opt.keys()?;
+    let state_key = StateKeyPair::from_sign_key(state_key).ver_key();
+    let contract = opt.l1.connect().await?;
+
+    tracing::info!(
+        staking_key = %BLSPubKey::from_private(&staking_key),
+        %state_key,
+        amount = opt.amount,
+        "registering node"
+    );
+    let epoch = stake_table::register(
+        &contract,
+        &staking_key,
+        &state_key,
+        opt.amount,
+        opt.valid_until_epoch,
+    )
+    .await?;
+    tracing::info!(epoch, "registered node");
+
+    Ok(())
+}
+
+async fn deposit(opt: Deposit) -> anyhow::Result<()> {
+    let contract = opt.l1.connect().await?;
+    let (balance, epoch) = stake_table::deposit(&contract, &opt.staking_key, opt.amount).await?;
+    tracing::info!(balance, epoch, "deposited stake");
+    Ok(())
+}
+
+async fn request_exit(opt: RequestExit) -> anyhow::Result<()> {
+    let contract = opt.l1.connect().await?;
+    let bls_vk = bls_key_to_g2_point(&opt.staking_key);
+    contract_send::<_, _, StakeTableErrors>(&contract.request_exit(bls_vk.clone()))
+        .await
+        .context("requesting exit")?;
+
+    let node = contract.lookup_node(bls_vk).call().await?;
+    tracing::info!(epoch = node.exit_epoch, "requested exit");
+    Ok(())
+}
+
+async fn withdraw(opt: Withdraw) -> anyhow::Result<()> {
+    let contract = opt.l1.connect().await?;
+    let call = contract.withdraw_funds(bls_key_to_g2_point(&opt.staking_key));
+    let amount = call.call().await?;
+    contract_send::<_, _, StakeTableErrors>(&call)
+        .await
+        .context("withdrawing stake")?;
+    tracing::info!(amount, "withdrew stake");
+    Ok(())
+}
+
+async fn list(opt: List) -> anyhow::Result<()> {
+    let contract = opt.l1.connect().await?;
+    let nodes = registered_nodes(&contract, opt.deployment_block).await?;
+
+    // Output on regular standard out, rather than as log messages, to make scripting easier.
+    match opt.epoch {
+        Some(epoch) => {
+            for node in stake_table_entries(&nodes, epoch) {
+                println!("{}", serde_json::to_string(&node)?);
+            }
+        }
+        None => {
+            for node in nodes {
+                println!(
+                    "{} {} account={:#x} balance={} register_epoch={} exit_epoch={}",
+                    node.staking_key,
+                    node.state_key,
+                    node.account,
+                    node.balance,
+                    node.register_epoch,
+                    node.exit_epoch
+                        .map(|epoch| epoch.to_string())
+                        .unwrap_or_else(|| "none".into()),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[async_std::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logging();
+    setup_backtrace();
+
+    match Command::parse() {
+        Command::Register(opt) => register(opt).await,
+        Command::Deposit(opt) => deposit(opt).await,
+        Command::RequestExit(opt) => request_exit(opt).await,
+        Command::Withdraw(opt) => withdraw(opt).await,
+        Command::List(opt) => list(opt).await,
+    }
+}
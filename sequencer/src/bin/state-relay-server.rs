@@ -3,7 +3,7 @@ use clap::Parser;
 use es_version::SEQUENCER_VERSION;
 use ethers::types::U256;
 use hotshot_state_prover::service::one_honest_threshold;
-use sequencer::state_signature::relay_server::run_relay_server;
+use sequencer::state_signature::relay_server::{run_relay_server_with_options, Options};
 
 #[derive(Parser)]
 struct Args {
@@ -16,15 +16,20 @@ struct Args {
     )]
     port: u16,
 
-    /// Total amount of stake.
-    /// WARNING: this is a temporary flag, should remove after integrating with stake table.
-    /// Related issue: [https://github.com/EspressoSystems/espresso-sequencer/issues/1022]
+    /// Total amount of stake, when no stake table source is given.
+    ///
+    /// In this case signatures from any key are accepted with a stake of 1 each, which is only
+    /// suitable for test networks. Production deployments should load the stake table using
+    /// `--state-relay-server-stake-table-url` or `--state-relay-server-stake-table-file`.
     #[clap(
         long,
         env = "ESPRESSO_STATE_SIGNATURE_TOTAL_STAKE",
         default_value = "5"
     )]
     total_stake: u64,
+
+    #[clap(flatten)]
+    options: Options,
 }
 
 #[async_std::main]
@@ -39,10 +44,11 @@ async fn main() {
         port = args.port,
         "starting state relay server, quorum threshold: {threshold}"
     );
-    run_relay_server(
+    run_relay_server_with_options(
         None,
         threshold,
         format!("http://0.0.0.0:{}", args.port).parse().unwrap(),
+        args.options,
         SEQUENCER_VERSION,
     )
     .await
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use anyhow::Context;
+use anyhow::{ensure, Context};
 use async_std::{
     sync::{Arc, RwLock},
     task::{spawn, JoinHandle},
@@ -30,7 +30,10 @@ use hotshot_types::{
 use url::Url;
 use vbs::version::StaticVersionType;
 
-use crate::{state_signature::StateSigner, static_stake_table_commitment, Node, SeqTypes};
+use crate::{
+    signer::RemoteSigner, state_signature::StateSigner, static_stake_table_commitment, Node,
+    SeqTypes,
+};
 /// The consensus handle
 pub type Consensus<N, P> = SystemContextHandle<SeqTypes, Node<N, P>>;
 
@@ -74,9 +77,10 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, Ver: StaticVersionTyp
     pub async fn init(
         network_config: NetworkConfig<PubKey>,
         instance_state: NodeState,
-        persistence: P,
+        mut persistence: P,
         network: Arc<N>,
         state_relay_server: Option<Url>,
+        remote_signer: Option<RemoteSigner<Ver>>,
         metrics: &dyn Metrics,
         stake_table_capacity: u64,
         _: Ver,
@@ -127,6 +131,7 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, Ver: StaticVersionTyp
             config.num_nodes_without_stake,
         )));
 
+        persistence.start_background_tasks(metrics);
         let persistence = Arc::new(RwLock::new(persistence));
 
         let handle = SystemContext::init(
@@ -144,7 +149,27 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence, Ver: StaticVersionTyp
         .await?
         .0;
 
-        let mut state_signer = StateSigner::new(state_key_pair, stake_table_commit);
+        let mut state_signer = match remote_signer {
+            Some(signer) => {
+                let state_signer = StateSigner::remote(signer, stake_table_commit).await?;
+                // The stake table is what light client provers use to check our signatures, so
+                // signing with any other key would be wasted work.
+                if let Some(peer) = config
+                    .known_nodes_with_stake
+                    .iter()
+                    .find(|peer| peer.stake_table_entry.stake_key == pub_key)
+                {
+                    ensure!(
+                        &peer.state_ver_key == state_signer.ver_key(),
+                        "remote signer holds state key {}, but the stake table has {} for {pub_key}",
+                        state_signer.ver_key(),
+                        peer.state_ver_key,
+                    );
+                }
+                state_signer
+            }
+            None => StateSigner::new(state_key_pair, stake_table_commit),
+        };
         if let Some(url) = state_relay_server {
             state_signer = state_signer.with_relay_server(url);
         }
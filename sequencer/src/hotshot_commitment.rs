@@ -1,15 +1,21 @@
 use std::{error::Error, time::Duration};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use async_std::{sync::Arc, task::sleep};
 use async_trait::async_trait;
-use contract_bindings::hot_shot::{HotShot, HotShotErrors, Qc};
+use clap::ValueEnum;
+use contract_bindings::{
+    hot_shot::{HotShot, HotShotErrors, Qc},
+    light_client::LightClient,
+};
+use derive_more::Display;
 use espresso_types::Header;
 use ethers::prelude::*;
 use futures::{
     future,
     stream::{self, StreamExt},
 };
+use hotshot_contract_adapter::light_client::{verify_block_merkle_proof, ParsedLightClientState};
 use hotshot_query_service::{availability::LeafQueryData, types::HeightIndexed};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
@@ -18,11 +24,33 @@ use sequencer_utils::{commitment_to_u256, contract_send, init_signer, Signer};
 use surf_disco::Url;
 use vbs::version::StaticVersionType;
 
-use crate::SeqTypes;
+use crate::{api::endpoints::BlockMerkleProofQueryData, SeqTypes};
 
 const RETRY_DELAY: Duration = Duration::from_secs(1);
 
-type HotShotClient<Ver> = surf_disco::Client<hotshot_query_service::Error, Ver>;
+/// How often the commitment task checks the `LightClient` contract for new snapshots.
+const LIGHT_CLIENT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+pub type HotShotClient<Ver> = surf_disco::Client<hotshot_query_service::Error, Ver>;
+
+/// Where the commitment task publishes HotShot block commitments.
+#[derive(Clone, Copy, Debug, Display, Default, PartialEq, Eq, ValueEnum)]
+pub enum CommitmentTaskMode {
+    /// Post the commitment of every block to the legacy `HotShot` contract.
+    ///
+    /// This duplicates the snapshots published to the `LightClient` contract by the state prover,
+    /// and will be removed once all consumers of the `HotShot` contract have migrated.
+    #[default]
+    #[display(fmt = "legacy")]
+    Legacy,
+    /// Do not write to layer 1.
+    ///
+    /// Snapshots are published only through the `LightClient` contract, by the state prover. The
+    /// task follows the snapshots in the `LightClient` contract, and queries formerly answered by
+    /// the `HotShot` contract can be answered by a [`LightClientReader`].
+    #[display(fmt = "light-client")]
+    LightClient,
+}
 
 #[derive(Clone, Debug)]
 pub struct CommitmentTaskOptions {
@@ -36,14 +64,26 @@ pub struct CommitmentTaskOptions {
     /// the RPC will be used.
     pub l1_chain_id: Option<u64>,
 
+    /// Where to publish block commitments.
+    pub mode: CommitmentTaskMode,
+
     /// Address of HotShot contract on layer 1.
-    pub hotshot_address: Address,
+    ///
+    /// Required in [`CommitmentTaskMode::Legacy`].
+    pub hotshot_address: Option<Address>,
+
+    /// Address of LightClient contract on layer 1.
+    ///
+    /// Required in [`CommitmentTaskMode::LightClient`].
+    pub light_client_address: Option<Address>,
 
     /// Mnemonic phrase for a funded wallet.
     ///
     /// This is the wallet that will be used to send blocks sequenced by HotShot to the sequencer
     /// contract. It must be funded with ETH on layer 1.
-    pub sequencer_mnemonic: String,
+    ///
+    /// Required in [`CommitmentTaskMode::Legacy`].
+    pub sequencer_mnemonic: Option<String>,
 
     /// Index of a funded account derived from sequencer-mnemonic.
     pub sequencer_account_index: u32,
@@ -73,22 +113,158 @@ pub async fn run_hotshot_commitment_task<Ver: StaticVersionType>(opt: &Commitmen
     .build();
     hotshot.connect(None).await;
 
-    // init a signer connecting to the HotShot contract
-    let signer = init_signer(
-        &opt.l1_provider,
-        &opt.sequencer_mnemonic,
-        opt.sequencer_account_index,
-    )
-    .await
-    .map(Arc::new)
-    .unwrap();
-    let contract = HotShot::new(opt.hotshot_address, signer.clone());
+    commitment_task(opt, hotshot).await;
+}
+
+/// Run the commitment task in the configured mode, reading HotShot data from `hotshot`.
+async fn commitment_task<D: HotShotDataSource + Clone + 'static>(
+    opt: &CommitmentTaskOptions,
+    hotshot: D,
+) {
+    match opt.mode {
+        CommitmentTaskMode::Legacy => {
+            tracing::warn!(
+                "posting block commitments to the legacy HotShot contract; this duplicates the \
+                 snapshots in the LightClient contract and will be removed in a future release"
+            );
+
+            // init a signer connecting to the HotShot contract
+            let signer = init_signer(
+                &opt.l1_provider,
+                opt.sequencer_mnemonic
+                    .as_ref()
+                    .expect("mnemonic must be specified"),
+                opt.sequencer_account_index,
+            )
+            .await
+            .map(Arc::new)
+            .unwrap();
+            let contract = HotShot::new(
+                opt.hotshot_address
+                    .expect("HotShot contract address must be specified"),
+                signer.clone(),
+            );
+
+            sequence(hotshot, contract, opt.delay).await;
+        }
+        CommitmentTaskMode::LightClient => {
+            let provider = Provider::<Http>::try_from(opt.l1_provider.to_string()).unwrap();
+            let contract = LightClient::new(
+                opt.light_client_address
+                    .expect("LightClient contract address must be specified"),
+                Arc::new(provider),
+            );
+
+            follow_light_client(&hotshot, &LightClientReader::new(contract, hotshot.clone())).await;
+        }
+    }
+}
+
+/// Follow the snapshots published to the `LightClient` contract, without writing to layer 1.
+async fn follow_light_client<M: Middleware + 'static>(
+    hotshot: &impl HotShotDataSource,
+    reader: &LightClientReader<M, impl HotShotDataSource>,
+) {
+    let mut last_snapshot = None;
+    loop {
+        if let Some(snapshot) = poll_light_client(hotshot, reader, last_snapshot).await {
+            last_snapshot = Some(snapshot);
+        }
+        sleep(LIGHT_CLIENT_POLL_INTERVAL).await;
+    }
+}
 
-    sequence(hotshot, contract, opt.delay).await;
+/// Check the `LightClient` contract for a snapshot other than `last_snapshot`.
+///
+/// Returns the block height of the new snapshot, if there is one.
+async fn poll_light_client<M: Middleware + 'static>(
+    hotshot: &impl HotShotDataSource,
+    reader: &LightClientReader<M, impl HotShotDataSource>,
+    last_snapshot: Option<u64>,
+) -> Option<u64> {
+    match reader.block_height().await {
+        Ok(snapshot) if last_snapshot != Some(snapshot) => {
+            match hotshot.block_height().await {
+                Ok(height) => tracing::info!(
+                    snapshot,
+                    lag = height.saturating_sub(snapshot),
+                    "light client snapshot published"
+                ),
+                Err(err) => tracing::warn!(
+                    snapshot,
+                    "light client snapshot published, unable to get HotShot block height: {err}"
+                ),
+            }
+            Some(snapshot)
+        }
+        Ok(snapshot) => {
+            tracing::debug!(snapshot, "no new light client snapshot");
+            None
+        }
+        Err(err) => {
+            tracing::error!("error reading LightClient contract: {err:#}");
+            None
+        }
+    }
 }
 
-async fn sequence<Ver: StaticVersionType>(
-    hotshot: HotShotClient<Ver>,
+/// Answers queries formerly made to the legacy `HotShot` contract, using `LightClient` data.
+///
+/// The `LightClient` contract only stores commitments for the blocks at which it was updated. The
+/// commitment of any earlier block is proven against the block Merkle tree of the latest finalized
+/// snapshot, using a proof from an untrusted query service.
+#[derive(Clone, Debug)]
+pub struct LightClientReader<M, D> {
+    contract: LightClient<M>,
+    query_service: D,
+}
+
+impl<M: Middleware + 'static, D: HotShotDataSource> LightClientReader<M, D> {
+    pub fn new(contract: LightClient<M>, query_service: D) -> Self {
+        Self {
+            contract,
+            query_service,
+        }
+    }
+
+    /// The number of blocks with a commitment available, like `HotShot.blockHeight`.
+    pub async fn block_height(&self) -> anyhow::Result<u64> {
+        Ok(self.finalized_state().await?.block_height)
+    }
+
+    /// The commitment of the block at `height`, like `HotShot.commitments`.
+    ///
+    /// As in the `HotShot` contract, this is zero if the block has not been committed yet.
+    pub async fn commitment(&self, height: u64) -> anyhow::Result<U256> {
+        let state = self.finalized_state().await?;
+        if height >= state.block_height {
+            return Ok(U256::zero());
+        }
+        let res = self
+            .query_service
+            .get_block_merkle_proof(state.block_height, height)
+            .await
+            .context(format!(
+                "fetching proof of block {height} for snapshot {}",
+                state.block_height
+            ))?;
+        let comm = verify_block_merkle_proof(&state, &res.root, height, &res.proof)?;
+        Ok(U256::from_little_endian(&comm))
+    }
+
+    async fn finalized_state(&self) -> anyhow::Result<ParsedLightClientState> {
+        Ok(self
+            .contract
+            .get_finalized_state()
+            .call()
+            .await
+            .context("reading finalized state from LightClient contract")?
+            .into())
+    }
+}
+
+async fn sequence(
+    hotshot: impl HotShotDataSource,
     contract: HotShot<Signer>,
     delay: Option<Duration>,
 ) {
@@ -138,12 +314,17 @@ async fn sequence<Ver: StaticVersionType>(
 
 /// Trait for generalized query service for parts of the HotShot data involved in syncing with L1 contract
 #[async_trait]
-trait HotShotDataSource {
+pub trait HotShotDataSource: Send + Sync {
     type Error: Error + Send + Sync + 'static;
 
     async fn block_height(&self) -> Result<u64, Self::Error>;
     async fn wait_for_block_height(&self, height: u64) -> Result<(), Self::Error>;
     async fn get_leaf(&self, height: u64) -> Result<LeafQueryData<SeqTypes>, Self::Error>;
+    async fn get_block_merkle_proof(
+        &self,
+        snapshot: u64,
+        height: u64,
+    ) -> Result<BlockMerkleProofQueryData, Self::Error>;
 }
 
 #[async_trait]
@@ -168,6 +349,16 @@ impl<Ver: StaticVersionType> HotShotDataSource for HotShotClient<Ver> {
             .send()
             .await
     }
+
+    async fn get_block_merkle_proof(
+        &self,
+        snapshot: u64,
+        height: u64,
+    ) -> Result<BlockMerkleProofQueryData, Self::Error> {
+        self.get(&format!("block-state/light-client/{snapshot}/{height}"))
+            .send()
+            .await
+    }
 }
 
 /// Error type during synchronization between data sources (e.g. L1, query services)
@@ -278,12 +469,18 @@ mod test {
     use async_compatibility_layer::logging::{setup_backtrace, setup_logging};
     use async_std::task::spawn;
     use committable::Committable;
-    use contract_bindings::hot_shot::{NewBlocksCall, NewBlocksFilter};
-    use espresso_types::{L1Client, Leaf, NodeState, ValidatedState};
-    use ethers::{abi::AbiDecode, providers::Middleware};
+    use contract_bindings::light_client_mock::{LightClientMock, LightClientMockErrors};
+    use espresso_light_client::block_comm_root;
+    use espresso_types::{L1Client, Leaf, NodeState, ValidatedState, BLOCK_MERKLE_TREE_HEIGHT};
     use futures::FutureExt;
+    use hotshot_contract_adapter::jellyfish::field_to_u256;
     use hotshot_types::simple_certificate::QuorumCertificate;
-    use sequencer_utils::{test_utils::TestL1System, AnvilOptions};
+    use jf_merkle_tree::{prelude::SHA3MerkleTree, MerkleTreeScheme};
+    use sequencer_utils::{
+        deployer::{deploy_mock_light_client_contract, Contracts},
+        test_utils::TestL1System,
+        AnvilOptions,
+    };
     use surf_disco::{Error, StatusCode};
 
     use super::*;
@@ -326,6 +523,40 @@ mod test {
                     )
                 })
         }
+
+        async fn get_block_merkle_proof(
+            &self,
+            snapshot: u64,
+            height: u64,
+        ) -> Result<BlockMerkleProofQueryData, Self::Error> {
+            let not_found = || {
+                Self::Error::catch_all(
+                    StatusCode::NOT_FOUND,
+                    format!("no proof for height {height} in snapshot {snapshot}"),
+                )
+            };
+            let tree = self.block_merkle_tree(snapshot).ok_or_else(not_found)?;
+            let (_, proof) = tree.lookup(height).expect_ok().map_err(|_| not_found())?;
+            Ok(BlockMerkleProofQueryData {
+                root: tree.commitment(),
+                proof,
+            })
+        }
+    }
+
+    impl MockDataSource {
+        /// The block Merkle tree in the header at height `snapshot`.
+        ///
+        /// Returns [`None`] if any of the leaves below `snapshot` are missing.
+        fn block_merkle_tree(&self, snapshot: u64) -> Option<SHA3MerkleTree<[u8; 32]>> {
+            let block_hashes = self
+                .leaves
+                .get(..snapshot as usize)?
+                .iter()
+                .map(|leaf| Some(<[u8; 32]>::from(leaf.as_ref()?.block_hash())))
+                .collect::<Option<Vec<_>>>()?;
+            Some(SHA3MerkleTree::from_elems(Some(BLOCK_MERKLE_TREE_HEIGHT), block_hashes).unwrap())
+        }
     }
 
     async fn mock_leaf(height: u64, node_state: &NodeState) -> LeafQueryData<SeqTypes> {
@@ -336,22 +567,37 @@ mod test {
         LeafQueryData::new(leaf, qc).unwrap()
     }
 
-    async fn wait_for_new_batches(
-        l1: &TestL1System,
-        from_block: u64,
-    ) -> (NewBlocksFilter, LogMeta) {
-        l1.hotshot
-            .new_blocks_filter()
-            .from_block(from_block)
-            // Ethers does not set the contract address on filters created via contract bindings.
-            // This seems like a bug and I have reported it:
-            // https://github.com/gakonst/ethers-rs/issues/2528. In the mean time we can work around
-            // by setting the address manually.
-            .address(l1.hotshot.address().into())
-            .query_with_meta()
-            .await
-            .unwrap()
-            .remove(0)
+    async fn deploy_light_client(l1: &TestL1System) -> (LightClientMock<Signer>, Arc<Signer>) {
+        let signer = Arc::new(
+            init_signer(l1.provider.url(), TEST_MNEMONIC, l1.clients.funded[0].index)
+                .await
+                .unwrap(),
+        );
+        let address =
+            deploy_mock_light_client_contract(signer.clone(), &mut Contracts::default(), None)
+                .await
+                .unwrap();
+        (LightClientMock::new(address, signer.clone()), signer)
+    }
+
+    /// Publish a snapshot of the blocks in `data` below `height` to the mock `LightClient`.
+    async fn publish_snapshot(
+        light_client: &LightClientMock<Signer>,
+        data: &MockDataSource,
+        height: u64,
+    ) {
+        let state = ParsedLightClientState {
+            block_height: height,
+            block_comm_root: field_to_u256(
+                block_comm_root(&data.block_merkle_tree(height).unwrap().commitment()).unwrap(),
+            ),
+            ..ParsedLightClientState::dummy_genesis()
+        };
+        contract_send::<_, _, LightClientMockErrors>(
+            &light_client.set_finalized_state(state.into()),
+        )
+        .await
+        .unwrap();
     }
 
     #[async_std::test]
@@ -362,71 +608,58 @@ mod test {
         let anvil = AnvilOptions::default().spawn().await;
 
         let l1 = TestL1System::deploy(anvil.provider()).await.unwrap();
+        let (light_client, signer) = deploy_light_client(&l1).await;
 
-        let l1_initial_block = l1.provider.get_block_number().await.unwrap();
-        let initial_batch_num = l1.hotshot.block_height().call().await.unwrap();
-
-        let adaptor_l1_signer = Arc::new(
-            init_signer(l1.provider.url(), TEST_MNEMONIC, l1.clients.funded[0].index)
-                .await
-                .unwrap(),
-        );
-
-        // Create a few test batches.
-        let num_batches = l1.hotshot.max_blocks().call().await.unwrap().as_usize();
+        // Create a few test blocks.
+        let num_blocks = 10;
         let mut data = MockDataSource::default();
 
         let node_state =
             NodeState::mock().with_l1(L1Client::new(anvil.provider().url().clone(), 1));
 
-        for i in 0..num_batches {
-            data.leaves
-                .push(Some(mock_leaf(i as u64, &node_state).await));
+        for i in 0..num_blocks {
+            data.leaves.push(Some(mock_leaf(i, &node_state).await));
         }
-        tracing::info!("sequencing batches: {:?}", data.leaves);
-
-        // Connect to the HotShot contract with the expected L1 client.
-        let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
-
-        // Ensure the transaction we're going to execute is less than the Geth RPC size limit.
-        let txn = build_sequence_batches_txn(
-            &l1.hotshot,
-            data.leaves.clone().into_iter().map(Option::unwrap),
-        )
-        .tx;
-        let size = txn.rlp().len();
-        tracing::info!("transaction is {size} bytes");
-        assert!(size < 131072);
-
-        // Sequence them in the HotShot contract.
-        sync_with_l1(num_batches, &data, &hotshot).await.unwrap();
+        tracing::info!("sequencing blocks: {:?}", data.leaves);
+
+        // Start the task in light client mode, without a wallet or a HotShot contract.
+        let opt = CommitmentTaskOptions {
+            l1_provider: l1.provider.url().clone(),
+            l1_chain_id: None,
+            mode: CommitmentTaskMode::LightClient,
+            hotshot_address: None,
+            light_client_address: Some(light_client.address()),
+            sequencer_mnemonic: None,
+            sequencer_account_index: 0,
+            query_service_url: None,
+            request_timeout: Duration::from_secs(5),
+            delay: None,
+        };
+        let task = {
+            let data = data.clone();
+            spawn(async move { commitment_task(&opt, data).await })
+        };
 
-        // Check the NewBatches event.
-        let (event, meta) = wait_for_new_batches(&l1, l1_initial_block.as_u64()).await;
-        assert_eq!(event.first_block_number, initial_batch_num);
+        // Publish a snapshot of the blocks, as the state prover would.
+        publish_snapshot(&light_client, &data, num_blocks).await;
 
-        let calldata = l1
-            .provider
-            .get_transaction(meta.transaction_hash)
-            .await
-            .unwrap()
-            .unwrap()
-            .input;
-        let call = NewBlocksCall::decode(calldata).unwrap();
-        assert_eq!(
-            call.qcs,
-            data.leaves
-                .into_iter()
-                .map(Option::unwrap)
-                .map(|leaf| Qc {
-                    height: leaf.height().into(),
-                    block_commitment: U256::from_little_endian(&<[u8; 32]>::from(
-                        leaf.block_hash()
-                    )),
-                    ..Default::default()
-                })
-                .collect::<Vec<_>>()
+        // The snapshot commits to the same blocks the task used to send to the HotShot contract.
+        let reader = LightClientReader::new(
+            LightClient::new(light_client.address(), signer),
+            data.clone(),
         );
+        assert_eq!(reader.block_height().await.unwrap(), num_blocks);
+        for leaf in data.leaves.iter().map(|leaf| leaf.clone().unwrap()) {
+            assert_eq!(
+                reader.commitment(leaf.height()).await.unwrap(),
+                commitment_to_u256(leaf.block_hash())
+            );
+        }
+
+        // The task keeps following the light client, and never writes to the HotShot contract.
+        sleep(Duration::from_secs(3)).await;
+        assert!(task.now_or_never().is_none());
+        assert_eq!(l1.hotshot.block_height().call().await.unwrap().as_u64(), 0);
     }
 
     #[async_std::test]
@@ -437,61 +670,56 @@ mod test {
         let anvil = AnvilOptions::default().spawn().await;
 
         let l1 = TestL1System::deploy(anvil.provider()).await.unwrap();
-        let mut from_block = l1.provider.get_block_number().await.unwrap();
-        let adaptor_l1_signer = Arc::new(
-            init_signer(l1.provider.url(), TEST_MNEMONIC, l1.clients.funded[0].index)
-                .await
-                .unwrap(),
-        );
+        let (light_client, signer) = deploy_light_client(&l1).await;
 
-        // Create a test batch.
+        // Create a test block.
         let mut data = MockDataSource::default();
 
         let node_state =
             NodeState::mock().with_l1(L1Client::new(anvil.provider().url().clone(), 1));
         data.leaves.push(Some(mock_leaf(0, &node_state).await));
+        publish_snapshot(&light_client, &data, 1).await;
 
-        // Connect to the HotShot contract with the expected L1 client.
-        let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
-
-        // Sequence them in the HotShot contract.
-        sync_with_l1(1, &data, &hotshot).await.unwrap();
+        let reader = LightClientReader::new(
+            LightClient::new(light_client.address(), signer.clone()),
+            data.clone(),
+        );
 
-        // Check the NewBatches event.
-        let (event, meta) = wait_for_new_batches(&l1, from_block.as_u64()).await;
-        assert_eq!(event.first_block_number.as_u64(), 0);
-        from_block = meta.block_number + 1;
+        // Reading the same snapshot again gives the same answers, and never reports a block which
+        // is not yet in a snapshot.
+        for _ in 0..2 {
+            assert_eq!(reader.block_height().await.unwrap(), 1);
+            assert_eq!(
+                reader.commitment(0).await.unwrap(),
+                commitment_to_u256(data.leaves[0].clone().unwrap().block_hash())
+            );
+            assert_eq!(reader.commitment(1).await.unwrap(), 0.into());
+        }
 
-        // Sequencing the same batch again should block until new blocks are available.
-        let fut = {
-            let data = data.clone();
-            let hotshot = hotshot.clone();
-            spawn(async move { sync_with_l1(1, &data, &hotshot).await })
-        };
-        // Sleep for a few seconds and make sure nothing happened.
-        sleep(Duration::from_secs(3)).await;
-        assert!(fut.now_or_never().is_none());
-        assert_eq!(l1.hotshot.block_height().call().await.unwrap().as_u64(), 1);
+        // Polling the same snapshot again does not report it as new.
+        assert_eq!(poll_light_client(&data, &reader, None).await, Some(1));
+        assert_eq!(poll_light_client(&data, &reader, Some(1)).await, None);
+        assert_eq!(poll_light_client(&data, &reader, Some(1)).await, None);
 
-        // Once a new batch is available, we can sequence it.
+        // Once a new block is in a snapshot, we can read it.
         data.leaves.push(Some(mock_leaf(1, &node_state).await));
-        sync_with_l1(1, &data, &hotshot).await.unwrap();
-        let (event, _) = wait_for_new_batches(&l1, from_block.as_u64()).await;
-        assert_eq!(event.first_block_number.as_u64(), 1);
+        publish_snapshot(&light_client, &data, 2).await;
+        let reader = LightClientReader::new(
+            LightClient::new(light_client.address(), signer),
+            data.clone(),
+        );
+        assert_eq!(poll_light_client(&data, &reader, Some(1)).await, Some(2));
 
         // Double-check the data in the contract.
         assert_eq!(
-            l1.hotshot.commitments(0.into()).call().await.unwrap(),
+            reader.commitment(0).await.unwrap(),
             commitment_to_u256(data.leaves[0].clone().unwrap().block_hash())
         );
         assert_eq!(
-            l1.hotshot.commitments(1.into()).call().await.unwrap(),
+            reader.commitment(1).await.unwrap(),
             commitment_to_u256(data.leaves[1].clone().unwrap().block_hash())
         );
-        assert_eq!(
-            l1.hotshot.commitments(2.into()).call().await.unwrap(),
-            0.into()
-        );
+        assert_eq!(reader.commitment(2).await.unwrap(), 0.into());
     }
 
     #[async_std::test]
@@ -502,36 +730,120 @@ mod test {
         let anvil = AnvilOptions::default().spawn().await;
 
         let l1 = TestL1System::deploy(anvil.provider()).await.unwrap();
-        let l1_initial_block = l1.provider.get_block_number().await.unwrap();
-        let adaptor_l1_signer = Arc::new(
-            init_signer(l1.provider.url(), TEST_MNEMONIC, l1.clients.funded[0].index)
-                .await
-                .unwrap(),
-        );
+        let (light_client, signer) = deploy_light_client(&l1).await;
 
         let node_state =
             NodeState::mock().with_l1(L1Client::new(anvil.provider().url().clone(), 1));
+        let mut data = MockDataSource::default();
+        for i in 0..3 {
+            data.leaves.push(Some(mock_leaf(i, &node_state).await));
+        }
+        publish_snapshot(&light_client, &data, 3).await;
+
+        // If the query service is missing some leaves, it cannot prove any block against the
+        // snapshot, but we can still read and follow the snapshot itself.
+        let mut partial = data.clone();
+        partial.leaves[2] = None;
+        let reader = LightClientReader::new(
+            LightClient::new(light_client.address(), signer.clone()),
+            partial.clone(),
+        );
+        assert_eq!(reader.block_height().await.unwrap(), 3);
+        reader.commitment(0).await.unwrap_err();
+        assert_eq!(poll_light_client(&partial, &reader, None).await, Some(3));
+
+        // The reader rejects proofs which don't match the snapshot.
+        let bad = LightClientReader::new(
+            LightClient::new(light_client.address(), signer),
+            MockDataSource {
+                leaves: data.leaves.iter().rev().cloned().collect(),
+            },
+        );
+        assert_eq!(bad.block_height().await.unwrap(), 3);
+        bad.commitment(0).await.unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_follow_light_client() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = AnvilOptions::default().spawn().await;
+
+        let l1 = TestL1System::deploy(anvil.provider()).await.unwrap();
+        let (light_client, signer) = deploy_light_client(&l1).await;
 
-        // Create a sequence of leaves, some of which are missing.
+        let node_state =
+            NodeState::mock().with_l1(L1Client::new(anvil.provider().url().clone(), 1));
         let mut data = MockDataSource::default();
-        data.leaves
-            .extend([None, Some(mock_leaf(1, &node_state).await), None]);
+        for i in 0..5 {
+            data.leaves.push(Some(mock_leaf(i, &node_state).await));
+        }
+        let reader = LightClientReader::new(
+            LightClient::new(light_client.address(), signer),
+            data.clone(),
+        );
 
-        // Connect to the HotShot contract with the expected L1 client.
-        let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer);
+        // The genesis snapshot is reported once.
+        assert_eq!(poll_light_client(&data, &reader, None).await, Some(0));
+        assert_eq!(poll_light_client(&data, &reader, Some(0)).await, None);
 
-        // If the first leaf is missing, we cannot make any progress, and sync should fail.
-        sync_with_l1(3, &data, &hotshot).await.unwrap_err();
+        // A new snapshot is reported once.
+        publish_snapshot(&light_client, &data, 4).await;
+        assert_eq!(poll_light_client(&data, &reader, Some(0)).await, Some(4));
+        assert_eq!(poll_light_client(&data, &reader, Some(4)).await, None);
 
-        // If the first leaf is present but subsequent leaves are missing, we should sequence the
-        // leaves that are available.
+        // The task follows the snapshots without exiting.
+        let task = {
+            let data = data.clone();
+            spawn(async move { follow_light_client(&data, &reader).await })
+        };
+        sleep(Duration::from_secs(3)).await;
+        assert!(task.now_or_never().is_none());
+    }
 
-        data.leaves[0] = Some(mock_leaf(0, &node_state).await);
-        sync_with_l1(3, &data, &hotshot).await.unwrap();
+    #[async_std::test]
+    async fn test_light_client_reader() {
+        setup_logging();
+        setup_backtrace();
+
+        let anvil = AnvilOptions::default().spawn().await;
+
+        let l1 = TestL1System::deploy(anvil.provider()).await.unwrap();
+        let (light_client, adaptor_l1_signer) = deploy_light_client(&l1).await;
 
-        // Check the NewBatches event.
-        let event = wait_for_new_batches(&l1, l1_initial_block.as_u64()).await.0;
-        assert_eq!(event.first_block_number, 0.into());
-        assert_eq!(event.num_blocks, 2.into());
+        let node_state =
+            NodeState::mock().with_l1(L1Client::new(anvil.provider().url().clone(), 1));
+        let mut data = MockDataSource::default();
+        for i in 0..5 {
+            data.leaves.push(Some(mock_leaf(i, &node_state).await));
+        }
+
+        // Sequence the first 4 blocks in the legacy HotShot contract.
+        let hotshot = HotShot::new(l1.hotshot.address(), adaptor_l1_signer.clone());
+        let legacy = MockDataSource {
+            leaves: data.leaves[..4].to_vec(),
+        };
+        sync_with_l1(4, &legacy, &hotshot).await.unwrap();
+
+        // Publish a light client snapshot at height 4, which commits to the same blocks.
+        let reader = LightClientReader::new(
+            LightClient::new(light_client.address(), adaptor_l1_signer),
+            data.clone(),
+        );
+        assert_eq!(reader.block_height().await.unwrap(), 0);
+        publish_snapshot(&light_client, &data, 4).await;
+
+        // The reader answers the legacy queries exactly like the HotShot contract.
+        assert_eq!(
+            reader.block_height().await.unwrap(),
+            l1.hotshot.block_height().call().await.unwrap().as_u64()
+        );
+        for i in 0..6 {
+            assert_eq!(
+                reader.commitment(i).await.unwrap(),
+                l1.hotshot.commitments(i.into()).call().await.unwrap()
+            );
+        }
     }
 }
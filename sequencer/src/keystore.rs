@@ -0,0 +1,420 @@
+//! Encrypted keystore for consensus and builder keys.
+//!
+//! The keystore format is modeled on [EIP-2335](https://eips.ethereum.org/EIPS/eip-2335): secrets
+//! are encrypted with AES-128-CTR under a key derived from a passphrase using scrypt, and a SHA-256
+//! checksum over the second half of the derived key and the ciphertext is used to detect a wrong
+//! passphrase. Unlike EIP-2335, a single keystore can hold all the keys an operator needs: a BLS
+//! staking key, a Schnorr state signing key and an Ethereum key for a builder. The public parts of
+//! these keys are stored in the clear, so they can be inspected without the passphrase.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use aes::Aes128;
+use anyhow::{bail, ensure, Context};
+use clap::Parser;
+use ctr::{
+    cipher::{KeyIvInit, StreamCipher},
+    Ctr128BE,
+};
+use derivative::Derivative;
+use espresso_types::eth_signature_key::EthKeyPair;
+use ethers::{core::k256::ecdsa::SigningKey, types::Address, utils::hex};
+use hotshot::types::{BLSPubKey, SignatureKey};
+use hotshot_types::{
+    light_client::{StateKeyPair, StateSignKey},
+    signature_key::BLSPrivKey,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+/// The version of the keystore format.
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// scrypt work factor (as log2 of N) for new keystores. This is the EIP-2335 default, N = 2^18.
+const DEFAULT_LOG_N: u8 = 18;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const DKLEN: usize = 32;
+
+/// The keys which can be held in a keystore.
+#[derive(Clone, Default, Derivative)]
+#[derivative(Debug)]
+pub struct Keys {
+    /// BLS key used for consensus (the staking key).
+    #[derivative(Debug = "ignore")]
+    pub staking: Option<BLSPrivKey>,
+    /// Schnorr key used to sign light client state updates (the state key).
+    #[derivative(Debug = "ignore")]
+    pub state: Option<StateSignKey>,
+    /// Ethereum key used by a builder to pay fees.
+    pub eth: Option<EthKeyPair>,
+}
+
+impl Keys {
+    /// The public keys corresponding to these keys.
+    pub fn public_keys(&self) -> PublicKeys {
+        PublicKeys {
+            staking: self
+                .staking
+                .as_ref()
+                .map(|key| BLSPubKey::from_private(key).to_string()),
+            state: self.state.as_ref().map(|key| {
+                StateKeyPair::from_sign_key(key.clone())
+                    .ver_key()
+                    .to_string()
+            }),
+            eth: self.eth.as_ref().map(|key| key.address()),
+        }
+    }
+}
+
+/// The public keys of the keys held in a keystore.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKeys {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staking: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eth: Option<Address>,
+}
+
+/// The encrypted form of [`Keys`].
+#[derive(Default, Serialize, Deserialize)]
+struct Secrets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    staking: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    eth: Option<String>,
+}
+
+/// An encrypted keystore.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    #[serde(default)]
+    pub description: String,
+    pub public_keys: PublicKeys,
+    crypto: Crypto,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Crypto {
+    kdf: CryptoModule<KdfParams>,
+    checksum: CryptoModule<EmptyParams>,
+    cipher: CryptoModule<CipherParams>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CryptoModule<P> {
+    function: String,
+    params: P,
+    message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct KdfParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct EmptyParams {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+impl Keystore {
+    /// Encrypt `keys` under `passphrase`.
+    pub fn encrypt(keys: &Keys, passphrase: &str, description: String) -> anyhow::Result<Self> {
+        Self::encrypt_with_work(keys, passphrase, description, DEFAULT_LOG_N)
+    }
+
+    fn encrypt_with_work(
+        keys: &Keys,
+        passphrase: &str,
+        description: String,
+        log_n: u8,
+    ) -> anyhow::Result<Self> {
+        let secrets = Secrets {
+            staking: keys.staking.as_ref().map(|key| key.to_string()),
+            state: keys.state.as_ref().map(|key| key.to_string()),
+            eth: keys
+                .eth
+                .as_ref()
+                .map(|key| hex::encode(key.signer().signer().to_bytes())),
+        };
+        let mut secret = Zeroizing::new(serde_json::to_vec(&secrets)?);
+
+        let mut salt = [0; 32];
+        OsRng.fill_bytes(&mut salt);
+        let kdf = KdfParams {
+            dklen: DKLEN,
+            n: 1 << log_n,
+            r: SCRYPT_R,
+            p: SCRYPT_P,
+            salt: hex::encode(salt),
+        };
+        let dk = derive_key(&kdf, passphrase)?;
+
+        let mut iv = [0; 16];
+        OsRng.fill_bytes(&mut iv);
+        Ctr128BE::<Aes128>::new(dk[..16].into(), &iv.into()).apply_keystream(&mut secret);
+        let checksum = checksum(&dk, &secret);
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            description,
+            public_keys: keys.public_keys(),
+            crypto: Crypto {
+                kdf: CryptoModule {
+                    function: "scrypt".into(),
+                    params: kdf,
+                    message: String::new(),
+                },
+                checksum: CryptoModule {
+                    function: "sha256".into(),
+                    params: EmptyParams {},
+                    message: hex::encode(checksum),
+                },
+                cipher: CryptoModule {
+                    function: "aes-128-ctr".into(),
+                    params: CipherParams {
+                        iv: hex::encode(iv),
+                    },
+                    message: hex::encode(&*secret),
+                },
+            },
+        })
+    }
+
+    /// Decrypt the keys in this keystore using `passphrase`.
+    pub fn decrypt(&self, passphrase: &str) -> anyhow::Result<Keys> {
+        ensure!(
+            self.version == KEYSTORE_VERSION,
+            "unsupported keystore version {}",
+            self.version
+        );
+        let crypto = &self.crypto;
+        ensure!(
+            crypto.kdf.function == "scrypt",
+            "unsupported KDF {}",
+            crypto.kdf.function
+        );
+        ensure!(
+            crypto.checksum.function == "sha256",
+            "unsupported checksum {}",
+            crypto.checksum.function
+        );
+        ensure!(
+            crypto.cipher.function == "aes-128-ctr",
+            "unsupported cipher {}",
+            crypto.cipher.function
+        );
+
+        let dk = derive_key(&crypto.kdf.params, passphrase)?;
+        let mut secret = Zeroizing::new(
+            hex::decode(&crypto.cipher.message).context("malformed cipher message")?,
+        );
+        let expected = hex::decode(&crypto.checksum.message).context("malformed checksum")?;
+        ensure!(
+            checksum(&dk, &secret).as_slice() == expected.as_slice(),
+            "incorrect passphrase"
+        );
+
+        let iv: [u8; 16] = hex::decode(&crypto.cipher.params.iv)
+            .context("malformed IV")?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("IV must be 16 bytes"))?;
+        Ctr128BE::<Aes128>::new(dk[..16].into(), &iv.into()).apply_keystream(&mut secret);
+
+        let secrets: Secrets = serde_json::from_slice(&secret).context("malformed secrets")?;
+        let keys = Keys {
+            staking: secrets
+                .staking
+                .map(|key| key.parse())
+                .transpose()
+                .context("malformed staking key")?,
+            state: secrets
+                .state
+                .map(|key| key.parse())
+                .transpose()
+                .context("malformed state key")?,
+            eth: secrets
+                .eth
+                .map(|key| -> anyhow::Result<_> {
+                    let bytes = Zeroizing::new(hex::decode(key)?);
+                    Ok(EthKeyPair::from(SigningKey::from_slice(&bytes)?))
+                })
+                .transpose()
+                .context("malformed Ethereum key")?,
+        };
+        ensure!(
+            keys.public_keys() == self.public_keys,
+            "decrypted keys do not match the public keys in the keystore"
+        );
+        Ok(keys)
+    }
+
+    /// Load a keystore from a file.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).context(format!("reading keystore {}", path.display()))?;
+        Self::from_bytes(&bytes).context(format!("parsing keystore {}", path.display()))
+    }
+
+    /// Parse a keystore.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+
+    /// Whether `bytes` look like a keystore, as opposed to some other kind of key file.
+    pub fn is_keystore(bytes: &[u8]) -> bool {
+        bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{')
+    }
+
+    /// Save this keystore to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .context(format!("writing keystore {}", path.display()))
+    }
+}
+
+fn derive_key(params: &KdfParams, passphrase: &str) -> anyhow::Result<Zeroizing<[u8; DKLEN]>> {
+    ensure!(
+        params.dklen == DKLEN,
+        "unsupported key length {}",
+        params.dklen
+    );
+    if !params.n.is_power_of_two() || params.n < 2 {
+        bail!("scrypt parameter n must be a power of 2");
+    }
+    let log_n = params.n.trailing_zeros() as u8;
+    let salt = hex::decode(&params.salt).context("malformed salt")?;
+    let scrypt_params =
+        scrypt::Params::new(log_n, params.r, params.p).context("invalid scrypt parameters")?;
+    let mut dk = Zeroizing::new([0; DKLEN]);
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &scrypt_params, &mut dk[..])
+        .context("deriving key")?;
+    Ok(dk)
+}
+
+fn checksum(dk: &[u8; DKLEN], ciphertext: &[u8]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(&dk[16..])
+        .chain_update(ciphertext)
+        .finalize()
+        .into()
+}
+
+/// Options for unlocking a keystore.
+#[derive(Parser, Clone, Default, Derivative)]
+#[derivative(Debug)]
+pub struct PassphraseOptions {
+    /// Passphrase to unlock the keystore.
+    #[clap(
+        long,
+        env = "ESPRESSO_KEYSTORE_PASSPHRASE",
+        conflicts_with = "keystore_passphrase_file"
+    )]
+    #[derivative(Debug = "ignore")]
+    pub keystore_passphrase: Option<String>,
+
+    /// Path to a file containing the passphrase to unlock the keystore.
+    #[clap(long, env = "ESPRESSO_KEYSTORE_PASSPHRASE_FILE")]
+    pub keystore_passphrase_file: Option<PathBuf>,
+}
+
+impl PassphraseOptions {
+    /// Get the configured passphrase.
+    pub fn passphrase(&self) -> anyhow::Result<Zeroizing<String>> {
+        if let Some(passphrase) = &self.keystore_passphrase {
+            Ok(Zeroizing::new(passphrase.clone()))
+        } else if let Some(path) = &self.keystore_passphrase_file {
+            let passphrase = Zeroizing::new(
+                fs::read_to_string(path)
+                    .context(format!("reading passphrase from {}", path.display()))?,
+            );
+            // Ignore a trailing newline, which most editors add.
+            Ok(Zeroizing::new(
+                passphrase.trim_end_matches(['\r', '\n']).to_string(),
+            ))
+        } else {
+            bail!("a keystore passphrase or passphrase file is required")
+        }
+    }
+
+    /// Load the keystore at `path` and unlock it.
+    pub fn unlock(&self, path: impl AsRef<Path>) -> anyhow::Result<Keys> {
+        let path = path.as_ref();
+        Keystore::load(path)?
+            .decrypt(&self.passphrase()?)
+            .context(format!("unlocking keystore {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let (_, staking) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+        let state = StateKeyPair::generate_from_seed_indexed([0; 32], 0);
+        let eth = EthKeyPair::from_mnemonic(
+            "test test test test test test test test test test test junk",
+            0u32,
+        )
+        .unwrap();
+        let keys = Keys {
+            staking: Some(staking),
+            state: Some(state.sign_key_ref().clone()),
+            eth: Some(eth.clone()),
+        };
+
+        let keystore = Keystore::encrypt_with_work(&keys, "passphrase", "test".into(), 4).unwrap();
+        assert_eq!(keystore.public_keys, keys.public_keys());
+        assert_eq!(keystore.public_keys.eth, Some(eth.address()));
+
+        // Round trip through the serialized form.
+        let keystore =
+            Keystore::from_bytes(&serde_json::to_vec_pretty(&keystore).unwrap()).unwrap();
+        let decrypted = keystore.decrypt("passphrase").unwrap();
+        assert_eq!(decrypted.public_keys(), keys.public_keys());
+        assert_eq!(decrypted.eth, Some(eth));
+
+        let err = keystore.decrypt("wrong").unwrap_err();
+        assert!(err.to_string().contains("incorrect passphrase"), "{err:#}");
+    }
+
+    #[test]
+    fn test_keystore_partial() {
+        let eth = EthKeyPair::from_mnemonic(
+            "test test test test test test test test test test test junk",
+            1u32,
+        )
+        .unwrap();
+        let keys = Keys {
+            eth: Some(eth.clone()),
+            ..Default::default()
+        };
+        let keystore = Keystore::encrypt_with_work(&keys, "", String::new(), 4).unwrap();
+        let decrypted = keystore.decrypt("").unwrap();
+        assert!(decrypted.staking.is_none());
+        assert!(decrypted.state.is_none());
+        assert_eq!(decrypted.eth, Some(eth));
+    }
+}
@@ -4,12 +4,14 @@ pub mod context;
 pub mod genesis;
 
 pub mod hotshot_commitment;
+pub mod keystore;
 pub mod options;
+pub mod signer;
 pub mod state_signature;
 
 mod message_compat_tests;
 
-use anyhow::Context;
+use anyhow::{bail, ensure, Context};
 use async_std::sync::RwLock;
 use catchup::StatePeers;
 use context::SequencerContext;
@@ -22,6 +24,7 @@ use hotshot_example_types::auction_results_provider_types::TestAuctionResultsPro
 // Should move `STAKE_TABLE_CAPACITY` in the sequencer repo when we have variate stake table support
 use libp2p::Multiaddr;
 use network::libp2p::split_off_peer_id;
+use signer::RemoteSigner;
 use state_signature::static_stake_table_commitment;
 use url::Url;
 pub mod persistence;
@@ -100,7 +103,8 @@ pub struct NetworkParams {
     pub orchestrator_url: Url,
     pub state_relay_server_url: Url,
     pub private_staking_key: BLSPrivKey,
-    pub private_state_key: StateSignKey,
+    /// The private state key, which may be omitted if states are signed by a remote signer.
+    pub private_state_key: Option<StateSignKey>,
     pub state_peers: Vec<Url>,
     pub config_peers: Option<Vec<Url>>,
     pub catchup_backoff: BackoffParams,
@@ -122,6 +126,7 @@ pub struct L1Params {
 pub async fn init_node<P: PersistenceOptions, Ver: StaticVersionType + 'static>(
     genesis: Genesis,
     network_params: NetworkParams,
+    remote_signer: Option<RemoteSigner<Ver>>,
     metrics: &dyn Metrics,
     persistence_opt: P,
     l1_params: L1Params,
@@ -154,7 +159,14 @@ pub async fn init_node<P: PersistenceOptions, Ver: StaticVersionType + 'static>(
         network_config_file: None,
     };
     let orchestrator_client = OrchestratorClient::new(validator_args);
-    let state_key_pair = StateKeyPair::from_sign_key(network_params.private_state_key);
+    let has_state_key = network_params.private_state_key.is_some();
+    let state_key_pair = match (network_params.private_state_key, &remote_signer) {
+        (Some(key), _) => StateKeyPair::from_sign_key(key),
+        // Consensus never uses the state key, so without a local key we can fill in a throwaway
+        // one. All state signatures go through the remote signer.
+        (None, Some(_)) => StateKeyPair::generate(),
+        (None, None) => bail!("a private state key is required unless a remote signer is used"),
+    };
     let my_config = ValidatorConfig {
         public_key: pub_key,
         private_key: network_params.private_staking_key,
@@ -193,6 +205,11 @@ pub async fn init_node<P: PersistenceOptions, Ver: StaticVersionType + 'static>(
         }
         // Otherwise, this is a fresh network; load from the orchestrator.
         (None, None) => {
+            // The orchestrator builds the stake table from the state keys nodes register with.
+            ensure!(
+                has_state_key,
+                "a private state key is required to register with the orchestrator"
+            );
             tracing::info!("loading network config from orchestrator");
             tracing::error!(
                 "waiting for other nodes to connect, DO NOT RESTART until fully connected"
@@ -352,6 +369,7 @@ pub async fn init_node<P: PersistenceOptions, Ver: StaticVersionType + 'static>(
         persistence,
         network,
         Some(network_params.state_relay_server_url),
+        remote_signer,
         metrics,
         genesis.stake_table.capacity,
         bind_version,
@@ -688,6 +706,7 @@ pub mod testing {
                 persistence_opt.create().await.unwrap(),
                 network,
                 self.state_relay_url.clone(),
+                None,
                 metrics,
                 stake_table_capacity,
                 bind_version,
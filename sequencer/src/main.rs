@@ -9,7 +9,9 @@ use sequencer::{
     api::{self, data_source::DataSourceOptions},
     init_node,
     options::{Modules, Options},
-    persistence, Genesis, L1Params, NetworkParams,
+    persistence,
+    signer::RemoteSigner,
+    Genesis, L1Params, NetworkParams,
 };
 use vbs::version::StaticVersionType;
 
@@ -28,6 +30,11 @@ async fn main() -> anyhow::Result<()> {
     } else if let Some(storage) = modules.storage_sql.take() {
         init_with_storage(modules, opt, storage, SEQUENCER_VERSION).await
     } else {
+        #[cfg(feature = "sqlite")]
+        if let Some(storage) = modules.storage_sqlite.take() {
+            return init_with_storage(modules, opt, storage, SEQUENCER_VERSION).await;
+        }
+
         // Persistence is required. If none is provided, just use the local file system.
         init_with_storage(
             modules,
@@ -52,6 +59,9 @@ where
     tracing::info!(?genesis, "genesis");
 
     let (private_staking_key, private_state_key) = opt.private_keys()?;
+    let remote_signer = opt
+        .remote_signer_url
+        .map(|url| RemoteSigner::new(url, opt.remote_signer_token));
     let l1_params = L1Params {
         url: opt.l1_provider_url,
         events_max_block_range: opt.l1_events_max_block_range,
@@ -126,6 +136,7 @@ where
                             init_node(
                                 genesis,
                                 network_params,
+                                remote_signer,
                                 &*metrics,
                                 storage_opt,
                                 l1_params,
@@ -145,6 +156,7 @@ where
             init_node(
                 genesis,
                 network_params,
+                remote_signer,
                 &NoMetrics,
                 storage_opt,
                 l1_params,
@@ -3,6 +3,7 @@ use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
     fmt::{self, Formatter},
+    fs,
     iter::once,
     num::ParseIntError,
     path::PathBuf,
@@ -10,7 +11,7 @@ use std::{
     time::Duration,
 };
 
-use anyhow::{bail, Context};
+use anyhow::{bail, ensure, Context};
 use bytesize::ByteSize;
 use clap::{error::ErrorKind, Args, FromArgMatches, Parser};
 use cld::ClDuration;
@@ -21,8 +22,14 @@ use hotshot_types::{light_client::StateSignKey, signature_key::BLSPrivKey};
 use libp2p::Multiaddr;
 use snafu::Snafu;
 use url::Url;
+use zeroize::Zeroizing;
 
-use crate::{api, persistence};
+use crate::{
+    api,
+    keystore::{Keystore, PassphraseOptions},
+    persistence,
+    persistence::encryption::{EncryptionOptions, KEY_FILE_CONTEXT},
+};
 
 // This options struct is a bit unconventional. The sequencer has multiple optional modules which
 // can be added, in any combination, to the service. These include, for example, the API server.
@@ -105,6 +112,25 @@ pub struct Options {
     #[derivative(Debug(format_with = "Display::fmt"))]
     pub state_relay_server_url: Url,
 
+    /// URL of a remote signer to sign light client states with.
+    ///
+    /// If set, light client state signatures are requested from the signer (for example, an
+    /// `espresso-signer` process), which holds the state key, and the private state key need not be
+    /// given to this node. The state key held by the signer must match this node's entry in the
+    /// stake table. Consensus votes are still signed with the local staking key, since HotShot
+    /// requires the staking key in process.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_REMOTE_SIGNER_URL")]
+    pub remote_signer_url: Option<Url>,
+
+    /// Token to authenticate with the remote signer.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_REMOTE_SIGNER_TOKEN",
+        requires = "remote_signer_url"
+    )]
+    #[derivative(Debug = "ignore")]
+    pub remote_signer_token: Option<String>,
+
     /// Path to TOML file containing genesis state.
     #[clap(
         long,
@@ -120,10 +146,23 @@ pub struct Options {
     /// * ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY
     /// * ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY
     ///
+    /// Alternatively, the file may be a keystore holding both keys, which is unlocked with the
+    /// keystore passphrase.
+    ///
     /// Appropriate key files can be generated with the `keygen` utility program.
+    ///
+    /// The key file may be encrypted (see `--encryption-key-file` and `--encryption-passphrase`).
     #[clap(long, name = "KEY_FILE", env = "ESPRESSO_SEQUENCER_KEY_FILE")]
     pub key_file: Option<PathBuf>,
 
+    /// Encryption of the key file at rest.
+    #[clap(flatten)]
+    pub key_file_encryption: EncryptionOptions,
+
+    /// Passphrase for a KEY_FILE which is a keystore.
+    #[clap(flatten)]
+    pub keystore_passphrase: PassphraseOptions,
+
     /// Private staking key.
     ///
     /// This can be used as an alternative to KEY_FILE.
@@ -208,25 +247,50 @@ impl Options {
         ModuleArgs(self.modules.clone()).parse()
     }
 
-    pub fn private_keys(&self) -> anyhow::Result<(BLSPrivKey, StateSignKey)> {
+    /// The private staking and state keys.
+    ///
+    /// The state key is only optional if a remote signer is used to sign light client states.
+    pub fn private_keys(&self) -> anyhow::Result<(BLSPrivKey, Option<StateSignKey>)> {
+        let (staking, state) = self.load_private_keys()?;
+        ensure!(
+            state.is_some() || self.remote_signer_url.is_some(),
+            "a private state key is required unless a remote signer is used"
+        );
+        Ok((staking, state))
+    }
+
+    fn load_private_keys(&self) -> anyhow::Result<(BLSPrivKey, Option<StateSignKey>)> {
         if let Some(path) = &self.key_file {
-            let vars = dotenvy::from_path_iter(path)?.collect::<Result<HashMap<_, _>, _>>()?;
+            let bytes = fs::read(path).context(format!("reading {}", path.display()))?;
+            let bytes = Zeroizing::new(
+                self.key_file_encryption
+                    .encryption()?
+                    .open(KEY_FILE_CONTEXT, bytes)
+                    .context("decrypting key file")?,
+            );
+            if Keystore::is_keystore(&bytes) {
+                let keys = Keystore::from_bytes(&bytes)?
+                    .decrypt(&self.keystore_passphrase.passphrase()?)
+                    .context(format!("unlocking keystore {}", path.display()))?;
+                let staking = keys.staking.context("keystore missing staking key")?;
+                return Ok((staking, keys.state));
+            }
+
+            let vars =
+                dotenvy::from_read_iter(bytes.as_slice()).collect::<Result<HashMap<_, _>, _>>()?;
             let staking = vars
                 .get("ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY")
                 .context("key file missing ESPRESSO_SEQUENCER_PRIVATE_STAKING_KEY")?
                 .parse()?;
             let state = vars
                 .get("ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY")
-                .context("key file missing ESPRESSO_SEQUENCER_PRIVATE_STATE_KEY")?
-                .parse()?;
-            Ok((staking, state))
-        } else if let (Some(staking), Some(state)) = (
-            self.private_staking_key.clone(),
-            self.private_state_key.clone(),
-        ) {
+                .map(|key| key.parse())
+                .transpose()?;
             Ok((staking, state))
+        } else if let Some(staking) = self.private_staking_key.clone() {
+            Ok((staking, self.private_state_key.clone()))
         } else {
-            bail!("neither key file nor full set of private keys was provided")
+            bail!("neither key file nor private staking key was provided")
         }
     }
 }
@@ -370,6 +434,10 @@ impl ModuleArgs {
                 SequencerModule::StorageSql(m) => {
                     curr = m.add(&mut modules.storage_sql, &mut provided)?
                 }
+                #[cfg(feature = "sqlite")]
+                SequencerModule::StorageSqlite(m) => {
+                    curr = m.add(&mut modules.storage_sqlite, &mut provided)?
+                }
                 SequencerModule::Http(m) => curr = m.add(&mut modules.http, &mut provided)?,
                 SequencerModule::Query(m) => curr = m.add(&mut modules.query, &mut provided)?,
                 SequencerModule::Submit(m) => curr = m.add(&mut modules.submit, &mut provided)?,
@@ -409,15 +477,27 @@ macro_rules! module {
 
 module!("storage-fs", persistence::fs::Options);
 module!("storage-sql", persistence::sql::Options);
+#[cfg(feature = "sqlite")]
+module!("storage-sqlite", persistence::sqlite::Options);
 module!("http", api::options::Http);
 module!("query", api::options::Query, requires: "http");
 module!("submit", api::options::Submit, requires: "http");
 module!("status", api::options::Status, requires: "http");
-module!("state", api::options::State, requires: "http", "storage-sql");
+module!(
+    "state",
+    api::options::State,
+    requires: "http",
+    "storage-fs|storage-sql|storage-sqlite"
+);
 module!("catchup", api::options::Catchup, requires: "http");
 module!("config", api::options::Config, requires: "http");
 module!("hotshot-events", api::options::HotshotEvents, requires: "http");
-module!("explorer", api::options::Explorer, requires: "http", "storage-sql");
+module!(
+    "explorer",
+    api::options::Explorer,
+    requires: "http",
+    "storage-fs|storage-sql"
+);
 
 #[derive(Clone, Debug, Args)]
 struct Module<Options: ModuleInfo> {
@@ -442,8 +522,9 @@ impl<Options: ModuleInfo> Module<Options> {
                 format!("optional module {} can only be started once", Options::NAME),
             ));
         }
+        // A requirement of the form `a|b` is satisfied by any one of the alternatives.
         for req in Options::requires() {
-            if !provided.contains(&req) {
+            if !req.split('|').any(|alt| provided.contains(alt)) {
                 return Err(clap::Error::raw(
                     ErrorKind::MissingRequiredArgument,
                     format!("module {} is missing required module {req}", Options::NAME),
@@ -471,6 +552,9 @@ enum SequencerModule {
     StorageFs(Module<persistence::fs::Options>),
     /// Use a Postgres database for persistent storage.
     StorageSql(Module<persistence::sql::Options>),
+    /// Use an embedded SQLite database for persistent storage.
+    #[cfg(feature = "sqlite")]
+    StorageSqlite(Module<persistence::sqlite::Options>),
     /// Run the query API module.
     ///
     /// This module requires the http module to be started.
@@ -490,7 +574,8 @@ enum SequencerModule {
     Config(Module<api::options::Config>),
     /// Run the merklized state  API module.
     ///
-    /// This module requires the http and storage-sql modules to be started.
+    /// This module requires the http module and a storage module (storage-fs, storage-sql or
+    /// storage-sqlite) to be started.
     State(Module<api::options::State>),
     /// Run the hotshot events API module.
     ///
@@ -498,7 +583,8 @@ enum SequencerModule {
     HotshotEvents(Module<api::options::HotshotEvents>),
     /// Run the explorer API module.
     ///
-    /// This module requires the http and storage-sql modules to be started.
+    /// This module requires the http module and either the storage-fs or storage-sql module to be
+    /// started.
     Explorer(Module<api::options::Explorer>),
 }
 
@@ -506,6 +592,8 @@ enum SequencerModule {
 pub struct Modules {
     pub storage_fs: Option<persistence::fs::Options>,
     pub storage_sql: Option<persistence::sql::Options>,
+    #[cfg(feature = "sqlite")]
+    pub storage_sqlite: Option<persistence::sqlite::Options>,
     pub http: Option<api::options::Http>,
     pub query: Option<api::options::Query>,
     pub submit: Option<api::options::Submit>,
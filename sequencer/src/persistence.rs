@@ -12,9 +12,14 @@ use async_trait::async_trait;
 use committable::Commitment;
 use espresso_types::ChainConfig;
 
+pub mod encryption;
 pub mod fs;
+pub mod integrity;
 pub mod no_storage;
+pub mod retention;
 pub mod sql;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
 #[async_trait]
 pub trait ChainConfigPersistence: Sized + Send + Sync + 'static {
@@ -38,6 +43,9 @@ mod testing {
 
         async fn tmp_storage() -> Self::Storage;
         async fn connect(storage: &Self::Storage) -> Self;
+
+        /// Run the consensus artifact compactor once, without waiting for the background task.
+        async fn compact(&self);
     }
 }
 
@@ -62,7 +70,10 @@ mod persistence_tests {
     use jf_vid::VidScheme;
     use testing::TestablePersistence;
 
-    use super::*;
+    use super::{
+        integrity::{check, CheckablePersistence, EntryId, Severity},
+        *,
+    };
 
     #[async_std::test]
     pub async fn test_anchor_leaf<P: TestablePersistence>() {
@@ -148,6 +159,97 @@ mod persistence_tests {
         );
     }
 
+    #[async_std::test]
+    pub async fn test_integrity_check<P: TestablePersistence + CheckablePersistence>() {
+        setup_logging();
+        setup_backtrace();
+
+        let tmp = P::tmp_storage().await;
+        let mut storage = P::connect(&tmp).await;
+
+        // Empty storage is trivially consistent.
+        assert_eq!(check(&storage).await.unwrap(), vec![]);
+
+        // A consistent anchor leaf passes the check.
+        let leaf1 = Leaf::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        let qc = QuorumCertificate::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        storage.save_anchor_leaf(&leaf1, &qc).await.unwrap();
+        assert_eq!(check(&storage).await.unwrap(), vec![]);
+
+        // A leaf which does not match its QC is flagged.
+        let mut leaf2 = leaf1.clone();
+        *leaf2.block_header_mut().height_mut() += 1;
+        storage.save_anchor_leaf(&leaf2, &qc).await.unwrap();
+        let anomalies = check(&storage).await.unwrap();
+        assert_eq!(anomalies.len(), 1, "{anomalies:?}");
+        assert_eq!(anomalies[0].id, EntryId::AnchorLeaf);
+        assert_eq!(anomalies[0].severity, Severity::Error);
+
+        // Quarantining the leaf removes it from storage, so the node can start from genesis.
+        let entry = storage.entry(&EntryId::AnchorLeaf).await.unwrap();
+        storage
+            .quarantine(&entry, &anomalies[0].reason)
+            .await
+            .unwrap();
+        assert_eq!(storage.load_anchor_leaf().await.unwrap(), None);
+        assert_eq!(check(&storage).await.unwrap(), vec![]);
+    }
+
+    #[async_std::test]
+    pub async fn test_integrity_check_chain<P: TestablePersistence + CheckablePersistence>() {
+        setup_logging();
+        setup_backtrace();
+
+        let tmp = P::tmp_storage().await;
+        let mut storage = P::connect(&tmp).await;
+
+        let leaf = Leaf::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        let (_, privkey) = BLSPubKey::generated_from_seed_indexed([0; 32], 1);
+        let mut proposal = Proposal {
+            data: QuorumProposal::<SeqTypes> {
+                block_header: leaf.block_header().clone(),
+                view_number: ViewNumber::genesis(),
+                justify_qc: QuorumCertificate::genesis(
+                    &ValidatedState::default(),
+                    &NodeState::mock(),
+                )
+                .await,
+                upgrade_certificate: None,
+                proposal_certificate: None,
+            },
+            signature: PubKey::sign(&privkey, &[]).unwrap(),
+            _pd: Default::default(),
+        };
+        storage.append_quorum_proposal(&proposal).await.unwrap();
+
+        // Extend the genesis proposal with a proposal justified by a QC for it.
+        let parent = Leaf::from_quorum_proposal(&proposal.data);
+        proposal.data.view_number = ViewNumber::new(1);
+        proposal.data.justify_qc.view_number = ViewNumber::genesis();
+        proposal.data.justify_qc.data.leaf_commit = parent.commit();
+        proposal.data.justify_qc.vote_commitment = proposal.data.justify_qc.data.commit();
+        *proposal.data.block_header.height_mut() = parent.height() + 1;
+        storage.append_quorum_proposal(&proposal).await.unwrap();
+        assert_eq!(check(&storage).await.unwrap(), vec![]);
+
+        // A proposal which skips a block is flagged.
+        *proposal.data.block_header.height_mut() = parent.height() + 2;
+        storage.append_quorum_proposal(&proposal).await.unwrap();
+        let anomalies = check(&storage).await.unwrap();
+        assert_eq!(anomalies.len(), 1, "{anomalies:?}");
+        assert_eq!(anomalies[0].id, EntryId::QuorumProposal(ViewNumber::new(1)));
+        assert_eq!(anomalies[0].severity, Severity::Error);
+
+        // So is a proposal justified by a QC for a different leaf than the one stored.
+        *proposal.data.block_header.height_mut() = parent.height() + 1;
+        proposal.data.justify_qc.data.leaf_commit = leaf.commit();
+        proposal.data.justify_qc.vote_commitment = proposal.data.justify_qc.data.commit();
+        storage.append_quorum_proposal(&proposal).await.unwrap();
+        let anomalies = check(&storage).await.unwrap();
+        assert_eq!(anomalies.len(), 1, "{anomalies:?}");
+        assert_eq!(anomalies[0].id, EntryId::QuorumProposal(ViewNumber::new(1)));
+    }
+
     #[async_std::test]
     pub async fn test_append_and_collect_garbage<P: TestablePersistence>() {
         setup_logging();
@@ -335,6 +437,7 @@ mod persistence_tests {
         // Test garbage collection
         // Deleting da proposals and vid shares with view number <=2
         storage.collect_garbage(ViewNumber::new(2)).await.unwrap();
+        storage.compact().await;
 
         for i in 0..=2 {
             assert_eq!(
@@ -0,0 +1,391 @@
+//! Encryption at rest for file system storage and key files.
+//!
+//! Encryption is optional. When it is enabled, every file written by the file system persistence
+//! backend is sealed with XChaCha20-Poly1305 under a 256-bit key, which is either read from an
+//! external key file or derived from a passphrase using scrypt. Sealed files are self-describing:
+//! each one starts with a header containing a magic string, an identifier for the key that sealed
+//! it and, for passphrase-derived keys, the KDF salt. This lets us detect encrypted files when no
+//! key is configured, and report a wrong key as such instead of as corrupt data. Once encryption is
+//! enabled, files which are not sealed are rejected, since anyone who can write to the storage
+//! directory could otherwise replace sealed files with plaintext of their choosing. Existing
+//! plaintext storage must be encrypted with the `rotate-storage-key` utility before encryption is
+//! enabled.
+//!
+//! Each file is sealed with a context, which is authenticated as associated data: for storage
+//! files, their path relative to the storage root, and for key files, [`KEY_FILE_CONTEXT`]. A
+//! sealed file moved to another path, for example a proposal for one view copied over another,
+//! fails authentication. The context does not identify a version of a file, so restoring an older
+//! sealed copy of a file at the same path is not detected. Protecting against rollback requires
+//! state kept outside the storage directory.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{ensure, Context};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use clap::Parser;
+use derivative::Derivative;
+use ethers::utils::hex;
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+/// Magic string at the start of every sealed file.
+pub const MAGIC: &[u8; 8] = b"ESQENC01";
+
+/// The context with which key files are sealed.
+///
+/// Key files are often copied between machines and directories, so unlike storage files they are
+/// not bound to their path.
+pub const KEY_FILE_CONTEXT: &[u8] = b"key file";
+
+const KEY_ID_LEN: usize = 8;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + KEY_ID_LEN + SALT_LEN + NONCE_LEN;
+
+// scrypt parameters for passphrase-derived keys (N = 2^15, r = 8, p = 1).
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+type KeyBytes = Zeroizing<[u8; 32]>;
+
+/// Options for encrypting data at rest.
+#[derive(Parser, Clone, Default, Derivative)]
+#[derivative(Debug)]
+pub struct EncryptionOptions {
+    /// Path to a file containing a hex-encoded 32-byte key used to encrypt data at rest.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_ENCRYPTION_KEY_FILE",
+        conflicts_with = "encryption_passphrase"
+    )]
+    pub encryption_key_file: Option<PathBuf>,
+
+    /// Passphrase from which to derive the key used to encrypt data at rest.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ENCRYPTION_PASSPHRASE")]
+    #[derivative(Debug = "ignore")]
+    pub encryption_passphrase: Option<String>,
+}
+
+impl EncryptionOptions {
+    /// The configured source of the encryption key, if encryption is enabled.
+    pub fn key_source(&self) -> Option<KeySource> {
+        if let Some(path) = &self.encryption_key_file {
+            Some(KeySource::File(path.clone()))
+        } else {
+            self.encryption_passphrase
+                .clone()
+                .map(|passphrase| KeySource::Passphrase(Zeroizing::new(passphrase)))
+        }
+    }
+
+    /// Load the configured encryption key, if any.
+    pub fn encryption(&self) -> anyhow::Result<Encryption> {
+        Ok(match self.key_source() {
+            Some(source) => Encryption::new(source)?,
+            None => Encryption::none(),
+        })
+    }
+}
+
+/// Where an encryption key comes from.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub enum KeySource {
+    /// A file containing a hex-encoded 32-byte key.
+    File(PathBuf),
+    /// A passphrase, from which keys are derived using scrypt.
+    Passphrase(#[derivative(Debug = "ignore")] Zeroizing<String>),
+}
+
+impl KeySource {
+    /// Get the key for files sealed with `salt`.
+    ///
+    /// Keys read from a file do not depend on the salt.
+    fn key(&self, salt: &[u8; SALT_LEN]) -> anyhow::Result<KeyBytes> {
+        let mut key = KeyBytes::default();
+        match self {
+            Self::File(path) => {
+                let hex_key = Zeroizing::new(
+                    fs::read_to_string(path)
+                        .context(format!("reading encryption key from {}", path.display()))?,
+                );
+                let bytes = Zeroizing::new(
+                    hex::decode(hex_key.trim().trim_start_matches("0x"))
+                        .context("encryption key file is not valid hex")?,
+                );
+                ensure!(
+                    bytes.len() == key.len(),
+                    "encryption key has {} bytes (expected {})",
+                    bytes.len(),
+                    key.len()
+                );
+                key.copy_from_slice(&bytes);
+            }
+            Self::Passphrase(passphrase) => {
+                let params = scrypt::Params::new(SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)
+                    .context("invalid scrypt parameters")?;
+                scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key[..])
+                    .context("deriving encryption key")?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// A key, along with the information needed to recognize files it has sealed.
+struct SealingKey {
+    id: [u8; KEY_ID_LEN],
+    salt: [u8; SALT_LEN],
+    cipher: XChaCha20Poly1305,
+}
+
+impl SealingKey {
+    fn new(key: KeyBytes, salt: [u8; SALT_LEN]) -> Self {
+        let mut id = [0; KEY_ID_LEN];
+        id.copy_from_slice(&Sha256::digest(&key[..])[..KEY_ID_LEN]);
+        Self {
+            id,
+            salt,
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key[..])),
+        }
+    }
+}
+
+/// A key used to seal and open files.
+struct Cipher {
+    source: KeySource,
+    /// The key used to seal new files.
+    sealing: Arc<SealingKey>,
+    /// Keys derived for other salts, so we only have to run the KDF once per salt.
+    opening: Mutex<HashMap<[u8; SALT_LEN], Arc<SealingKey>>>,
+}
+
+/// Encryption applied to data at rest, if any.
+#[derive(Clone, Default)]
+pub struct Encryption(Option<Arc<Cipher>>);
+
+impl std::fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(cipher) => f
+                .debug_tuple("Encryption")
+                .field(&cipher.source)
+                .field(&hex::encode(cipher.sealing.id))
+                .finish(),
+            None => write!(f, "Encryption(None)"),
+        }
+    }
+}
+
+impl Encryption {
+    /// Do not encrypt data.
+    ///
+    /// Plaintext files can be opened, but opening a sealed file fails.
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    /// Encrypt data with a key from `source`.
+    pub fn new(source: KeySource) -> anyhow::Result<Self> {
+        // Each instance seals files with its own random salt. Only passphrase-derived keys depend
+        // on the salt; for keys read from a file, the salt is ignored and left as zero.
+        let mut salt = [0; SALT_LEN];
+        if matches!(source, KeySource::Passphrase(_)) {
+            OsRng.fill_bytes(&mut salt);
+        }
+        let sealing = Arc::new(SealingKey::new(source.key(&salt)?, salt));
+        Ok(Self(Some(Arc::new(Cipher {
+            opening: Mutex::new([(salt, sealing.clone())].into()),
+            sealing,
+            source,
+        }))))
+    }
+
+    /// Whether this encrypts data.
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Seal `plaintext` for `context`, if encryption is enabled.
+    ///
+    /// The sealed data can only be opened with the same `context`.
+    pub fn seal(&self, context: &[u8], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let Some(cipher) = &self.0 else {
+            return Ok(plaintext.to_vec());
+        };
+        let key = &cipher.sealing;
+
+        let mut nonce = [0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = key
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: context,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+
+        let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&key.id);
+        sealed.extend_from_slice(&key.salt);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Open the contents of a file.
+    ///
+    /// If encryption is enabled, the contents must be sealed, and are decrypted and authenticated,
+    /// along with the `context` they were sealed for. Otherwise, the contents must not be sealed,
+    /// and are returned as is.
+    pub fn open(&self, context: &[u8], bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let Some(cipher) = &self.0 else {
+            ensure!(
+                !is_sealed(&bytes),
+                "data is encrypted, but no encryption key is configured"
+            );
+            return Ok(bytes);
+        };
+        ensure!(
+            is_sealed(&bytes),
+            "data is not encrypted, but encryption is enabled; use rotate-storage-key to encrypt \
+             existing data"
+        );
+
+        let (id, rest) = bytes[MAGIC.len()..].split_at(KEY_ID_LEN);
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let salt: [u8; SALT_LEN] = salt.try_into().unwrap();
+
+        let key = cipher.opening_key(&salt)?;
+        ensure!(
+            key.id == id,
+            "data was encrypted with a different key (key id {}, expected {})",
+            hex::encode(id),
+            hex::encode(key.id)
+        );
+        key.cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: context,
+                },
+            )
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "encrypted data failed authentication (it may have been tampered with, or \
+                     moved from another path)"
+                )
+            })
+    }
+
+    /// Whether `bytes` were sealed by this key.
+    pub fn sealed_by(&self, bytes: &[u8]) -> bool {
+        let Some(cipher) = &self.0 else {
+            return false;
+        };
+        if !is_sealed(bytes) {
+            return false;
+        }
+        let id = &bytes[MAGIC.len()..MAGIC.len() + KEY_ID_LEN];
+        let salt = bytes[MAGIC.len() + KEY_ID_LEN..][..SALT_LEN]
+            .try_into()
+            .unwrap();
+        cipher.opening_key(&salt).is_ok_and(|key| key.id == id)
+    }
+}
+
+impl Cipher {
+    fn opening_key(&self, salt: &[u8; SALT_LEN]) -> anyhow::Result<Arc<SealingKey>> {
+        if let Some(key) = self.opening.lock().unwrap().get(salt) {
+            return Ok(key.clone());
+        }
+        let key = match &self.source {
+            // Keys read from a file do not depend on the salt, so there is only one.
+            KeySource::File(_) => self.sealing.clone(),
+            KeySource::Passphrase(_) => Arc::new(SealingKey::new(self.source.key(salt)?, *salt)),
+        };
+        self.opening.lock().unwrap().insert(*salt, key.clone());
+        Ok(key)
+    }
+}
+
+/// Whether `bytes` are the contents of a sealed file.
+pub fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_LEN && bytes.starts_with(MAGIC)
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const CONTEXT: &[u8] = b"da/1.txt";
+
+    #[test]
+    fn test_seal_open() {
+        let tmp = TempDir::new().unwrap();
+        let key_file = tmp.path().join("key");
+        fs::write(&key_file, hex::encode([1; 32])).unwrap();
+
+        let plaintext = b"some consensus data".to_vec();
+        for source in [
+            KeySource::File(key_file.clone()),
+            KeySource::Passphrase(Zeroizing::new("correct horse".into())),
+        ] {
+            let encryption = Encryption::new(source.clone()).unwrap();
+            let sealed = encryption.seal(CONTEXT, &plaintext).unwrap();
+            assert!(is_sealed(&sealed));
+            assert!(encryption.sealed_by(&sealed));
+            assert_ne!(&sealed[HEADER_LEN..], plaintext.as_slice());
+
+            // The same key, loaded again (with a new salt for passphrases), opens the file.
+            let reloaded = Encryption::new(source).unwrap();
+            assert_eq!(reloaded.open(CONTEXT, sealed.clone()).unwrap(), plaintext);
+
+            // Sealed data is bound to its context.
+            let err = reloaded.open(b"da/2.txt", sealed.clone()).unwrap_err();
+            assert!(err.to_string().contains("failed authentication"), "{err:#}");
+
+            // Plaintext is rejected once encryption is enabled, so that it cannot be swapped in
+            // for sealed data. It only passes through without a key.
+            let err = reloaded.open(CONTEXT, plaintext.clone()).unwrap_err();
+            assert!(err.to_string().contains("not encrypted"), "{err:#}");
+            assert_eq!(
+                Encryption::none().open(CONTEXT, plaintext.clone()).unwrap(),
+                plaintext
+            );
+
+            // Without a key, or with the wrong key, sealed data cannot be opened.
+            Encryption::none()
+                .open(CONTEXT, sealed.clone())
+                .unwrap_err();
+            let wrong =
+                Encryption::new(KeySource::Passphrase(Zeroizing::new("wrong".into()))).unwrap();
+            assert!(!wrong.sealed_by(&sealed));
+            let err = wrong.open(CONTEXT, sealed.clone()).unwrap_err();
+            assert!(err.to_string().contains("different key"), "{err:#}");
+
+            // Tampering is detected.
+            let mut tampered = sealed;
+            *tampered.last_mut().unwrap() ^= 1;
+            reloaded.open(CONTEXT, tampered).unwrap_err();
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::{
     collections::BTreeMap,
-    fs::{self, File, OpenOptions},
-    io::{Read, Seek, SeekFrom, Write},
+    fs::{self, OpenOptions},
+    io::Write,
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, ensure, Context};
 use async_trait::async_trait;
 use clap::Parser;
 use espresso_types::{
@@ -18,11 +19,16 @@ use hotshot_types::{
     event::HotShotAction,
     message::Proposal,
     simple_certificate::QuorumCertificate,
-    traits::node_implementation::ConsensusTime,
+    traits::{metrics::Metrics, node_implementation::ConsensusTime},
     utils::View,
     vote::HasViewNumber,
 };
 
+use super::{
+    encryption::{Encryption, EncryptionOptions},
+    integrity::{decode_proposal, CheckablePersistence, Entry, EntryId, EntryValue},
+    retention::{ArtifactKind, ArtifactStore, Compactor, RetentionOptions},
+};
 use crate::ViewNumber;
 
 /// Options for file system backed persistence.
@@ -34,6 +40,23 @@ pub struct Options {
 
     #[clap(long, env = "ESPRESSO_SEQUENCER_STORE_UNDECIDED_STATE", hide = true)]
     store_undecided_state: bool,
+
+    /// Retention policy for consensus artifacts.
+    #[clap(flatten)]
+    retention: RetentionOptions,
+
+    /// Encryption of persistent data at rest.
+    #[clap(flatten)]
+    encryption: EncryptionOptions,
+
+    /// Number of recent blocks for which all versions of merklized state are retained.
+    ///
+    /// Older versions of the fee and block Merkle trees stored by the query service are deleted,
+    /// except for the nodes needed to serve state at the oldest retained height, so the latest state
+    /// of every account is always kept. State queries (including catchup requests from peers) for
+    /// heights before the retained window fail. If not set, all versions are kept.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_PRUNER_MERKLIZED_STATE_RETENTION")]
+    merklized_state_retention: Option<u64>,
 }
 
 impl Default for Options {
@@ -47,12 +70,72 @@ impl Options {
         Self {
             path,
             store_undecided_state: false,
+            retention: Default::default(),
+            encryption: Default::default(),
+            merklized_state_retention: None,
         }
     }
 
+    pub fn with_encryption(mut self, encryption: EncryptionOptions) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
     pub(crate) fn path(&self) -> &Path {
         &self.path
     }
+
+    pub fn with_merklized_state_retention(mut self, retention: u64) -> Self {
+        self.merklized_state_retention = Some(retention);
+        self
+    }
+
+    /// The number of recent blocks for which all versions of merklized state are retained.
+    pub(crate) fn merklized_state_retention(&self) -> Option<u64> {
+        self.merklized_state_retention
+    }
+
+    pub(crate) fn is_encrypted(&self) -> bool {
+        self.encryption.key_source().is_some()
+    }
+
+    /// The files which make up consensus storage, and are encrypted if encryption is enabled.
+    ///
+    /// This excludes any query service data which may be stored under the same path, and swap
+    /// files left behind by an interrupted write, which are never read. Quarantined files are also
+    /// excluded, since they were quarantined because they could not be read, but the reasons
+    /// recorded for them are included.
+    pub fn consensus_files(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for name in [
+            "hotshot.cfg",
+            "highest_voted_view",
+            "anchor_leaf",
+            "undecided_state",
+        ] {
+            let path = self.path.join(name);
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+        for dir in ["da", "vid", "quorum_proposals"] {
+            collect_files(&self.path.join(dir), &mut files)?;
+        }
+        let mut quarantined = vec![];
+        collect_files(&self.path.join("quarantine"), &mut quarantined)?;
+        files.extend(
+            quarantined
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "reason")),
+        );
+        files.retain(|path| path.extension().map_or(true, |ext| ext != "swp"));
+        Ok(files)
+    }
+
+    /// The context with which the consensus storage file at `path` is sealed.
+    pub fn sealing_context(&self, path: &Path) -> Vec<u8> {
+        sealing_context(&self.path, path)
+    }
 }
 
 #[async_trait]
@@ -61,6 +144,13 @@ impl PersistenceOptions for Options {
 
     async fn create(self) -> anyhow::Result<Persistence> {
         Ok(Persistence {
+            compactor: Compactor::new(
+                Artifacts {
+                    path: self.path.clone(),
+                },
+                self.retention,
+            ),
+            encryption: self.encryption.encryption()?,
             path: self.path,
             store_undecided_state: self.store_undecided_state,
         })
@@ -76,6 +166,8 @@ impl PersistenceOptions for Options {
 pub struct Persistence {
     path: PathBuf,
     store_undecided_state: bool,
+    encryption: Encryption,
+    compactor: Compactor<Artifacts>,
 }
 
 impl Persistence {
@@ -107,27 +199,69 @@ impl Persistence {
         self.path.join("quorum_proposals")
     }
 
+    /// The file in which the entry `id` is stored.
+    fn entry_path(&self, id: &EntryId) -> PathBuf {
+        match id {
+            EntryId::Config => self.config_path(),
+            EntryId::AnchorLeaf => self.anchor_leaf_path(),
+            EntryId::VotedView => self.voted_view_path(),
+            EntryId::UndecidedState => self.undecided_state_path(),
+            EntryId::DaProposal(view) => self
+                .da_dir_path()
+                .join(view.u64().to_string())
+                .with_extension("txt"),
+            EntryId::VidShare(view) => self
+                .vid_dir_path()
+                .join(view.u64().to_string())
+                .with_extension("txt"),
+            EntryId::QuorumProposal(view) => self
+                .quorum_proposals_dir_path()
+                .join(view.u64().to_string())
+                .with_extension("txt"),
+            EntryId::Stray(path) => path.clone(),
+        }
+    }
+
+    fn quarantine_dir_path(&self) -> PathBuf {
+        self.path.join("quarantine")
+    }
+
+    /// Read the contents of a file, decrypting them if necessary.
+    fn read(&self, path: &Path) -> anyhow::Result<Vec<u8>> {
+        let bytes = fs::read(path).context(format!("reading {}", path.display()))?;
+        self.open(path, bytes)
+    }
+
+    /// Decrypt the raw contents of the file at `path`, if necessary.
+    fn open(&self, path: &Path, raw: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        self.encryption
+            .open(&sealing_context(&self.path, path), raw)
+            .context(format!("decrypting {}", path.display()))
+    }
+
     /// Overwrite a file if a condition is met.
     ///
-    /// The file at `path`, if it exists, is opened in read mode and passed to `pred`. If `pred`
-    /// returns `true`, or if there was no existing file, then `write` is called to update the
-    /// contents of the file. `write` receives a truncated file open in write mode and sets the
+    /// The contents of the file at `path`, if it exists, are read and passed to `pred`. If `pred`
+    /// returns `true`, or if there was no existing file, then `write` is called to produce the new
     /// contents of the file.
     ///
+    /// Contents are encrypted and decrypted transparently, so `pred` and `write` always deal in
+    /// plaintext.
+    ///
     /// The final replacement of the original file is atomic; that is, `path` will be modified only
     /// if the entire update succeeds.
     fn replace(
         &mut self,
         path: &Path,
-        pred: impl FnOnce(File) -> anyhow::Result<bool>,
-        write: impl FnOnce(File) -> anyhow::Result<()>,
+        pred: impl FnOnce(Vec<u8>) -> anyhow::Result<bool>,
+        write: impl FnOnce() -> anyhow::Result<Vec<u8>>,
     ) -> anyhow::Result<()> {
         if path.is_file() {
             // If there is an existing file, check if it is suitable to replace. Note that this
             // check is not atomic with respect to the subsequent write at the file system level,
             // but this object is the only one which writes to this file, and we have a mutable
             // reference, so this should be safe.
-            if !pred(File::open(path)?)? {
+            if !pred(self.read(path)?)? {
                 // If we are not overwriting the file, we are done and consider the whole operation
                 // successful.
                 return Ok(());
@@ -136,14 +270,21 @@ impl Persistence {
 
         // Either there is no existing file or we have decided to overwrite the file. Write the new
         // contents into a temporary file so we can update `path` atomically using `rename`.
+        // The swap file is sealed for its final location, since that is where it will be read.
+        let bytes = self
+            .encryption
+            .seal(&sealing_context(&self.path, path), &write()?)?;
         let mut swap_path = path.to_owned();
         swap_path.set_extension("swp");
-        let swap = OpenOptions::new()
+        let mut swap = OpenOptions::new()
             .write(true)
             .truncate(true)
             .create(true)
             .open(&swap_path)?;
-        write(swap)?;
+        swap.write_all(&bytes)?;
+        // Make sure the new contents are durable before they replace the original file, so that a
+        // crash cannot leave an empty or partial file at `path`.
+        swap.sync_all()?;
 
         // Now we can replace the original file.
         fs::rename(swap_path, path)?;
@@ -162,8 +303,9 @@ impl SequencerPersistence for Persistence {
         }
         tracing::info!("loading config from {}", path.display());
 
-        let bytes =
-            fs::read(&path).context(format!("unable to read config from {}", path.display()))?;
+        let bytes = self
+            .read(&path)
+            .context(format!("unable to read config from {}", path.display()))?;
         let json = serde_json::from_slice(&bytes).context("config file is not valid JSON")?;
         let json = migrate_network_config(json).context("migration of network config failed")?;
         let config = serde_json::from_value(json).context("malformed config file")?;
@@ -173,36 +315,25 @@ impl SequencerPersistence for Persistence {
     async fn save_config(&mut self, cfg: &NetworkConfig) -> anyhow::Result<()> {
         let path = self.config_path();
         tracing::info!("saving config to {}", path.display());
-        Ok(cfg.to_file(path.display().to_string())?)
+        fs::create_dir_all(&self.path).context("failed to create storage dir")?;
+        self.replace(
+            &path,
+            |_| {
+                // Always overwrite the previous config.
+                Ok(true)
+            },
+            || serde_json::to_vec_pretty(cfg).context("serialize config"),
+        )
     }
 
-    async fn collect_garbage(&mut self, view: ViewNumber) -> anyhow::Result<()> {
-        let view_number = view.u64();
-
-        let delete_files = |dir_path: PathBuf| -> anyhow::Result<()> {
-            if !dir_path.is_dir() {
-                return Ok(());
-            }
-
-            for entry in fs::read_dir(dir_path)? {
-                let entry = entry?;
-                let path = entry.path();
-
-                if let Some(file) = path.file_stem().and_then(|n| n.to_str()) {
-                    if let Ok(v) = file.parse::<u64>() {
-                        if v <= view_number {
-                            fs::remove_file(&path)?;
-                        }
-                    }
-                }
-            }
-
-            Ok(())
-        };
+    fn start_background_tasks(&mut self, metrics: &dyn Metrics) {
+        self.compactor.start(metrics);
+    }
 
-        delete_files(self.da_dir_path())?;
-        delete_files(self.vid_dir_path())?;
-        delete_files(self.quorum_proposals_dir_path())
+    async fn collect_garbage(&mut self, view: ViewNumber) -> anyhow::Result<()> {
+        // Artifacts up to `view` are deleted in the background, according to the retention policy.
+        self.compactor.decide(view);
+        Ok(())
     }
 
     async fn load_latest_acted_view(&self) -> anyhow::Result<Option<ViewNumber>> {
@@ -210,7 +341,8 @@ impl SequencerPersistence for Persistence {
         if !path.is_file() {
             return Ok(None);
         }
-        let bytes = fs::read(self.voted_view_path())?
+        let bytes = self
+            .read(&path)?
             .try_into()
             .map_err(|bytes| anyhow!("malformed voted view file: {bytes:?}"))?;
         Ok(Some(ViewNumber::new(u64::from_le_bytes(bytes))))
@@ -223,18 +355,16 @@ impl SequencerPersistence for Persistence {
     ) -> anyhow::Result<()> {
         self.replace(
             &self.anchor_leaf_path(),
-            |mut file| {
+            |bytes| {
                 // Check if we already have a later leaf before writing the new one. The height of
                 // the latest saved leaf is in the first 8 bytes of the file.
-                if file.metadata()?.len() < 8 {
+                if bytes.len() < 8 {
                     // This shouldn't happen, but if there is an existing file smaller than 8 bytes,
                     // it is not encoding a valid height, and we want to proceed with the swap.
                     tracing::warn!("anchor leaf file smaller than 8 bytes will be replaced");
                     return Ok(true);
                 }
-                let mut height_bytes = [0; 8];
-                file.read_exact(&mut height_bytes).context("read height")?;
-                let height = u64::from_le_bytes(height_bytes);
+                let height = u64::from_le_bytes(bytes[..8].try_into().unwrap());
                 if height >= leaf.height() {
                     tracing::warn!(
                         saved_height = height,
@@ -248,14 +378,12 @@ impl SequencerPersistence for Persistence {
                 // with the swap.
                 Ok(true)
             },
-            |mut file| {
+            || {
                 // Save the new leaf. First we write the height.
-                file.write_all(&leaf.height().to_le_bytes())
-                    .context("write height")?;
+                let mut bytes = leaf.height().to_le_bytes().to_vec();
                 // Now serialize and write out the actual leaf and its corresponding QC.
-                let bytes = bincode::serialize(&(leaf, qc)).context("serialize leaf")?;
-                file.write_all(&bytes).context("write leaf")?;
-                Ok(())
+                bincode::serialize_into(&mut bytes, &(leaf, qc)).context("serialize leaf")?;
+                Ok(bytes)
             },
         )
     }
@@ -267,15 +395,13 @@ impl SequencerPersistence for Persistence {
         if !path.is_file() {
             return Ok(None);
         }
-        let mut file = File::open(path)?;
+        let bytes = self.read(&path)?;
 
         // The first 8 bytes just contain the height of the leaf. We can skip this.
-        file.seek(SeekFrom::Start(8)).context("seek")?;
-        let bytes = file
-            .bytes()
-            .collect::<Result<Vec<_>, _>>()
-            .context("read")?;
-        Ok(Some(bincode::deserialize(&bytes).context("deserialize")?))
+        ensure!(bytes.len() >= 8, "anchor leaf file smaller than 8 bytes");
+        Ok(Some(
+            bincode::deserialize(&bytes[8..]).context("deserialize")?,
+        ))
     }
 
     async fn load_undecided_state(
@@ -285,7 +411,7 @@ impl SequencerPersistence for Persistence {
         if !path.is_file() {
             return Ok(None);
         }
-        let bytes = fs::read(&path).context("read")?;
+        let bytes = self.read(&path)?;
         Ok(Some(bincode::deserialize(&bytes).context("deserialize")?))
     }
 
@@ -301,7 +427,7 @@ impl SequencerPersistence for Persistence {
             return Ok(None);
         }
 
-        let da_bytes = fs::read(file_path)?;
+        let da_bytes = self.read(&file_path)?;
 
         let da_proposal: Proposal<SeqTypes, DaProposal<SeqTypes>> =
             bincode::deserialize(&da_bytes)?;
@@ -320,7 +446,7 @@ impl SequencerPersistence for Persistence {
             return Ok(None);
         }
 
-        let vid_share_bytes = fs::read(file_path)?;
+        let vid_share_bytes = self.read(&file_path)?;
         let vid_share: Proposal<SeqTypes, VidDisperseShare<SeqTypes>> =
             bincode::deserialize(&vid_share_bytes)?;
         Ok(Some(vid_share))
@@ -344,11 +470,7 @@ impl SequencerPersistence for Persistence {
                 tracing::warn!(view_number, "duplicate VID share");
                 Ok(false)
             },
-            |mut file| {
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-                file.write_all(&proposal_bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&proposal).context("serialize proposal"),
         )
     }
     async fn append_da(
@@ -369,11 +491,7 @@ impl SequencerPersistence for Persistence {
                 tracing::warn!(view_number, "duplicate DA proposal");
                 Ok(false)
             },
-            |mut file| {
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-                file.write_all(&proposal_bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&proposal).context("serialize proposal"),
         )
     }
     async fn record_action(
@@ -383,9 +501,7 @@ impl SequencerPersistence for Persistence {
     ) -> anyhow::Result<()> {
         self.replace(
             &self.voted_view_path(),
-            |mut file| {
-                let mut bytes = vec![];
-                file.read_to_end(&mut bytes)?;
+            |bytes| {
                 let bytes = bytes
                     .try_into()
                     .map_err(|bytes| anyhow!("malformed voted view file: {bytes:?}"))?;
@@ -394,10 +510,7 @@ impl SequencerPersistence for Persistence {
                 // Overwrite the file if the saved view is older than the new view.
                 Ok(saved_view < view)
             },
-            |mut file| {
-                file.write_all(&view.u64().to_le_bytes())?;
-                Ok(())
-            },
+            || Ok(view.u64().to_le_bytes().to_vec()),
         )
     }
     async fn update_undecided_state(
@@ -415,12 +528,7 @@ impl SequencerPersistence for Persistence {
                 // Always overwrite the previous file.
                 Ok(true)
             },
-            |mut file| {
-                let bytes =
-                    bincode::serialize(&(leaves, state)).context("serializing undecided state")?;
-                file.write_all(&bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&(leaves, state)).context("serializing undecided state"),
         )
     }
     async fn append_quorum_proposal(
@@ -439,12 +547,7 @@ impl SequencerPersistence for Persistence {
                 // Always overwrite the previous file
                 Ok(true)
             },
-            |mut file| {
-                let proposal_bytes = bincode::serialize(&proposal).context("serialize proposal")?;
-
-                file.write_all(&proposal_bytes)?;
-                Ok(())
-            },
+            || bincode::serialize(&proposal).context("serialize proposal"),
         )
     }
     async fn load_quorum_proposals(
@@ -489,7 +592,7 @@ impl SequencerPersistence for Persistence {
                 );
 
                 // Now, we'll try and load the proposal associated with this function.
-                let proposal_bytes = fs::read(file)?;
+                let proposal_bytes = self.read(&file)?;
 
                 // Then, deserialize.
                 let proposal: Proposal<SeqTypes, QuorumProposal<SeqTypes>> =
@@ -504,6 +607,284 @@ impl SequencerPersistence for Persistence {
     }
 }
 
+/// The consensus artifacts in file system storage.
+///
+/// Each artifact is stored in its own file, named after its view, in a directory for each type of
+/// artifact. The modification time of the file is the time the artifact was stored.
+#[derive(Debug)]
+struct Artifacts {
+    path: PathBuf,
+}
+
+impl Artifacts {
+    fn dir_path(&self, kind: ArtifactKind) -> PathBuf {
+        self.path.join(match kind {
+            ArtifactKind::DaProposal => "da",
+            ArtifactKind::VidShare => "vid",
+            ArtifactKind::QuorumProposal => "quorum_proposals",
+        })
+    }
+
+    /// The files in the directory for `kind`, with the view each file belongs to.
+    fn files(&self, kind: ArtifactKind) -> anyhow::Result<Vec<(PathBuf, u64)>> {
+        let dir_path = self.dir_path(kind);
+        if !dir_path.is_dir() {
+            return Ok(vec![]);
+        }
+
+        let mut files = vec![];
+        for entry in fs::read_dir(dir_path)? {
+            let path = entry?.path();
+            if let Some(view) = path
+                .file_stem()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.parse::<u64>().ok())
+            {
+                files.push((path, view));
+            }
+        }
+        Ok(files)
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for Artifacts {
+    async fn delete(
+        &self,
+        kind: ArtifactKind,
+        max_view: ViewNumber,
+        before: Option<SystemTime>,
+    ) -> anyhow::Result<u64> {
+        let mut deleted = 0;
+        for (path, view) in self.files(kind)? {
+            if view > max_view.u64() {
+                continue;
+            }
+            if let Some(before) = before {
+                if fs::metadata(&path)?.modified()? >= before {
+                    continue;
+                }
+            }
+            fs::remove_file(&path)?;
+            deleted += 1;
+        }
+        Ok(deleted)
+    }
+
+    async fn stored_bytes(&self, kind: ArtifactKind) -> anyhow::Result<u64> {
+        let mut bytes = 0;
+        for (path, _) in self.files(kind)? {
+            bytes += fs::metadata(&path)?.len();
+        }
+        Ok(bytes)
+    }
+}
+
+#[async_trait]
+impl CheckablePersistence for Persistence {
+    async fn entry_ids(&self) -> anyhow::Result<Vec<EntryId>> {
+        let mut ids = vec![];
+        if !self.path.is_dir() {
+            return Ok(ids);
+        }
+
+        for id in [
+            EntryId::Config,
+            EntryId::VotedView,
+            EntryId::AnchorLeaf,
+            EntryId::UndecidedState,
+        ] {
+            if self.entry_path(&id).is_file() {
+                ids.push(id);
+            }
+        }
+
+        let dirs: [(PathBuf, fn(ViewNumber) -> EntryId); 3] = [
+            (self.da_dir_path(), EntryId::DaProposal),
+            (self.vid_dir_path(), EntryId::VidShare),
+            (self.quorum_proposals_dir_path(), EntryId::QuorumProposal),
+        ];
+        for (dir, id) in dirs {
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if !path.is_file() {
+                    continue;
+                }
+                let view = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok());
+                match view {
+                    Some(view) if path.extension().is_some_and(|ext| ext == "txt") => {
+                        ids.push(id(ViewNumber::new(view)));
+                    }
+                    _ => ids.push(EntryId::Stray(path)),
+                }
+            }
+        }
+
+        // Swap files are left behind at the top level if we crash while replacing a file.
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "swp") {
+                ids.push(EntryId::Stray(path));
+            }
+        }
+
+        Ok(ids)
+    }
+
+    async fn entry(&self, id: &EntryId) -> anyhow::Result<Entry> {
+        if let EntryId::Stray(_) = id {
+            return Ok(Entry {
+                id: id.clone(),
+                raw: vec![],
+                value: Ok(EntryValue::Stray),
+            });
+        }
+
+        let path = self.entry_path(id);
+        let raw = fs::read(&path).context(format!("reading {}", path.display()))?;
+        let value = self
+            .open(&path, raw.clone())
+            .and_then(|bytes| decode_file(id, &bytes));
+        Ok(Entry {
+            id: id.clone(),
+            raw,
+            value,
+        })
+    }
+
+    async fn quarantine(&mut self, entry: &Entry, reason: &str) -> anyhow::Result<()> {
+        let path = self.entry_path(&entry.id);
+
+        // Keep the same layout in the quarantine directory, without overwriting anything that was
+        // quarantined previously.
+        let relative = path.strip_prefix(&self.path).unwrap_or(&path);
+        let dest = self.quarantine_dir_path().join(relative);
+        let dest = (0..)
+            .map(|i| {
+                if i == 0 {
+                    dest.clone()
+                } else {
+                    with_suffix(&dest, &format!(".{i}"))
+                }
+            })
+            .find(|dest| !dest.exists())
+            .unwrap();
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).context("failed to create quarantine dir")?;
+        }
+
+        tracing::warn!(
+            from = %path.display(),
+            to = %dest.display(),
+            "quarantining {}: {reason}",
+            entry.id
+        );
+        fs::rename(&path, &dest).context(format!("moving {}", path.display()))?;
+        let reason_path = with_suffix(&dest, ".reason");
+        let reason = self.encryption.seal(
+            &sealing_context(&self.path, &reason_path),
+            reason.as_bytes(),
+        )?;
+        fs::write(&reason_path, reason)?;
+        Ok(())
+    }
+}
+
+/// Decode the contents of the file storing the entry `id`.
+fn decode_file(id: &EntryId, bytes: &[u8]) -> anyhow::Result<EntryValue> {
+    match id {
+        EntryId::Config => {
+            let json = serde_json::from_slice(bytes).context("config file is not valid JSON")?;
+            let json =
+                migrate_network_config(json).context("migration of network config failed")?;
+            let config = serde_json::from_value(json).context("malformed config file")?;
+            Ok(EntryValue::Config(Box::new(config)))
+        }
+        EntryId::VotedView => {
+            let bytes = bytes
+                .try_into()
+                .map_err(|_| anyhow!("malformed voted view file: {bytes:?}"))?;
+            Ok(EntryValue::VotedView(ViewNumber::new(u64::from_le_bytes(
+                bytes,
+            ))))
+        }
+        EntryId::AnchorLeaf => {
+            // The first 8 bytes contain the height of the leaf, which must match the leaf.
+            ensure!(bytes.len() >= 8, "anchor leaf file smaller than 8 bytes");
+            let (height, bytes) = bytes.split_at(8);
+            let height = u64::from_le_bytes(height.try_into().unwrap());
+            let (leaf, qc): (Leaf, QuorumCertificate<SeqTypes>) =
+                bincode::deserialize(bytes).context("deserialize")?;
+            ensure!(
+                height == leaf.height(),
+                "anchor leaf file has height {height}, but leaf has height {}",
+                leaf.height()
+            );
+            Ok(EntryValue::AnchorLeaf(Box::new((leaf, qc))))
+        }
+        EntryId::UndecidedState => Ok(EntryValue::UndecidedState(Box::new(
+            bincode::deserialize(bytes).context("deserialize")?,
+        ))),
+        EntryId::DaProposal(_) | EntryId::VidShare(_) | EntryId::QuorumProposal(_) => {
+            decode_proposal(id, bytes)
+        }
+        EntryId::Stray(_) => Ok(EntryValue::Stray),
+    }
+}
+
+/// Recursively collect the regular files under `dir`, if it exists.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir).context(format!("reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The context with which the file at `path`, under the storage root `root`, is sealed.
+///
+/// This is the path of the file relative to `root`, so that a sealed file cannot be moved to another
+/// path in the storage without failing authentication, while the storage as a whole can still be
+/// moved. Quarantined files keep the context of the location they were quarantined from, without
+/// the numeric suffix which keeps them from overwriting earlier quarantined files.
+fn sealing_context(root: &Path, path: &Path) -> Vec<u8> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let relative = match relative.strip_prefix("quarantine") {
+        Ok(original) => {
+            if original
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.chars().all(|c| c.is_ascii_digit()))
+            {
+                original.with_extension("")
+            } else {
+                original.to_owned()
+            }
+        }
+        Err(_) => relative.to_owned(),
+    };
+    relative.to_string_lossy().into_owned().into_bytes()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
 /// Update a `NetworkConfig` that may have originally been persisted with an old version.
 fn migrate_network_config(
     mut network_config: serde_json::Value,
@@ -575,6 +956,10 @@ mod testing {
         async fn connect(storage: &Self::Storage) -> Self {
             Options::new(storage.path().into()).create().await.unwrap()
         }
+
+        async fn compact(&self) {
+            self.compactor.compact().await.unwrap();
+        }
     }
 }
 
@@ -590,9 +975,18 @@ mod generic_tests {
 
 #[cfg(test)]
 mod test {
+    use espresso_types::{NodeState, ValidatedState};
+    use hotshot_types::event::HotShotAction;
     use serde_json::json;
+    use tempfile::TempDir;
 
-    use super::*;
+    use super::{
+        super::{
+            encryption::{is_sealed, Encryption},
+            integrity::{check, Severity},
+        },
+        *,
+    };
 
     #[test]
     fn test_config_migrations_add_builder_urls() {
@@ -687,4 +1081,237 @@ mod test {
 
         assert_eq!(migrate_network_config(before.clone()).unwrap(), before);
     }
+
+    #[async_std::test]
+    async fn test_quarantine_partial_files() {
+        let tmp = TempDir::new().unwrap();
+        let mut storage = Options::new(tmp.path().into()).create().await.unwrap();
+
+        // Simulate a crash while writing a VID share, and while replacing the anchor leaf.
+        fs::create_dir_all(storage.vid_dir_path()).unwrap();
+        fs::write(storage.vid_dir_path().join("5.txt"), [1, 2, 3]).unwrap();
+        fs::write(storage.anchor_leaf_path().with_extension("swp"), [4, 5, 6]).unwrap();
+
+        // The partial VID share prevents it from being loaded.
+        storage
+            .load_vid_share(ViewNumber::new(5))
+            .await
+            .unwrap_err();
+
+        let mut anomalies = check(&storage).await.unwrap();
+        anomalies.sort_by_key(|anomaly| anomaly.id.clone());
+        assert_eq!(anomalies.len(), 2, "{anomalies:?}");
+        assert_eq!(anomalies[0].id, EntryId::VidShare(ViewNumber::new(5)));
+        assert_eq!(anomalies[0].severity, Severity::Error);
+        assert_eq!(
+            anomalies[1].id,
+            EntryId::Stray(storage.anchor_leaf_path().with_extension("swp"))
+        );
+        assert_eq!(anomalies[1].severity, Severity::Warning);
+
+        for anomaly in &anomalies {
+            let entry = storage.entry(&anomaly.id).await.unwrap();
+            storage.quarantine(&entry, &anomaly.reason).await.unwrap();
+        }
+
+        // The corrupt share is out of the way, but preserved in the quarantine directory.
+        assert_eq!(
+            storage.load_vid_share(ViewNumber::new(5)).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            fs::read(storage.quarantine_dir_path().join("vid/5.txt")).unwrap(),
+            [1, 2, 3]
+        );
+        assert!(storage
+            .quarantine_dir_path()
+            .join("vid/5.txt.reason")
+            .is_file());
+        assert!(storage
+            .quarantine_dir_path()
+            .join("anchor_leaf.swp")
+            .is_file());
+        assert_eq!(check(&storage).await.unwrap(), vec![]);
+
+        // Quarantined files are left out of key rotation, but the reasons for them are not.
+        let files = Options::new(tmp.path().into()).consensus_files().unwrap();
+        assert!(files.contains(&storage.quarantine_dir_path().join("vid/5.txt.reason")));
+        assert!(!files.contains(&storage.quarantine_dir_path().join("vid/5.txt")));
+    }
+
+    #[async_std::test]
+    async fn test_encrypted_quarantine() {
+        let tmp = TempDir::new().unwrap();
+        let options = Options::new(tmp.path().into()).with_encryption(EncryptionOptions {
+            encryption_passphrase: Some("passphrase".into()),
+            ..Default::default()
+        });
+        let mut storage = options.clone().create().await.unwrap();
+
+        // A plaintext VID share cannot be opened once encryption is enabled.
+        fs::create_dir_all(storage.vid_dir_path()).unwrap();
+        fs::write(storage.vid_dir_path().join("5.txt"), [1, 2, 3]).unwrap();
+        let anomalies = check(&storage).await.unwrap();
+        assert_eq!(anomalies.len(), 1, "{anomalies:?}");
+        let entry = storage.entry(&anomalies[0].id).await.unwrap();
+        storage
+            .quarantine(&entry, &anomalies[0].reason)
+            .await
+            .unwrap();
+
+        // The reason is sealed like every other file, and can be opened with the storage key.
+        let path = storage.quarantine_dir_path().join("vid/5.txt.reason");
+        let sealed = fs::read(&path).unwrap();
+        assert!(is_sealed(&sealed));
+        let reason = options
+            .encryption
+            .encryption()
+            .unwrap()
+            .open(&options.sealing_context(&path), sealed)
+            .unwrap();
+        assert_eq!(reason, anomalies[0].reason.as_bytes());
+    }
+
+    #[async_std::test]
+    async fn test_encrypted_storage() {
+        let tmp = TempDir::new().unwrap();
+        let encryption = EncryptionOptions {
+            encryption_passphrase: Some("correct horse battery staple".into()),
+            ..Default::default()
+        };
+        let options = Options::new(tmp.path().into()).with_encryption(encryption.clone());
+        let mut storage = options.clone().create().await.unwrap();
+
+        let leaf = Leaf::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        let qc = QuorumCertificate::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        storage.save_anchor_leaf(&leaf, &qc).await.unwrap();
+        storage
+            .record_action(ViewNumber::new(1), HotShotAction::Vote)
+            .await
+            .unwrap();
+
+        // Everything on disk is encrypted.
+        for path in [storage.anchor_leaf_path(), storage.voted_view_path()] {
+            assert!(is_sealed(&fs::read(&path).unwrap()), "{}", path.display());
+        }
+
+        // Storage can be reopened with the same passphrase.
+        let mut storage = options.create().await.unwrap();
+        assert_eq!(
+            storage.load_anchor_leaf().await.unwrap().unwrap(),
+            (leaf.clone(), qc.clone())
+        );
+        assert_eq!(
+            storage.load_latest_acted_view().await.unwrap(),
+            Some(ViewNumber::new(1))
+        );
+        assert_eq!(check(&storage).await.unwrap(), vec![]);
+
+        // Conditional writes see through the encryption.
+        storage
+            .record_action(ViewNumber::new(0), HotShotAction::Vote)
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.load_latest_acted_view().await.unwrap(),
+            Some(ViewNumber::new(1))
+        );
+
+        // Without the key, or with the wrong key, the data cannot be read.
+        let storage = Options::new(tmp.path().into()).create().await.unwrap();
+        storage.load_anchor_leaf().await.unwrap_err();
+        let storage = Options::new(tmp.path().into())
+            .with_encryption(EncryptionOptions {
+                encryption_passphrase: Some("wrong".into()),
+                ..Default::default()
+            })
+            .create()
+            .await
+            .unwrap();
+        storage.load_latest_acted_view().await.unwrap_err();
+    }
+
+    #[async_std::test]
+    async fn test_encrypted_file_moved() {
+        let tmp = TempDir::new().unwrap();
+        let options = Options::new(tmp.path().into()).with_encryption(EncryptionOptions {
+            encryption_passphrase: Some("passphrase".into()),
+            ..Default::default()
+        });
+        let mut storage = options.clone().create().await.unwrap();
+
+        let leaf = Leaf::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        let qc = QuorumCertificate::genesis(&ValidatedState::default(), &NodeState::mock()).await;
+        storage.save_anchor_leaf(&leaf, &qc).await.unwrap();
+        storage
+            .record_action(ViewNumber::new(1), HotShotAction::Vote)
+            .await
+            .unwrap();
+
+        // A sealed file is bound to its path: copied over another file, it fails authentication,
+        // even though it was sealed with the right key.
+        fs::copy(storage.anchor_leaf_path(), storage.voted_view_path()).unwrap();
+        let err = storage.load_latest_acted_view().await.unwrap_err();
+        assert!(
+            format!("{err:#}").contains("failed authentication"),
+            "{err:#}"
+        );
+
+        // The storage as a whole can still be moved.
+        let moved = TempDir::new().unwrap();
+        fs::rename(storage.anchor_leaf_path(), moved.path().join("anchor_leaf")).unwrap();
+        let storage = Options::new(moved.path().into())
+            .with_encryption(options.encryption.clone())
+            .create()
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.load_anchor_leaf().await.unwrap().unwrap(),
+            (leaf, qc)
+        );
+    }
+
+    #[async_std::test]
+    async fn test_enable_encryption() {
+        let tmp = TempDir::new().unwrap();
+        let mut storage = Options::new(tmp.path().into()).create().await.unwrap();
+        storage
+            .record_action(ViewNumber::new(1), HotShotAction::Vote)
+            .await
+            .unwrap();
+
+        // Once encryption is enabled, plaintext storage is rejected, so that plaintext cannot be
+        // swapped in for encrypted files.
+        let options = Options::new(tmp.path().into()).with_encryption(EncryptionOptions {
+            encryption_passphrase: Some("passphrase".into()),
+            ..Default::default()
+        });
+        let storage = options.clone().create().await.unwrap();
+        storage.load_latest_acted_view().await.unwrap_err();
+
+        // After encrypting the existing files, as `rotate-storage-key` does, the storage is
+        // readable, and stays encrypted as it is rewritten.
+        let encryption = options.encryption.encryption().unwrap();
+        for path in options.consensus_files().unwrap() {
+            let context = options.sealing_context(&path);
+            let plaintext = Encryption::none()
+                .open(&context, fs::read(&path).unwrap())
+                .unwrap();
+            fs::write(&path, encryption.seal(&context, &plaintext).unwrap()).unwrap();
+        }
+        let mut storage = options.create().await.unwrap();
+        assert_eq!(
+            storage.load_latest_acted_view().await.unwrap(),
+            Some(ViewNumber::new(1))
+        );
+        storage
+            .record_action(ViewNumber::new(2), HotShotAction::Vote)
+            .await
+            .unwrap();
+        assert!(is_sealed(&fs::read(storage.voted_view_path()).unwrap()));
+        assert_eq!(
+            storage.load_latest_acted_view().await.unwrap(),
+            Some(ViewNumber::new(2))
+        );
+    }
 }
@@ -0,0 +1,491 @@
+//! Integrity checking for consensus storage.
+//!
+//! A node which crashes in the middle of writing to consensus storage can leave behind entries which
+//! cannot be decoded, and which then prevent the node from restarting. [`check`] validates every
+//! entry read from a [`CheckablePersistence`] and reports any anomalies. Entries are read one at a
+//! time, so checking does not require holding all of consensus storage in memory. Corrupt entries
+//! can then be [quarantined](CheckablePersistence::quarantine): removed from storage, but preserved
+//! for inspection, so that the node can start and recover the missing data from its peers.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
+
+use anyhow::{bail, ensure, Context};
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use espresso_types::{v0::traits::SequencerPersistence, Leaf, NetworkConfig, PubKey};
+use hotshot::traits::election::static_committee::GeneralStaticCommittee;
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{DaProposal, QuorumProposal, VidDisperseShare},
+    message::Proposal,
+    simple_certificate::QuorumCertificate,
+    traits::{
+        election::Membership, node_implementation::ConsensusTime, signature_key::SignatureKey,
+    },
+    utils::View,
+    vote::{Certificate, HasViewNumber},
+};
+use sha2::{Digest, Sha256};
+
+use crate::{SeqTypes, ViewNumber};
+
+/// Identifies an entry in consensus storage.
+///
+/// Entries are ordered so that the network config and the anchor leaf, which other entries are
+/// checked against, come first.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntryId {
+    Config,
+    AnchorLeaf,
+    VotedView,
+    UndecidedState,
+    DaProposal(ViewNumber),
+    VidShare(ViewNumber),
+    QuorumProposal(ViewNumber),
+    /// A file which is not part of consensus storage, such as a temporary file left behind by an
+    /// interrupted write.
+    Stray(PathBuf),
+}
+
+impl EntryId {
+    /// The kind of entry, as a short name.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Config => "config",
+            Self::AnchorLeaf => "anchor_leaf",
+            Self::VotedView => "highest_voted_view",
+            Self::UndecidedState => "undecided_state",
+            Self::DaProposal(_) => "da_proposal",
+            Self::VidShare(_) => "vid_share",
+            Self::QuorumProposal(_) => "quorum_proposal",
+            Self::Stray(_) => "stray",
+        }
+    }
+
+    /// The view under which this entry is stored, if it is stored by view.
+    pub fn view(&self) -> Option<ViewNumber> {
+        match self {
+            Self::DaProposal(view) | Self::VidShare(view) | Self::QuorumProposal(view) => {
+                Some(*view)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for EntryId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stray(path) => write!(f, "stray file {}", path.display()),
+            _ => match self.view() {
+                Some(view) => write!(f, "{} {}", self.kind(), view.u64()),
+                None => write!(f, "{}", self.kind()),
+            },
+        }
+    }
+}
+
+/// The decoded contents of an entry in consensus storage.
+#[derive(Clone, Debug)]
+pub enum EntryValue {
+    Config(Box<NetworkConfig>),
+    AnchorLeaf(Box<(Leaf, QuorumCertificate<SeqTypes>)>),
+    VotedView(ViewNumber),
+    UndecidedState(Box<(CommitmentMap<Leaf>, BTreeMap<ViewNumber, View<SeqTypes>>)>),
+    DaProposal(Box<Proposal<SeqTypes, DaProposal<SeqTypes>>>),
+    VidShare(Box<Proposal<SeqTypes, VidDisperseShare<SeqTypes>>>),
+    QuorumProposal(Box<Proposal<SeqTypes, QuorumProposal<SeqTypes>>>),
+    Stray,
+}
+
+/// An entry read from consensus storage.
+#[derive(Debug)]
+pub struct Entry {
+    pub id: EntryId,
+    /// The entry as stored, so that it can be preserved if the entry is quarantined.
+    pub raw: Vec<u8>,
+    /// The decoded entry, or the error encountered while decoding it.
+    pub value: anyhow::Result<EntryValue>,
+}
+
+/// Consensus storage which can be checked for corruption and repaired.
+#[async_trait]
+pub trait CheckablePersistence: SequencerPersistence {
+    /// List the entries in storage.
+    async fn entry_ids(&self) -> anyhow::Result<Vec<EntryId>>;
+
+    /// Read a single entry from storage.
+    ///
+    /// Unlike the loading functions of [`SequencerPersistence`], this does not fail if the entry
+    /// cannot be decoded. Instead, the decoding error is returned as part of the entry.
+    async fn entry(&self, id: &EntryId) -> anyhow::Result<Entry>;
+
+    /// Remove an entry from storage, preserving its raw contents along with `reason`.
+    async fn quarantine(&mut self, entry: &Entry, reason: &str) -> anyhow::Result<()>;
+}
+
+/// How serious an [`Anomaly`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The entry is unexpected, but will not prevent the node from starting.
+    Warning,
+    /// The entry is corrupt or inconsistent, and may prevent the node from starting.
+    Error,
+}
+
+/// A problem found with an entry in consensus storage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Anomaly {
+    pub id: EntryId,
+    pub severity: Severity,
+    pub reason: String,
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}: {}", self.severity, self.id, self.reason)
+    }
+}
+
+/// Check the entries in consensus storage.
+///
+/// Each entry is checked for:
+/// * successful decoding
+/// * consistency of leaves and QCs with their commitments
+/// * valid QC signatures from the stake table, if the network config is stored
+/// * consistency of each proposal with the view it is stored under, and of each quorum proposal
+///   with the QC that justifies it
+/// * valid signatures on proposals from a node in the stake table, if the network config is stored
+/// * files which are not part of consensus storage, such as leftover temporary files
+///
+/// The quorum proposals are also checked against each other and the anchor leaf: wherever the leaf
+/// justified by a proposal's QC is stored, it must be the leaf the QC is for, and the proposal must
+/// extend it by exactly one block.
+///
+/// Proposals which are not newer than the anchor leaf are not flagged, since they may be kept
+/// around by the consensus artifact retention policy.
+pub async fn check(storage: &impl CheckablePersistence) -> anyhow::Result<Vec<Anomaly>> {
+    let mut ids = storage.entry_ids().await.context("listing entries")?;
+    ids.sort();
+
+    let mut checker = Checker::default();
+    for id in ids {
+        let entry = storage.entry(&id).await.context(format!("reading {id}"))?;
+        checker.check(&entry);
+    }
+    Ok(checker.finish())
+}
+
+/// The position of a leaf in the chain.
+#[derive(Clone, Copy, Debug)]
+struct ChainLink {
+    leaf: Commitment<Leaf>,
+    view: ViewNumber,
+    height: u64,
+}
+
+/// How a quorum proposal links to its parent.
+#[derive(Clone, Copy, Debug)]
+struct ProposalLink {
+    link: ChainLink,
+    justify_view: ViewNumber,
+    justify_leaf: Commitment<Leaf>,
+}
+
+/// State accumulated while checking entries one at a time.
+///
+/// Entries must be checked in [`EntryId`] order, so that the network config and the anchor leaf
+/// are known before any entries which are checked against them. Only a small summary of each
+/// quorum proposal is retained, for checking the links between proposals once every entry has been
+/// seen.
+#[derive(Default)]
+struct Checker {
+    config_checked: bool,
+    known_keys: Option<HashSet<PubKey>>,
+    membership: Option<GeneralStaticCommittee<SeqTypes, PubKey>>,
+    anchor: Option<ChainLink>,
+    proposals: BTreeMap<ViewNumber, ProposalLink>,
+    anomalies: Vec<Anomaly>,
+}
+
+impl Checker {
+    fn check(&mut self, entry: &Entry) {
+        if entry.id != EntryId::Config && !self.config_checked {
+            self.config_checked = true;
+            if self.known_keys.is_none() {
+                tracing::warn!("network config not found, signatures will not be checked");
+            }
+        }
+
+        let value = match &entry.value {
+            Ok(value) => value,
+            Err(err) => {
+                self.anomalies.push(Anomaly {
+                    id: entry.id.clone(),
+                    severity: Severity::Error,
+                    reason: format!("cannot be decoded: {err:#}"),
+                });
+                return;
+            }
+        };
+        if let Err(err) = self.check_value(&entry.id, value) {
+            self.anomalies.push(Anomaly {
+                id: entry.id.clone(),
+                severity: Severity::Error,
+                reason: format!("{err:#}"),
+            });
+        } else if let EntryValue::Stray = value {
+            self.anomalies.push(Anomaly {
+                id: entry.id.clone(),
+                severity: Severity::Warning,
+                reason:
+                    "not part of consensus storage, possibly left behind by an interrupted write"
+                        .into(),
+            });
+        }
+    }
+
+    /// Check the links between quorum proposals, and return all the anomalies found.
+    fn finish(mut self) -> Vec<Anomaly> {
+        for (view, proposal) in &self.proposals {
+            if let Err(err) = self.check_link(proposal) {
+                self.anomalies.push(Anomaly {
+                    id: EntryId::QuorumProposal(*view),
+                    severity: Severity::Error,
+                    reason: format!("{err:#}"),
+                });
+            }
+        }
+        self.anomalies
+    }
+
+    fn check_link(&self, proposal: &ProposalLink) -> anyhow::Result<()> {
+        if let Some(anchor) = &self.anchor {
+            if proposal.link.view <= anchor.view {
+                return Ok(());
+            }
+        }
+        let parent = match self.anchor {
+            Some(anchor) if anchor.view == proposal.justify_view => anchor,
+            _ => match self.proposals.get(&proposal.justify_view) {
+                Some(parent) => parent.link,
+                // We don't have the parent, so there is nothing to check against.
+                None => return Ok(()),
+            },
+        };
+        ensure!(
+            proposal.justify_leaf == parent.leaf,
+            "justified by a QC for leaf {}, but the leaf from view {} is {}",
+            proposal.justify_leaf,
+            parent.view.u64(),
+            parent.leaf
+        );
+        ensure!(
+            proposal.link.height == parent.height + 1,
+            "proposes block {}, but its parent from view {} is block {}",
+            proposal.link.height,
+            parent.view.u64(),
+            parent.height
+        );
+        Ok(())
+    }
+
+    fn check_value(&mut self, id: &EntryId, value: &EntryValue) -> anyhow::Result<()> {
+        match value {
+            EntryValue::VotedView(_) | EntryValue::Stray => {}
+            EntryValue::Config(cfg) => {
+                let stake_table = cfg.config.known_nodes_with_stake.clone();
+                self.known_keys = Some(
+                    stake_table
+                        .iter()
+                        .map(|peer| peer.stake_table_entry.stake_key)
+                        .collect(),
+                );
+                self.membership = Some(GeneralStaticCommittee::create_election(
+                    stake_table.clone(),
+                    stake_table,
+                    0,
+                ));
+            }
+            EntryValue::AnchorLeaf(anchor) => {
+                let (leaf, qc) = &**anchor;
+                self.check_qc(qc)?;
+                ensure!(
+                    qc.data.leaf_commit == leaf.commit(),
+                    "leaf {} does not match QC for {}",
+                    leaf.commit(),
+                    qc.data.leaf_commit
+                );
+                ensure!(
+                    qc.view_number == leaf.view_number(),
+                    "leaf is from view {}, but QC is from view {}",
+                    leaf.view_number().u64(),
+                    qc.view_number.u64()
+                );
+                self.anchor = Some(ChainLink {
+                    leaf: leaf.commit(),
+                    view: leaf.view_number(),
+                    height: leaf.height(),
+                });
+            }
+            EntryValue::UndecidedState(state) => {
+                for (commit, leaf) in &state.0 {
+                    ensure!(
+                        *commit == leaf.commit(),
+                        "leaf {} stored under commitment {commit}",
+                        leaf.commit()
+                    );
+                    self.check_qc(&leaf.justify_qc())
+                        .context(format!("invalid justify QC for leaf {commit}"))?;
+                }
+            }
+            EntryValue::DaProposal(proposal) => {
+                check_view(id, proposal.data.view_number())?;
+                let hash = Sha256::digest(&proposal.data.encoded_transactions);
+                self.check_signature(&proposal.signature, &hash)?;
+            }
+            EntryValue::VidShare(share) => {
+                check_view(id, share.data.view_number())?;
+                self.check_signature(&share.signature, share.data.payload_commitment.as_ref())?;
+            }
+            EntryValue::QuorumProposal(proposal) => {
+                let view = proposal.data.view_number();
+                check_view(id, view)?;
+
+                let qc = &proposal.data.justify_qc;
+                self.check_qc(qc).context("invalid justify QC")?;
+                ensure!(
+                    qc.view_number < view || view == ViewNumber::genesis(),
+                    "justified by a QC from view {}, which is not older than the proposal",
+                    qc.view_number.u64()
+                );
+
+                let leaf = Leaf::from_quorum_proposal(&proposal.data);
+                self.check_signature(&proposal.signature, leaf.commit().as_ref())?;
+                self.proposals.insert(
+                    view,
+                    ProposalLink {
+                        link: ChainLink {
+                            leaf: leaf.commit(),
+                            view,
+                            height: leaf.height(),
+                        },
+                        justify_view: qc.view_number,
+                        justify_leaf: qc.data.leaf_commit,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn check_qc(&self, qc: &QuorumCertificate<SeqTypes>) -> anyhow::Result<()> {
+        ensure!(
+            qc.vote_commitment == qc.data.commit(),
+            "QC vote commitment does not match QC data"
+        );
+        if let Some(membership) = &self.membership {
+            ensure!(
+                qc.is_valid_cert(membership),
+                "QC is not signed by a quorum of the stake table"
+            );
+        }
+        Ok(())
+    }
+
+    fn check_signature(
+        &self,
+        signature: &<PubKey as SignatureKey>::PureAssembledSignatureType,
+        data: &[u8],
+    ) -> anyhow::Result<()> {
+        let Some(keys) = &self.known_keys else {
+            return Ok(());
+        };
+        ensure!(
+            keys.iter().any(|key| key.validate(signature, data)),
+            "not signed by any node in the stake table"
+        );
+        Ok(())
+    }
+}
+
+fn check_view(id: &EntryId, view: ViewNumber) -> anyhow::Result<()> {
+    ensure!(
+        id.view() == Some(view),
+        "stored as {id}, but contains data for view {}",
+        view.u64()
+    );
+    Ok(())
+}
+
+/// Decode a proposal, which every backend stores as a single `bincode` blob.
+pub(crate) fn decode_proposal(id: &EntryId, bytes: &[u8]) -> anyhow::Result<EntryValue> {
+    Ok(match id {
+        EntryId::DaProposal(_) => EntryValue::DaProposal(Box::new(bincode::deserialize(bytes)?)),
+        EntryId::VidShare(_) => EntryValue::VidShare(Box::new(bincode::deserialize(bytes)?)),
+        EntryId::QuorumProposal(_) => {
+            EntryValue::QuorumProposal(Box::new(bincode::deserialize(bytes)?))
+        }
+        _ => bail!("{id} is not a proposal"),
+    })
+}
+
+/// Read the anchor leaf from the separate leaf and QC columns used by the SQL backends.
+pub(crate) fn sql_anchor_leaf_entry(
+    leaf: Option<Vec<u8>>,
+    qc: Option<Vec<u8>>,
+) -> anyhow::Result<Entry> {
+    let raw = bincode::serialize(&(&leaf, &qc))?;
+    let value = (|| -> anyhow::Result<_> {
+        let leaf = bincode::deserialize(&leaf.context("missing leaf")?).context("leaf")?;
+        let qc = bincode::deserialize(&qc.context("missing QC")?).context("QC")?;
+        Ok(EntryValue::AnchorLeaf(Box::new((leaf, qc))))
+    })();
+    Ok(Entry {
+        id: EntryId::AnchorLeaf,
+        raw,
+        value,
+    })
+}
+
+/// Read the undecided state from the separate leaves and state columns used by the SQL backends.
+pub(crate) fn sql_undecided_state_entry(leaves: Vec<u8>, state: Vec<u8>) -> anyhow::Result<Entry> {
+    let raw = bincode::serialize(&(&leaves, &state))?;
+    let value = (|| -> anyhow::Result<_> {
+        let leaves = bincode::deserialize(&leaves).context("leaves")?;
+        let state = bincode::deserialize(&state).context("state")?;
+        Ok(EntryValue::UndecidedState(Box::new((leaves, state))))
+    })();
+    Ok(Entry {
+        id: EntryId::UndecidedState,
+        raw,
+        value,
+    })
+}
+
+/// The table and view under which an entry is stored by the SQL backends.
+///
+/// Entries which are not stored by view are the only row in their table.
+pub(crate) fn sql_location(id: &EntryId) -> anyhow::Result<(&'static str, Option<i64>)> {
+    Ok(match id {
+        EntryId::Config => ("network_config", None),
+        EntryId::AnchorLeaf => ("anchor_leaf", None),
+        EntryId::VotedView => ("highest_voted_view", None),
+        EntryId::UndecidedState => ("undecided_state", None),
+        EntryId::DaProposal(view) => ("da_proposal", Some(view.u64() as i64)),
+        EntryId::VidShare(view) => ("vid_share", Some(view.u64() as i64)),
+        EntryId::QuorumProposal(view) => ("quorum_proposals", Some(view.u64() as i64)),
+        EntryId::Stray(_) => bail!("{id} is not stored in a SQL database"),
+    })
+}
+
+/// The tables in which the SQL backends store proposals, with the corresponding entry IDs.
+pub(crate) const SQL_PROPOSAL_TABLES: [(&str, fn(ViewNumber) -> EntryId); 3] = [
+    ("da_proposal", EntryId::DaProposal),
+    ("vid_share", EntryId::VidShare),
+    ("quorum_proposals", EntryId::QuorumProposal),
+];
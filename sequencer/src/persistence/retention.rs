@@ -0,0 +1,381 @@
+//! Retention of consensus artifacts.
+//!
+//! Consensus storage accumulates a DA proposal, a VID share and a quorum proposal for every view.
+//! These are needed until the view is decided, and can be useful for some time after that (for
+//! example, to serve our VID shares to peers recovering old payloads). Instead of deleting them as
+//! soon as a view is decided, persistence backends hand them off to a [`Compactor`], which deletes
+//! them in the background according to a configurable [`RetentionOptions`] policy.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use async_std::task::sleep;
+use async_trait::async_trait;
+use clap::Parser;
+use hotshot_types::traits::{
+    metrics::{Counter, Gauge, Metrics},
+    node_implementation::ConsensusTime,
+};
+
+use crate::{context::TaskList, options::parse_duration, ViewNumber};
+
+/// Retention policy for consensus artifacts: DA proposals, VID shares and quorum proposals.
+///
+/// By default, artifacts are deleted once the view they belong to is decided.
+#[derive(Parser, Clone, Copy, Debug)]
+pub struct RetentionOptions {
+    /// Keep consensus artifacts for this many views after they are decided.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_CONSENSUS_RETENTION_VIEWS",
+        conflicts_with_all = ["consensus_retention_duration", "consensus_archive"],
+    )]
+    pub consensus_retention_views: Option<u64>,
+
+    /// Keep consensus artifacts for this long after they are stored, even if they are decided.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_CONSENSUS_RETENTION_DURATION",
+        value_parser = parse_duration,
+        conflicts_with = "consensus_archive",
+    )]
+    pub consensus_retention_duration: Option<Duration>,
+
+    /// Never delete consensus artifacts.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_CONSENSUS_ARCHIVE")]
+    pub consensus_archive: bool,
+
+    /// How often to delete consensus artifacts which are no longer retained.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_CONSENSUS_COMPACTION_INTERVAL",
+        value_parser = parse_duration,
+        default_value = "1m",
+    )]
+    pub consensus_compaction_interval: Duration,
+}
+
+impl Default for RetentionOptions {
+    fn default() -> Self {
+        Self::parse_from(std::iter::empty::<String>())
+    }
+}
+
+impl RetentionOptions {
+    /// The latest view whose artifacts may be deleted, given the latest decided view.
+    ///
+    /// Returns [`None`] if no artifacts may be deleted.
+    fn max_deletable_view(&self, decided: ViewNumber) -> Option<ViewNumber> {
+        if self.consensus_archive {
+            return None;
+        }
+        match self.consensus_retention_views {
+            Some(views) => decided.u64().checked_sub(views).map(ViewNumber::new),
+            None => Some(decided),
+        }
+    }
+
+    /// Artifacts stored before this time may be deleted.
+    fn deletable_before(&self, now: SystemTime) -> Option<SystemTime> {
+        self.consensus_retention_duration
+            .and_then(|duration| now.checked_sub(duration))
+    }
+}
+
+/// A type of consensus artifact subject to retention.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ArtifactKind {
+    DaProposal,
+    VidShare,
+    QuorumProposal,
+}
+
+impl ArtifactKind {
+    pub const ALL: [Self; 3] = [Self::DaProposal, Self::VidShare, Self::QuorumProposal];
+}
+
+impl Display for ArtifactKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DaProposal => write!(f, "da_proposal"),
+            Self::VidShare => write!(f, "vid_share"),
+            Self::QuorumProposal => write!(f, "quorum_proposal"),
+        }
+    }
+}
+
+/// Storage for consensus artifacts, as seen by the [`Compactor`].
+#[async_trait]
+pub(crate) trait ArtifactStore: Send + Sync + 'static {
+    /// Delete artifacts of type `kind` from views up to and including `max_view`.
+    ///
+    /// If `before` is specified, only artifacts stored before that time are deleted. Returns the
+    /// number of artifacts deleted.
+    async fn delete(
+        &self,
+        kind: ArtifactKind,
+        max_view: ViewNumber,
+        before: Option<SystemTime>,
+    ) -> anyhow::Result<u64>;
+
+    /// The total size of stored artifacts of type `kind`, in bytes.
+    async fn stored_bytes(&self, kind: ArtifactKind) -> anyhow::Result<u64>;
+}
+
+/// Deletes consensus artifacts in the background, according to a [`RetentionOptions`] policy.
+#[derive(Debug)]
+pub(crate) struct Compactor<S> {
+    store: Arc<S>,
+    opt: RetentionOptions,
+    decided: Arc<Mutex<Option<ViewNumber>>>,
+    task: Option<Arc<TaskList>>,
+}
+
+impl<S> Clone for Compactor<S> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            opt: self.opt,
+            decided: self.decided.clone(),
+            task: self.task.clone(),
+        }
+    }
+}
+
+impl<S: ArtifactStore> Compactor<S> {
+    pub(crate) fn new(store: S, opt: RetentionOptions) -> Self {
+        Self {
+            store: Arc::new(store),
+            opt,
+            decided: Default::default(),
+            task: None,
+        }
+    }
+
+    /// Record that `view` has been decided, so that artifacts up to `view` may be deleted.
+    ///
+    /// The artifacts are not deleted immediately, but the next time the compactor runs.
+    pub(crate) fn decide(&self, view: ViewNumber) {
+        let mut decided = self.decided.lock().unwrap();
+        if decided.map_or(true, |decided| decided < view) {
+            *decided = Some(view);
+        }
+    }
+
+    /// Delete all artifacts which are no longer retained.
+    ///
+    /// Returns the number of artifacts deleted.
+    pub(crate) async fn compact(&self) -> anyhow::Result<u64> {
+        let decided = *self.decided.lock().unwrap();
+        let Some(max_view) = decided.and_then(|view| self.opt.max_deletable_view(view)) else {
+            return Ok(0);
+        };
+        let before = self.opt.deletable_before(SystemTime::now());
+
+        let mut deleted = 0;
+        for kind in ArtifactKind::ALL {
+            deleted += self.store.delete(kind, max_view, before).await?;
+        }
+        tracing::debug!(?max_view, ?before, deleted, "compacted consensus storage");
+        Ok(deleted)
+    }
+
+    /// Start running the compactor periodically in the background.
+    ///
+    /// The background task stops when this compactor and all of its clones are dropped.
+    pub(crate) fn start(&mut self, metrics: &dyn Metrics) {
+        if self.task.is_some() {
+            return;
+        }
+
+        let metrics = CompactorMetrics::new(metrics);
+        let compactor = self.clone();
+        let mut tasks = TaskList::default();
+        tasks.spawn("consensus storage compactor", async move {
+            loop {
+                match compactor.compact().await {
+                    Ok(deleted) => metrics.deleted.add(deleted as usize),
+                    Err(err) => tracing::warn!("error compacting consensus storage: {err:#}"),
+                }
+                for (kind, gauge) in &metrics.stored_bytes {
+                    match compactor.store.stored_bytes(*kind).await {
+                        Ok(bytes) => gauge.set(bytes as usize),
+                        Err(err) => tracing::warn!(%kind, "error measuring stored bytes: {err:#}"),
+                    }
+                }
+                sleep(compactor.opt.consensus_compaction_interval).await;
+            }
+        });
+        self.task = Some(Arc::new(tasks));
+    }
+}
+
+struct CompactorMetrics {
+    stored_bytes: Vec<(ArtifactKind, Box<dyn Gauge>)>,
+    deleted: Box<dyn Counter>,
+}
+
+impl CompactorMetrics {
+    fn new(metrics: &dyn Metrics) -> Self {
+        let metrics = metrics.subgroup("consensus_storage".into());
+        Self {
+            stored_bytes: ArtifactKind::ALL
+                .into_iter()
+                .map(|kind| {
+                    (
+                        kind,
+                        metrics.create_gauge(format!("{kind}_bytes"), Some("bytes".into())),
+                    )
+                })
+                .collect(),
+            deleted: metrics.create_counter("deleted_artifacts".into(), None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use async_std::sync::RwLock;
+
+    use super::*;
+
+    /// In-memory artifact storage, recording the time each artifact was stored.
+    #[derive(Debug, Default)]
+    struct MemoryStore(RwLock<BTreeMap<(u64, ViewNumber), SystemTime>>);
+
+    fn key(kind: ArtifactKind) -> u64 {
+        ArtifactKind::ALL.iter().position(|k| *k == kind).unwrap() as u64
+    }
+
+    impl MemoryStore {
+        async fn insert(&self, view: u64, time: SystemTime) {
+            for kind in ArtifactKind::ALL {
+                self.0
+                    .write()
+                    .await
+                    .insert((key(kind), ViewNumber::new(view)), time);
+            }
+        }
+
+        async fn views(&self) -> Vec<u64> {
+            let mut views = self
+                .0
+                .read()
+                .await
+                .keys()
+                .map(|(_, view)| view.u64())
+                .collect::<Vec<_>>();
+            views.sort();
+            views.dedup();
+            views
+        }
+    }
+
+    #[async_trait]
+    impl ArtifactStore for MemoryStore {
+        async fn delete(
+            &self,
+            kind: ArtifactKind,
+            max_view: ViewNumber,
+            before: Option<SystemTime>,
+        ) -> anyhow::Result<u64> {
+            let mut artifacts = self.0.write().await;
+            let len = artifacts.len();
+            artifacts.retain(|(k, view), time| {
+                *k != key(kind) || *view > max_view || before.is_some_and(|before| *time >= before)
+            });
+            Ok((len - artifacts.len()) as u64)
+        }
+
+        async fn stored_bytes(&self, kind: ArtifactKind) -> anyhow::Result<u64> {
+            Ok(self
+                .0
+                .read()
+                .await
+                .keys()
+                .filter(|(k, _)| *k == key(kind))
+                .count() as u64)
+        }
+    }
+
+    async fn compactor(opt: RetentionOptions) -> Compactor<MemoryStore> {
+        let store = MemoryStore::default();
+        let now = SystemTime::now();
+        for view in 0..10 {
+            // Artifacts for each view are stored one hour after the previous view.
+            store
+                .insert(view, now - Duration::from_secs(3600 * (10 - view)))
+                .await;
+        }
+        Compactor::new(store, opt)
+    }
+
+    #[async_std::test]
+    async fn test_retention_default() {
+        let compactor = compactor(Default::default()).await;
+
+        // Nothing is deleted until a view is decided.
+        assert_eq!(compactor.compact().await.unwrap(), 0);
+
+        compactor.decide(ViewNumber::new(4));
+        assert_eq!(compactor.compact().await.unwrap(), 15);
+        assert_eq!(compactor.store.views().await, [5, 6, 7, 8, 9]);
+
+        // Deciding an older view has no effect.
+        compactor.decide(ViewNumber::new(2));
+        assert_eq!(compactor.compact().await.unwrap(), 0);
+        assert_eq!(compactor.store.views().await, [5, 6, 7, 8, 9]);
+    }
+
+    #[async_std::test]
+    async fn test_retention_views() {
+        let compactor = compactor(RetentionOptions {
+            consensus_retention_views: Some(3),
+            ..Default::default()
+        })
+        .await;
+
+        compactor.decide(ViewNumber::new(2));
+        assert_eq!(compactor.compact().await.unwrap(), 0);
+
+        compactor.decide(ViewNumber::new(6));
+        compactor.compact().await.unwrap();
+        assert_eq!(compactor.store.views().await, [4, 5, 6, 7, 8, 9]);
+    }
+
+    #[async_std::test]
+    async fn test_retention_duration() {
+        let compactor = compactor(RetentionOptions {
+            consensus_retention_duration: Some(Duration::from_secs(3600 * 5 + 60)),
+            ..Default::default()
+        })
+        .await;
+
+        // Artifacts older than the retention period are deleted, but only once decided.
+        compactor.decide(ViewNumber::new(2));
+        compactor.compact().await.unwrap();
+        assert_eq!(compactor.store.views().await, [3, 4, 5, 6, 7, 8, 9]);
+
+        compactor.decide(ViewNumber::new(9));
+        compactor.compact().await.unwrap();
+        assert_eq!(compactor.store.views().await, [5, 6, 7, 8, 9]);
+    }
+
+    #[async_std::test]
+    async fn test_retention_archive() {
+        let compactor = compactor(RetentionOptions {
+            consensus_archive: true,
+            ..Default::default()
+        })
+        .await;
+
+        compactor.decide(ViewNumber::new(9));
+        assert_eq!(compactor.compact().await.unwrap(), 0);
+        assert_eq!(compactor.store.views().await.len(), 10);
+    }
+}
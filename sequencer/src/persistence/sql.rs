@@ -1,9 +1,12 @@
-use std::{collections::BTreeMap, time::Duration};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::Context;
 use async_std::{
     stream::StreamExt,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
 };
 use async_trait::async_trait;
 use clap::Parser;
@@ -26,12 +29,22 @@ use hotshot_types::{
     event::HotShotAction,
     message::Proposal,
     simple_certificate::QuorumCertificate,
-    traits::node_implementation::ConsensusTime,
+    traits::{metrics::Metrics, node_implementation::ConsensusTime},
     utils::View,
     vote::HasViewNumber,
 };
 
-use crate::{catchup::SqlStateCatchup, options::parse_duration, SeqTypes, ViewNumber};
+use super::{
+    integrity::{
+        decode_proposal, sql_anchor_leaf_entry, sql_location, sql_undecided_state_entry,
+        CheckablePersistence, Entry, EntryId, EntryValue, SQL_PROPOSAL_TABLES,
+    },
+    retention::{ArtifactKind, ArtifactStore, Compactor, RetentionOptions},
+};
+use crate::{
+    api::pruning::DataClass, catchup::SqlStateCatchup, options::parse_duration, SeqTypes,
+    ViewNumber,
+};
 
 /// Options for Postgres-backed persistence.
 #[derive(Parser, Clone, Derivative, Default)]
@@ -115,6 +128,10 @@ pub struct Options {
     /// fetching from peers.
     #[clap(long, env = "ESPRESSO_SEQUENCER_ARCHIVE", conflicts_with = "prune")]
     pub(crate) archive: bool,
+
+    /// Retention policy for consensus artifacts.
+    #[clap(flatten)]
+    pub(crate) retention: RetentionOptions,
 }
 
 impl TryFrom<Options> for Config {
@@ -204,6 +221,105 @@ pub struct PruningOptions {
         value_parser = parse_duration,
     )]
     interval: Option<Duration>,
+
+    /// Retention period for leaves.
+    ///
+    /// Leaves older than this are deleted, even if the rest of the block is retained. This applies
+    /// independently of the pruner enabled by `--prune`, which deletes whole blocks.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRUNER_LEAF_RETENTION",
+        value_parser = parse_duration,
+    )]
+    leaf_retention: Option<Duration>,
+
+    /// Retention period for block payloads.
+    ///
+    /// Payloads older than this are deleted, while the headers and summaries of their blocks are
+    /// retained. This applies independently of the pruner enabled by `--prune`, which deletes
+    /// whole blocks.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRUNER_PAYLOAD_RETENTION",
+        value_parser = parse_duration,
+    )]
+    payload_retention: Option<Duration>,
+
+    /// Retention period for VID common data and shares.
+    ///
+    /// VID data older than this is deleted, even if the rest of the block is retained. This applies
+    /// independently of the pruner enabled by `--prune`, which deletes whole blocks.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_PRUNER_VID_COMMON_RETENTION",
+        value_parser = parse_duration,
+    )]
+    vid_common_retention: Option<Duration>,
+
+    /// Number of recent blocks for which all versions of merklized state are retained.
+    ///
+    /// Older versions of the fee and block Merkle trees are deleted, except for the nodes needed to
+    /// serve state at the oldest retained height, so the latest state of every account is always
+    /// kept. State queries (including catchup requests from peers) for heights before the retained
+    /// window fail. This applies independently of the pruner enabled by `--prune`, which does not
+    /// prune merklized state.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_PRUNER_MERKLIZED_STATE_RETENTION")]
+    merklized_state_retention: Option<u64>,
+}
+
+impl PruningOptions {
+    /// Whether any pruning parameter was given.
+    pub(crate) fn is_set(&self) -> bool {
+        let Self {
+            pruning_threshold,
+            minimum_retention,
+            target_retention,
+            batch_size,
+            max_usage,
+            interval,
+            leaf_retention,
+            payload_retention,
+            vid_common_retention,
+            merklized_state_retention,
+        } = self;
+        pruning_threshold.is_some()
+            || minimum_retention.is_some()
+            || target_retention.is_some()
+            || batch_size.is_some()
+            || max_usage.is_some()
+            || interval.is_some()
+            || leaf_retention.is_some()
+            || payload_retention.is_some()
+            || vid_common_retention.is_some()
+            || merklized_state_retention.is_some()
+    }
+
+    /// The retention period for each class of data which is pruned separately from whole blocks.
+    pub(crate) fn class_retention(&self) -> BTreeMap<DataClass, Duration> {
+        [
+            (DataClass::Leaf, self.leaf_retention),
+            (DataClass::Payload, self.payload_retention),
+            (DataClass::VidCommon, self.vid_common_retention),
+        ]
+        .into_iter()
+        .filter_map(|(class, retention)| Some((class, retention?)))
+        .collect()
+    }
+
+    /// The number of recent blocks for which all versions of merklized state are retained.
+    pub(crate) fn merklized_state_retention(&self) -> Option<u64> {
+        self.merklized_state_retention
+    }
+
+    /// The number of blocks to prune in a single transaction.
+    pub(crate) fn batch_size(&self) -> u64 {
+        self.batch_size.unwrap_or(1000)
+    }
+
+    /// How often to run the pruner.
+    pub(crate) fn interval(&self) -> Duration {
+        self.interval.unwrap_or(Duration::from_secs(3600))
+    }
 }
 
 impl From<PruningOptions> for PrunerCfg {
@@ -238,6 +354,13 @@ impl PersistenceOptions for Options {
     async fn create(self) -> anyhow::Result<Persistence> {
         Ok(Persistence {
             store_undecided_state: self.store_undecided_state,
+            compactor: Compactor::new(
+                Artifacts {
+                    opt: self.clone(),
+                    db: Default::default(),
+                },
+                self.retention,
+            ),
             db: SqlStorage::connect(self.try_into()?).await?,
         })
     }
@@ -252,15 +375,94 @@ impl PersistenceOptions for Options {
 pub struct Persistence {
     db: SqlStorage,
     store_undecided_state: bool,
+    compactor: Compactor<Artifacts>,
+}
+
+/// The consensus artifacts in a Postgres database.
+///
+/// The compactor runs in the background, concurrently with consensus storage, so it uses its own
+/// connection to the database, which is opened the first time it is needed.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct Artifacts {
+    opt: Options,
+    #[derivative(Debug = "ignore")]
+    db: Mutex<Option<SqlStorage>>,
+}
+
+impl Artifacts {
+    fn table(kind: ArtifactKind) -> &'static str {
+        match kind {
+            ArtifactKind::DaProposal => "da_proposal",
+            ArtifactKind::VidShare => "vid_share",
+            ArtifactKind::QuorumProposal => "quorum_proposals",
+        }
+    }
+
+    async fn db(&self) -> anyhow::Result<MutexGuard<'_, Option<SqlStorage>>> {
+        let mut db = self.db.lock().await;
+        if db.is_none() {
+            *db = Some(SqlStorage::connect(self.opt.clone().try_into()?).await?);
+        }
+        Ok(db)
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for Artifacts {
+    async fn delete(
+        &self,
+        kind: ArtifactKind,
+        max_view: ViewNumber,
+        before: Option<SystemTime>,
+    ) -> anyhow::Result<u64> {
+        let stmt = format!(
+            "DELETE FROM {} WHERE view <= $1 AND ($2::FLOAT8 IS NULL OR created < to_timestamp($2))",
+            Self::table(kind)
+        );
+        let max_view = max_view.u64() as i64;
+        let before = before
+            .map(|before| before.duration_since(UNIX_EPOCH))
+            .transpose()?
+            .map(|before| before.as_secs_f64());
+
+        let mut db = self.db().await?;
+        let mut deleted = 0;
+        transaction(db.as_mut().unwrap(), |mut tx| {
+            let deleted = &mut deleted;
+            async move {
+                *deleted = tx
+                    .execute(&stmt, [sql_param(&max_view), sql_param(&before)])
+                    .await?;
+                Ok(())
+            }
+            .boxed()
+        })
+        .await?;
+        Ok(deleted)
+    }
+
+    async fn stored_bytes(&self, kind: ArtifactKind) -> anyhow::Result<u64> {
+        let stmt = format!(
+            "SELECT coalesce(sum(octet_length(data)), 0)::BIGINT AS bytes FROM {}",
+            Self::table(kind)
+        );
+        let db = self.db().await?;
+        let Some(row) = db.as_ref().unwrap().query_opt_static(&stmt).await? else {
+            return Ok(0);
+        };
+        let bytes: i64 = row.try_get("bytes")?;
+        Ok(bytes as u64)
+    }
 }
 
-pub(crate) async fn transaction(
+pub(crate) async fn transaction<T>(
     sql: &mut SqlStorage,
-    f: impl FnOnce(Transaction) -> BoxFuture<anyhow::Result<()>>,
-) -> anyhow::Result<()> {
+    f: impl FnOnce(Transaction) -> BoxFuture<anyhow::Result<T>>,
+) -> anyhow::Result<T> {
     let tx = sql.transaction().await?;
     match f(tx).await {
-        Ok(_) => {
+        Ok(res) => {
             if let Err(err) = sql.commit().await {
                 tracing::warn!("transaction failed, reverting: {err:#}");
                 sql.revert().await;
@@ -268,7 +470,7 @@ pub(crate) async fn transaction(
                 return Err(err.into());
             }
 
-            Ok(())
+            Ok(res)
         }
         Err(err) => {
             tracing::warn!("transaction failed, reverting: {err:#}");
@@ -324,22 +526,14 @@ impl SequencerPersistence for Persistence {
         .await
     }
 
-    async fn collect_garbage(&mut self, view: ViewNumber) -> anyhow::Result<()> {
-        transaction(&mut self.db, |mut tx| {
-            async move {
-                let stmt1 = "DELETE FROM vid_share where view <= $1";
-                tx.execute(stmt1, [&(view.u64() as i64)]).await?;
-
-                let stmt2 = "DELETE FROM da_proposal where view <= $1";
-                tx.execute(stmt2, [&(view.u64() as i64)]).await?;
+    fn start_background_tasks(&mut self, metrics: &dyn Metrics) {
+        self.compactor.start(metrics);
+    }
 
-                let stmt3 = "DELETE FROM quorum_proposals where view <= $1";
-                tx.execute(stmt3, [&(view.u64() as i64)]).await?;
-                Ok(())
-            }
-            .boxed()
-        })
-        .await
+    async fn collect_garbage(&mut self, view: ViewNumber) -> anyhow::Result<()> {
+        // Artifacts up to `view` are deleted in the background, according to the retention policy.
+        self.compactor.decide(view);
+        Ok(())
     }
 
     async fn save_anchor_leaf(
@@ -624,6 +818,155 @@ impl SequencerPersistence for Persistence {
     }
 }
 
+#[async_trait]
+impl CheckablePersistence for Persistence {
+    async fn entry_ids(&self) -> anyhow::Result<Vec<EntryId>> {
+        let mut ids = vec![];
+        for (id, query) in [
+            (EntryId::Config, "SELECT 1 FROM network_config LIMIT 1"),
+            (
+                EntryId::VotedView,
+                "SELECT 1 FROM highest_voted_view WHERE id = 0",
+            ),
+            (
+                EntryId::AnchorLeaf,
+                "SELECT 1 FROM anchor_leaf WHERE id = 0",
+            ),
+            (
+                EntryId::UndecidedState,
+                "SELECT 1 FROM undecided_state WHERE id = 0",
+            ),
+        ] {
+            if self.db.query_opt_static(query).await?.is_some() {
+                ids.push(id);
+            }
+        }
+
+        for (table, id) in SQL_PROPOSAL_TABLES {
+            let rows = self
+                .db
+                .query_static(&format!("SELECT view FROM {table} ORDER BY view"))
+                .await?
+                .collect::<Vec<_>>()
+                .await;
+            for row in rows {
+                let view: i64 = row?.try_get("view")?;
+                ids.push(id(ViewNumber::new(view as u64)));
+            }
+        }
+
+        Ok(ids)
+    }
+
+    async fn entry(&self, id: &EntryId) -> anyhow::Result<Entry> {
+        match id {
+            EntryId::Config => {
+                let row = self
+                    .db
+                    .query_opt_static("SELECT config FROM network_config ORDER BY id DESC LIMIT 1")
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                let config: serde_json::Value = row.try_get("config")?;
+                Ok(Entry {
+                    id: id.clone(),
+                    raw: serde_json::to_vec(&config)?,
+                    value: serde_json::from_value(config)
+                        .map(|config| EntryValue::Config(Box::new(config)))
+                        .context("malformed config"),
+                })
+            }
+            EntryId::VotedView => {
+                let row = self
+                    .db
+                    .query_opt_static("SELECT view FROM highest_voted_view WHERE id = 0")
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                let view: Option<i64> = row.try_get("view")?;
+                Ok(Entry {
+                    id: id.clone(),
+                    raw: view.unwrap_or_default().to_le_bytes().to_vec(),
+                    value: view
+                        .map(|view| EntryValue::VotedView(ViewNumber::new(view as u64)))
+                        .context("missing view"),
+                })
+            }
+            EntryId::AnchorLeaf => {
+                let row = self
+                    .db
+                    .query_opt_static("SELECT leaf, qc FROM anchor_leaf WHERE id = 0")
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                sql_anchor_leaf_entry(row.try_get("leaf")?, row.try_get("qc")?)
+            }
+            EntryId::UndecidedState => {
+                let row = self
+                    .db
+                    .query_opt_static("SELECT leaves, state FROM undecided_state WHERE id = 0")
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                sql_undecided_state_entry(row.try_get("leaves")?, row.try_get("state")?)
+            }
+            _ => {
+                let (table, view) = sql_location(id)?;
+                let row = self
+                    .db
+                    .query_opt(
+                        &format!("SELECT data FROM {table} WHERE view = $1"),
+                        [&view.context("proposal entry without a view")?],
+                    )
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                let raw: Option<Vec<u8>> = row.try_get("data")?;
+                let value = match &raw {
+                    Some(bytes) => decode_proposal(id, bytes),
+                    None => Err(anyhow::anyhow!("missing data")),
+                };
+                Ok(Entry {
+                    id: id.clone(),
+                    raw: raw.unwrap_or_default(),
+                    value,
+                })
+            }
+        }
+    }
+
+    async fn quarantine(&mut self, entry: &Entry, reason: &str) -> anyhow::Result<()> {
+        let (table, view) = sql_location(&entry.id)?;
+        tracing::warn!("quarantining {}: {reason}", entry.id);
+
+        let kind = entry.id.kind().to_string();
+        let raw = entry.raw.clone();
+        let reason = reason.to_string();
+        transaction(&mut self.db, |mut tx| {
+            async move {
+                tx.execute_one_with_retries(
+                    "INSERT INTO quarantine (kind, view, data, reason) VALUES ($1, $2, $3, $4)",
+                    [
+                        sql_param(&kind),
+                        sql_param(&view),
+                        sql_param(&raw),
+                        sql_param(&reason),
+                    ],
+                )
+                .await?;
+                match view {
+                    Some(view) => {
+                        tx.execute(&format!("DELETE FROM {table} WHERE view = $1"), [view])
+                            .await?
+                    }
+                    None => {
+                        tx.execute(&format!("DELETE FROM {table}"), [] as [i64; 0])
+                            .await?
+                    }
+                };
+                Ok(())
+            }
+            .boxed()
+        })
+        .await
+    }
+}
+
 pub(crate) fn sql_param<T: ToSql + Sync>(param: &T) -> &(dyn ToSql + Sync) {
     param
 }
@@ -654,6 +997,10 @@ mod testing {
             .await
             .unwrap()
         }
+
+        async fn compact(&self) {
+            self.compactor.compact().await.unwrap();
+        }
     }
 }
 
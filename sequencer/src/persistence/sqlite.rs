@@ -0,0 +1,879 @@
+//! Embedded SQLite persistence.
+//!
+//! This backend provides the same functionality as the Postgres backend in [`super::sql`], but
+//! stores everything in a single database file on the local file system, so that a full-featured
+//! node can be run without a separate database server.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{ensure, Context};
+use async_std::sync::RwLock;
+use async_trait::async_trait;
+use clap::Parser;
+use espresso_types::{
+    v0::traits::{PersistenceOptions, SequencerPersistence, StateCatchup},
+    BackoffParams, Leaf, NetworkConfig,
+};
+use hotshot_types::{
+    consensus::CommitmentMap,
+    data::{DaProposal, QuorumProposal, VidDisperseShare},
+    event::HotShotAction,
+    message::Proposal,
+    simple_certificate::QuorumCertificate,
+    traits::{metrics::Metrics, node_implementation::ConsensusTime},
+    utils::View,
+    vote::HasViewNumber,
+};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    Executor, Row, SqlitePool,
+};
+
+use super::{
+    integrity::{
+        decode_proposal, sql_anchor_leaf_entry, sql_location, sql_undecided_state_entry,
+        CheckablePersistence, Entry, EntryId, EntryValue, SQL_PROPOSAL_TABLES,
+    },
+    retention::{ArtifactKind, ArtifactStore, Compactor, RetentionOptions},
+    sql::PruningOptions,
+};
+use crate::{
+    api::sqlite::SqliteStorage, catchup::SqlStateCatchup, options::parse_duration, SeqTypes,
+    ViewNumber,
+};
+
+/// Options for SQLite-backed persistence.
+#[derive(Parser, Clone, Debug)]
+pub struct Options {
+    /// Path to the SQLite database file.
+    ///
+    /// The file (and any missing parent directories) will be created if it does not exist.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SQLITE_PATH")]
+    pub(crate) path: PathBuf,
+
+    /// Maximum number of open connections to the database.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SQLITE_MAX_CONNECTIONS",
+        default_value = "10"
+    )]
+    pub(crate) max_connections: u32,
+
+    /// How long to wait for a lock on the database before failing a statement.
+    ///
+    /// SQLite allows only a single writer at a time, so concurrent writers (e.g. consensus storage
+    /// and the query service) will wait up to this long for each other.
+    #[clap(
+        long,
+        env = "ESPRESSO_SEQUENCER_SQLITE_BUSY_TIMEOUT",
+        default_value = "30s",
+        value_parser = parse_duration,
+    )]
+    pub(crate) busy_timeout: Duration,
+
+    /// Specifies the maximum number of concurrent fetch requests allowed from peers.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_FETCH_RATE_LIMIT")]
+    pub(crate) fetch_rate_limit: Option<usize>,
+
+    /// The minimum delay between active fetches in a stream.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_ACTIVE_FETCH_DELAY", value_parser = parse_duration)]
+    pub(crate) active_fetch_delay: Option<Duration>,
+
+    /// The minimum delay between loading chunks in a stream.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_CHUNK_FETCH_DELAY", value_parser = parse_duration)]
+    pub(crate) chunk_fetch_delay: Option<Duration>,
+
+    #[clap(long, env = "ESPRESSO_SEQUENCER_STORE_UNDECIDED_STATE", hide = true)]
+    pub(crate) store_undecided_state: bool,
+
+    /// Prune query service data.
+    ///
+    /// Pruning is not supported by the SQLite backend: this option, and the pruning parameters
+    /// shared with the Postgres backend, are only accepted so that the node refuses to start when
+    /// they are given, rather than silently keeping all data.
+    #[clap(long, env = "ESPRESSO_SEQUENCER_SQLITE_PRUNE", hide = true)]
+    pub(crate) prune: bool,
+
+    /// Pruning parameters (not supported).
+    #[clap(flatten)]
+    pub(crate) pruning: PruningOptions,
+
+    /// Retention policy for consensus artifacts.
+    #[clap(flatten)]
+    pub(crate) retention: RetentionOptions,
+}
+
+impl Options {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_connections: 10,
+            busy_timeout: Duration::from_secs(30),
+            fetch_rate_limit: None,
+            active_fetch_delay: None,
+            chunk_fetch_delay: None,
+            store_undecided_state: false,
+            prune: false,
+            pruning: Default::default(),
+            retention: Default::default(),
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open a connection pool to the database, creating and migrating it if necessary.
+    pub(crate) async fn connect(&self) -> anyhow::Result<SqlitePool> {
+        ensure!(
+            !self.prune && !self.pruning.is_set(),
+            "pruning is not supported with SQLite storage"
+        );
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context(format!(
+                "unable to create database directory {}",
+                parent.display()
+            ))?;
+        }
+
+        let opt = SqliteConnectOptions::new()
+            .filename(&self.path)
+            .create_if_missing(true)
+            // Write-ahead logging allows readers to proceed concurrently with the single writer.
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .foreign_keys(true)
+            .busy_timeout(self.busy_timeout);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(self.max_connections)
+            .connect_with(opt)
+            .await
+            .context(format!("opening database {}", self.path.display()))?;
+        run_migrations(&pool).await?;
+        Ok(pool)
+    }
+
+    /// Delete the database, including any write-ahead log and shared memory files.
+    pub(crate) fn reset_db(&self) -> anyhow::Result<()> {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.path.clone().into_os_string();
+            path.push(suffix);
+            let path = PathBuf::from(path);
+            if path.exists() {
+                fs::remove_file(&path).context(format!("removing {}", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::parse_from(std::iter::empty::<String>())
+    }
+}
+
+/// A schema migration.
+struct Migration {
+    version: i64,
+    name: &'static str,
+    sql: &'static str,
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal, $path:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            sql: include_str!($path),
+        }
+    };
+}
+
+/// Schema migrations for the SQLite database.
+///
+/// The Postgres migrations in `api/migrations` are reused wherever their schema can be expressed in
+/// SQLite, keeping their original version numbers, and are translated by [`sqlite_dialect`] before
+/// they are applied. Only the query service tables (which Postgres gets from the query service
+/// itself), the merklized state tables (which rely on Postgres arrays) and the artifact timestamps
+/// (which Postgres stores as `TIMESTAMPTZ`) are defined separately in `api/sqlite-migrations`.
+const MIGRATIONS: &[Migration] = &[
+    migration!(
+        1,
+        "query_storage",
+        "../../api/sqlite-migrations/V1__query_storage.sql"
+    ),
+    migration!(
+        12,
+        "network_config",
+        "../../api/migrations/V12__network_config.sql"
+    ),
+    migration!(
+        13,
+        "consensus_state",
+        "../../api/migrations/V13__consensus_state.sql"
+    ),
+    migration!(
+        14,
+        "state_tables",
+        "../../api/sqlite-migrations/V14__state_tables.sql"
+    ),
+    migration!(
+        15,
+        "undecided_state",
+        "../../api/migrations/V15__undecided_state.sql"
+    ),
+    migration!(
+        32,
+        "saved_proposals",
+        "../../api/migrations/V32__saved_proposals.sql"
+    ),
+    migration!(
+        33,
+        "chain_config_table",
+        "../../api/migrations/V33__chain_config_table.sql"
+    ),
+    migration!(37, "quarantine", "../../api/migrations/V37__quarantine.sql"),
+    migration!(
+        38,
+        "consensus_artifact_time",
+        "../../api/sqlite-migrations/V38__consensus_artifact_time.sql"
+    ),
+];
+
+/// Translate the Postgres-specific syntax used in shared migrations into SQLite.
+///
+/// SQLite accepts Postgres type names such as `BYTEA` and `JSONB`, so only constructs with a
+/// different meaning need translating. In particular, a `SERIAL` column would not be assigned IDs
+/// automatically, whereas an `INTEGER PRIMARY KEY` is.
+fn sqlite_dialect(sql: &str) -> String {
+    sql.replace("SERIAL PRIMARY KEY", "INTEGER PRIMARY KEY")
+        .replace("DEFAULT now()", "DEFAULT CURRENT_TIMESTAMP")
+}
+
+async fn run_migrations(pool: &SqlitePool) -> anyhow::Result<()> {
+    pool.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name    TEXT NOT NULL
+        )",
+    )
+    .await
+    .context("creating migrations table")?;
+
+    let applied = sqlx::query("SELECT max(version) AS version FROM schema_migrations")
+        .fetch_one(pool)
+        .await?
+        .try_get::<Option<i64>, _>("version")?
+        .unwrap_or(0);
+    for migration in MIGRATIONS.iter().filter(|m| m.version > applied) {
+        tracing::info!(
+            version = migration.version,
+            name = migration.name,
+            "applying migration"
+        );
+        let mut tx = pool.begin().await?;
+        tx.execute(sqlite_dialect(migration.sql).as_str())
+            .await
+            .context(format!("applying migration V{}", migration.version))?;
+        sqlx::query("INSERT INTO schema_migrations (version, name) VALUES ($1, $2)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl PersistenceOptions for Options {
+    type Persistence = Persistence;
+
+    async fn create(self) -> anyhow::Result<Persistence> {
+        let db = self.connect().await?;
+        Ok(Persistence {
+            store_undecided_state: self.store_undecided_state,
+            compactor: Compactor::new(Artifacts { db: db.clone() }, self.retention),
+            db,
+        })
+    }
+
+    async fn reset(self) -> anyhow::Result<()> {
+        self.reset_db()
+    }
+}
+
+/// SQLite-backed persistence.
+#[derive(Clone, Debug)]
+pub struct Persistence {
+    db: SqlitePool,
+    store_undecided_state: bool,
+    compactor: Compactor<Artifacts>,
+}
+
+/// The consensus artifacts in a SQLite database.
+#[derive(Debug)]
+struct Artifacts {
+    db: SqlitePool,
+}
+
+impl Artifacts {
+    fn table(kind: ArtifactKind) -> &'static str {
+        match kind {
+            ArtifactKind::DaProposal => "da_proposal",
+            ArtifactKind::VidShare => "vid_share",
+            ArtifactKind::QuorumProposal => "quorum_proposals",
+        }
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for Artifacts {
+    async fn delete(
+        &self,
+        kind: ArtifactKind,
+        max_view: ViewNumber,
+        before: Option<SystemTime>,
+    ) -> anyhow::Result<u64> {
+        let before = before
+            .map(|before| before.duration_since(UNIX_EPOCH))
+            .transpose()?
+            .map(|before| before.as_secs() as i64);
+        let res = sqlx::query(&format!(
+            "DELETE FROM {} WHERE view <= $1 AND ($2 IS NULL OR created < $2)",
+            Self::table(kind)
+        ))
+        .bind(max_view.u64() as i64)
+        .bind(before)
+        .execute(&self.db)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    async fn stored_bytes(&self, kind: ArtifactKind) -> anyhow::Result<u64> {
+        let row = sqlx::query(&format!(
+            "SELECT coalesce(sum(length(data)), 0) AS bytes FROM {}",
+            Self::table(kind)
+        ))
+        .fetch_one(&self.db)
+        .await?;
+        let bytes: i64 = row.try_get("bytes")?;
+        Ok(bytes as u64)
+    }
+}
+
+#[async_trait]
+impl SequencerPersistence for Persistence {
+    fn into_catchup_provider(
+        self,
+        backoff: BackoffParams,
+    ) -> anyhow::Result<Arc<dyn StateCatchup>> {
+        Ok(Arc::new(SqlStateCatchup::new(
+            Arc::new(RwLock::new(SqliteStorage::new(self.db))),
+            backoff,
+        )))
+    }
+
+    async fn load_config(&self) -> anyhow::Result<Option<NetworkConfig>> {
+        tracing::info!("loading config from SQLite");
+
+        // Select the most recent config (although there should only be one).
+        let Some(row) = sqlx::query("SELECT config FROM network_config ORDER BY id DESC LIMIT 1")
+            .fetch_optional(&self.db)
+            .await?
+        else {
+            tracing::info!("config not found");
+            return Ok(None);
+        };
+        let config: String = row.try_get("config")?;
+        Ok(serde_json::from_str(&config)?)
+    }
+
+    async fn save_config(&mut self, cfg: &NetworkConfig) -> anyhow::Result<()> {
+        tracing::info!("saving config to SQLite");
+        let json = serde_json::to_string(cfg)?;
+
+        sqlx::query("INSERT INTO network_config (config) VALUES ($1)")
+            .bind(json)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    fn start_background_tasks(&mut self, metrics: &dyn Metrics) {
+        self.compactor.start(metrics);
+    }
+
+    async fn collect_garbage(&mut self, view: ViewNumber) -> anyhow::Result<()> {
+        // Artifacts up to `view` are deleted in the background, according to the retention policy.
+        self.compactor.decide(view);
+        Ok(())
+    }
+
+    async fn save_anchor_leaf(
+        &mut self,
+        leaf: &Leaf,
+        qc: &QuorumCertificate<SeqTypes>,
+    ) -> anyhow::Result<()> {
+        // SQLite evaluates every expression in the `SET` clause against the existing row, so the
+        // height comparison in each `CASE` sees the old height even though `height` is also being
+        // updated.
+        let stmt = "
+            INSERT INTO anchor_leaf (id, height, view, leaf, qc) VALUES (0, $1, $2, $3, $4)
+            ON CONFLICT (id) DO UPDATE SET
+                height = max(anchor_leaf.height, excluded.height),
+                view = CASE
+                    WHEN excluded.height > anchor_leaf.height THEN excluded.view
+                    ELSE anchor_leaf.view
+                END,
+                leaf = CASE
+                    WHEN excluded.height > anchor_leaf.height THEN excluded.leaf
+                    ELSE anchor_leaf.leaf
+                END,
+                qc = CASE
+                    WHEN excluded.height > anchor_leaf.height THEN excluded.qc
+                    ELSE anchor_leaf.qc
+                END
+        ";
+
+        sqlx::query(stmt)
+            .bind(leaf.height() as i64)
+            .bind(qc.view_number.u64() as i64)
+            .bind(bincode::serialize(leaf)?)
+            .bind(bincode::serialize(qc)?)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    async fn load_latest_acted_view(&self) -> anyhow::Result<Option<ViewNumber>> {
+        Ok(
+            sqlx::query("SELECT view FROM highest_voted_view WHERE id = 0")
+                .fetch_optional(&self.db)
+                .await?
+                .map(|row| row.try_get::<i64, _>("view"))
+                .transpose()?
+                .map(|view| ViewNumber::new(view as u64)),
+        )
+    }
+
+    async fn load_anchor_leaf(
+        &self,
+    ) -> anyhow::Result<Option<(Leaf, QuorumCertificate<SeqTypes>)>> {
+        let Some(row) = sqlx::query("SELECT leaf, qc FROM anchor_leaf WHERE id = 0")
+            .fetch_optional(&self.db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let leaf_bytes: Vec<u8> = row.try_get("leaf")?;
+        let leaf = bincode::deserialize(&leaf_bytes)?;
+
+        let qc_bytes: Vec<u8> = row.try_get("qc")?;
+        let qc = bincode::deserialize(&qc_bytes)?;
+
+        Ok(Some((leaf, qc)))
+    }
+
+    async fn load_undecided_state(
+        &self,
+    ) -> anyhow::Result<Option<(CommitmentMap<Leaf>, BTreeMap<ViewNumber, View<SeqTypes>>)>> {
+        let Some(row) = sqlx::query("SELECT leaves, state FROM undecided_state WHERE id = 0")
+            .fetch_optional(&self.db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let leaves_bytes: Vec<u8> = row.try_get("leaves")?;
+        let leaves = bincode::deserialize(&leaves_bytes)?;
+
+        let state_bytes: Vec<u8> = row.try_get("state")?;
+        let state = bincode::deserialize(&state_bytes)?;
+
+        Ok(Some((leaves, state)))
+    }
+
+    async fn load_da_proposal(
+        &self,
+        view: ViewNumber,
+    ) -> anyhow::Result<Option<Proposal<SeqTypes, DaProposal<SeqTypes>>>> {
+        sqlx::query("SELECT data FROM da_proposal WHERE view = $1")
+            .bind(view.u64() as i64)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| {
+                let bytes: Vec<u8> = row.try_get("data")?;
+                anyhow::Result::<_>::Ok(bincode::deserialize(&bytes)?)
+            })
+            .transpose()
+    }
+
+    async fn load_vid_share(
+        &self,
+        view: ViewNumber,
+    ) -> anyhow::Result<Option<Proposal<SeqTypes, VidDisperseShare<SeqTypes>>>> {
+        sqlx::query("SELECT data FROM vid_share WHERE view = $1")
+            .bind(view.u64() as i64)
+            .fetch_optional(&self.db)
+            .await?
+            .map(|row| {
+                let bytes: Vec<u8> = row.try_get("data")?;
+                anyhow::Result::<_>::Ok(bincode::deserialize(&bytes)?)
+            })
+            .transpose()
+    }
+
+    async fn load_quorum_proposals(
+        &self,
+    ) -> anyhow::Result<Option<BTreeMap<ViewNumber, Proposal<SeqTypes, QuorumProposal<SeqTypes>>>>>
+    {
+        let rows = sqlx::query("SELECT view, data FROM quorum_proposals")
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(Some(
+            rows.into_iter()
+                .map(|row| {
+                    let view: i64 = row.try_get("view")?;
+                    let bytes: Vec<u8> = row.try_get("data")?;
+                    let proposal: Proposal<SeqTypes, QuorumProposal<SeqTypes>> =
+                        bincode::deserialize(&bytes)?;
+                    Ok((ViewNumber::new(view as u64), proposal))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ))
+    }
+
+    async fn append_vid(
+        &mut self,
+        proposal: &Proposal<SeqTypes, VidDisperseShare<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let view = proposal.data.view_number().u64();
+        let data_bytes = bincode::serialize(proposal).context("serializing VID share")?;
+
+        sqlx::query(
+            "INSERT INTO vid_share (view, data, created) VALUES ($1, $2, unixepoch())
+             ON CONFLICT (view) DO UPDATE SET data = excluded.data",
+        )
+        .bind(view as i64)
+        .bind(data_bytes)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn append_da(
+        &mut self,
+        proposal: &Proposal<SeqTypes, DaProposal<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let view = proposal.data.view_number().u64();
+        let data_bytes = bincode::serialize(proposal).context("serializing DA proposal")?;
+
+        sqlx::query(
+            "INSERT INTO da_proposal (view, data, created) VALUES ($1, $2, unixepoch())
+             ON CONFLICT (view) DO UPDATE SET data = excluded.data",
+        )
+        .bind(view as i64)
+        .bind(data_bytes)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_action(
+        &mut self,
+        view: ViewNumber,
+        _action: HotShotAction,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO highest_voted_view (id, view) VALUES (0, $1)
+             ON CONFLICT (id) DO UPDATE SET view = max(highest_voted_view.view, excluded.view)",
+        )
+        .bind(view.u64() as i64)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn update_undecided_state(
+        &mut self,
+        leaves: CommitmentMap<Leaf>,
+        state: BTreeMap<ViewNumber, View<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        if !self.store_undecided_state {
+            return Ok(());
+        }
+
+        let leaves_bytes = bincode::serialize(&leaves).context("serializing leaves")?;
+        let state_bytes = bincode::serialize(&state).context("serializing state")?;
+
+        sqlx::query(
+            "INSERT INTO undecided_state (id, leaves, state) VALUES (0, $1, $2)
+             ON CONFLICT (id) DO UPDATE SET leaves = excluded.leaves, state = excluded.state",
+        )
+        .bind(leaves_bytes)
+        .bind(state_bytes)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn append_quorum_proposal(
+        &mut self,
+        proposal: &Proposal<SeqTypes, QuorumProposal<SeqTypes>>,
+    ) -> anyhow::Result<()> {
+        let view_number = proposal.data.view_number().u64();
+        let proposal_bytes = bincode::serialize(&proposal).context("serializing proposal")?;
+
+        sqlx::query(
+            "INSERT INTO quorum_proposals (view, data, created) VALUES ($1, $2, unixepoch())
+             ON CONFLICT (view) DO UPDATE SET data = excluded.data",
+        )
+        .bind(view_number as i64)
+        .bind(proposal_bytes)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CheckablePersistence for Persistence {
+    async fn entry_ids(&self) -> anyhow::Result<Vec<EntryId>> {
+        let mut ids = vec![];
+        for (id, query) in [
+            (EntryId::Config, "SELECT 1 FROM network_config LIMIT 1"),
+            (
+                EntryId::VotedView,
+                "SELECT 1 FROM highest_voted_view WHERE id = 0",
+            ),
+            (
+                EntryId::AnchorLeaf,
+                "SELECT 1 FROM anchor_leaf WHERE id = 0",
+            ),
+            (
+                EntryId::UndecidedState,
+                "SELECT 1 FROM undecided_state WHERE id = 0",
+            ),
+        ] {
+            if sqlx::query(query).fetch_optional(&self.db).await?.is_some() {
+                ids.push(id);
+            }
+        }
+
+        for (table, id) in SQL_PROPOSAL_TABLES {
+            let views: Vec<i64> =
+                sqlx::query_scalar(&format!("SELECT view FROM {table} ORDER BY view"))
+                    .fetch_all(&self.db)
+                    .await?;
+            ids.extend(
+                views
+                    .into_iter()
+                    .map(|view| id(ViewNumber::new(view as u64))),
+            );
+        }
+
+        Ok(ids)
+    }
+
+    async fn entry(&self, id: &EntryId) -> anyhow::Result<Entry> {
+        match id {
+            EntryId::Config => {
+                let row = sqlx::query("SELECT config FROM network_config ORDER BY id DESC LIMIT 1")
+                    .fetch_optional(&self.db)
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                let config: Option<String> = row.try_get("config")?;
+                let config = config.unwrap_or_default();
+                let value = serde_json::from_str(&config)
+                    .map(|config| EntryValue::Config(Box::new(config)))
+                    .context("malformed config");
+                Ok(Entry {
+                    id: id.clone(),
+                    raw: config.into_bytes(),
+                    value,
+                })
+            }
+            EntryId::VotedView => {
+                let row = sqlx::query("SELECT view FROM highest_voted_view WHERE id = 0")
+                    .fetch_optional(&self.db)
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                let view: Option<i64> = row.try_get("view")?;
+                Ok(Entry {
+                    id: id.clone(),
+                    raw: view.unwrap_or_default().to_le_bytes().to_vec(),
+                    value: view
+                        .map(|view| EntryValue::VotedView(ViewNumber::new(view as u64)))
+                        .context("missing view"),
+                })
+            }
+            EntryId::AnchorLeaf => {
+                let row = sqlx::query("SELECT leaf, qc FROM anchor_leaf WHERE id = 0")
+                    .fetch_optional(&self.db)
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                sql_anchor_leaf_entry(row.try_get("leaf")?, row.try_get("qc")?)
+            }
+            EntryId::UndecidedState => {
+                let row = sqlx::query("SELECT leaves, state FROM undecided_state WHERE id = 0")
+                    .fetch_optional(&self.db)
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                sql_undecided_state_entry(row.try_get("leaves")?, row.try_get("state")?)
+            }
+            _ => {
+                let (table, view) = sql_location(id)?;
+                let row = sqlx::query(&format!("SELECT data FROM {table} WHERE view = $1"))
+                    .bind(view.context("proposal entry without a view")?)
+                    .fetch_optional(&self.db)
+                    .await?
+                    .with_context(|| format!("{id} not found"))?;
+                let raw: Option<Vec<u8>> = row.try_get("data")?;
+                let value = match &raw {
+                    Some(bytes) => decode_proposal(id, bytes),
+                    None => Err(anyhow::anyhow!("missing data")),
+                };
+                Ok(Entry {
+                    id: id.clone(),
+                    raw: raw.unwrap_or_default(),
+                    value,
+                })
+            }
+        }
+    }
+
+    async fn quarantine(&mut self, entry: &Entry, reason: &str) -> anyhow::Result<()> {
+        let (table, view) = sql_location(&entry.id)?;
+        tracing::warn!("quarantining {}: {reason}", entry.id);
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query("INSERT INTO quarantine (kind, view, data, reason) VALUES ($1, $2, $3, $4)")
+            .bind(entry.id.kind())
+            .bind(view)
+            .bind(&entry.raw)
+            .bind(reason)
+            .execute(&mut *tx)
+            .await?;
+        match view {
+            Some(view) => {
+                sqlx::query(&format!("DELETE FROM {table} WHERE view = $1"))
+                    .bind(view)
+                    .execute(&mut *tx)
+                    .await?
+            }
+            None => {
+                sqlx::query(&format!("DELETE FROM {table}"))
+                    .execute(&mut *tx)
+                    .await?
+            }
+        };
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use tempfile::TempDir;
+
+    use super::{super::testing::TestablePersistence, *};
+
+    #[async_trait]
+    impl TestablePersistence for Persistence {
+        type Storage = TempDir;
+
+        async fn tmp_storage() -> Self::Storage {
+            TempDir::new().unwrap()
+        }
+
+        async fn connect(storage: &Self::Storage) -> Self {
+            Options::new(storage.path().join("sequencer.db"))
+                .create()
+                .await
+                .unwrap()
+        }
+
+        async fn compact(&self) {
+            self.compactor.compact().await.unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod generic_tests {
+    use super::{super::persistence_tests, Persistence};
+    // For some reason this is the only way to import the macro defined in another module of this
+    // crate.
+    use crate::*;
+
+    instantiate_persistence_tests!(Persistence);
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[async_std::test]
+    async fn test_pruning_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("sequencer.db");
+        for args in [
+            vec!["--prune"],
+            vec!["--payload-retention", "1h"],
+            vec!["--merklized-state-retention", "100"],
+        ] {
+            let opt = Options::parse_from(
+                ["sqlite", "--path", path.to_str().unwrap()]
+                    .into_iter()
+                    .chain(args.iter().copied()),
+            );
+            let err = opt.connect().await.unwrap_err();
+            assert!(err.to_string().contains("pruning"), "{args:?}: {err:#}");
+        }
+        Options::new(path).connect().await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_shared_migrations_assign_ids() {
+        let dir = TempDir::new().unwrap();
+        let pool = Options::new(dir.path().join("sequencer.db"))
+            .connect()
+            .await
+            .unwrap();
+
+        // The Postgres migrations declare these IDs as `SERIAL`, which must still be assigned
+        // automatically in SQLite.
+        for _ in 0..2 {
+            sqlx::query("INSERT INTO network_config (config) VALUES ('{}')")
+                .execute(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO quarantine (kind, data, reason) VALUES ('leaf', x'00', '')")
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        for table in ["network_config", "quarantine"] {
+            let ids = sqlx::query(&format!("SELECT id FROM {table} ORDER BY id"))
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|row| row.get::<i64, _>("id"))
+                .collect::<Vec<_>>();
+            assert_eq!(ids, [1, 2], "{table}");
+        }
+    }
+}
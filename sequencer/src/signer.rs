@@ -0,0 +1,357 @@
+//! Signing light client states with the state key, and builder fees with the builder key.
+//!
+//! The [`Signer`] trait abstracts over where keys are held. A [`LocalSigner`] holds them in process
+//! memory, while a [`RemoteSigner`] forwards signing requests over HTTP to a signer running in a
+//! separate, isolated process (such as the `espresso-signer` binary, which serves a [`LocalSigner`]
+//! using the API in the [`server`] module). Signers can apply [slashing protection](slashing),
+//! refusing to sign anything which could be used as evidence of double signing. Builder signatures
+//! do not belong to a view, and are not subject to slashing protection.
+//!
+//! Only the sequencer's light client state signatures go through this interface. Consensus votes
+//! are signed by HotShot with the staking key in process. The builder binaries do not use it
+//! either: they hand the builder key to `hotshot-builder-core`, which signs fees and block
+//! information itself, so the builder key still lives in the builder process. The builder
+//! signing methods here are only served to callers that hold a [`Signer`].
+
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Context};
+use async_std::sync::Mutex;
+use async_trait::async_trait;
+use derivative::Derivative;
+use espresso_types::{
+    eth_signature_key::{BuilderSignature, EthKeyPair},
+    FeeAccount, NsTable,
+};
+use hotshot_types::{
+    light_client::{
+        CircuitField, LightClientState, StateKeyPair, StateSignature, StateSignatureScheme,
+        StateVerKey,
+    },
+    traits::signature_key::BuilderSignatureKey,
+    vid::VidCommitment,
+};
+use jf_signature::SignatureScheme;
+use serde::{Deserialize, Serialize};
+use surf_disco::Client;
+use tide_disco::error::ServerError;
+use url::Url;
+use vbs::version::StaticVersionType;
+
+use crate::keystore::Keys;
+
+pub mod server;
+pub mod slashing;
+
+use slashing::SlashingProtection;
+
+/// Slashing protection domain for light client state signatures.
+const STATE_DOMAIN: &str = "state";
+
+/// The public keys of the keys held by a signer.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerKeys {
+    /// Schnorr state key.
+    pub state: Option<StateVerKey>,
+    /// Builder fee account.
+    pub fee_account: Option<FeeAccount>,
+}
+
+/// A request to sign a builder fee.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSignRequest {
+    pub fee_amount: u64,
+    pub metadata: NsTable,
+    pub vid_commitment: VidCommitment,
+}
+
+/// Something which can sign on behalf of a node.
+#[async_trait]
+pub trait Signer: Debug + Send + Sync {
+    /// The public keys of the keys held by this signer.
+    async fn keys(&self) -> anyhow::Result<SignerKeys>;
+
+    /// Sign a light client state with the state key.
+    async fn sign_state(&self, state: &LightClientState) -> anyhow::Result<StateSignature>;
+
+    /// Sign a builder fee with the builder key.
+    ///
+    /// This produces the same signature as [`BuilderSignatureKey::sign_fee`].
+    async fn sign_fee(&self, request: &FeeSignRequest) -> anyhow::Result<BuilderSignature>;
+
+    /// Sign an arbitrary builder message with the builder key.
+    ///
+    /// This produces the same signature as [`BuilderSignatureKey::sign_builder_message`].
+    async fn sign_builder_message(&self, message: &[u8]) -> anyhow::Result<BuilderSignature>;
+}
+
+/// A signer holding keys in process memory.
+#[derive(Debug)]
+pub struct LocalSigner {
+    keys: Keys,
+    protection: Mutex<SlashingProtection>,
+}
+
+impl LocalSigner {
+    /// A signer with the given keys, protected against double signing for the life of the process.
+    pub fn new(keys: Keys) -> Self {
+        Self {
+            keys,
+            protection: Mutex::new(SlashingProtection::in_memory()),
+        }
+    }
+
+    /// Use a persistent slashing protection database.
+    pub fn with_slashing_protection(mut self, protection: SlashingProtection) -> Self {
+        self.protection = Mutex::new(protection);
+        self
+    }
+
+    fn builder_key(&self) -> anyhow::Result<&EthKeyPair> {
+        self.keys
+            .eth
+            .as_ref()
+            .context("signer does not hold a builder key")
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    async fn keys(&self) -> anyhow::Result<SignerKeys> {
+        Ok(SignerKeys {
+            state: self
+                .keys
+                .state
+                .as_ref()
+                .map(|key| StateKeyPair::from_sign_key(key.clone()).ver_key()),
+            fee_account: self.keys.eth.as_ref().map(|key| key.fee_account()),
+        })
+    }
+
+    async fn sign_state(&self, state: &LightClientState) -> anyhow::Result<StateSignature> {
+        let key = self
+            .keys
+            .state
+            .as_ref()
+            .context("signer does not hold a state key")?;
+
+        // Hold the lock until we have signed, so concurrent requests are checked in order.
+        let mut protection = self.protection.lock().await;
+        protection.check_and_record(
+            STATE_DOMAIN,
+            state.view_number as u64,
+            &bincode::serialize(state)?,
+        )?;
+
+        let msg: [CircuitField; 7] = state.into();
+        StateSignatureScheme::sign(&(), key, msg, &mut rand::thread_rng())
+            .map_err(|err| anyhow!("failed to sign state: {err}"))
+    }
+
+    async fn sign_fee(&self, request: &FeeSignRequest) -> anyhow::Result<BuilderSignature> {
+        FeeAccount::sign_fee(
+            self.builder_key()?,
+            request.fee_amount,
+            &request.metadata,
+            &request.vid_commitment,
+        )
+        .map_err(|err| anyhow!("failed to sign fee: {err}"))
+    }
+
+    async fn sign_builder_message(&self, message: &[u8]) -> anyhow::Result<BuilderSignature> {
+        FeeAccount::sign_builder_message(self.builder_key()?, message)
+            .map_err(|err| anyhow!("failed to sign builder message: {err}"))
+    }
+}
+
+/// A signer in another process, reached over HTTP.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct RemoteSigner<Ver: StaticVersionType> {
+    url: Url,
+    #[derivative(Debug = "ignore")]
+    auth_token: Option<String>,
+    #[derivative(Debug = "ignore")]
+    client: Client<ServerError, Ver>,
+}
+
+impl<Ver: StaticVersionType> RemoteSigner<Ver> {
+    /// A signer at `url`, authenticating with `auth_token` if the signer requires one.
+    pub fn new(url: Url, auth_token: Option<String>) -> Self {
+        Self {
+            client: Client::new(url.clone()),
+            url,
+            auth_token,
+        }
+    }
+
+    fn authorize<T>(
+        &self,
+        req: surf_disco::Request<T, ServerError, Ver>,
+    ) -> surf_disco::Request<T, ServerError, Ver> {
+        match &self.auth_token {
+            Some(token) => req.header("Authorization", format!("Bearer {token}")),
+            None => req,
+        }
+    }
+}
+
+#[async_trait]
+impl<Ver: StaticVersionType> Signer for RemoteSigner<Ver> {
+    async fn keys(&self) -> anyhow::Result<SignerKeys> {
+        self.authorize(self.client.get("signer/keys"))
+            .send()
+            .await
+            .context(format!("fetching keys from remote signer {}", self.url))
+    }
+
+    async fn sign_state(&self, state: &LightClientState) -> anyhow::Result<StateSignature> {
+        self.authorize(self.client.post("signer/state"))
+            .body_binary(state)?
+            .send()
+            .await
+            .context(format!("signing state with remote signer {}", self.url))
+    }
+
+    async fn sign_fee(&self, request: &FeeSignRequest) -> anyhow::Result<BuilderSignature> {
+        self.authorize(self.client.post("signer/fee"))
+            .body_binary(request)?
+            .send()
+            .await
+            .context(format!("signing fee with remote signer {}", self.url))
+    }
+
+    async fn sign_builder_message(&self, message: &[u8]) -> anyhow::Result<BuilderSignature> {
+        self.authorize(self.client.post("signer/builder_message"))
+            .body_binary(&message)?
+            .send()
+            .await
+            .context(format!(
+                "signing builder message with remote signer {}",
+                self.url
+            ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ark_ff::Zero;
+    use espresso_types::NsTableBuilder;
+    use hotshot_types::traits::block_contents::{vid_commitment, GENESIS_VID_NUM_STORAGE_NODES};
+    use portpicker::pick_unused_port;
+    use tempfile::TempDir;
+    use vbs::version::StaticVersion;
+
+    use super::{server::run_signer_server, slashing::DoubleSignError, *};
+
+    type Ver = StaticVersion<0, 1>;
+
+    fn keys() -> Keys {
+        Keys {
+            state: Some(
+                StateKeyPair::generate_from_seed_indexed([0; 32], 0)
+                    .sign_key_ref()
+                    .clone(),
+            ),
+            eth: Some(FeeAccount::generated_from_seed_indexed([0; 32], 0).1),
+            ..Default::default()
+        }
+    }
+
+    fn state(view: usize) -> LightClientState {
+        LightClientState {
+            view_number: view,
+            block_height: view,
+            block_comm_root: CircuitField::zero(),
+            fee_ledger_comm: CircuitField::zero(),
+            stake_table_comm: (
+                CircuitField::zero(),
+                CircuitField::zero(),
+                CircuitField::zero(),
+            ),
+        }
+    }
+
+    async fn test_signer(signer: &impl Signer) {
+        let keys = signer.keys().await.unwrap();
+        let state_key = keys.state.unwrap();
+
+        // Sign a state, and sign it again.
+        let sig = signer.sign_state(&state(1)).await.unwrap();
+        let msg: [CircuitField; 7] = (&state(1)).into();
+        StateSignatureScheme::verify(&(), &state_key, msg, &sig).unwrap();
+        signer.sign_state(&state(1)).await.unwrap();
+
+        // Conflicting states are refused.
+        let mut conflicting = state(1);
+        conflicting.block_height = 2;
+        signer.sign_state(&conflicting).await.unwrap_err();
+        signer.sign_state(&state(0)).await.unwrap_err();
+        signer.sign_state(&state(2)).await.unwrap();
+
+        // Builder signatures are not subject to slashing protection, so the same fee can be signed
+        // again.
+        let fee_account = keys.fee_account.unwrap();
+        let request = FeeSignRequest {
+            fee_amount: 1,
+            metadata: NsTableBuilder::new().into_ns_table(),
+            vid_commitment: vid_commitment(&[], GENESIS_VID_NUM_STORAGE_NODES),
+        };
+        for _ in 0..2 {
+            let sig = signer.sign_fee(&request).await.unwrap();
+            assert!(fee_account.validate_fee_signature(
+                &sig,
+                request.fee_amount,
+                &request.metadata,
+                &request.vid_commitment
+            ));
+        }
+        let sig = signer.sign_builder_message(b"message").await.unwrap();
+        assert!(fee_account.validate_builder_signature(&sig, b"message"));
+    }
+
+    #[async_std::test]
+    async fn test_local_signer() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("slashing.json");
+        let signer = LocalSigner::new(keys())
+            .with_slashing_protection(SlashingProtection::open(path.clone()).unwrap());
+        test_signer(&signer).await;
+
+        // Protection persists across restarts of the signer.
+        let signer = LocalSigner::new(keys())
+            .with_slashing_protection(SlashingProtection::open(path).unwrap());
+        let err = signer.sign_state(&state(1)).await.unwrap_err();
+        assert!(err.downcast_ref::<DoubleSignError>().is_some(), "{err:#}");
+    }
+
+    #[async_std::test]
+    async fn test_remote_signer() {
+        let port = pick_unused_port().unwrap();
+        let url: Url = format!("http://localhost:{port}").parse().unwrap();
+        let options = server::Options {
+            auth_token: Some("secret".into()),
+            ..Default::default()
+        };
+        async_std::task::spawn(run_signer_server(
+            LocalSigner::new(keys()),
+            format!("http://0.0.0.0:{port}").parse().unwrap(),
+            options,
+            Ver::instance(),
+        ));
+
+        // Requests without the right token are rejected.
+        let signer = RemoteSigner::<Ver>::new(url.clone(), None);
+        signer.client.connect(None).await;
+        signer.keys().await.unwrap_err();
+        let signer = RemoteSigner::<Ver>::new(url.clone(), Some("wrong".into()));
+        signer.sign_state(&state(1)).await.unwrap_err();
+
+        let signer = RemoteSigner::<Ver>::new(url, Some("secret".into()));
+        assert_eq!(
+            signer.keys().await.unwrap(),
+            LocalSigner::new(keys()).keys().await.unwrap()
+        );
+        test_signer(&signer).await;
+    }
+}
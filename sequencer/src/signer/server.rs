@@ -0,0 +1,155 @@
+//! A web server exposing a [`Signer`] to other processes.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_std::sync::RwLock;
+use clap::Args;
+use derivative::Derivative;
+use futures::FutureExt;
+use hotshot_types::light_client::LightClientState;
+use sha2::{Digest, Sha256};
+use tide_disco::{
+    api::ApiError,
+    error::ServerError,
+    method::{ReadState, WriteState},
+    Api, App, Error as _, RequestParams, StatusCode,
+};
+use url::Url;
+use vbs::version::StaticVersionType;
+
+use super::{slashing::DoubleSignError, FeeSignRequest, LocalSigner, Signer};
+
+/// configurability options for the signer web server
+#[derive(Args, Clone, Default, Derivative)]
+#[derivative(Debug)]
+pub struct Options {
+    #[arg(long = "signer-api-path", env = "ESPRESSO_SIGNER_API_PATH")]
+    /// path to API
+    pub api_path: Option<PathBuf>,
+
+    /// Token clients must present to use the signer.
+    ///
+    /// If set, every request must carry an `Authorization: Bearer <token>` header.
+    #[arg(long = "signer-auth-token", env = "ESPRESSO_SIGNER_AUTH_TOKEN")]
+    #[derivative(Debug = "ignore")]
+    pub auth_token: Option<String>,
+}
+
+/// Check the bearer token on a request against the digest of the expected token.
+///
+/// Digests are compared rather than the tokens themselves, so that the time taken to reject a
+/// token does not reveal how much of it was right.
+fn authorize(req: &RequestParams, token: Option<&[u8; 32]>) -> Result<(), ServerError> {
+    let Some(token) = token else {
+        return Ok(());
+    };
+    let presented = req
+        .header("Authorization")
+        .and_then(|value| value.as_str().strip_prefix("Bearer "))
+        .map(|presented| <[u8; 32]>::from(Sha256::digest(presented)));
+    if presented.as_ref() == Some(token) {
+        Ok(())
+    } else {
+        Err(ServerError::catch_all(
+            StatusCode::UNAUTHORIZED,
+            "missing or invalid signer auth token".into(),
+        ))
+    }
+}
+
+fn server_error(err: anyhow::Error) -> ServerError {
+    // Refusals to double sign are the caller's fault, and are reported distinctly so that a node
+    // can tell them apart from the signer being unavailable.
+    let status = if err.downcast_ref::<DoubleSignError>().is_some() {
+        StatusCode::CONFLICT
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    ServerError::catch_all(status, format!("{err:#}"))
+}
+
+/// Set up APIs for the signer
+fn define_api<State, Ver: StaticVersionType + 'static>(
+    options: &Options,
+    _: Ver,
+) -> Result<Api<State, ServerError, Ver>, ApiError>
+where
+    State: 'static + Send + Sync + ReadState + WriteState,
+    <State as ReadState>::State: Send + Sync + Signer,
+{
+    let mut api =
+        match &options.api_path {
+            Some(path) => Api::<State, ServerError, Ver>::from_file(path)?,
+            None => {
+                let toml: toml::Value = toml::from_str(include_str!("../../api/signer.toml"))
+                    .map_err(|err| ApiError::CannotReadToml {
+                        reason: err.to_string(),
+                    })?;
+                Api::<State, ServerError, Ver>::new(toml)?
+            }
+        };
+
+    let token = options
+        .auth_token
+        .as_ref()
+        .map(|token| <[u8; 32]>::from(Sha256::digest(token)));
+    api.get("getkeys", move |req, signer| {
+        async move {
+            authorize(&req, token.as_ref())?;
+            signer.keys().await.map_err(server_error)
+        }
+        .boxed()
+    })?
+    .post("signstate", move |req, signer| {
+        async move {
+            authorize(&req, token.as_ref())?;
+            let state = req
+                .body_auto::<LightClientState, Ver>(Ver::instance())
+                .map_err(ServerError::from_request_error)?;
+            signer.sign_state(&state).await.map_err(server_error)
+        }
+        .boxed()
+    })?
+    .post("signfee", move |req, signer| {
+        async move {
+            authorize(&req, token.as_ref())?;
+            let request = req
+                .body_auto::<FeeSignRequest, Ver>(Ver::instance())
+                .map_err(ServerError::from_request_error)?;
+            signer.sign_fee(&request).await.map_err(server_error)
+        }
+        .boxed()
+    })?
+    .post("signbuildermessage", move |req, signer| {
+        async move {
+            authorize(&req, token.as_ref())?;
+            let message = req
+                .body_auto::<Vec<u8>, Ver>(Ver::instance())
+                .map_err(ServerError::from_request_error)?;
+            signer
+                .sign_builder_message(&message)
+                .await
+                .map_err(server_error)
+        }
+        .boxed()
+    })?;
+
+    Ok(api)
+}
+
+/// Serve `signer` at `url`.
+pub async fn run_signer_server<Ver: StaticVersionType + 'static>(
+    signer: LocalSigner,
+    url: Url,
+    options: Options,
+    bind_version: Ver,
+) -> anyhow::Result<()> {
+    let api = define_api(&options, bind_version).context("defining signer API")?;
+
+    let mut app = App::<RwLock<LocalSigner>, ServerError>::with_state(RwLock::new(signer));
+    app.register_module("signer", api)
+        .context("registering signer API")?;
+    app.serve(url, bind_version).await?;
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! Slashing protection for signers.
+//!
+//! Signing two different messages for the same view (or signing for a view after having moved on
+//! to a later one) is evidence of misbehavior, and can get a node slashed. A signer with slashing
+//! protection keeps a record of the latest view it has signed in each domain, along with a digest
+//! of the message it signed, and refuses any request which would contradict that record. Signing
+//! the exact same message again is allowed, so that a node which crashes after requesting a
+//! signature can safely request it again.
+//!
+//! The record is written to disk before each signature is released, so protection survives
+//! restarts of the signer.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use ethers::utils::hex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use snafu::Snafu;
+
+/// A signature was refused because it could be used as evidence of double signing.
+#[derive(Clone, Debug, Snafu)]
+#[snafu(display(
+    "refusing to sign {domain} message for view {view}: already signed a different message for \
+     view {signed_view}"
+))]
+pub struct DoubleSignError {
+    pub domain: String,
+    pub view: u64,
+    pub signed_view: u64,
+}
+
+/// The latest message signed in a domain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Record {
+    view: u64,
+    digest: String,
+}
+
+/// A slashing protection database.
+#[derive(Debug, Default)]
+pub struct SlashingProtection {
+    /// Where the database is persisted, if anywhere.
+    path: Option<PathBuf>,
+    records: BTreeMap<String, Record>,
+}
+
+impl SlashingProtection {
+    /// Open a database persisted at `path`, creating it if it does not exist.
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let records = if path.is_file() {
+            let bytes = fs::read(&path).context(format!("reading {}", path.display()))?;
+            serde_json::from_slice(&bytes).context("malformed slashing protection database")?
+        } else {
+            Default::default()
+        };
+        Ok(Self {
+            path: Some(path),
+            records,
+        })
+    }
+
+    /// A database which is not persisted.
+    ///
+    /// This protects against double signing only for the lifetime of the process.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Check that signing `message` for `view` in `domain` is safe, and record that we did.
+    ///
+    /// The record is persisted before this function returns successfully, so the caller may
+    /// release a signature of `message` as soon as this returns.
+    pub fn check_and_record(
+        &mut self,
+        domain: &str,
+        view: u64,
+        message: &[u8],
+    ) -> anyhow::Result<()> {
+        let digest = hex::encode(Sha256::digest(message));
+        if let Some(record) = self.records.get(domain) {
+            if view < record.view || (view == record.view && digest != record.digest) {
+                return Err(DoubleSignError {
+                    domain: domain.into(),
+                    view,
+                    signed_view: record.view,
+                }
+                .into());
+            }
+            if view == record.view {
+                // Signing the same message again is harmless.
+                return Ok(());
+            }
+        }
+
+        self.records.insert(domain.into(), Record { view, digest });
+        self.save()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        // Write atomically, and make sure the new record is durable before we release anything
+        // signed under it.
+        let swap_path = path.with_extension("swp");
+        let mut swap = File::create(&swap_path)?;
+        swap.write_all(&serde_json::to_vec_pretty(&self.records)?)?;
+        swap.sync_all()?;
+        fs::rename(&swap_path, path).context(format!(
+            "writing slashing protection database {}",
+            path.display()
+        ))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_slashing_protection() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("slashing.json");
+        let mut db = SlashingProtection::open(path.clone()).unwrap();
+
+        db.check_and_record("state", 1, b"a").unwrap();
+        // Signing the same message again is fine, signing a different one is not.
+        db.check_and_record("state", 1, b"a").unwrap();
+        let err = db.check_and_record("state", 1, b"b").unwrap_err();
+        assert!(err.downcast_ref::<DoubleSignError>().is_some(), "{err:#}");
+        // Domains are independent.
+        db.check_and_record("consensus/vote", 1, b"b").unwrap();
+        // Moving forward is fine, moving back is not.
+        db.check_and_record("state", 2, b"c").unwrap();
+        db.check_and_record("state", 1, b"a").unwrap_err();
+
+        // Protection survives a restart.
+        let mut db = SlashingProtection::open(path).unwrap();
+        db.check_and_record("state", 2, b"d").unwrap_err();
+        db.check_and_record("consensus/vote", 0, b"b").unwrap_err();
+        db.check_and_record("state", 3, b"d").unwrap();
+    }
+}
@@ -1,18 +1,20 @@
 //! Utilities for generating and storing the most recent light client state signatures.
 
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
 
-use ark_ff::PrimeField;
-use ark_serialize::CanonicalSerialize;
+use anyhow::Context;
 use async_std::sync::RwLock;
+use espresso_light_client::{block_comm_root, fee_ledger_comm};
 use espresso_types::Leaf;
 use hotshot::types::{Event, EventType};
 use hotshot_stake_table::vec_based::StakeTable;
 use hotshot_types::{
     event::LeafInfo,
     light_client::{
-        CircuitField, LightClientState, StateSignature, StateSignatureRequestBody,
-        StateSignatureScheme, StateVerKey,
+        CircuitField, LightClientState, StateSignature, StateSignatureRequestBody, StateVerKey,
     },
     signature_key::BLSPubKey,
     traits::{
@@ -22,14 +24,15 @@ use hotshot_types::{
     },
     PeerConfig,
 };
-use jf_crhf::CRHF;
-use jf_rescue::{crhf::VariableLengthRescueCRHF, RescueError};
-use jf_signature::SignatureScheme;
 use surf_disco::{Client, Url};
 use tide_disco::error::ServerError;
 use vbs::version::StaticVersionType;
 
-use crate::{SeqTypes, StateKeyPair};
+use crate::{
+    keystore::Keys,
+    signer::{LocalSigner, RemoteSigner, Signer},
+    SeqTypes, StateKeyPair,
+};
 
 /// A relay server that's collecting and serving the light client state signatures
 pub mod relay_server;
@@ -39,8 +42,11 @@ const SIGNATURE_STORAGE_CAPACITY: usize = 100;
 
 #[derive(Debug)]
 pub struct StateSigner<Ver: StaticVersionType> {
-    /// Key pair for signing a new light client state
-    key_pair: StateKeyPair,
+    /// Key for verifying our light client state signatures
+    ver_key: StateVerKey,
+
+    /// Signer holding the corresponding signing key
+    signer: Arc<dyn Signer>,
 
     /// The most recent light client state signatures
     signatures: RwLock<StateSignatureMemStorage>,
@@ -54,8 +60,13 @@ pub struct StateSigner<Ver: StaticVersionType> {
 
 impl<Ver: StaticVersionType> StateSigner<Ver> {
     pub fn new(key_pair: StateKeyPair, stake_table_comm: StakeTableCommitmentType) -> Self {
+        let keys = Keys {
+            state: Some(key_pair.sign_key_ref().clone()),
+            ..Default::default()
+        };
         Self {
-            key_pair,
+            ver_key: key_pair.ver_key(),
+            signer: Arc::new(LocalSigner::new(keys)),
             stake_table_comm,
             signatures: Default::default(),
             relay_server_client: Default::default(),
@@ -68,6 +79,33 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
         self
     }
 
+    /// Sign states with a remote signer, which holds the state key in its own process.
+    ///
+    /// The public state key is taken from the remote signer, so this node does not need the private
+    /// state key at all.
+    pub async fn remote(
+        signer: RemoteSigner<Ver>,
+        stake_table_comm: StakeTableCommitmentType,
+    ) -> anyhow::Result<Self> {
+        let ver_key = signer
+            .keys()
+            .await?
+            .state
+            .context("remote signer does not hold a state key")?;
+        Ok(Self {
+            ver_key,
+            signer: Arc::new(signer),
+            stake_table_comm,
+            signatures: Default::default(),
+            relay_server_client: Default::default(),
+        })
+    }
+
+    /// The public key for our light client state signatures.
+    pub fn ver_key(&self) -> &StateVerKey {
+        &self.ver_key
+    }
+
     pub(super) async fn handle_event(&self, event: &Event<SeqTypes>) {
         let EventType::Decide { leaf_chain, .. } = &event.event else {
             return;
@@ -77,12 +115,18 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
         };
         match form_light_client_state(leaf, &self.stake_table_comm) {
             Ok(state) => {
-                let signature = self.sign_new_state(&state).await;
+                let signature = match self.sign_new_state(&state).await {
+                    Ok(signature) => signature,
+                    Err(err) => {
+                        tracing::error!("Error signing light client state: {err:#}");
+                        return;
+                    }
+                };
                 tracing::debug!("New leaves decided. Latest block height: {}", leaf.height(),);
 
                 if let Some(client) = &self.relay_server_client {
                     let request_body = StateSignatureRequestBody {
-                        key: self.key_pair.ver_key(),
+                        key: self.ver_key.clone(),
                         state,
                         signature,
                     };
@@ -110,20 +154,13 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
     }
 
     /// Sign the light client state at given height and store it.
-    async fn sign_new_state(&self, state: &LightClientState) -> StateSignature {
-        let msg: [CircuitField; 7] = state.into();
-        let signature = StateSignatureScheme::sign(
-            &(),
-            self.key_pair.sign_key_ref(),
-            msg,
-            &mut rand::thread_rng(),
-        )
-        .unwrap();
+    async fn sign_new_state(&self, state: &LightClientState) -> anyhow::Result<StateSignature> {
+        let signature = self.signer.sign_state(state).await?;
         let mut pool_guard = self.signatures.write().await;
         pool_guard.push(
             state.block_height as u64,
             StateSignatureRequestBody {
-                key: self.key_pair.ver_key(),
+                key: self.ver_key.clone(),
                 state: state.clone(),
                 signature: signature.clone(),
             },
@@ -132,39 +169,20 @@ impl<Ver: StaticVersionType> StateSigner<Ver> {
             "New signature added for block height {}",
             state.block_height
         );
-        signature
+        Ok(signature)
     }
 }
 
-fn hash_bytes_to_field(bytes: &[u8]) -> Result<CircuitField, RescueError> {
-    // make sure that `mod_order` won't happen.
-    let bytes_len = ((<CircuitField as PrimeField>::MODULUS_BIT_SIZE + 7) / 8 - 1) as usize;
-    let elem = bytes
-        .chunks(bytes_len)
-        .map(CircuitField::from_le_bytes_mod_order)
-        .collect::<Vec<_>>();
-    Ok(VariableLengthRescueCRHF::<_, 1>::evaluate(elem)?[0])
-}
-
 fn form_light_client_state(
     leaf: &Leaf,
     stake_table_comm: &StakeTableCommitmentType,
 ) -> anyhow::Result<LightClientState> {
     let header = leaf.block_header();
-    let mut block_comm_root_bytes = vec![];
-    header
-        .block_merkle_tree_root()
-        .serialize_compressed(&mut block_comm_root_bytes)?;
-
-    let mut fee_ledger_comm_bytes = vec![];
-    header
-        .fee_merkle_tree_root()
-        .serialize_compressed(&mut fee_ledger_comm_bytes)?;
     Ok(LightClientState {
         view_number: leaf.view_number().u64() as usize,
         block_height: leaf.height() as usize,
-        block_comm_root: hash_bytes_to_field(&block_comm_root_bytes)?,
-        fee_ledger_comm: hash_bytes_to_field(&fee_ledger_comm_bytes)?,
+        block_comm_root: block_comm_root(&header.block_merkle_tree_root())?,
+        fee_ledger_comm: fee_ledger_comm(&header.fee_merkle_tree_root())?,
         stake_table_comm: *stake_table_comm,
     })
 }
@@ -1,18 +1,32 @@
 use std::{
-    collections::{BTreeSet, HashMap},
-    path::PathBuf,
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
+use anyhow::{ensure, Context};
 use async_compatibility_layer::channel::OneShotReceiver;
-use async_std::sync::RwLock;
+use async_std::{
+    sync::{Mutex, RwLock},
+    task::{sleep, spawn},
+};
+use async_trait::async_trait;
 use clap::Args;
+use espresso_types::PubKey;
 use ethers::types::U256;
 use futures::FutureExt;
 use hotshot_stake_table::vec_based::config::FieldType;
-use hotshot_types::light_client::{
-    StateSignature, StateSignatureScheme, StateSignaturesBundle, StateVerKey,
+use hotshot_state_prover::service::one_honest_threshold;
+use hotshot_types::{
+    light_client::{StateSignature, StateSignatureScheme, StateSignaturesBundle, StateVerKey},
+    traits::signature_key::StakeTableEntryType,
+    PeerConfig,
 };
 use jf_signature::SignatureScheme;
+use surf_disco::Client;
 use tide_disco::{
     api::ApiError,
     error::ServerError,
@@ -23,24 +37,42 @@ use url::Url;
 use vbs::version::StaticVersionType;
 
 use super::{LightClientState, StateSignatureRequestBody};
+use crate::{api::data_source::PublicNetworkConfig, options::parse_duration};
+
+/// Default number of available signature bundles to keep.
+const DEFAULT_BUNDLE_RETENTION: usize = 1000;
+
+/// Default interval between refreshes of the stake table.
+const DEFAULT_STAKE_TABLE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How far past the latest available bundle signatures are accepted, in blocks.
+///
+/// Honest nodes sign each state as it is decided, so they never get far ahead of the latest bundle.
+/// Without a limit, anyone in the stake table could make the relay server store signatures for
+/// arbitrarily many future heights.
+const PENDING_WINDOW: u64 = 100;
+
+/// Signature bundles which are still collecting signatures, by block height.
+type PendingBundles = BTreeMap<u64, HashMap<LightClientState, StateSignaturesBundle>>;
 
 /// State that checks the light client state update and the signature collection
+///
+/// Signatures being collected and bundles which are available to serve are locked separately, so
+/// that nodes submitting signatures don't block the prover from fetching the available signatures.
 #[derive(Default)]
 struct StateRelayServerState {
-    /// Minimum weight to form an available state signature bundle
-    threshold: U256,
-    /// Stake table
-    known_nodes: HashMap<StateVerKey, U256>,
-    /// Signatures bundles for each block height
-    bundles: HashMap<u64, HashMap<LightClientState, StateSignaturesBundle>>,
+    /// Stake table, shared with the task which refreshes it
+    stake_table: Arc<RwLock<RelayStakeTable>>,
 
-    /// The latest state signatures bundle whose total weight exceeds the threshold
-    latest_available_bundle: Option<StateSignaturesBundle>,
-    /// The block height of the latest available state signature bundle
-    latest_block_height: Option<u64>,
+    /// Signatures bundles for each block height above the latest available bundle
+    pending: Mutex<PendingBundles>,
+    /// State signature bundles whose total weight exceeds the threshold, by block height
+    available: RwLock<BTreeMap<u64, StateSignaturesBundle>>,
+    /// Number of available bundles to keep
+    retention: usize,
 
-    /// A ordered queue of block heights, used for garbage collection.
-    queue: BTreeSet<u64>,
+    /// Where signatures and bundles are persisted, if anywhere
+    storage: Option<RelayStorage>,
 
     /// shutdown signal
     shutdown: Option<OneShotReceiver<()>>,
@@ -49,7 +81,8 @@ struct StateRelayServerState {
 impl StateRelayServerState {
     pub fn new(threshold: U256) -> Self {
         Self {
-            threshold,
+            stake_table: Arc::new(RwLock::new(RelayStakeTable::uniform(threshold))),
+            retention: DEFAULT_BUNDLE_RETENTION,
             ..Default::default()
         }
     }
@@ -61,34 +94,86 @@ impl StateRelayServerState {
         self.shutdown = shutdown_listener;
         self
     }
+
+    /// Weigh signatures using `stake_table`.
+    pub fn with_stake_table(self, stake_table: RelayStakeTable) -> Self {
+        Self {
+            stake_table: Arc::new(RwLock::new(stake_table)),
+            ..self
+        }
+    }
+
+    /// Keep at most `retention` available bundles.
+    pub fn with_retention(mut self, retention: usize) -> Self {
+        self.retention = retention.max(1);
+        self
+    }
+
+    /// Persist signatures and bundles in `storage`, restoring any that were previously persisted.
+    pub fn with_storage(mut self, storage: RelayStorage) -> anyhow::Result<Self> {
+        let (pending, available) = storage.load()?;
+        tracing::info!(
+            pending = pending.len(),
+            available = available.len(),
+            latest = ?available.last_key_value().map(|(height, _)| height),
+            "restored state signatures from storage"
+        );
+        self.pending = Mutex::new(pending);
+        self.available = RwLock::new(available);
+        self.storage = Some(storage);
+        Ok(self)
+    }
+
+    async fn latest_block_height(&self) -> Option<u64> {
+        self.available
+            .read()
+            .await
+            .last_key_value()
+            .map(|(height, _)| *height)
+    }
 }
 
-// TODO(Chengyu): move this `RwLock` inside `StateRelayServerState` so that when nodes are submitting
-//                signatures, it won't block the prover from fetching the available signatures.
 type State = RwLock<StateRelayServerState>;
 type Error = ServerError;
 
+fn storage_error(err: anyhow::Error) -> Error {
+    tracing::error!("error persisting state signatures: {err:#}");
+    Error::catch_all(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("error persisting state signatures: {err:#}"),
+    )
+}
+
+#[async_trait]
 pub trait StateRelayServerDataSource {
     /// Get the latest available signatures bundle.
     /// # Errors
     /// Errors if there's no available signatures bundle.
-    fn get_latest_signature_bundle(&self) -> Result<StateSignaturesBundle, Error>;
+    async fn get_latest_signature_bundle(&self) -> Result<StateSignaturesBundle, Error>;
+
+    /// Get the available signatures bundle at the given block height.
+    /// # Errors
+    /// Errors if there's no available signatures bundle at this height, either because it never
+    /// collected enough signatures or because it is too old and has been garbage collected.
+    async fn get_signature_bundle(&self, height: u64) -> Result<StateSignaturesBundle, Error>;
 
     /// Post a signature to the relay server
     /// # Errors
-    /// Errors if the signature is invalid, already posted, or no longer needed.
-    fn post_signature(
-        &mut self,
+    /// Errors if the signature is invalid, already posted, no longer needed, or too far past the
+    /// latest available bundle.
+    async fn post_signature(
+        &self,
         key: StateVerKey,
         state: LightClientState,
         signature: StateSignature,
     ) -> Result<(), Error>;
 }
 
+#[async_trait]
 impl StateRelayServerDataSource for StateRelayServerState {
-    fn get_latest_signature_bundle(&self) -> Result<StateSignaturesBundle, Error> {
-        match &self.latest_available_bundle {
-            Some(bundle) => Ok(bundle.clone()),
+    async fn get_latest_signature_bundle(&self) -> Result<StateSignaturesBundle, Error> {
+        match self.available.read().await.last_key_value() {
+            Some((_, bundle)) => Ok(bundle.clone()),
             None => Err(tide_disco::error::ServerError::catch_all(
                 StatusCode::NOT_FOUND,
                 "The light client state signatures are not ready.".to_owned(),
@@ -96,24 +181,46 @@ impl StateRelayServerDataSource for StateRelayServerState {
         }
     }
 
-    fn post_signature(
-        &mut self,
+    async fn get_signature_bundle(&self, height: u64) -> Result<StateSignaturesBundle, Error> {
+        match self.available.read().await.get(&height) {
+            Some(bundle) => Ok(bundle.clone()),
+            None => Err(tide_disco::error::ServerError::catch_all(
+                StatusCode::NOT_FOUND,
+                format!("No light client state signatures are available at height {height}."),
+            )),
+        }
+    }
+
+    async fn post_signature(
+        &self,
         key: StateVerKey,
         state: LightClientState,
         signature: StateSignature,
     ) -> Result<(), Error> {
-        if (state.block_height as u64) <= self.latest_block_height.unwrap_or(0) {
+        let block_height = state.block_height as u64;
+        let latest = self.latest_block_height().await;
+        if block_height <= latest.unwrap_or(0) {
             // This signature is no longer needed
             return Ok(());
         }
-        let one = U256::one();
-        let weight = self.known_nodes.get(&key).unwrap_or(&one);
-        // TODO(Chengyu): We don't know where to fetch the stake table yet.
-        // Related issue: [https://github.com/EspressoSystems/espresso-sequencer/issues/1022]
-        // .ok_or(tide_disco::error::ServerError::catch_all(
-        //     StatusCode::Unauthorized,
-        //     "The posted key is not found in the stake table.".to_owned(),
-        // ))?;
+        // Until the first bundle is available we don't know how far along the chain is, so we only
+        // limit future heights relative to an available bundle.
+        if let Some(latest) = latest {
+            if block_height > latest + PENDING_WINDOW {
+                return Err(tide_disco::error::ServerError::catch_all(
+                    StatusCode::BAD_REQUEST,
+                    format!(
+                        "Block height {block_height} is too far past the latest available signatures at height {latest}."
+                    ),
+                ));
+            }
+        }
+        if self.stake_table.read().await.weight(&key).is_none() {
+            return Err(tide_disco::error::ServerError::catch_all(
+                StatusCode::UNAUTHORIZED,
+                "The posted key is not found in the stake table.".to_owned(),
+            ));
+        }
         let state_msg: [FieldType; 7] = (&state).into();
         if StateSignatureScheme::verify(&(), &key, state_msg, &signature).is_err() {
             return Err(tide_disco::error::ServerError::catch_all(
@@ -121,12 +228,15 @@ impl StateRelayServerDataSource for StateRelayServerState {
                 "The posted signature is not valid.".to_owned(),
             ));
         }
-        let block_height = state.block_height as u64;
-        // TODO(Chengyu): this serialization should be removed once `LightClientState` implements `Eq`.
-        let bundles_at_height = self.bundles.entry(block_height).or_insert_with(|| {
-            self.queue.insert(block_height);
-            Default::default()
-        });
+
+        let mut pending = self.pending.lock().await;
+        // Check again now that we hold the lock, in case a bundle became available in the meantime.
+        if block_height <= self.latest_block_height().await.unwrap_or(0) {
+            return Ok(());
+        }
+        // Work on a copy of the signatures at this height, so that nothing changes in memory unless
+        // it has been persisted.
+        let mut bundles_at_height = pending.get(&block_height).cloned().unwrap_or_default();
         let bundle = bundles_at_height
             .entry(state.clone())
             .or_insert(StateSignaturesBundle {
@@ -149,21 +259,59 @@ impl StateRelayServerDataSource for StateRelayServerState {
             }
             std::collections::hash_map::Entry::Vacant(entry) => {
                 entry.insert(signature);
-                bundle.accumulated_weight += *weight;
             }
         }
 
-        if bundle.accumulated_weight >= self.threshold {
-            tracing::info!(
-                "State signature bundle at block height {} is ready to serve.",
-                block_height
-            );
-            self.latest_block_height = Some(block_height);
-            self.latest_available_bundle = Some(bundle.clone());
-            while let Some(height) = self.queue.pop_first() {
-                self.bundles.remove(&height);
-                if height == block_height {
-                    break;
+        // Recompute the weight from scratch, in case the stake table has changed since the other
+        // signatures were collected.
+        let stake_table = self.stake_table.read().await;
+        bundle.accumulated_weight = stake_table.total_weight(bundle.signatures.keys());
+        let complete = bundle.accumulated_weight >= stake_table.threshold;
+        drop(stake_table);
+        let Some(bundle) = complete.then(|| bundle.clone()) else {
+            if let Some(storage) = &self.storage {
+                storage
+                    .save_pending(block_height, &bundles_at_height)
+                    .map_err(storage_error)?;
+            }
+            pending.insert(block_height, bundles_at_height);
+            return Ok(());
+        };
+
+        tracing::info!(
+            "State signature bundle at block height {} is ready to serve.",
+            block_height
+        );
+        // Persist the new bundle before serving it, so a restart never goes backwards.
+        if let Some(storage) = &self.storage {
+            storage
+                .save_bundle(block_height, &bundle)
+                .map_err(storage_error)?;
+        }
+        let mut available = self.available.write().await;
+        available.insert(block_height, bundle);
+        while available.len() > self.retention {
+            let (height, _) = available.pop_first().unwrap();
+            if let Some(storage) = &self.storage {
+                if let Err(err) = storage.remove_bundle(height) {
+                    tracing::warn!(height, "failed to delete old signature bundle: {err:#}");
+                }
+            }
+        }
+        drop(available);
+
+        // Signatures for this height and earlier are no longer needed, nor are signatures which
+        // were accepted before there was a bundle to measure the window from and are now out of it.
+        let stale = pending
+            .keys()
+            .copied()
+            .filter(|&h| h <= block_height || h > block_height + PENDING_WINDOW)
+            .collect::<Vec<_>>();
+        for height in stale {
+            pending.remove(&height);
+            if let Some(storage) = &self.storage {
+                if let Err(err) = storage.remove_pending(height) {
+                    tracing::warn!(height, "failed to delete stale signatures: {err:#}");
                 }
             }
         }
@@ -171,8 +319,244 @@ impl StateRelayServerDataSource for StateRelayServerState {
     }
 }
 
+/// The stake table used to weigh state signatures.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelayStakeTable {
+    /// Stake of each known state key, or `None` to accept any key with a stake of 1.
+    known_nodes: Option<HashMap<StateVerKey, U256>>,
+    /// Minimum weight to form an available state signature bundle
+    threshold: U256,
+}
+
+impl RelayStakeTable {
+    /// Accept signatures from any key, each with a stake of 1.
+    ///
+    /// This is only suitable for test and demo networks in which every node has the same stake.
+    pub fn uniform(threshold: U256) -> Self {
+        Self {
+            known_nodes: None,
+            threshold,
+        }
+    }
+
+    /// Weigh signatures according to the stake of each peer.
+    ///
+    /// A bundle is available once it has signatures from more than a third of the total stake.
+    pub fn from_peers<'a>(peers: impl IntoIterator<Item = &'a PeerConfig<PubKey>>) -> Self {
+        let mut known_nodes = HashMap::<StateVerKey, U256>::new();
+        for peer in peers {
+            *known_nodes.entry(peer.state_ver_key.clone()).or_default() +=
+                peer.stake_table_entry.stake();
+        }
+        let total_stake = known_nodes
+            .values()
+            .fold(U256::zero(), |total, stake| total + stake);
+        Self {
+            known_nodes: Some(known_nodes),
+            threshold: one_honest_threshold(total_stake),
+        }
+    }
+
+    /// The stake of `key`, or `None` if it is not in the stake table.
+    fn weight(&self, key: &StateVerKey) -> Option<U256> {
+        match &self.known_nodes {
+            Some(known_nodes) => known_nodes.get(key).copied(),
+            None => Some(U256::one()),
+        }
+    }
+
+    /// The total stake of `keys`, ignoring keys which are not in the stake table.
+    fn total_weight<'a>(&self, keys: impl IntoIterator<Item = &'a StateVerKey>) -> U256 {
+        keys.into_iter()
+            .filter_map(|key| self.weight(key))
+            .fold(U256::zero(), |total, stake| total + stake)
+    }
+}
+
+/// Where the relay server gets its stake table.
+#[derive(Clone, Debug)]
+pub enum StakeTableSource {
+    /// The `config/hotshot` endpoint of a sequencer node.
+    Sequencer(Url),
+    /// A JSON file containing a network config, in the format served by `config/hotshot`.
+    File(PathBuf),
+}
+
+impl StakeTableSource {
+    pub async fn fetch<Ver: StaticVersionType>(&self) -> anyhow::Result<RelayStakeTable> {
+        let config: PublicNetworkConfig = match self {
+            Self::Sequencer(url) => Client::<ServerError, Ver>::new(url.clone())
+                .get("config/hotshot")
+                .send()
+                .await
+                .context(format!("fetching stake table from {url}"))?,
+            Self::File(path) => serde_json::from_slice(
+                &fs::read(path).context(format!("reading {}", path.display()))?,
+            )
+            .context(format!("malformed network config {}", path.display()))?,
+        };
+        ensure!(!config.stake_table().is_empty(), "stake table is empty");
+        Ok(RelayStakeTable::from_peers(config.stake_table()))
+    }
+}
+
+/// Load the stake table, retrying until it is available.
+async fn load_stake_table<Ver: StaticVersionType>(
+    source: &StakeTableSource,
+    retry_interval: Duration,
+) -> RelayStakeTable {
+    loop {
+        match source.fetch::<Ver>().await {
+            Ok(stake_table) => break stake_table,
+            Err(err) => {
+                tracing::error!("failed to load stake table, will retry: {err:#}");
+                sleep(retry_interval).await;
+            }
+        }
+    }
+}
+
+/// Keep `stake_table` up to date with `source`.
+async fn refresh_stake_table<Ver: StaticVersionType>(
+    source: StakeTableSource,
+    stake_table: Arc<RwLock<RelayStakeTable>>,
+    interval: Duration,
+) {
+    loop {
+        sleep(interval).await;
+        let new = match source.fetch::<Ver>().await {
+            Ok(new) => new,
+            Err(err) => {
+                tracing::warn!("failed to refresh stake table: {err:#}");
+                continue;
+            }
+        };
+        let mut stake_table = stake_table.write().await;
+        if *stake_table != new {
+            tracing::info!(threshold = %new.threshold, "stake table changed");
+            *stake_table = new;
+        }
+    }
+}
+
+/// File system storage for the relay server.
+///
+/// Signatures which are still being collected are stored in `pending/<height>`, and available
+/// bundles in `bundles/<height>`, so that a restarted relay server can resume collecting signatures
+/// and immediately serve the bundles it had already formed.
+#[derive(Clone, Debug)]
+pub struct RelayStorage {
+    path: PathBuf,
+}
+
+impl RelayStorage {
+    pub fn open(path: PathBuf) -> anyhow::Result<Self> {
+        let storage = Self { path };
+        fs::create_dir_all(storage.pending_dir())?;
+        fs::create_dir_all(storage.bundles_dir())?;
+        Ok(storage)
+    }
+
+    fn pending_dir(&self) -> PathBuf {
+        self.path.join("pending")
+    }
+
+    fn bundles_dir(&self) -> PathBuf {
+        self.path.join("bundles")
+    }
+
+    fn load(&self) -> anyhow::Result<(PendingBundles, BTreeMap<u64, StateSignaturesBundle>)> {
+        let mut available = BTreeMap::new();
+        for (height, bytes) in read_dir(&self.bundles_dir())? {
+            let bundle: StateSignaturesBundle = bincode::deserialize(&bytes)
+                .context(format!("malformed signature bundle at height {height}"))?;
+            available.insert(height, bundle);
+        }
+
+        let latest = available.last_key_value().map(|(height, _)| *height);
+        let mut pending = PendingBundles::new();
+        for (height, bytes) in read_dir(&self.pending_dir())? {
+            if latest.is_some_and(|latest| height <= latest) {
+                // We may have crashed before cleaning up signatures that were no longer needed.
+                self.remove_pending(height)?;
+                continue;
+            }
+            let bundles: Vec<StateSignaturesBundle> = bincode::deserialize(&bytes)
+                .context(format!("malformed signatures at height {height}"))?;
+            pending.insert(
+                height,
+                bundles
+                    .into_iter()
+                    .map(|bundle| (bundle.state.clone(), bundle))
+                    .collect(),
+            );
+        }
+        Ok((pending, available))
+    }
+
+    fn save_pending(
+        &self,
+        height: u64,
+        bundles: &HashMap<LightClientState, StateSignaturesBundle>,
+    ) -> anyhow::Result<()> {
+        let bundles = bundles.values().collect::<Vec<_>>();
+        write_atomic(
+            &self.pending_dir().join(height.to_string()),
+            &bincode::serialize(&bundles)?,
+        )
+    }
+
+    fn remove_pending(&self, height: u64) -> anyhow::Result<()> {
+        remove_if_exists(&self.pending_dir().join(height.to_string()))
+    }
+
+    fn save_bundle(&self, height: u64, bundle: &StateSignaturesBundle) -> anyhow::Result<()> {
+        write_atomic(
+            &self.bundles_dir().join(height.to_string()),
+            &bincode::serialize(bundle)?,
+        )
+    }
+
+    fn remove_bundle(&self, height: u64) -> anyhow::Result<()> {
+        remove_if_exists(&self.bundles_dir().join(height.to_string()))
+    }
+}
+
+/// Read all files in `dir` named by a block height.
+fn read_dir(dir: &Path) -> anyhow::Result<Vec<(u64, Vec<u8>)>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(height) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse().ok())
+        else {
+            // Ignore temporary files from interrupted writes.
+            continue;
+        };
+        files.push((height, fs::read(&path)?));
+    }
+    Ok(files)
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let swap_path = path.with_extension("swp");
+    let mut swap = File::create(&swap_path)?;
+    swap.write_all(bytes)?;
+    swap.sync_all()?;
+    fs::rename(&swap_path, path).context(format!("writing {}", path.display()))
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
 /// configurability options for the web server
-#[derive(Args, Default)]
+#[derive(Args, Clone, Debug)]
 pub struct Options {
     #[arg(
         long = "state-relay-server-api-path",
@@ -180,6 +564,73 @@ pub struct Options {
     )]
     /// path to API
     pub api_path: Option<PathBuf>,
+
+    /// Directory in which to persist signatures and signature bundles.
+    ///
+    /// If not set, signatures are kept in memory only, and are lost when the relay server restarts.
+    #[arg(
+        long = "state-relay-server-storage-path",
+        env = "ESPRESSO_STATE_RELAY_SERVER_STORAGE_PATH"
+    )]
+    pub storage_path: Option<PathBuf>,
+
+    /// Number of available signature bundles to keep and serve by block height.
+    #[arg(
+        long = "state-relay-server-bundle-retention",
+        env = "ESPRESSO_STATE_RELAY_SERVER_BUNDLE_RETENTION",
+        default_value_t = DEFAULT_BUNDLE_RETENTION
+    )]
+    pub bundle_retention: usize,
+
+    /// URL of a sequencer node to fetch the stake table from.
+    ///
+    /// If neither this nor a stake table file is given, signatures from any key are accepted, each
+    /// with a stake of 1.
+    #[arg(
+        long = "state-relay-server-stake-table-url",
+        env = "ESPRESSO_STATE_RELAY_SERVER_STAKE_TABLE_URL",
+        conflicts_with = "stake_table_file"
+    )]
+    pub stake_table_url: Option<Url>,
+
+    /// JSON file to load the stake table from, in the format served by `config/hotshot`.
+    #[arg(
+        long = "state-relay-server-stake-table-file",
+        env = "ESPRESSO_STATE_RELAY_SERVER_STAKE_TABLE_FILE"
+    )]
+    pub stake_table_file: Option<PathBuf>,
+
+    /// How often to check the stake table for changes.
+    #[arg(
+        long = "state-relay-server-stake-table-refresh-interval",
+        env = "ESPRESSO_STATE_RELAY_SERVER_STAKE_TABLE_REFRESH_INTERVAL",
+        default_value = "1m",
+        value_parser = parse_duration
+    )]
+    pub stake_table_refresh_interval: Duration,
+}
+
+impl Options {
+    fn stake_table_source(&self) -> Option<StakeTableSource> {
+        if let Some(url) = &self.stake_table_url {
+            Some(StakeTableSource::Sequencer(url.clone()))
+        } else {
+            self.stake_table_file.clone().map(StakeTableSource::File)
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            api_path: None,
+            storage_path: None,
+            bundle_retention: DEFAULT_BUNDLE_RETENTION,
+            stake_table_url: None,
+            stake_table_file: None,
+            stake_table_refresh_interval: DEFAULT_STAKE_TABLE_REFRESH_INTERVAL,
+        }
+    }
 }
 
 /// Set up APIs for relay server
@@ -205,9 +656,20 @@ where
     };
 
     api.get("getlateststate", |_req, state| {
-        async move { state.get_latest_signature_bundle() }.boxed()
+        async move { state.get_latest_signature_bundle().await }.boxed()
+    })?
+    .get("getstate", |req, state| {
+        async move {
+            let height = req
+                .integer_param("height")
+                .map_err(Error::from_request_error)?;
+            state.get_signature_bundle(height).await
+        }
+        .boxed()
     })?
-    .post("poststatesignature", |req, state| {
+    // Posting signatures only needs shared access to the state, which has its own fine-grained
+    // locking, so we take a read lock rather than blocking readers with a write lock.
+    .at("poststatesignature", |req, state| {
         async move {
             let StateSignatureRequestBody {
                 key,
@@ -216,7 +678,11 @@ where
             } = req
                 .body_auto::<StateSignatureRequestBody, Ver>(Ver::instance())
                 .map_err(Error::from_request_error)?;
-            state.post_signature(key, lcstate, signature)
+            state
+                .read(|state| {
+                    async move { state.post_signature(key, lcstate, signature).await }.boxed()
+                })
+                .await
         }
         .boxed()
     })?;
@@ -230,14 +696,47 @@ pub async fn run_relay_server<Ver: StaticVersionType + 'static>(
     url: Url,
     bind_version: Ver,
 ) -> std::io::Result<()> {
-    let options = Options::default();
+    run_relay_server_with_options(
+        shutdown_listener,
+        threshold,
+        url,
+        Options::default(),
+        bind_version,
+    )
+    .await
+}
 
+pub async fn run_relay_server_with_options<Ver: StaticVersionType + 'static>(
+    shutdown_listener: Option<OneShotReceiver<()>>,
+    threshold: U256,
+    url: Url,
+    options: Options,
+    bind_version: Ver,
+) -> std::io::Result<()> {
     let api = define_api(&options, bind_version).unwrap();
 
-    // We don't have a stake table yet, putting some temporary value here.
-    // Related issue: [https://github.com/EspressoSystems/espresso-sequencer/issues/1022]
-    let state =
-        State::new(StateRelayServerState::new(threshold).with_shutdown_signal(shutdown_listener));
+    // Without a stake table source, `threshold` applies to signatures from any key with a stake of
+    // 1 each.
+    let mut relay_state = StateRelayServerState::new(threshold)
+        .with_retention(options.bundle_retention)
+        .with_shutdown_signal(shutdown_listener);
+    if let Some(source) = options.stake_table_source() {
+        let interval = options.stake_table_refresh_interval;
+        let stake_table = load_stake_table::<Ver>(&source, interval).await;
+        tracing::info!(threshold = %stake_table.threshold, "loaded stake table from {source:?}");
+        relay_state = relay_state.with_stake_table(stake_table);
+        spawn(refresh_stake_table::<Ver>(
+            source,
+            relay_state.stake_table.clone(),
+            interval,
+        ));
+    }
+    if let Some(path) = options.storage_path {
+        relay_state = RelayStorage::open(path)
+            .and_then(|storage| relay_state.with_storage(storage))
+            .map_err(std::io::Error::other)?;
+    }
+    let state = State::new(relay_state);
     let mut app = App::<State, Error>::with_state(state);
 
     app.register_module("api", api).unwrap();
@@ -246,3 +745,197 @@ pub async fn run_relay_server<Ver: StaticVersionType + 'static>(
 
     app_future.await
 }
+
+#[cfg(test)]
+mod test {
+    use ark_ff::Zero;
+    use hotshot_types::{
+        light_client::{CircuitField, StateKeyPair},
+        traits::signature_key::SignatureKey,
+    };
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn state(height: usize) -> LightClientState {
+        LightClientState {
+            view_number: height,
+            block_height: height,
+            block_comm_root: CircuitField::zero(),
+            fee_ledger_comm: CircuitField::zero(),
+            stake_table_comm: (
+                CircuitField::zero(),
+                CircuitField::zero(),
+                CircuitField::zero(),
+            ),
+        }
+    }
+
+    fn sign(key: &StateKeyPair, state: &LightClientState) -> StateSignature {
+        let msg: [FieldType; 7] = state.into();
+        StateSignatureScheme::sign(&(), key.sign_key_ref(), msg, &mut rand::thread_rng()).unwrap()
+    }
+
+    #[async_std::test]
+    async fn test_relay_storage() {
+        let tmp = TempDir::new().unwrap();
+        let keys = (0..3)
+            .map(|i| StateKeyPair::generate_from_seed_indexed([0; 32], i))
+            .collect::<Vec<_>>();
+        let open = || {
+            StateRelayServerState::new(U256::from(2))
+                .with_storage(RelayStorage::open(tmp.path().into()).unwrap())
+                .unwrap()
+        };
+
+        // Complete a bundle at height 1 and start collecting signatures at height 2.
+        let relay = open();
+        for key in &keys[..2] {
+            relay
+                .post_signature(key.ver_key(), state(1), sign(key, &state(1)))
+                .await
+                .unwrap();
+        }
+        relay
+            .post_signature(keys[0].ver_key(), state(2), sign(&keys[0], &state(2)))
+            .await
+            .unwrap();
+        relay.get_signature_bundle(2).await.unwrap_err();
+
+        // After a restart, the completed bundle is still served and the pending signature counts
+        // towards the next bundle.
+        let relay = open();
+        let bundle = relay.get_latest_signature_bundle().await.unwrap();
+        assert_eq!(bundle.state, state(1));
+        assert_eq!(bundle.signatures.len(), 2);
+        relay
+            .post_signature(keys[1].ver_key(), state(2), sign(&keys[1], &state(2)))
+            .await
+            .unwrap();
+        assert_eq!(
+            relay.get_latest_signature_bundle().await.unwrap().state,
+            state(2)
+        );
+        assert_eq!(relay.get_signature_bundle(1).await.unwrap().state, state(1));
+
+        // Signatures for old heights are ignored.
+        relay
+            .post_signature(keys[2].ver_key(), state(1), sign(&keys[2], &state(1)))
+            .await
+            .unwrap();
+        assert_eq!(
+            relay
+                .get_signature_bundle(1)
+                .await
+                .unwrap()
+                .signatures
+                .len(),
+            2
+        );
+    }
+
+    #[async_std::test]
+    async fn test_relay_stake_weight() {
+        let keys = (0..3)
+            .map(|i| StateKeyPair::generate_from_seed_indexed([0; 32], i))
+            .collect::<Vec<_>>();
+        // Key 1 holds more than a third of the stake on its own, key 0 does not.
+        let peers = keys[..2]
+            .iter()
+            .zip([5, 10])
+            .enumerate()
+            .map(|(i, (key, stake))| PeerConfig {
+                stake_table_entry: PubKey::generated_from_seed_indexed([0; 32], i as u64)
+                    .0
+                    .stake_table_entry(stake),
+                state_ver_key: key.ver_key(),
+            })
+            .collect::<Vec<_>>();
+        let relay = StateRelayServerState::new(U256::one())
+            .with_stake_table(RelayStakeTable::from_peers(&peers));
+
+        // Unknown keys are rejected.
+        let err = relay
+            .post_signature(keys[2].ver_key(), state(1), sign(&keys[2], &state(1)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::UNAUTHORIZED);
+
+        relay
+            .post_signature(keys[0].ver_key(), state(1), sign(&keys[0], &state(1)))
+            .await
+            .unwrap();
+        relay.get_signature_bundle(1).await.unwrap_err();
+        relay
+            .post_signature(keys[1].ver_key(), state(1), sign(&keys[1], &state(1)))
+            .await
+            .unwrap();
+        let bundle = relay.get_signature_bundle(1).await.unwrap();
+        assert_eq!(bundle.accumulated_weight, U256::from(15));
+
+        relay
+            .post_signature(keys[1].ver_key(), state(2), sign(&keys[1], &state(2)))
+            .await
+            .unwrap();
+        relay.get_signature_bundle(2).await.unwrap();
+    }
+
+    #[async_std::test]
+    async fn test_relay_pending_window() {
+        let tmp = TempDir::new().unwrap();
+        let keys = (0..2)
+            .map(|i| StateKeyPair::generate_from_seed_indexed([0; 32], i))
+            .collect::<Vec<_>>();
+        let relay = StateRelayServerState::new(U256::from(2))
+            .with_storage(RelayStorage::open(tmp.path().into()).unwrap())
+            .unwrap();
+        async fn post(
+            relay: &StateRelayServerState,
+            key: &StateKeyPair,
+            height: usize,
+        ) -> Result<(), Error> {
+            relay
+                .post_signature(key.ver_key(), state(height), sign(key, &state(height)))
+                .await
+        }
+
+        // Before any bundle is available, any height is accepted.
+        let far = 10 * PENDING_WINDOW as usize;
+        post(&relay, &keys[0], far).await.unwrap();
+        for key in &keys {
+            post(&relay, key, 1).await.unwrap();
+        }
+        relay.get_signature_bundle(1).await.unwrap();
+
+        // Once a bundle is available, heights too far past it are rejected, and signatures which
+        // were accepted earlier but are now out of the window are dropped.
+        let last = 1 + PENDING_WINDOW as usize;
+        post(&relay, &keys[0], last).await.unwrap();
+        let err = post(&relay, &keys[0], last + 1).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            relay.pending.lock().await.keys().collect::<Vec<_>>(),
+            [&(last as u64)]
+        );
+        let persisted = fs::read_dir(tmp.path().join("pending"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(persisted, [last.to_string().as_str()]);
+    }
+
+    #[async_std::test]
+    async fn test_relay_retention() {
+        let key = StateKeyPair::generate_from_seed_indexed([0; 32], 0);
+        let relay = StateRelayServerState::new(U256::one()).with_retention(2);
+        for height in 1..=3 {
+            relay
+                .post_signature(key.ver_key(), state(height), sign(&key, &state(height)))
+                .await
+                .unwrap();
+        }
+        relay.get_signature_bundle(1).await.unwrap_err();
+        relay.get_signature_bundle(2).await.unwrap();
+        relay.get_signature_bundle(3).await.unwrap();
+    }
+}
@@ -15,7 +15,8 @@ use hotshot_types::{
     message::Proposal,
     simple_certificate::QuorumCertificate,
     traits::{
-        node_implementation::ConsensusTime, storage::Storage, ValidatedState as HotShotState,
+        metrics::Metrics, node_implementation::ConsensusTime, storage::Storage,
+        ValidatedState as HotShotState,
     },
     utils::View,
 };
@@ -327,6 +328,12 @@ pub trait SequencerPersistence: Sized + Send + Sync + 'static {
         bail!("state catchup is not implemented for this persistence type");
     }
 
+    /// Start background tasks which maintain this storage, such as deleting old data.
+    ///
+    /// This is called once, when consensus starts using this storage. `metrics` can be used to
+    /// report on the state of the storage.
+    fn start_background_tasks(&mut self, _metrics: &dyn Metrics) {}
+
     /// Load the orchestrator config from storage.
     ///
     /// Returns `None` if no config exists (we are joining a network for the first time). Fails with
@@ -5,6 +5,7 @@ use async_std::sync::Arc;
 use clap::{builder::OsStr, Parser, ValueEnum};
 use contract_bindings::{
     erc1967_proxy::ERC1967Proxy,
+    example_token::ExampleToken,
     fee_contract::FeeContract,
     hot_shot::HotShot,
     light_client::{LightClient, LIGHTCLIENT_ABI},
@@ -13,6 +14,7 @@ use contract_bindings::{
     light_client_state_update_vk_mock::LightClientStateUpdateVKMock,
     plonk_verifier::PlonkVerifier,
     shared_types::LightClientState,
+    stake_table::StakeTable,
 };
 use derive_more::Display;
 use ethers::{prelude::*, signers::coins_bip39::English, solc::artifacts::BytecodeObject};
@@ -20,6 +22,14 @@ use futures::future::{BoxFuture, FutureExt};
 use hotshot_contract_adapter::light_client::ParsedLightClientState;
 use url::Url;
 
+/// Maximum number of registrations (and exits) per epoch in a newly deployed stake table.
+pub const STAKE_TABLE_MAX_CHURN_RATE: u64 = 20;
+
+/// Supply of the example token deployed for staking when no token is given.
+///
+/// The whole supply is minted to the deployer.
+pub const STAKE_TABLE_TOKEN_SUPPLY: u128 = 1_000_000_000_000_000_000_000_000_000;
+
 /// Set of predeployed contracts.
 #[derive(Clone, Debug, Parser)]
 pub struct DeployedContracts {
@@ -50,6 +60,14 @@ pub struct DeployedContracts {
     /// Use an already-deployed FeeContract.sol proxy instead of deploying a new one.
     #[clap(long, env = Contract::FeeContractProxy)]
     fee_contract_proxy: Option<Address>,
+
+    /// Use an already-deployed ERC20 token for staking instead of deploying a new one.
+    #[clap(long, env = Contract::StakeTableToken)]
+    stake_table_token: Option<Address>,
+
+    /// Use an already-deployed StakeTable.sol instead of deploying a new one.
+    #[clap(long, env = Contract::StakeTable)]
+    stake_table: Option<Address>,
 }
 
 /// An identifier for a particular contract.
@@ -69,6 +87,10 @@ pub enum Contract {
     FeeContract,
     #[display(fmt = "ESPRESSO_SEQUENCER_FEE_CONTRACT_PROXY_ADDRESS")]
     FeeContractProxy,
+    #[display(fmt = "ESPRESSO_SEQUENCER_STAKE_TABLE_TOKEN_ADDRESS")]
+    StakeTableToken,
+    #[display(fmt = "ESPRESSO_SEQUENCER_STAKE_TABLE_ADDRESS")]
+    StakeTable,
 }
 
 impl From<Contract> for OsStr {
@@ -105,6 +127,12 @@ impl From<DeployedContracts> for Contracts {
         if let Some(addr) = deployed.fee_contract_proxy {
             m.insert(Contract::FeeContractProxy, addr);
         }
+        if let Some(addr) = deployed.stake_table_token {
+            m.insert(Contract::StakeTableToken, addr);
+        }
+        if let Some(addr) = deployed.stake_table {
+            m.insert(Contract::StakeTable, addr);
+        }
         Self(m)
     }
 }
@@ -386,6 +414,30 @@ pub async fn deploy(
             .await?;
     }
 
+    // `StakeTable.sol`
+    if should_deploy(ContractGroup::StakeTable, &only) {
+        // The stake table reads the current epoch from the light client, so it must be deployed
+        // after (or alongside) the light client proxy.
+        let light_client = contracts
+            .get_contract_address(Contract::LightClientProxy)
+            .context("deploying StakeTable.sol requires a light client proxy")?;
+        let token = contracts
+            .deploy_tx(
+                Contract::StakeTableToken,
+                ExampleToken::deploy(l1.clone(), U256::from(STAKE_TABLE_TOKEN_SUPPLY))?,
+            )
+            .await?;
+        contracts
+            .deploy_tx(
+                Contract::StakeTable,
+                StakeTable::deploy(
+                    l1.clone(),
+                    (token, light_client, STAKE_TABLE_MAX_CHURN_RATE),
+                )?,
+            )
+            .await?;
+    }
+
     Ok(contracts)
 }
 
@@ -402,4 +454,5 @@ pub enum ContractGroup {
     HotShot,
     FeeContract,
     LightClient,
+    StakeTable,
 }
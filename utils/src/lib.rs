@@ -22,6 +22,7 @@ use url::Url;
 
 pub mod deployer;
 pub mod ser;
+pub mod stake_table;
 pub mod test_utils;
 
 pub type Signer = SignerMiddleware<Provider<Http>, LocalWallet>;
@@ -0,0 +1,302 @@
+//! Interacting with the `StakeTable` contract.
+//!
+//! The contract only stores a hash of each node's BLS key, so the full keys of registered nodes are
+//! recovered from the `Registered` events emitted when they were registered.
+
+use anyhow::{ensure, Context};
+use contract_bindings::{
+    example_token::ExampleToken,
+    stake_table::{StakeTable, StakeTableErrors},
+};
+use ethers::prelude::*;
+use hotshot_contract_adapter::stake_table::{
+    bls_key_to_g2_point, g2_point_to_bls_key, point_to_state_key, sign_account, state_key_to_point,
+};
+use hotshot_types::{
+    light_client::StateVerKey,
+    signature_key::{BLSPrivKey, BLSPubKey},
+    traits::signature_key::SignatureKey,
+    PeerConfig,
+};
+
+use crate::contract_send;
+
+/// A node registered in the `StakeTable` contract.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisteredNode {
+    pub staking_key: BLSPubKey,
+    pub state_key: StateVerKey,
+    /// The L1 account which registered the node, and which controls its stake.
+    pub account: Address,
+    pub balance: u64,
+    /// The first epoch in which the node is part of the stake table.
+    pub register_epoch: u64,
+    /// The first epoch in which the node is no longer part of the stake table, if it has requested
+    /// to exit.
+    pub exit_epoch: Option<u64>,
+}
+
+impl RegisteredNode {
+    /// Whether this node is part of the stake table in `epoch`.
+    pub fn is_active(&self, epoch: u64) -> bool {
+        self.register_epoch <= epoch && self.exit_epoch.map_or(true, |exit| epoch < exit)
+    }
+
+    /// The HotShot stake table entry for this node.
+    pub fn peer_config(&self) -> PeerConfig<BLSPubKey> {
+        PeerConfig {
+            stake_table_entry: self.staking_key.stake_table_entry(self.balance),
+            state_ver_key: self.state_key.clone(),
+        }
+    }
+}
+
+/// Get all nodes currently registered in the stake table.
+///
+/// Registrations are searched for starting from `from_block`, which should be the block in which
+/// the contract was deployed. Nodes which have withdrawn their stake are not included.
+pub async fn registered_nodes<M: Middleware + 'static>(
+    contract: &StakeTable<M>,
+    from_block: u64,
+) -> anyhow::Result<Vec<RegisteredNode>> {
+    let registrations = contract
+        .registered_filter()
+        .from_block(from_block)
+        .query()
+        .await
+        .context("fetching registration events")?;
+
+    let mut nodes = vec![];
+    for event in registrations {
+        // The balance and epochs may have changed since registration, so look up the current state
+        // of the node.
+        let node = contract
+            .lookup_node(event.bls_vk.clone())
+            .call()
+            .await
+            .context("looking up node")?;
+        if node.account == Address::zero() {
+            // The node has withdrawn its stake and been removed from the stake table.
+            continue;
+        }
+        nodes.push(RegisteredNode {
+            staking_key: g2_point_to_bls_key(&event.bls_vk)?,
+            state_key: point_to_state_key(&event.schnorr_vk)?,
+            account: node.account,
+            balance: node.balance,
+            register_epoch: node.register_epoch,
+            exit_epoch: (node.exit_epoch != 0).then_some(node.exit_epoch),
+        });
+    }
+    Ok(nodes)
+}
+
+/// The HotShot stake table for `epoch`, from the nodes registered in the contract.
+pub fn stake_table_entries(nodes: &[RegisteredNode], epoch: u64) -> Vec<PeerConfig<BLSPubKey>> {
+    nodes
+        .iter()
+        .filter(|node| node.is_active(epoch))
+        .map(RegisteredNode::peer_config)
+        .collect()
+}
+
+/// Register a node, staking `amount` of the stake table's token from the sender's account.
+///
+/// If the registration would not take effect by `valid_until_epoch`, it is aborted. If
+/// `valid_until_epoch` is not given, the node is registered in the next available epoch.
+///
+/// Returns the epoch in which the node will join the stake table.
+pub async fn register<M: Middleware + 'static>(
+    contract: &StakeTable<M>,
+    staking_key: &BLSPrivKey,
+    state_key: &StateVerKey,
+    amount: u64,
+    valid_until_epoch: Option<u64>,
+) -> anyhow::Result<u64>
+where
+    M::Provider: Clone,
+{
+    let account = contract
+        .client()
+        .default_sender()
+        .context("no account to register from")?;
+    let bls_vk = bls_key_to_g2_point(&BLSPubKey::from_private(staking_key));
+    let bls_sig = sign_account(staking_key, account)?;
+
+    let (next_epoch, _) = contract.next_registration_epoch().call().await?;
+    let valid_until_epoch = valid_until_epoch.unwrap_or(next_epoch);
+
+    approve(contract, amount).await?;
+    contract_send::<_, _, StakeTableErrors>(&contract.register(
+        bls_vk.clone(),
+        state_key_to_point(state_key),
+        amount,
+        // Native staking, the only kind the contract currently supports.
+        0,
+        bls_sig,
+        valid_until_epoch,
+    ))
+    .await
+    .context("registering node")?;
+
+    let node = contract.lookup_node(bls_vk).call().await?;
+    ensure!(node.account == account, "node was not registered");
+    Ok(node.register_epoch)
+}
+
+/// Add `amount` of the stake table's token from the sender's account to a node's stake.
+///
+/// Returns the new balance and the epoch in which it takes effect.
+pub async fn deposit<M: Middleware + 'static>(
+    contract: &StakeTable<M>,
+    staking_key: &BLSPubKey,
+    amount: u64,
+) -> anyhow::Result<(u64, u64)>
+where
+    M::Provider: Clone,
+{
+    let bls_vk = bls_key_to_g2_point(staking_key);
+    approve(contract, amount).await?;
+
+    // Read the result by simulating the call before sending it.
+    let call = contract.deposit(bls_vk, amount);
+    let (balance, epoch) = call.call().await?;
+    contract_send::<_, _, StakeTableErrors>(&call)
+        .await
+        .context("depositing stake")?;
+    Ok((balance, epoch))
+}
+
+/// Allow the stake table to transfer `amount` of its token from the sender's account.
+async fn approve<M: Middleware + 'static>(
+    contract: &StakeTable<M>,
+    amount: u64,
+) -> anyhow::Result<()> {
+    let token = contract.token_address().call().await?;
+    let token = ExampleToken::new(token, contract.client());
+    let tx = token
+        .approve(contract.address(), amount.into())
+        .send()
+        .await
+        .context("sending approval transaction")?
+        .await
+        .context("waiting for approval transaction")?
+        .context("approval transaction not mined")?;
+    ensure!(tx.status == Some(1.into()), "approval transaction reverted");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use contract_bindings::light_client_mock::{LightClientMock, LightClientMockErrors};
+    use futures::FutureExt;
+    use hotshot_contract_adapter::light_client::ParsedLightClientState;
+    use hotshot_types::light_client::StateKeyPair;
+
+    use super::*;
+    use crate::{
+        deployer::{deploy, Contract, ContractGroup, Contracts},
+        init_signer, AnvilOptions,
+    };
+
+    const MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[async_std::test]
+    async fn test_stake_table_contract() {
+        let anvil = AnvilOptions::default().spawn().await;
+        let contracts = deploy(
+            anvil.url(),
+            MNEMONIC.into(),
+            0,
+            true,
+            Some(vec![ContractGroup::LightClient, ContractGroup::StakeTable]),
+            async { Ok(ParsedLightClientState::dummy_genesis()) }.boxed(),
+            Contracts::new(),
+        )
+        .await
+        .unwrap();
+
+        let l1 = Arc::new(init_signer(&anvil.url(), MNEMONIC, 0).await.unwrap());
+        let stake_table = StakeTable::new(
+            contracts
+                .get_contract_address(Contract::StakeTable)
+                .unwrap(),
+            l1.clone(),
+        );
+        let light_client = LightClientMock::new(
+            contracts
+                .get_contract_address(Contract::LightClientProxy)
+                .unwrap(),
+            l1.clone(),
+        );
+
+        // Register two nodes, which join in the next epoch.
+        let keys = (0..2)
+            .map(|i| {
+                (
+                    BLSPubKey::generated_from_seed_indexed([0; 32], i),
+                    StateKeyPair::generate_from_seed_indexed([0; 32], i).ver_key(),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (i, ((_, staking_key), state_key)) in keys.iter().enumerate() {
+            let epoch = register(
+                &stake_table,
+                staking_key,
+                state_key,
+                10 * (i as u64 + 1),
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(epoch, 1);
+        }
+
+        let nodes = registered_nodes(&stake_table, 0).await.unwrap();
+        assert_eq!(nodes.len(), 2);
+        assert!(stake_table_entries(&nodes, 0).is_empty());
+        let entries = stake_table_entries(&nodes, 1);
+        assert_eq!(entries.len(), 2);
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.stake_table_entry.stake_key, keys[i].0 .0);
+            assert_eq!(entry.stake_table_entry.stake_amount, (10 * (i + 1)).into());
+            assert_eq!(entry.state_ver_key, keys[i].1);
+        }
+
+        // Once active, a node can deposit more stake and request to exit.
+        contract_send::<_, _, LightClientMockErrors>(&light_client.set_current_epoch(2))
+            .await
+            .unwrap();
+        let (balance, _) = deposit(&stake_table, &keys[1].0 .0, 5).await.unwrap();
+        assert_eq!(balance, 25);
+        contract_send::<_, _, StakeTableErrors>(
+            &stake_table.request_exit(bls_key_to_g2_point(&keys[0].0 .0)),
+        )
+        .await
+        .unwrap();
+
+        let nodes = registered_nodes(&stake_table, 0).await.unwrap();
+        let exit_epoch = nodes[0].exit_epoch.unwrap();
+        let entries = stake_table_entries(&nodes, exit_epoch);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].stake_table_entry.stake_key, keys[1].0 .0);
+        assert_eq!(entries[0].stake_table_entry.stake_amount, 25.into());
+
+        // After the escrow period, the exited node can withdraw, and is removed.
+        contract_send::<_, _, LightClientMockErrors>(
+            &light_client.set_current_epoch(exit_epoch + 10),
+        )
+        .await
+        .unwrap();
+        contract_send::<_, _, StakeTableErrors>(
+            &stake_table.withdraw_funds(bls_key_to_g2_point(&keys[0].0 .0)),
+        )
+        .await
+        .unwrap();
+        let nodes = registered_nodes(&stake_table, 0).await.unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].staking_key, keys[1].0 .0);
+    }
+}